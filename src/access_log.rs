@@ -0,0 +1,165 @@
+//! Log de acceso estructurado: una línea por petición, en JSON o en el
+//! formato "combined" de Apache, con latencia, estado de la caché, llamadas a
+//! Roblox y clave de API.
+//!
+//! `ACCESS_LOG=json|combined` lo activa; `ACCESS_LOG_FILE` lo manda a un
+//! archivo (en modo append) en lugar de a stdout. En "combined" la clave va en
+//! el campo de usuario y al final se añaden latencia (ms), caché y llamadas.
+
+use std::{
+    fs::OpenOptions,
+    io::{self, LineWriter, Write},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use axum::{
+    body::HttpBody,
+    extract::State,
+    http::{header, HeaderMap, Request},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{config::Config, request_id, usage::RequestUsage, AppState};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AccessLogFormat {
+    Json,
+    Combined,
+}
+
+pub struct AccessLog {
+    format: AccessLogFormat,
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl AccessLog {
+    /// `None` si el log está desactivado. Si no se puede abrir el archivo se
+    /// escribe en stdout, con un aviso.
+    pub fn new(config: &Config) -> Option<Self> {
+        let format = config.access_log?;
+        let out: Box<dyn Write + Send> = match &config.access_log_file {
+            Some(path) => match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => Box::new(LineWriter::new(file)),
+                Err(e) => {
+                    warn!("No se pudo abrir {}: {e}; usando stdout", path.display());
+                    Box::new(io::stdout())
+                }
+            },
+            None => Box::new(io::stdout()),
+        };
+        Some(AccessLog {
+            format,
+            out: Mutex::new(out),
+        })
+    }
+
+    fn write(&self, entry: &Entry) {
+        let line = match self.format {
+            AccessLogFormat::Json => serde_json::to_string(entry).unwrap_or_default(),
+            AccessLogFormat::Combined => entry.combined(),
+        };
+        let mut out = self.out.lock().unwrap();
+        if let Err(e) = writeln!(out, "{line}") {
+            warn!("No se pudo escribir el log de acceso: {e}");
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    time: DateTime<Utc>,
+    request_id: Option<String>,
+    remote_addr: Option<String>,
+    method: String,
+    /// Ruta con la query.
+    uri: String,
+    version: String,
+    status: u16,
+    bytes: Option<u64>,
+    latency_ms: f64,
+    /// `HIT` o `MISS` en las rutas con caché.
+    cache: Option<String>,
+    upstream_calls: Option<u64>,
+    api_key: Option<String>,
+    referer: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl Entry {
+    fn combined(&self) -> String {
+        let dash = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+        format!(
+            "{} - {} [{}] \"{} {} {}\" {} {} \"{}\" \"{}\" {:.1} {} {}",
+            dash(&self.remote_addr),
+            dash(&self.api_key),
+            self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+            self.method,
+            self.uri,
+            self.version,
+            self.status,
+            self.bytes.map_or("-".to_string(), |b| b.to_string()),
+            dash(&self.referer),
+            dash(&self.user_agent),
+            self.latency_ms,
+            dash(&self.cache),
+            self.upstream_calls
+                .map_or("-".to_string(), |c| c.to_string()),
+        )
+    }
+}
+
+fn header_str(headers: &HeaderMap, name: impl header::AsHeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Middleware: escribe una línea por petición si `ACCESS_LOG` está activo.
+pub async fn log<B>(
+    State(state): State<Arc<AppState>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(access_log) = &state.access_log else {
+        return next.run(req).await;
+    };
+    let started = Instant::now();
+    let time = Utc::now();
+    let method = req.method().to_string();
+    let uri = req.uri().to_string();
+    let version = format!("{:?}", req.version());
+    let headers = req.headers();
+    // Sin `ConnectInfo`: detrás de un proxy es lo único útil de todos modos.
+    let remote_addr = header_str(headers, "x-forwarded-for")
+        .and_then(|v| v.split(',').next().map(|a| a.trim().to_string()));
+    let referer = header_str(headers, header::REFERER);
+    let user_agent = header_str(headers, header::USER_AGENT);
+
+    let response = next.run(req).await;
+
+    let usage = response.extensions().get::<RequestUsage>();
+    access_log.write(&Entry {
+        time,
+        request_id: header_str(response.headers(), request_id::HEADER),
+        remote_addr,
+        method,
+        uri,
+        version,
+        status: response.status().as_u16(),
+        bytes: response.body().size_hint().exact(),
+        latency_ms: started.elapsed().as_micros() as f64 / 1000.0,
+        cache: header_str(response.headers(), "x-cache"),
+        upstream_calls: usage.map(|u| u.upstream_calls),
+        api_key: usage.and_then(|u| u.tenant.clone()),
+        referer,
+        user_agent,
+    });
+    response
+}
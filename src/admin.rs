@@ -0,0 +1,188 @@
+use std::sync::{Arc, OnceLock};
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, State},
+    http::{header, request::Parts, StatusCode},
+    Json,
+};
+use ring::{hmac, rand::SystemRandom};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    config::Setting, error::ApiError, queue::QueueSnapshot, supervisor::TaskReport,
+    upstream::STATS_WINDOW, AppState,
+};
+
+/// Extractor que exige `Authorization: Bearer <ADMIN_TOKEN>`.
+/// Sin `ADMIN_TOKEN` configurado, los endpoints de administración quedan
+/// deshabilitados.
+pub struct AdminAuth;
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for AdminAuth {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(expected) = state.config.admin_token.as_deref() else {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "ADMIN_DISABLED",
+                "Endpoints de administración deshabilitados (falta ADMIN_TOKEN)",
+            ));
+        };
+
+        let provided = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+
+        match provided {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(AdminAuth),
+            _ => Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "UNAUTHORIZED",
+                "Token de administración inválido o ausente",
+            )),
+        }
+    }
+}
+
+/// Compara secretos sin que el tiempo dependa de dónde difieren ni de su
+/// longitud: se firman ambos con una clave aleatoria del proceso y
+/// `hmac::verify` compara las dos firmas, que siempre miden lo mismo.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    static KEY: OnceLock<hmac::Key> = OnceLock::new();
+    let key = KEY.get_or_init(|| {
+        hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new())
+            .expect("clave aleatoria para comparar secretos")
+    });
+    hmac::verify(key, a, hmac::sign(key, b).as_ref()).is_ok()
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamsResponse {
+    ok: bool,
+    window_secs: u64,
+    concurrency: Concurrency,
+    endpoints: Vec<EndpointStats>,
+}
+
+/// Estado del limitador AIMD de peticiones a Roblox.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct Concurrency {
+    limit: usize,
+    inflight: usize,
+    min: usize,
+    max: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct EndpointStats {
+    host: &'static str,
+    path: &'static str,
+    calls: usize,
+    errors: usize,
+    error_rate: f64,
+    short_circuited: usize,
+    p50_ms: Option<u64>,
+    p95_ms: Option<u64>,
+    hedges_fired: u64,
+    hedges_won: u64,
+    in_cooldown: bool,
+}
+
+/// `GET /admin/upstreams`: tasa de error y latencias por endpoint de Roblox.
+pub async fn upstreams(
+    _: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Json<UpstreamsResponse> {
+    let hosts = state.upstreams.snapshot();
+    let endpoints = state
+        .upstreams
+        .endpoint_reports()
+        .into_iter()
+        .map(|report| {
+            let upstream = report.endpoint.upstream();
+            EndpointStats {
+                host: upstream.host(),
+                path: report.endpoint.path(),
+                calls: report.calls,
+                errors: report.errors,
+                error_rate: report.error_rate(),
+                short_circuited: report.short_circuited,
+                p50_ms: report.p50.map(|d| d.as_millis() as u64),
+                p95_ms: report.p95.map(|d| d.as_millis() as u64),
+                hedges_fired: report.hedges_fired,
+                hedges_won: report.hedges_won,
+                in_cooldown: hosts
+                    .iter()
+                    .any(|(u, stats)| *u == upstream && stats.cooldown.is_some()),
+            }
+        })
+        .collect();
+
+    let limiter = state.outbound.snapshot();
+    Json(UpstreamsResponse {
+        ok: true,
+        window_secs: STATS_WINDOW.as_secs(),
+        concurrency: Concurrency {
+            limit: limiter.limit,
+            inflight: limiter.inflight,
+            min: limiter.min,
+            max: limiter.max,
+        },
+        endpoints,
+    })
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct QueuesResponse {
+    ok: bool,
+    queues: Vec<QueueSnapshot>,
+}
+
+/// `GET /admin/queues`: profundidad y descartes de las colas en segundo plano.
+pub async fn queues(_: AdminAuth, State(state): State<Arc<AppState>>) -> Json<QueuesResponse> {
+    Json(QueuesResponse {
+        ok: true,
+        queues: vec![state.race_losers.snapshot()],
+    })
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct TasksResponse {
+    ok: bool,
+    tasks: Vec<TaskReport>,
+}
+
+/// `GET /admin/tasks`: estado de las tareas de fondo supervisadas.
+pub async fn tasks(_: AdminAuth, State(state): State<Arc<AppState>>) -> Json<TasksResponse> {
+    Json(TasksResponse {
+        ok: true,
+        tasks: state.tasks.reports(),
+    })
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ConfigResponse {
+    ok: bool,
+    settings: Vec<Setting>,
+}
+
+/// `GET /admin/config`: configuración efectiva y de dónde salió cada valor
+/// (entorno o por defecto). Los secretos salen como `…`.
+pub async fn config(_: AdminAuth, State(state): State<Arc<AppState>>) -> Json<ConfigResponse> {
+    Json(ConfigResponse {
+        ok: true,
+        settings: state.config.effective(),
+    })
+}
@@ -0,0 +1,76 @@
+//! Curva de espera entre intentos contra un upstream que falla.
+//!
+//! Exponencial (`base * 2^n`, con tope `max`) y con el jitter configurable:
+//! desde una IP residencial conviene esperar sin más, mientras que en un
+//! datacenter con muchas réplicas compartiendo IP hay que repartir los
+//! reintentos para no volver todos a la vez.
+
+use std::{str::FromStr, time::Duration};
+
+/// Cómo se aleatoriza cada espera.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Jitter {
+    /// Exponencial exacta, sin azar.
+    None,
+    /// Uniforme entre 0 y la exponencial.
+    Full,
+    /// Mitad fija de la exponencial más una mitad aleatoria.
+    Equal,
+    /// Uniforme entre `base` y el triple de la espera anterior.
+    Decorrelated,
+}
+
+impl Jitter {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Jitter::None => "none",
+            Jitter::Full => "full",
+            Jitter::Equal => "equal",
+            Jitter::Decorrelated => "decorrelated",
+        }
+    }
+}
+
+impl FromStr for Jitter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Jitter::None),
+            "full" => Ok(Jitter::Full),
+            "equal" => Ok(Jitter::Equal),
+            "decorrelated" => Ok(Jitter::Decorrelated),
+            other => Err(format!(
+                "jitter desconocido '{other}' (none, full, equal o decorrelated)"
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    pub jitter: Jitter,
+    pub base: Duration,
+    pub max: Duration,
+}
+
+impl Backoff {
+    /// Espera antes del intento `attempt` (1 = primer reintento). `prev` es la
+    /// espera anterior, que solo usa `Jitter::Decorrelated`.
+    pub fn delay(&self, attempt: u32, prev: Option<Duration>) -> Duration {
+        let exp = self
+            .base
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max);
+        match self.jitter {
+            Jitter::None => exp,
+            Jitter::Full => exp.mul_f64(fastrand::f64()),
+            Jitter::Equal => exp / 2 + (exp / 2).mul_f64(fastrand::f64()),
+            Jitter::Decorrelated => {
+                let upper = prev.unwrap_or(self.base).saturating_mul(3).max(self.base);
+                let spread = upper - self.base;
+                (self.base + spread.mul_f64(fastrand::f64())).min(self.max)
+            }
+        }
+    }
+}
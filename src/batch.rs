@@ -0,0 +1,101 @@
+//! Passes de varios usuarios en una sola llamada (`POST /users/passes`): un
+//! hub de donaciones con un servidor lleno pide los de todos los jugadores a
+//! la vez en lugar de hacer una petición por jugador.
+//!
+//! Los usuarios se escanean en paralelo (el limitador global de llamadas a
+//! Roblox sigue mandando) y salen de la caché cuando se puede, como en
+//! `/compare`. El máximo por llamada es `BATCH_MAX_USERS`.
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::future::join_all;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    cache::{self, CacheStatus},
+    error::ApiError,
+    extract,
+    roblox::client,
+    snapshots::SnapshotPass,
+    tenant::MaybeTenant,
+    AppState,
+};
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPassesResponse {
+    ok: bool,
+    count: usize,
+    /// Passes por userId, en el orden de `/user/:id/passes`.
+    users: BTreeMap<u64, Vec<SnapshotPass>>,
+}
+
+/// `POST /users/passes` con un array JSON de userIds.
+pub async fn user_passes(
+    State(state): State<Arc<AppState>>,
+    MaybeTenant(tenant): MaybeTenant,
+    headers: HeaderMap,
+    extract::Json(requested): extract::Json<Vec<u64>>,
+) -> Result<Response, ApiError> {
+    let max = state.config.batch_max_users;
+    let mut user_ids: Vec<u64> = Vec::with_capacity(requested.len());
+    for id in requested {
+        if !user_ids.contains(&id) {
+            user_ids.push(id);
+        }
+    }
+    if user_ids.is_empty() || user_ids.len() > max || user_ids.contains(&0) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_BODY",
+            format!("El cuerpo debe ser un array de entre 1 y {max} ids de usuario"),
+        ));
+    }
+    info!("/users/passes ({} usuarios)", user_ids.len());
+
+    let max_age = cache::max_age(&headers, tenant.as_ref(), &state.config);
+    let results = join_all(
+        user_ids
+            .iter()
+            .map(|&user_id| client::cached_full_list(&state, user_id, max_age, tenant.as_ref())),
+    )
+    .await;
+
+    let mut status = CacheStatus::Hit {
+        age: Duration::ZERO,
+    };
+    let mut users = BTreeMap::new();
+    for (user_id, result) in user_ids.into_iter().zip(results) {
+        let (passes, user_status) = match result {
+            Ok(list) => list,
+            Err(exhausted) => return Ok(exhausted),
+        };
+        status = status.combine(user_status);
+        let passes = passes
+            .into_iter()
+            .map(|p| SnapshotPass {
+                id: p.id,
+                name: p.name,
+                price: p.price,
+            })
+            .collect();
+        users.insert(user_id, passes);
+    }
+
+    let mut response = Json(BatchPassesResponse {
+        ok: true,
+        count: users.len(),
+        users,
+    })
+    .into_response();
+    status.apply(response.headers_mut());
+    Ok(response)
+}
@@ -0,0 +1,132 @@
+//! Informe de arranque: un solo objeto JSON en una línea de stdout
+//! (`"event":"boot"`), fuera del formato de los logs, con lo que quedó
+//! resuelto al arrancar: puerto, features compiladas, modo de Roblox, caché,
+//! almacenamiento y la primera ronda de conexiones precalentadas. Sustituye
+//! al banner para que el despliegue pueda comprobar que el servicio arrancó
+//! en el modo esperado.
+//!
+//! Se emite cuando termina el precalentado (o pasa `UPSTREAM_TIMEOUT_SECS`
+//! esperándolo), con el servidor ya atendiendo.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use serde::Serialize;
+
+use crate::{warmup, AppState};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BootReport {
+    event: &'static str,
+    version: &'static str,
+    addr: String,
+    port: u16,
+    features: Vec<&'static str>,
+    upstream_mode: &'static str,
+    selfcheck: &'static str,
+    /// Desde que empezó `main` hasta que el listener quedó abierto.
+    ready_ms: u64,
+    cache: CacheReport,
+    /// No hay base de datos: cada almacén persiste en su carpeta o vive solo
+    /// en memoria.
+    storage: Vec<StoreReport>,
+    warmup: WarmupReport,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheReport {
+    backend: &'static str,
+    ttl_secs: u64,
+    retention_secs: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreReport {
+    name: &'static str,
+    /// `disk` o `memory`.
+    backend: &'static str,
+    dir: Option<String>,
+    /// Entradas recargadas del disco al arrancar.
+    loaded: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WarmupReport {
+    enabled: bool,
+    connections_per_host: usize,
+    /// `null` si está desactivado o la primera ronda no terminó a tiempo.
+    hosts: Option<Vec<warmup::WarmedHost>>,
+}
+
+fn features() -> Vec<&'static str> {
+    [
+        cfg!(feature = "catalog").then_some("catalog"),
+        cfg!(feature = "clothing").then_some("clothing"),
+        cfg!(feature = "watcher").then_some("watcher"),
+        cfg!(feature = "fault-injection").then_some("fault-injection"),
+        cfg!(feature = "redis").then_some("redis"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Espera al precalentado y escribe el informe.
+pub async fn report(state: Arc<AppState>, addr: SocketAddr, selfcheck: bool, ready: Duration) {
+    let config = &state.config;
+    let warmup_enabled = warmup::enabled(&state);
+    let hosts = if warmup_enabled {
+        state.warmup.wait(warmup::first_round_timeout(config)).await
+    } else {
+        None
+    };
+
+    let store = |name, dir: &Option<std::path::PathBuf>, loaded| StoreReport {
+        name,
+        backend: if dir.is_some() { "disk" } else { "memory" },
+        dir: dir.as_ref().map(|d| d.display().to_string()),
+        loaded,
+    };
+    let report = BootReport {
+        event: "boot",
+        version: env!("CARGO_PKG_VERSION"),
+        addr: addr.to_string(),
+        port: addr.port(),
+        features: features(),
+        upstream_mode: state.recording.as_str(),
+        selfcheck: if selfcheck { "ok" } else { "skipped" },
+        ready_ms: ready.as_millis() as u64,
+        cache: CacheReport {
+            backend: state.store.backend(),
+            ttl_secs: config.cache_ttl.as_secs(),
+            retention_secs: config.cache_retention.as_secs(),
+        },
+        storage: vec![
+            store("snapshots", &config.snapshot_dir, state.snapshots.users()),
+            store("booths", &config.booth_dir, state.booths.booths()),
+            store(
+                "collections",
+                &config.collection_dir,
+                state.collections.collections(),
+            ),
+            store(
+                "campaigns",
+                &config.campaign_dir,
+                state.campaigns.campaigns(),
+            ),
+        ],
+        warmup: WarmupReport {
+            enabled: warmup_enabled,
+            connections_per_host: config.upstream_warm_connections,
+            hosts,
+        },
+    };
+    // Directo a stdout: tiene que salir igual con `LOG_FORMAT=text` o `json`.
+    println!(
+        "{}",
+        serde_json::to_string(&report).expect("informe de arranque")
+    );
+}
@@ -0,0 +1,408 @@
+//! Configuración de las cabinas de donación (`/booths`): qué passes muestra
+//! cada una, el mensaje, el tema y la meta. Las DataStores de Roblox son
+//! incómodas para estado compartido entre servidores, así que los juegos la
+//! guardan aquí, por clave de API e id de cabina.
+//!
+//! Con `BOOTH_DIR` se persiste en disco, un JSON por tenant.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    config::Config,
+    error::ApiError,
+    extract::{self, DryRun},
+    recording::fnv1a,
+    tenant::Tenant,
+    AppState,
+};
+
+const MAX_BOOTH_ID_LEN: usize = 64;
+const MAX_SELECTED_PASSES: usize = 100;
+const MAX_MESSAGE_CHARS: usize = 500;
+const MAX_THEME_LEN: usize = 32;
+const MAX_GOAL_ID_LEN: usize = 64;
+
+/// Lo que guarda el juego para una cabina.
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BoothConfig {
+    /// Passes a mostrar, en orden.
+    #[serde(default)]
+    pub selected_passes: Vec<u64>,
+    pub message: Option<String>,
+    /// Nombre del tema (minúsculas, dígitos y guiones).
+    pub theme: Option<String>,
+    pub goal_id: Option<String>,
+}
+
+impl BoothConfig {
+    fn validate(&self) -> Result<(), String> {
+        if self.selected_passes.len() > MAX_SELECTED_PASSES {
+            return Err(format!(
+                "selectedPasses admite como mucho {MAX_SELECTED_PASSES} passes"
+            ));
+        }
+        if self
+            .message
+            .as_ref()
+            .is_some_and(|m| m.chars().count() > MAX_MESSAGE_CHARS)
+        {
+            return Err(format!(
+                "message admite como mucho {MAX_MESSAGE_CHARS} caracteres"
+            ));
+        }
+        if let Some(theme) = &self.theme {
+            let valid = !theme.is_empty()
+                && theme.len() <= MAX_THEME_LEN
+                && theme
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+            if !valid {
+                return Err(format!(
+                    "theme debe tener entre 1 y {MAX_THEME_LEN} caracteres [a-z0-9-]"
+                ));
+            }
+        }
+        if self
+            .goal_id
+            .as_ref()
+            .is_some_and(|g| g.is_empty() || g.len() > MAX_GOAL_ID_LEN)
+        {
+            return Err(format!(
+                "goalId debe tener entre 1 y {MAX_GOAL_ID_LEN} caracteres"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct StoredBooth {
+    updated_at: DateTime<Utc>,
+    config: BoothConfig,
+}
+
+/// Archivo de un tenant en `BOOTH_DIR`.
+#[derive(Serialize, Deserialize)]
+struct TenantFile {
+    tenant: String,
+    booths: BTreeMap<String, StoredBooth>,
+}
+
+pub struct BoothStore {
+    tenants: Mutex<HashMap<String, BTreeMap<String, StoredBooth>>>,
+    dir: Option<PathBuf>,
+}
+
+impl BoothStore {
+    /// Crea el almacén y, si hay `BOOTH_DIR`, recarga lo guardado.
+    pub fn new(config: &Config) -> Self {
+        let store = BoothStore {
+            tenants: Mutex::default(),
+            dir: config.booth_dir.clone(),
+        };
+        store.load();
+        store
+    }
+
+    /// Cabinas guardadas, de todos los tenants.
+    pub fn booths(&self) -> usize {
+        self.tenants
+            .lock()
+            .unwrap()
+            .values()
+            .map(BTreeMap::len)
+            .sum()
+    }
+
+    fn load(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut tenants = self.tenants.lock().unwrap();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let loaded = std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| {
+                    serde_json::from_slice::<TenantFile>(&bytes).map_err(|e| e.to_string())
+                });
+            match loaded {
+                Ok(file) => {
+                    tenants.insert(file.tenant, file.booths);
+                }
+                Err(e) => warn!("Ignorando {}: {e}", path.display()),
+            }
+        }
+    }
+
+    /// Reescribe el archivo de un tenant. El nombre es un hash del id, que
+    /// viene de `API_KEYS` y puede tener cualquier carácter.
+    fn persist(&self, tenant: &str, booths: &BTreeMap<String, StoredBooth>) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let path = dir.join(format!("{:016x}.json", fnv1a(tenant)));
+        let written = std::fs::create_dir_all(dir).and_then(|()| {
+            let file = TenantFile {
+                tenant: tenant.to_string(),
+                booths: booths.clone(),
+            };
+            let json = serde_json::to_vec(&file).map_err(std::io::Error::other)?;
+            let tmp = path.with_extension("json.tmp");
+            std::fs::write(&tmp, json)?;
+            std::fs::rename(&tmp, &path)
+        });
+        if let Err(e) = written {
+            warn!("No se pudo guardar {}: {e}", path.display());
+        }
+    }
+
+    /// Guarda una cabina (salvo con `dry_run`). Devuelve si es nueva, o
+    /// `Err(limit)` si el tenant ya tiene el máximo.
+    fn put(
+        &self,
+        tenant: &str,
+        booth_id: &str,
+        config: BoothConfig,
+        limit: usize,
+        dry_run: bool,
+    ) -> Result<(StoredBooth, bool), usize> {
+        let mut tenants = self.tenants.lock().unwrap();
+        let booths = tenants.entry(tenant.to_string()).or_default();
+        let is_new = !booths.contains_key(booth_id);
+        if is_new && booths.len() >= limit {
+            return Err(limit);
+        }
+        let stored = StoredBooth {
+            updated_at: Utc::now(),
+            config,
+        };
+        if !dry_run {
+            booths.insert(booth_id.to_string(), stored.clone());
+            self.persist(tenant, booths);
+        }
+        Ok((stored, is_new))
+    }
+
+    fn get(&self, tenant: &str, booth_id: &str) -> Option<StoredBooth> {
+        let tenants = self.tenants.lock().unwrap();
+        tenants.get(tenant)?.get(booth_id).cloned()
+    }
+
+    fn remove(&self, tenant: &str, booth_id: &str) -> bool {
+        let mut tenants = self.tenants.lock().unwrap();
+        let Some(booths) = tenants.get_mut(tenant) else {
+            return false;
+        };
+        let removed = booths.remove(booth_id).is_some();
+        if removed {
+            self.persist(tenant, booths);
+        }
+        removed
+    }
+
+    fn list(&self, tenant: &str) -> Vec<(String, StoredBooth)> {
+        let tenants = self.tenants.lock().unwrap();
+        tenants
+            .get(tenant)
+            .map(|booths| {
+                booths
+                    .iter()
+                    .map(|(id, b)| (id.clone(), b.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn invalid_booth(message: impl Into<String>) -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, "INVALID_BOOTH", message)
+}
+
+fn validate_booth_id(booth_id: &str) -> Result<(), ApiError> {
+    let valid = !booth_id.is_empty()
+        && booth_id.len() <= MAX_BOOTH_ID_LEN
+        && booth_id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if valid {
+        Ok(())
+    } else {
+        Err(invalid_booth(format!(
+            "El id de cabina debe tener entre 1 y {MAX_BOOTH_ID_LEN} caracteres [A-Za-z0-9_-]"
+        )))
+    }
+}
+
+fn booth_not_found(booth_id: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::NOT_FOUND,
+        "BOOTH_NOT_FOUND",
+        format!("No hay ninguna cabina '{booth_id}' para esta clave"),
+    )
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BoothResponse {
+    ok: bool,
+    booth_id: String,
+    /// RFC 3339.
+    updated_at: String,
+    config: BoothConfig,
+    /// Solo presente (y `true`) con `?dryRun=1`: no se guardó nada.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    dry_run: bool,
+}
+
+impl BoothResponse {
+    fn new(booth_id: String, stored: StoredBooth) -> Self {
+        BoothResponse {
+            ok: true,
+            booth_id,
+            updated_at: stored.updated_at.to_rfc3339(),
+            config: stored.config,
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BoothSummary {
+    booth_id: String,
+    /// RFC 3339.
+    updated_at: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct BoothListResponse {
+    ok: bool,
+    count: usize,
+    /// Máximo de cabinas para esta clave.
+    limit: usize,
+    booths: Vec<BoothSummary>,
+}
+
+/// `PUT /booths/:boothId`: crea o reemplaza la configuración de una cabina.
+/// Con `?dryRun=1` solo valida y responde lo que pasaría.
+pub async fn put_booth(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+    Path(booth_id): Path<String>,
+    DryRun(dry_run): DryRun,
+    extract::Json(config): extract::Json<BoothConfig>,
+) -> Result<(StatusCode, Json<BoothResponse>), ApiError> {
+    validate_booth_id(&booth_id)?;
+    config.validate().map_err(invalid_booth)?;
+    let max_bytes = state.config.booth_max_bytes;
+    let size = serde_json::to_vec(&config).map_or(0, |b| b.len());
+    if size > max_bytes {
+        return Err(ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "BOOTH_TOO_LARGE",
+            format!("La configuración ocupa {size} bytes (máx {max_bytes})"),
+        ));
+    }
+
+    let (stored, created) = state
+        .booths
+        .put(
+            &tenant.id,
+            &booth_id,
+            config,
+            state.config.booth_limit_per_key,
+            dry_run,
+        )
+        .map_err(|limit| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "BOOTH_LIMIT_REACHED",
+                format!("La clave ya tiene el máximo de {limit} cabinas"),
+            )
+        })?;
+    if dry_run {
+        info!("{} validó la cabina '{booth_id}' (dryRun)", tenant.id);
+    } else {
+        info!("{} guardó la cabina '{booth_id}'", tenant.id);
+    }
+
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    let mut response = BoothResponse::new(booth_id, stored);
+    response.dry_run = dry_run;
+    Ok((status, Json(response)))
+}
+
+/// `GET /booths/:boothId`
+pub async fn get_booth(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+    Path(booth_id): Path<String>,
+) -> Result<Json<BoothResponse>, ApiError> {
+    let stored = state
+        .booths
+        .get(&tenant.id, &booth_id)
+        .ok_or_else(|| booth_not_found(&booth_id))?;
+    Ok(Json(BoothResponse::new(booth_id, stored)))
+}
+
+/// `DELETE /booths/:boothId`
+pub async fn delete_booth(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+    Path(booth_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if !state.booths.remove(&tenant.id, &booth_id) {
+        return Err(booth_not_found(&booth_id));
+    }
+    info!("{} borró la cabina '{booth_id}'", tenant.id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `GET /booths`: cabinas de esta clave.
+pub async fn list_booths(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+) -> Json<BoothListResponse> {
+    let booths: Vec<BoothSummary> = state
+        .booths
+        .list(&tenant.id)
+        .into_iter()
+        .map(|(booth_id, stored)| BoothSummary {
+            booth_id,
+            updated_at: stored.updated_at.to_rfc3339(),
+        })
+        .collect();
+    Json(BoothListResponse {
+        ok: true,
+        count: booths.len(),
+        limit: state.config.booth_limit_per_key,
+        booths,
+    })
+}
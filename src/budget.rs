@@ -0,0 +1,137 @@
+//! Presupuesto de escaneos frescos por clave de API: cuántas veces por minuto
+//! puede un tenant provocar llamadas a Roblox (fallos de caché).
+//!
+//! Pasado el presupuesto no se corta al tenant con un 429: se le sirven los
+//! datos que haya en caché, aunque sean viejos, con `X-Degraded: cache-only`.
+//! Solo si no hay nada guardado para esa consulta se responde 429.
+//!
+//! Vale para todo lo que escanea: `/user/:id/passes` lo consulta con su
+//! propia clave de caché y el resto de rutas a través de
+//! `client::cached_full_list` y `client::budgeted_full_list`.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::{config::Config, error::ApiError, tenant::ApiKey};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+struct Window {
+    started: Instant,
+    used: u32,
+}
+
+/// Resultado de consultar el presupuesto de un tenant.
+#[derive(Clone, Copy)]
+pub struct BudgetStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Hasta que empieza la siguiente ventana.
+    pub reset: Duration,
+    /// Si se concedió el escaneo fresco pedido.
+    pub granted: bool,
+}
+
+impl BudgetStatus {
+    /// Añade `X-Fresh-Budget-Limit`, `-Remaining` y `-Reset` (segundos).
+    pub fn apply(self, headers: &mut HeaderMap) {
+        headers.insert("x-fresh-budget-limit", HeaderValue::from(self.limit));
+        headers.insert(
+            "x-fresh-budget-remaining",
+            HeaderValue::from(self.remaining),
+        );
+        headers.insert(
+            "x-fresh-budget-reset",
+            HeaderValue::from(self.reset.as_secs().max(1)),
+        );
+    }
+
+    /// 429 para cuando no quedan escaneos frescos ni hay nada en caché de
+    /// `subject` (`userId 1`, `groupId 2`).
+    pub fn exhausted(self, tenant: &str, subject: &str) -> Response {
+        let reset = self.reset.as_secs().max(1);
+        let mut response = ApiError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "FRESH_BUDGET_EXHAUSTED",
+            format!(
+                "La clave '{tenant}' agotó sus {} escaneos frescos por minuto y no hay datos en caché para {subject}; reintenta en {reset}s",
+                self.limit
+            ),
+        )
+        .into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(reset));
+        self.apply(response.headers_mut());
+        response
+    }
+}
+
+#[derive(Default)]
+pub struct FreshBudget {
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl FreshBudget {
+    /// Escaneos frescos por minuto de `tenant`, o `None` si no tiene límite.
+    pub fn limit_for(tenant: &ApiKey, config: &Config) -> Option<u32> {
+        let limit = tenant
+            .fresh_per_minute
+            .unwrap_or(config.fresh_fetches_per_minute);
+        (limit > 0).then_some(limit)
+    }
+
+    /// Como `check`, para la petición de `tenant`: `None` si es anónima o la
+    /// clave no tiene límite.
+    pub fn for_tenant(
+        &self,
+        tenant: Option<&ApiKey>,
+        config: &Config,
+        spend: bool,
+    ) -> Option<BudgetStatus> {
+        let tenant = tenant?;
+        let limit = Self::limit_for(tenant, config)?;
+        Some(self.check(&tenant.id, limit, spend))
+    }
+
+    /// Consulta el presupuesto de `tenant` y, con `spend`, gasta un escaneo
+    /// si queda alguno.
+    pub fn check(&self, tenant: &str, limit: u32, spend: bool) -> BudgetStatus {
+        let mut windows = self.windows.lock().unwrap();
+        let now = Instant::now();
+        let window = windows.entry(tenant.to_string()).or_insert(Window {
+            started: now,
+            used: 0,
+        });
+        if now.duration_since(window.started) >= WINDOW {
+            window.started = now;
+            window.used = 0;
+        }
+        let granted = spend && window.used < limit;
+        if granted {
+            window.used += 1;
+        }
+        BudgetStatus {
+            limit,
+            remaining: limit.saturating_sub(window.used),
+            reset: WINDOW.saturating_sub(now.duration_since(window.started)),
+            granted,
+        }
+    }
+
+    /// Olvida las ventanas ya terminadas.
+    pub fn prune(&self) {
+        self.windows
+            .lock()
+            .unwrap()
+            .retain(|_, w| w.started.elapsed() < WINDOW);
+    }
+}
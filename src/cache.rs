@@ -0,0 +1,632 @@
+//! Caché en memoria de las listas de passes ya escaneadas.
+//!
+//! Cada entrada se guarda hasta `CACHE_RETENTION_SECS`; cuánto de vieja se
+//! acepta una entrada lo decide cada petición: `Cache-Control: max-age=N`
+//! (o `no-cache`; en `/user/:id/passes` también `?fresh=true`), si no la
+//! preferencia de su clave de API (`maxAgeSecs` en `API_KEYS`) y si no
+//! `CACHE_TTL_SECS`. Así un kiosco puede conformarse con datos de hace 10
+//! minutos y un panel de administración pedir 30 segundos.
+//!
+//! También cuenta consultas y aciertos por usuario (`/admin/cache/top`), para
+//! ver qué usuarios conviene añadir al watcher.
+//!
+//! `GET /admin/cache/export` vuelca todas las entradas vigentes en un JSON
+//! portable y `POST /admin/cache/import` lo carga en otra instancia, para
+//! sembrar la caché de un servidor nuevo durante una migración. Cada entrada
+//! conserva su antigüedad: una lista importada no parece más fresca de lo que
+//! es.
+//!
+//! Con un backend compartido (`store`, Redis con `CACHE_REDIS_URL`), cada
+//! escaneo se guarda también allí en el mismo formato del export, y un fallo
+//! aquí se consulta allí antes de escanear: cada réplica aprovecha los
+//! escaneos de las demás. Los contadores de `/admin/cache/top` y el export
+//! siguen siendo de cada instancia.
+
+pub mod store;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use tracing::info;
+
+use crate::{
+    admin::AdminAuth,
+    config::Config,
+    error::ApiError,
+    extract::{self, DryRun, Query},
+    games::GameDetails,
+    guidance::{ScanCounts, ScanStats},
+    models::{FetchOptions, Gamepass},
+    pricing,
+    tenant::ApiKey,
+    views::PassSource,
+    AppState,
+};
+
+/// Usuarios que devuelve `/admin/cache/top` como máximo.
+const MAX_TOP: usize = 500;
+/// Los contadores de un usuario sin consultas en este tiempo se descartan.
+const HEAT_IDLE: Duration = Duration::from_secs(24 * 3600);
+/// Versión del formato de `/admin/cache/export`.
+const EXPORT_VERSION: u32 = 1;
+/// Tamaño máximo del cuerpo de `/admin/cache/import`.
+pub const IMPORT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// Opciones del escaneo que cambian el resultado, además del usuario.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheKey {
+    user_id: u64,
+    max_passes_per_game: Option<usize>,
+    active_games_only: bool,
+    game_details: bool,
+    include_groups: bool,
+    include_free: bool,
+}
+
+impl CacheKey {
+    pub(crate) fn new(user_id: u64, opts: &FetchOptions) -> Self {
+        CacheKey {
+            user_id,
+            max_passes_per_game: opts.max_passes_per_game,
+            active_games_only: opts.active_games_only,
+            game_details: opts.game_details,
+            include_groups: opts.include_groups,
+            include_free: opts.include_free,
+        }
+    }
+}
+
+struct Entry {
+    passes: Vec<Gamepass>,
+    stats: Arc<ScanStats>,
+    fetched_at: Instant,
+}
+
+impl Entry {
+    /// La entrada en el formato del export; `now` fija su antigüedad.
+    fn exported(&self, key: CacheKey, now: DateTime<Utc>) -> ExportedEntry {
+        ExportedEntry {
+            key,
+            fetched_at: now
+                - chrono::Duration::from_std(self.fetched_at.elapsed()).unwrap_or_default(),
+            stats: self.stats.counts(),
+            passes: self.passes.iter().map(ExportedPass::from).collect(),
+        }
+    }
+
+    /// Una entrada exportada (o de otra instancia) con `age` de antigüedad.
+    fn imported(exported: ExportedEntry, age: Duration) -> Self {
+        let mut games: HashMap<u64, Arc<GameDetails>> = HashMap::new();
+        Entry {
+            passes: exported
+                .passes
+                .into_iter()
+                .map(|pass| pass.into_gamepass(&mut games))
+                .collect(),
+            stats: Arc::new(exported.stats.into()),
+            fetched_at: Instant::now().checked_sub(age).unwrap_or_else(Instant::now),
+        }
+    }
+}
+
+/// Clave de una lista en el backend compartido.
+fn shared_key(key: &CacheKey) -> String {
+    format!(
+        "passes:{}",
+        serde_json::to_string(key).expect("CacheKey serializable")
+    )
+}
+
+/// Entrada servida desde la caché.
+pub(crate) struct Hit {
+    pub passes: Vec<Gamepass>,
+    pub stats: Arc<ScanStats>,
+    pub age: Duration,
+}
+
+/// Si la respuesta salió de la caché, para la cabecera `X-Cache`.
+#[derive(Clone, Copy)]
+pub enum CacheStatus {
+    Hit {
+        age: Duration,
+    },
+    /// Servida de caché sin respetar `max-age` porque el tenant agotó su
+    /// presupuesto de escaneos frescos.
+    CacheOnly {
+        age: Duration,
+    },
+    Miss,
+}
+
+impl CacheStatus {
+    /// Estado de una respuesta con varios usuarios: `HIT` solo si todos
+    /// salieron de la caché, con el `Age` del más viejo, y degradada si
+    /// alguno lo estaba.
+    pub fn combine(self, other: CacheStatus) -> CacheStatus {
+        use CacheStatus::*;
+        match (self, other) {
+            (Hit { age: a }, Hit { age: b }) => Hit { age: a.max(b) },
+            (CacheOnly { age: a }, Hit { age: b } | CacheOnly { age: b })
+            | (Hit { age: b }, CacheOnly { age: a }) => CacheOnly { age: a.max(b) },
+            (CacheOnly { age }, Miss) | (Miss, CacheOnly { age }) => CacheOnly { age },
+            (Miss, _) | (_, Miss) => Miss,
+        }
+    }
+
+    /// Añade `X-Cache` (y `Age` si es un acierto) a la respuesta.
+    pub fn apply(self, headers: &mut HeaderMap) {
+        match self {
+            CacheStatus::Hit { age } => {
+                headers.insert("x-cache", HeaderValue::from_static("HIT"));
+                headers.insert(header::AGE, HeaderValue::from(age.as_secs()));
+            }
+            CacheStatus::CacheOnly { age } => {
+                headers.insert("x-cache", HeaderValue::from_static("HIT"));
+                headers.insert(header::AGE, HeaderValue::from(age.as_secs()));
+                headers.insert("x-degraded", HeaderValue::from_static("cache-only"));
+            }
+            CacheStatus::Miss => {
+                headers.insert("x-cache", HeaderValue::from_static("MISS"));
+            }
+        }
+    }
+}
+
+/// Consultas a la caché de un usuario, sumando todas sus variantes de
+/// `CacheKey`.
+struct Heat {
+    hits: u64,
+    misses: u64,
+    last_request: Instant,
+    last_refresh: Option<DateTime<Utc>>,
+}
+
+pub struct PassCache {
+    entries: Mutex<HashMap<CacheKey, Entry>>,
+    heat: Mutex<HashMap<u64, Heat>>,
+    retention: Duration,
+    /// El backend, si lo comparten otras instancias; si no, basta `entries`.
+    shared: Option<Arc<dyn store::Cache>>,
+}
+
+impl PassCache {
+    pub fn new(config: &Config, store: &Arc<dyn store::Cache>) -> Self {
+        PassCache {
+            entries: Mutex::default(),
+            heat: Mutex::default(),
+            retention: config.cache_retention,
+            shared: store.is_shared().then(|| store.clone()),
+        }
+    }
+
+    /// La entrada de `key` si no es más vieja que `max_age`. Cuenta como
+    /// consulta del usuario, acierto o fallo.
+    pub(crate) async fn get(&self, key: &CacheKey, max_age: Duration) -> Option<Hit> {
+        let hit = match self.lookup(key, max_age) {
+            Some(hit) => Some(hit),
+            None => self.lookup_shared(key, max_age).await,
+        };
+        let mut heat = self.heat.lock().unwrap();
+        let heat = heat.entry(key.user_id).or_insert(Heat {
+            hits: 0,
+            misses: 0,
+            last_request: Instant::now(),
+            last_refresh: None,
+        });
+        heat.last_request = Instant::now();
+        if hit.is_some() {
+            heat.hits += 1;
+        } else {
+            heat.misses += 1;
+        }
+        hit
+    }
+
+    /// Como `get` pero sin límite de antigüedad ni contar la consulta: el
+    /// último recurso cuando no se puede escanear.
+    pub(crate) async fn get_stale(&self, key: &CacheKey) -> Option<Hit> {
+        match self.lookup(key, Duration::MAX) {
+            Some(hit) => Some(hit),
+            None => self.lookup_shared(key, Duration::MAX).await,
+        }
+    }
+
+    fn lookup(&self, key: &CacheKey, max_age: Duration) -> Option<Hit> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(key)?;
+        let age = entry.fetched_at.elapsed();
+        (age <= max_age && age <= self.retention).then(|| Hit {
+            passes: entry.passes.clone(),
+            stats: entry.stats.clone(),
+            age,
+        })
+    }
+
+    /// La entrada de `key` en el backend compartido, que se queda también
+    /// aquí para las siguientes consultas.
+    async fn lookup_shared(&self, key: &CacheKey, max_age: Duration) -> Option<Hit> {
+        let shared = self.shared.as_ref()?;
+        let exported: ExportedEntry = shared.get_json(&shared_key(key)).await?;
+        let age = (Utc::now() - exported.fetched_at)
+            .to_std()
+            .unwrap_or_default();
+        if age > max_age || age > self.retention || exported.key != *key {
+            return None;
+        }
+        let entry = Entry::imported(exported, age);
+        let hit = Hit {
+            passes: entry.passes.clone(),
+            stats: entry.stats.clone(),
+            age,
+        };
+        self.entries.lock().unwrap().insert(*key, entry);
+        Some(hit)
+    }
+
+    /// Guarda un escaneo, salvo que alguna llamada a Roblox fallara o venciera
+    /// su plazo: una lista incompleta no debe servirse durante minutos.
+    pub(crate) async fn insert(&self, key: CacheKey, passes: Vec<Gamepass>, stats: Arc<ScanStats>) {
+        if stats.has_upstream_errors() || stats.is_partial() {
+            return;
+        }
+        if let Some(heat) = self.heat.lock().unwrap().get_mut(&key.user_id) {
+            heat.last_refresh = Some(Utc::now());
+        }
+        let entry = Entry {
+            passes,
+            stats,
+            fetched_at: Instant::now(),
+        };
+        let exported = self
+            .shared
+            .is_some()
+            .then(|| entry.exported(key, Utc::now()));
+        self.entries.lock().unwrap().insert(key, entry);
+        if let (Some(shared), Some(exported)) = (&self.shared, exported) {
+            shared
+                .set_json(&shared_key(&key), &exported, self.retention)
+                .await;
+        }
+    }
+
+    /// Descarta las entradas más viejas que `CACHE_RETENTION_SECS`.
+    pub fn prune(&self) {
+        let retention = self.retention;
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|_, entry| entry.fetched_at.elapsed() <= retention);
+        self.heat
+            .lock()
+            .unwrap()
+            .retain(|_, heat| heat.last_request.elapsed() <= HEAT_IDLE);
+    }
+
+    /// Entradas vigentes, con su antigüedad convertida a fecha.
+    fn export(&self) -> Vec<ExportedEntry> {
+        let now = Utc::now();
+        let entries = self.entries.lock().unwrap();
+        let mut exported: Vec<ExportedEntry> = entries
+            .iter()
+            .filter(|(_, entry)| entry.fetched_at.elapsed() <= self.retention)
+            .map(|(key, entry)| entry.exported(*key, now))
+            .collect();
+        exported.sort_by_key(|e| (e.key.user_id, std::cmp::Reverse(e.fetched_at)));
+        exported
+    }
+
+    /// Carga entradas exportadas. Se saltan las ya caducadas y las que aquí
+    /// son más recientes. Con `dry_run` solo cuenta.
+    fn import(&self, exported: Vec<ExportedEntry>, dry_run: bool) -> ImportCounts {
+        let now = Utc::now();
+        let mut counts = ImportCounts::default();
+        let mut entries = self.entries.lock().unwrap();
+        for entry in exported {
+            // Una fecha futura (relojes desfasados) cuenta como recién escaneada.
+            let age = (now - entry.fetched_at).to_std().unwrap_or_default();
+            if age > self.retention {
+                counts.expired += 1;
+                continue;
+            }
+            if entries
+                .get(&entry.key)
+                .is_some_and(|current| current.fetched_at.elapsed() <= age)
+            {
+                counts.kept += 1;
+                continue;
+            }
+            counts.imported += 1;
+            if dry_run {
+                continue;
+            }
+            entries.insert(entry.key, Entry::imported(entry, age));
+        }
+        counts
+    }
+
+    /// Los `n` usuarios más consultados, de más a menos, y cuántos hay con
+    /// contadores.
+    fn top(&self, n: usize) -> (usize, Vec<UserHeat>) {
+        let heat = self.heat.lock().unwrap();
+        let mut users: Vec<UserHeat> = heat
+            .iter()
+            .map(|(&user_id, h)| {
+                let requests = h.hits + h.misses;
+                UserHeat {
+                    user_id,
+                    requests,
+                    hits: h.hits,
+                    misses: h.misses,
+                    hit_rate: h.hits as f64 / requests.max(1) as f64,
+                    last_refresh_at: h.last_refresh.map(|at| at.to_rfc3339()),
+                    watched: false,
+                }
+            })
+            .collect();
+        users.sort_by_key(|u| (std::cmp::Reverse(u.requests), u.user_id));
+        users.truncate(n);
+        (heat.len(), users)
+    }
+}
+
+/// Antigüedad máxima aceptable para esta petición.
+pub fn max_age(headers: &HeaderMap, tenant: Option<&ApiKey>, config: &Config) -> Duration {
+    let requested = headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .find_map(|directive| {
+            let directive = directive.trim();
+            if directive.eq_ignore_ascii_case("no-cache") {
+                return Some(Duration::ZERO);
+            }
+            let (name, value) = directive.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("max-age")
+                .then(|| value.trim().parse().ok().map(Duration::from_secs))?
+        });
+    requested
+        .or_else(|| tenant.and_then(|t| t.max_age))
+        .unwrap_or(config.cache_ttl)
+}
+
+#[derive(Deserialize)]
+pub struct TopQuery {
+    n: Option<usize>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserHeat {
+    user_id: u64,
+    requests: u64,
+    hits: u64,
+    misses: u64,
+    /// `hits / requests`.
+    hit_rate: f64,
+    /// Último escaneo guardado en caché (de cualquier variante), RFC 3339.
+    last_refresh_at: Option<String>,
+    /// Ya lo refresca el watcher.
+    watched: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheTopResponse {
+    ok: bool,
+    /// Usuarios con contadores (consultados en las últimas 24 h).
+    tracked_users: usize,
+    users: Vec<UserHeat>,
+}
+
+/// `GET /admin/cache/top?n=50`: usuarios más consultados y su tasa de
+/// aciertos.
+pub async fn top(
+    _: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TopQuery>,
+) -> Result<Json<CacheTopResponse>, ApiError> {
+    let n = query.n.unwrap_or(50);
+    if !(1..=MAX_TOP).contains(&n) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_QUERY",
+            format!("n debe estar entre 1 y {MAX_TOP}"),
+        ));
+    }
+    let (tracked_users, mut users) = state.cache.top(n);
+    for user in &mut users {
+        user.watched = state.is_watched(user.user_id);
+    }
+    Ok(Json(CacheTopResponse {
+        ok: true,
+        tracked_users,
+        users,
+    }))
+}
+
+/// Un pass guardado en caché, sin lo que se calcula en cada respuesta
+/// (nombres desambiguados, iconos, enlaces, precios originales).
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedPass {
+    id: u64,
+    name: String,
+    price: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    price_details: Option<pricing::PriceDetails>,
+    /// Campo de precio de Roblox (ver `pricing`).
+    #[serde(
+        default,
+        deserialize_with = "pricing::optional_price_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(with = "Option<String>")]
+    price_source: Option<pricing::PriceField>,
+    #[serde(default)]
+    source: PassSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    universe_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    game_name: Option<String>,
+    /// Metadatos del juego, si el escaneo los pidió (`gameDetails` en la
+    /// clave).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    game: Option<GameDetails>,
+}
+
+impl From<&Gamepass> for ExportedPass {
+    fn from(pass: &Gamepass) -> Self {
+        ExportedPass {
+            id: pass.id,
+            name: pass.name.clone(),
+            price: pass.price,
+            price_details: pass.price_details.clone(),
+            price_source: pass.price_source,
+            source: pass.source,
+            universe_id: pass.universe_id,
+            game_name: pass.game_name.clone(),
+            game: pass.game.as_deref().cloned(),
+        }
+    }
+}
+
+impl ExportedPass {
+    /// El `Gamepass` de caché. Los passes de un mismo juego comparten sus
+    /// metadatos, como tras un escaneo.
+    fn into_gamepass(self, games: &mut HashMap<u64, Arc<GameDetails>>) -> Gamepass {
+        let game = self.game.map(|game| match self.universe_id {
+            Some(universe_id) => games
+                .entry(universe_id)
+                .or_insert_with(|| Arc::new(game))
+                .clone(),
+            None => Arc::new(game),
+        });
+        Gamepass {
+            id: self.id,
+            name: self.name,
+            price: self.price,
+            original_price: self.price,
+            price_changed: false,
+            price_details: self.price_details,
+            price_source: self.price_source,
+            display_name: String::new(),
+            duplicate_of: None,
+            icon_url: None,
+            links: None,
+            source: self.source,
+            universe_id: self.universe_id,
+            game,
+            game_name: self.game_name,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedEntry {
+    key: CacheKey,
+    /// Cuándo se escaneó, RFC 3339.
+    fetched_at: DateTime<Utc>,
+    stats: ScanCounts,
+    passes: Vec<ExportedPass>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheExportResponse {
+    ok: bool,
+    /// Versión del formato; `/admin/cache/import` solo acepta la suya.
+    version: u32,
+    exported_at: String,
+    count: usize,
+    entries: Vec<ExportedEntry>,
+}
+
+/// `GET /admin/cache/export`: todas las entradas vigentes de la caché, en el
+/// formato que acepta `/admin/cache/import`.
+pub async fn export(_: AdminAuth, State(state): State<Arc<AppState>>) -> Json<CacheExportResponse> {
+    let entries = state.cache.export();
+    info!("Exportadas {} entradas de la caché", entries.len());
+    Json(CacheExportResponse {
+        ok: true,
+        version: EXPORT_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        count: entries.len(),
+        entries,
+    })
+}
+
+/// Cuerpo de `/admin/cache/import`: la respuesta de `/admin/cache/export`
+/// tal cual (los demás campos se ignoran).
+#[derive(Deserialize)]
+pub struct CacheImportRequest {
+    version: u32,
+    entries: Vec<ExportedEntry>,
+}
+
+#[derive(Default, Serialize, JsonSchema)]
+pub struct ImportCounts {
+    /// Entradas cargadas (o que se cargarían, con `dryRun`).
+    imported: usize,
+    /// Entradas más viejas que `CACHE_RETENTION_SECS`.
+    expired: usize,
+    /// Entradas que aquí ya estaban y son más recientes.
+    kept: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheImportResponse {
+    ok: bool,
+    #[serde(flatten)]
+    counts: ImportCounts,
+    dry_run: bool,
+}
+
+/// `POST /admin/cache/import`: siembra la caché con un export de otra
+/// instancia. Con `?dryRun=1` solo cuenta qué se cargaría.
+pub async fn import(
+    _: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    DryRun(dry_run): DryRun,
+    extract::Json(request): extract::Json<CacheImportRequest>,
+) -> Result<Json<CacheImportResponse>, ApiError> {
+    if request.version != EXPORT_VERSION {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_BODY",
+            format!(
+                "Versión de export no soportada: {} (se espera {EXPORT_VERSION})",
+                request.version
+            ),
+        ));
+    }
+    let counts = state.cache.import(request.entries, dry_run);
+    info!(
+        "Importadas {} entradas a la caché ({} caducadas, {} ya más recientes){}",
+        counts.imported,
+        counts.expired,
+        counts.kept,
+        if dry_run { " (dryRun)" } else { "" }
+    );
+    Ok(Json(CacheImportResponse {
+        ok: true,
+        counts,
+        dry_run,
+    }))
+}
@@ -0,0 +1,226 @@
+//! Dónde se guarda lo que se reutiliza entre peticiones: las listas de
+//! passes, los nombres de usuario resueltos y los detalles de economy (el
+//! precio de cada pass).
+//!
+//! Por defecto, en memoria de cada instancia. Con la feature `redis` y
+//! `CACHE_REDIS_URL`, en un Redis que comparten todas las réplicas, para que
+//! una instancia recién arrancada no empiece con la caché fría. Redis es
+//! solo una caché: si no responde, se sigue como un fallo de caché.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::config::Config;
+
+/// Valores opacos con caducidad. Las claves llevan delante su espacio
+/// (`passes:`, `username:`, `asset:`).
+pub trait Cache: Send + Sync {
+    /// `memory` o `redis`, para el informe de arranque.
+    fn backend(&self) -> &'static str;
+
+    /// Si lo guardado lo ven también otras instancias.
+    fn is_shared(&self) -> bool;
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Vec<u8>>>;
+
+    fn set<'a>(&'a self, key: &'a str, value: Vec<u8>, ttl: Duration) -> BoxFuture<'a, ()>;
+
+    /// Descarta lo caducado, si el backend no lo hace solo.
+    fn prune(&self) {}
+}
+
+impl dyn Cache {
+    /// `None` también si lo guardado no se puede leer como `T` (de otra
+    /// versión, por ejemplo).
+    pub async fn get_json<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let bytes = self.get(key).await?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    pub async fn set_json<T: Serialize>(&self, key: &str, value: &T, ttl: Duration) {
+        if ttl.is_zero() {
+            return;
+        }
+        if let Ok(bytes) = serde_json::to_vec(value) {
+            self.set(key, bytes, ttl).await;
+        }
+    }
+}
+
+/// El backend según la configuración: Redis si hay `CACHE_REDIS_URL` (y se
+/// compiló con `redis`), si no memoria.
+pub fn open(config: &Config) -> Arc<dyn Cache> {
+    #[cfg(feature = "redis")]
+    if let Some(url) = &config.cache_redis_url {
+        match redis_backend::RedisCache::new(url, &config.cache_redis_prefix) {
+            Ok(cache) => return Arc::new(cache),
+            Err(e) => tracing::error!("CACHE_REDIS_URL no válida ({e}); caché en memoria"),
+        }
+    }
+    #[cfg(not(feature = "redis"))]
+    let _ = config;
+    Arc::new(MemoryCache::default())
+}
+
+/// Caché de una sola instancia.
+#[derive(Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, (Vec<u8>, Instant)>>,
+}
+
+impl Cache for MemoryCache {
+    fn backend(&self) -> &'static str {
+        "memory"
+    }
+
+    fn is_shared(&self) -> bool {
+        false
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Vec<u8>>> {
+        let value = self
+            .entries
+            .lock()
+            .unwrap()
+            .get(key)
+            .filter(|(_, expires)| *expires > Instant::now())
+            .map(|(value, _)| value.clone());
+        Box::pin(async move { value })
+    }
+
+    fn set<'a>(&'a self, key: &'a str, value: Vec<u8>, ttl: Duration) -> BoxFuture<'a, ()> {
+        if let Some(expires) = Instant::now().checked_add(ttl) {
+            self.entries
+                .lock()
+                .unwrap()
+                .insert(key.to_string(), (value, expires));
+        }
+        Box::pin(async {})
+    }
+
+    fn prune(&self) {
+        let now = Instant::now();
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|_, (_, expires)| *expires > now);
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use redis_backend::RedisCache;
+
+#[cfg(feature = "redis")]
+mod redis_backend {
+    use std::time::Duration;
+
+    use futures::future::BoxFuture;
+    use redis::{aio::ConnectionManager, AsyncCommands};
+    use tokio::sync::OnceCell;
+    use tracing::warn;
+
+    use super::Cache;
+
+    /// Lo más que se espera a Redis por operación (incluida la conexión):
+    /// pasado, se sigue como si no estuviera en caché.
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// Caché compartida en Redis. Conecta con la primera operación y, si se
+    /// cae, `ConnectionManager` reconecta solo.
+    pub struct RedisCache {
+        client: redis::Client,
+        connection: OnceCell<ConnectionManager>,
+        prefix: String,
+    }
+
+    impl RedisCache {
+        pub fn new(url: &str, prefix: &str) -> redis::RedisResult<Self> {
+            Ok(RedisCache {
+                client: redis::Client::open(url)?,
+                connection: OnceCell::new(),
+                prefix: prefix.to_string(),
+            })
+        }
+
+        async fn connection(&self) -> Option<ConnectionManager> {
+            let connect = self
+                .connection
+                .get_or_try_init(|| ConnectionManager::new(self.client.clone()));
+            match tokio::time::timeout(TIMEOUT, connect).await {
+                Ok(Ok(connection)) => Some(connection.clone()),
+                Ok(Err(e)) => {
+                    warn!("Redis: no se pudo conectar: {e}");
+                    None
+                }
+                Err(_) => {
+                    warn!("Redis: sin conexión en {}ms", TIMEOUT.as_millis());
+                    None
+                }
+            }
+        }
+
+        /// Comprueba que Redis responde (para el self-check).
+        pub async fn ping(&self) -> Result<(), String> {
+            let mut connection = self
+                .connection()
+                .await
+                .ok_or_else(|| "sin conexión".to_string())?;
+            let ping = redis::cmd("PING");
+            let pong = ping.query_async::<String>(&mut connection);
+            match tokio::time::timeout(TIMEOUT, pong).await {
+                Ok(Ok(_)) => Ok(()),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(_) => Err(format!("sin respuesta en {}ms", TIMEOUT.as_millis())),
+            }
+        }
+    }
+
+    impl Cache for RedisCache {
+        fn backend(&self) -> &'static str {
+            "redis"
+        }
+
+        fn is_shared(&self) -> bool {
+            true
+        }
+
+        fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Vec<u8>>> {
+            Box::pin(async move {
+                let mut connection = self.connection().await?;
+                let key = format!("{}{key}", self.prefix);
+                match tokio::time::timeout(TIMEOUT, connection.get(&key)).await {
+                    Ok(Ok(value)) => value,
+                    Ok(Err(e)) => {
+                        warn!("Redis: GET {key} falló: {e}");
+                        None
+                    }
+                    Err(_) => {
+                        warn!("Redis: GET {key} sin respuesta");
+                        None
+                    }
+                }
+            })
+        }
+
+        fn set<'a>(&'a self, key: &'a str, value: Vec<u8>, ttl: Duration) -> BoxFuture<'a, ()> {
+            Box::pin(async move {
+                let Some(mut connection) = self.connection().await else {
+                    return;
+                };
+                let key = format!("{}{key}", self.prefix);
+                let set = connection.set_ex::<_, _, ()>(&key, value, ttl.as_secs().max(1));
+                match tokio::time::timeout(TIMEOUT, set).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => warn!("Redis: SET {key} falló: {e}"),
+                    Err(_) => warn!("Redis: SET {key} sin respuesta"),
+                }
+            })
+        }
+    }
+}
@@ -0,0 +1,706 @@
+//! Campañas de donación (`/campaigns`): una recaudación de fin de semana con
+//! varios creadores. Roblox no expone quién compró un pass ni cuándo, así que
+//! los servidores del juego informan cada compra con `POST /donations`
+//! (desde `ProcessReceipt`, con su `PurchaseId`) y una campaña suma las que
+//! caen en su ventana y van a sus participantes: el total, lo de cada
+//! participante y el ranking de donantes.
+//!
+//! Todo va por clave de API: cada tenant ve solo sus compras y campañas.
+//! Con `CAMPAIGN_DIR` se persiste en disco: las campañas de un tenant en un
+//! JSON y sus compras en un JSON Lines al que solo se añaden líneas.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Write,
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    config::Config,
+    error::ApiError,
+    extract::{self, Query},
+    pricing,
+    recording::fnv1a,
+    tenant::Tenant,
+    AppState,
+};
+
+const MAX_CAMPAIGN_ID_LEN: usize = 64;
+const MAX_CAMPAIGNS_PER_KEY: usize = 50;
+const MAX_PARTICIPANTS: usize = 100;
+const MAX_NAME_CHARS: usize = 100;
+const MAX_PURCHASE_ID_LEN: usize = 64;
+/// Margen para el reloj de los servidores del juego.
+const MAX_CLOCK_SKEW_SECS: i64 = 300;
+const DEFAULT_LEADERBOARD: usize = 10;
+const MAX_LEADERBOARD: usize = 100;
+
+/// Una compra informada por un servidor del juego.
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DonationEvent {
+    /// `PurchaseId` de `ProcessReceipt`: la misma compra informada dos veces
+    /// cuenta una.
+    pub purchase_id: String,
+    pub donor_id: u64,
+    /// Creador que recibe la donación.
+    pub recipient_id: u64,
+    pub pass_id: Option<u64>,
+    /// Robux pagados.
+    pub amount: i64,
+    /// RFC 3339; por defecto, cuando llega.
+    pub at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Donation {
+    purchase_id: String,
+    donor_id: u64,
+    recipient_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pass_id: Option<u64>,
+    amount: i64,
+    at: DateTime<Utc>,
+}
+
+impl DonationEvent {
+    fn validate(self) -> Result<Donation, ApiError> {
+        let invalid =
+            |message: String| ApiError::new(StatusCode::BAD_REQUEST, "INVALID_DONATION", message);
+        if self.purchase_id.is_empty() || self.purchase_id.len() > MAX_PURCHASE_ID_LEN {
+            return Err(invalid(format!(
+                "purchaseId debe tener entre 1 y {MAX_PURCHASE_ID_LEN} caracteres"
+            )));
+        }
+        if pricing::checked_price(self.amount).is_err() {
+            return Err(invalid(format!(
+                "amount debe estar entre 1 y {}",
+                pricing::MAX_PRICE
+            )));
+        }
+        let now = Utc::now();
+        let at = self.at.unwrap_or(now);
+        if at > now + Duration::seconds(MAX_CLOCK_SKEW_SECS) {
+            return Err(invalid("at no puede estar en el futuro".to_string()));
+        }
+        Ok(Donation {
+            purchase_id: self.purchase_id,
+            donor_id: self.donor_id,
+            recipient_id: self.recipient_id,
+            pass_id: self.pass_id,
+            amount: self.amount,
+            at,
+        })
+    }
+}
+
+/// Lo que se guarda de una campaña.
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CampaignConfig {
+    pub name: Option<String>,
+    /// Creadores participantes; cuentan las donaciones que reciben.
+    pub user_ids: Vec<u64>,
+    /// RFC 3339, incluido.
+    pub starts_at: DateTime<Utc>,
+    /// RFC 3339, excluido.
+    pub ends_at: DateTime<Utc>,
+}
+
+impl CampaignConfig {
+    fn validate(&mut self) -> Result<(), String> {
+        let mut seen = HashSet::new();
+        self.user_ids.retain(|id| seen.insert(*id));
+        if self.user_ids.is_empty() || self.user_ids.len() > MAX_PARTICIPANTS {
+            return Err(format!(
+                "userIds debe tener entre 1 y {MAX_PARTICIPANTS} creadores"
+            ));
+        }
+        if self.starts_at >= self.ends_at {
+            return Err("startsAt debe ser anterior a endsAt".to_string());
+        }
+        if self
+            .name
+            .as_ref()
+            .is_some_and(|n| n.chars().count() > MAX_NAME_CHARS)
+        {
+            return Err(format!(
+                "name admite como mucho {MAX_NAME_CHARS} caracteres"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct StoredCampaign {
+    updated_at: DateTime<Utc>,
+    config: CampaignConfig,
+}
+
+/// Archivo de campañas de un tenant en `CAMPAIGN_DIR`.
+#[derive(Serialize, Deserialize)]
+struct TenantFile {
+    tenant: String,
+    campaigns: BTreeMap<String, StoredCampaign>,
+}
+
+/// Línea del registro de compras de un tenant en `CAMPAIGN_DIR`.
+#[derive(Serialize, Deserialize)]
+struct LedgerLine {
+    tenant: String,
+    #[serde(flatten)]
+    donation: Donation,
+}
+
+#[derive(Default)]
+struct TenantData {
+    campaigns: BTreeMap<String, StoredCampaign>,
+    donations: Vec<Donation>,
+    purchase_ids: HashSet<String>,
+}
+
+impl TenantData {
+    /// Añade una compra; `false` si su `purchaseId` ya estaba.
+    fn record(&mut self, donation: Donation) -> bool {
+        if !self.purchase_ids.insert(donation.purchase_id.clone()) {
+            return false;
+        }
+        self.donations.push(donation);
+        true
+    }
+}
+
+pub struct CampaignStore {
+    tenants: Mutex<HashMap<String, TenantData>>,
+    dir: Option<PathBuf>,
+    /// Hilo que añade las compras al registro, fuera del camino de la
+    /// petición.
+    ledger: Option<mpsc::Sender<(PathBuf, Vec<u8>)>>,
+}
+
+impl CampaignStore {
+    /// Crea el almacén y, si hay `CAMPAIGN_DIR`, recarga lo guardado.
+    pub fn new(config: &Config) -> Self {
+        let store = CampaignStore {
+            tenants: Mutex::default(),
+            dir: config.campaign_dir.clone(),
+            ledger: config.campaign_dir.clone().map(spawn_ledger_writer),
+        };
+        store.load();
+        store
+    }
+
+    /// Campañas guardadas, de todos los tenants.
+    pub fn campaigns(&self) -> usize {
+        self.tenants
+            .lock()
+            .unwrap()
+            .values()
+            .map(|t| t.campaigns.len())
+            .sum()
+    }
+
+    fn load(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut tenants = self.tenants.lock().unwrap();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(bytes) = std::fs::read(&path) else {
+                continue;
+            };
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => match serde_json::from_slice::<TenantFile>(&bytes) {
+                    Ok(file) => {
+                        tenants.entry(file.tenant).or_default().campaigns = file.campaigns;
+                    }
+                    Err(e) => warn!("Ignorando {}: {e}", path.display()),
+                },
+                Some("jsonl") => {
+                    for line in bytes.split(|b| *b == b'\n').filter(|l| !l.is_empty()) {
+                        match serde_json::from_slice::<LedgerLine>(line) {
+                            Ok(line) => {
+                                tenants
+                                    .entry(line.tenant)
+                                    .or_default()
+                                    .record(line.donation);
+                            }
+                            Err(e) => warn!("Línea ignorada en {}: {e}", path.display()),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Ruta de un archivo del tenant. El nombre es un hash del id, que viene
+    /// de `API_KEYS` y puede tener cualquier carácter.
+    fn path(&self, tenant: &str, extension: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        Some(dir.join(format!("{:016x}.{extension}", fnv1a(tenant))))
+    }
+
+    fn persist_campaigns(&self, tenant: &str, campaigns: &BTreeMap<String, StoredCampaign>) {
+        let Some(path) = self.path(tenant, "json") else {
+            return;
+        };
+        let written = std::fs::create_dir_all(path.parent().unwrap()).and_then(|()| {
+            let file = TenantFile {
+                tenant: tenant.to_string(),
+                campaigns: campaigns.clone(),
+            };
+            let json = serde_json::to_vec(&file).map_err(std::io::Error::other)?;
+            let tmp = path.with_extension("json.tmp");
+            std::fs::write(&tmp, json)?;
+            std::fs::rename(&tmp, &path)
+        });
+        if let Err(e) = written {
+            warn!("No se pudo guardar {}: {e}", path.display());
+        }
+    }
+
+    /// Guarda una compra. `Ok(false)` si ya estaba (mismo `purchaseId`);
+    /// `Err(limit)` si el tenant ya tiene el máximo.
+    fn record(&self, tenant: &str, donation: Donation, limit: usize) -> Result<bool, usize> {
+        let mut tenants = self.tenants.lock().unwrap();
+        let data = tenants.entry(tenant.to_string()).or_default();
+        if data.purchase_ids.contains(&donation.purchase_id) {
+            return Ok(false);
+        }
+        if data.donations.len() >= limit {
+            return Err(limit);
+        }
+        if let (Some(path), Some(ledger)) = (self.path(tenant, "jsonl"), &self.ledger) {
+            let line = LedgerLine {
+                tenant: tenant.to_string(),
+                donation: donation.clone(),
+            };
+            let mut json = serde_json::to_vec(&line).unwrap_or_default();
+            json.push(b'\n');
+            let _ = ledger.send((path, json));
+        }
+        Ok(data.record(donation))
+    }
+
+    /// Guarda una campaña. Devuelve si es nueva, o `Err(limit)` si el tenant
+    /// ya tiene el máximo.
+    fn put(
+        &self,
+        tenant: &str,
+        campaign_id: &str,
+        config: CampaignConfig,
+    ) -> Result<(StoredCampaign, bool), usize> {
+        let mut tenants = self.tenants.lock().unwrap();
+        let campaigns = &mut tenants.entry(tenant.to_string()).or_default().campaigns;
+        let is_new = !campaigns.contains_key(campaign_id);
+        if is_new && campaigns.len() >= MAX_CAMPAIGNS_PER_KEY {
+            return Err(MAX_CAMPAIGNS_PER_KEY);
+        }
+        let stored = StoredCampaign {
+            updated_at: Utc::now(),
+            config,
+        };
+        campaigns.insert(campaign_id.to_string(), stored.clone());
+        self.persist_campaigns(tenant, campaigns);
+        Ok((stored, is_new))
+    }
+
+    /// La campaña y sus cuentas, con los `leaderboard` mejores donantes.
+    fn report(&self, tenant: &str, campaign_id: &str, leaderboard: usize) -> Option<Report> {
+        let tenants = self.tenants.lock().unwrap();
+        let data = tenants.get(tenant)?;
+        let stored = data.campaigns.get(campaign_id)?.clone();
+        let config = &stored.config;
+        let participants: HashSet<u64> = config.user_ids.iter().copied().collect();
+
+        let mut recipients: HashMap<u64, Tally> = HashMap::new();
+        let mut donors: HashMap<u64, Tally> = HashMap::new();
+        let counted = data.donations.iter().filter(|d| {
+            participants.contains(&d.recipient_id)
+                && config.starts_at <= d.at
+                && d.at < config.ends_at
+        });
+        for donation in counted {
+            recipients
+                .entry(donation.recipient_id)
+                .or_default()
+                .add(donation.amount);
+            donors
+                .entry(donation.donor_id)
+                .or_default()
+                .add(donation.amount);
+        }
+
+        let mut total = Tally::default();
+        for tally in recipients.values() {
+            total.robux += tally.robux;
+            total.donations += tally.donations;
+        }
+        let recipients = config
+            .user_ids
+            .iter()
+            .map(|&user_id| RecipientTotal {
+                user_id,
+                tally: recipients.get(&user_id).copied().unwrap_or_default(),
+            })
+            .collect();
+        let mut top: Vec<DonorTotal> = donors
+            .into_iter()
+            .map(|(donor_id, tally)| DonorTotal { donor_id, tally })
+            .collect();
+        top.sort_by_key(|d| (std::cmp::Reverse(d.tally.robux), d.donor_id));
+        top.truncate(leaderboard);
+        Some(Report {
+            stored,
+            total,
+            recipients,
+            leaderboard: top,
+        })
+    }
+
+    fn remove(&self, tenant: &str, campaign_id: &str) -> bool {
+        let mut tenants = self.tenants.lock().unwrap();
+        let Some(data) = tenants.get_mut(tenant) else {
+            return false;
+        };
+        let removed = data.campaigns.remove(campaign_id).is_some();
+        if removed {
+            self.persist_campaigns(tenant, &data.campaigns);
+        }
+        removed
+    }
+
+    fn list(&self, tenant: &str) -> Vec<(String, StoredCampaign)> {
+        let tenants = self.tenants.lock().unwrap();
+        tenants
+            .get(tenant)
+            .map(|data| {
+                data.campaigns
+                    .iter()
+                    .map(|(id, c)| (id.clone(), c.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Hilo que añade líneas al registro de compras. Si no arranca, las compras
+/// se quedan solo en memoria.
+fn spawn_ledger_writer(dir: PathBuf) -> mpsc::Sender<(PathBuf, Vec<u8>)> {
+    let (tx, rx) = mpsc::channel::<(PathBuf, Vec<u8>)>();
+    let spawned = std::thread::Builder::new()
+        .name("campaigns".to_string())
+        .spawn(move || {
+            for (path, line) in rx {
+                let written = std::fs::create_dir_all(&dir).and_then(|()| {
+                    std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&path)?
+                        .write_all(&line)
+                });
+                if let Err(e) = written {
+                    warn!("No se pudo guardar {}: {e}", path.display());
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Sin escritor de compras ({e}): solo en memoria");
+    }
+    tx
+}
+
+#[derive(Serialize, JsonSchema, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Tally {
+    robux: i64,
+    donations: usize,
+}
+
+impl Tally {
+    fn add(&mut self, amount: i64) {
+        self.robux += amount;
+        self.donations += 1;
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipientTotal {
+    user_id: u64,
+    #[serde(flatten)]
+    tally: Tally,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DonorTotal {
+    donor_id: u64,
+    #[serde(flatten)]
+    tally: Tally,
+}
+
+struct Report {
+    stored: StoredCampaign,
+    total: Tally,
+    recipients: Vec<RecipientTotal>,
+    leaderboard: Vec<DonorTotal>,
+}
+
+/// En qué punto de su ventana está una campaña.
+#[derive(Serialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum CampaignStatus {
+    Scheduled,
+    Active,
+    Ended,
+}
+
+impl CampaignStatus {
+    fn of(config: &CampaignConfig, now: DateTime<Utc>) -> Self {
+        if now < config.starts_at {
+            CampaignStatus::Scheduled
+        } else if now < config.ends_at {
+            CampaignStatus::Active
+        } else {
+            CampaignStatus::Ended
+        }
+    }
+}
+
+fn invalid_campaign(message: impl Into<String>) -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, "INVALID_CAMPAIGN", message)
+}
+
+fn validate_campaign_id(campaign_id: &str) -> Result<(), ApiError> {
+    let valid = !campaign_id.is_empty()
+        && campaign_id.len() <= MAX_CAMPAIGN_ID_LEN
+        && campaign_id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if valid {
+        Ok(())
+    } else {
+        Err(invalid_campaign(format!(
+            "El id de campaña debe tener entre 1 y {MAX_CAMPAIGN_ID_LEN} caracteres [A-Za-z0-9_-]"
+        )))
+    }
+}
+
+fn campaign_not_found(campaign_id: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::NOT_FOUND,
+        "CAMPAIGN_NOT_FOUND",
+        format!("No hay ninguna campaña '{campaign_id}' para esta clave"),
+    )
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DonationResponse {
+    ok: bool,
+    /// `true` si el `purchaseId` ya estaba registrado: no se contó otra vez.
+    duplicate: bool,
+    donation: Donation,
+}
+
+/// `POST /donations`: registra una compra informada por un servidor del juego.
+pub async fn post_donation(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+    extract::Json(event): extract::Json<DonationEvent>,
+) -> Result<(StatusCode, Json<DonationResponse>), ApiError> {
+    let donation = event.validate()?;
+    let limit = state.config.donation_limit_per_key;
+    let recorded = state
+        .campaigns
+        .record(&tenant.id, donation.clone(), limit)
+        .map_err(|limit| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "DONATION_LIMIT_REACHED",
+                format!("La clave ya tiene el máximo de {limit} compras registradas"),
+            )
+        })?;
+    let status = if recorded {
+        info!(
+            "{} registró la compra {} ({} Robux para userId={})",
+            tenant.id, donation.purchase_id, donation.amount, donation.recipient_id
+        );
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((
+        status,
+        Json(DonationResponse {
+            ok: true,
+            duplicate: !recorded,
+            donation,
+        }),
+    ))
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CampaignResponse {
+    ok: bool,
+    campaign_id: String,
+    status: CampaignStatus,
+    /// RFC 3339.
+    updated_at: String,
+    config: CampaignConfig,
+    /// Compras a los participantes dentro de la ventana.
+    total: Tally,
+    /// Por participante, en el orden de `userIds`.
+    recipients: Vec<RecipientTotal>,
+    /// Donantes que más Robux aportaron (`?limit=`, 10 por defecto).
+    leaderboard: Vec<DonorTotal>,
+}
+
+impl CampaignResponse {
+    fn new(campaign_id: String, report: Report) -> Self {
+        CampaignResponse {
+            ok: true,
+            campaign_id,
+            status: CampaignStatus::of(&report.stored.config, Utc::now()),
+            updated_at: report.stored.updated_at.to_rfc3339(),
+            config: report.stored.config,
+            total: report.total,
+            recipients: report.recipients,
+            leaderboard: report.leaderboard,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CampaignQuery {
+    limit: Option<usize>,
+}
+
+/// `PUT /campaigns/:campaignId`: crea o reemplaza una campaña.
+pub async fn put_campaign(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+    Path(campaign_id): Path<String>,
+    extract::Json(mut config): extract::Json<CampaignConfig>,
+) -> Result<(StatusCode, Json<CampaignResponse>), ApiError> {
+    validate_campaign_id(&campaign_id)?;
+    config.validate().map_err(invalid_campaign)?;
+    let (_, created) = state
+        .campaigns
+        .put(&tenant.id, &campaign_id, config)
+        .map_err(|limit| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "CAMPAIGN_LIMIT_REACHED",
+                format!("La clave ya tiene el máximo de {limit} campañas"),
+            )
+        })?;
+    info!("{} guardó la campaña '{campaign_id}'", tenant.id);
+
+    let report = state
+        .campaigns
+        .report(&tenant.id, &campaign_id, DEFAULT_LEADERBOARD)
+        .ok_or_else(|| campaign_not_found(&campaign_id))?;
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(CampaignResponse::new(campaign_id, report))))
+}
+
+/// `GET /campaigns/:campaignId?limit=10`: la campaña con sus totales.
+pub async fn get_campaign(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+    Path(campaign_id): Path<String>,
+    Query(query): Query<CampaignQuery>,
+) -> Result<Json<CampaignResponse>, ApiError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_LEADERBOARD)
+        .min(MAX_LEADERBOARD);
+    let report = state
+        .campaigns
+        .report(&tenant.id, &campaign_id, limit)
+        .ok_or_else(|| campaign_not_found(&campaign_id))?;
+    Ok(Json(CampaignResponse::new(campaign_id, report)))
+}
+
+/// `DELETE /campaigns/:campaignId`. Las compras se conservan.
+pub async fn delete_campaign(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+    Path(campaign_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if !state.campaigns.remove(&tenant.id, &campaign_id) {
+        return Err(campaign_not_found(&campaign_id));
+    }
+    info!("{} borró la campaña '{campaign_id}'", tenant.id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CampaignSummary {
+    campaign_id: String,
+    name: Option<String>,
+    status: CampaignStatus,
+    /// RFC 3339.
+    starts_at: String,
+    /// RFC 3339.
+    ends_at: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CampaignListResponse {
+    ok: bool,
+    count: usize,
+    campaigns: Vec<CampaignSummary>,
+}
+
+/// `GET /campaigns`: campañas de esta clave.
+pub async fn list_campaigns(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+) -> Json<CampaignListResponse> {
+    let now = Utc::now();
+    let campaigns: Vec<CampaignSummary> = state
+        .campaigns
+        .list(&tenant.id)
+        .into_iter()
+        .map(|(campaign_id, stored)| CampaignSummary {
+            campaign_id,
+            status: CampaignStatus::of(&stored.config, now),
+            starts_at: stored.config.starts_at.to_rfc3339(),
+            ends_at: stored.config.ends_at.to_rfc3339(),
+            name: stored.config.name,
+        })
+        .collect();
+    Json(CampaignListResponse {
+        ok: true,
+        count: campaigns.len(),
+        campaigns,
+    })
+}
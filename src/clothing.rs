@@ -0,0 +1,213 @@
+//! Ropa a la venta creada por el usuario (`/user/:id/clothing`): camisetas
+//! (T-shirts), camisas y pantalones. Muchos juegos de donaciones aceptan ropa
+//! además de gamepasses; `/user/:id/donatables` junta ambas listas y los
+//! developer products de sus juegos (ver `products`).
+//!
+//! Sale del catálogo (assetType 2, 11 y 12), con el mismo filtro de precio
+//! que los passes: solo artículos a la venta y por más de 0 Robux.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{
+    cache,
+    error::ApiError,
+    pricing, products,
+    roblox::{catalog, client},
+    tenant::MaybeTenant,
+    upstream::Endpoint,
+    AppState,
+};
+
+/// Páginas de 30 artículos que se piden como máximo al catálogo.
+const MAX_PAGES: usize = 5;
+
+/// T-shirts, camisas y pantalones (ver `ItemType::from_asset_type`).
+const CLOTHING_ASSET_TYPES: [u64; 3] = [2, 11, 12];
+
+/// Tipo de artículo con el que se puede donar.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemType {
+    GamePass,
+    DeveloperProduct,
+    TShirt,
+    Shirt,
+    Pants,
+}
+
+impl ItemType {
+    /// Tipo de ropa según el `assetType` del catálogo.
+    fn from_asset_type(asset_type: u64) -> Option<Self> {
+        match asset_type {
+            2 => Some(ItemType::TShirt),
+            11 => Some(ItemType::Shirt),
+            12 => Some(ItemType::Pants),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DonationItem {
+    #[serde(rename = "type")]
+    pub item_type: ItemType,
+    pub id: u64,
+    pub name: String,
+    pub price: i64,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClothingResponse {
+    ok: bool,
+    user_id: u64,
+    count: usize,
+    items: Vec<DonationItem>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DonatablesResponse {
+    ok: bool,
+    user_id: u64,
+    count: usize,
+    /// Passes, developer products y ropa, del más barato al más caro.
+    items: Vec<DonationItem>,
+}
+
+/// Ropa a la venta del usuario. `None` si falla la primera página; un fallo
+/// en las siguientes devuelve lo ya recogido.
+async fn fetch_clothing(state: &AppState, user_id: u64) -> Option<Vec<DonationItem>> {
+    let catalog::CatalogItems { items, error } =
+        catalog::search(state, user_id, &CLOTHING_ASSET_TYPES, MAX_PAGES).await;
+    if let Some(error) = error {
+        warn!(
+            "Catálogo: fallo en la página {} de la ropa de userId={user_id}",
+            error.page
+        );
+        if error.page == 0 {
+            return None;
+        }
+    }
+
+    let mut result = Vec::new();
+    for item in items {
+        let Some(item_type) = ItemType::from_asset_type(item.asset_type) else {
+            continue;
+        };
+        // `price` es null cuando el artículo no está a la venta.
+        let Some(price) = item.price else {
+            continue;
+        };
+        let price = match pricing::checked_price(price) {
+            Ok(price) => price,
+            Err(pricing::InvalidPrice::NotPositive) => continue,
+            Err(pricing::InvalidPrice::OutOfRange(price)) => {
+                warn!(
+                    "Precio fuera de rango ({price}), se omite el artículo {}",
+                    item.id
+                );
+                state.quarantine.record(
+                    Endpoint::CatalogSearch,
+                    "precio fuera de rango",
+                    &item.raw,
+                );
+                continue;
+            }
+        };
+        result.push(DonationItem {
+            item_type,
+            id: item.id,
+            name: item.name.unwrap_or_else(|| "Clothing".to_string()),
+            price,
+        });
+    }
+
+    info!("Total ropa con precio > 0 para {user_id}: {}", result.len());
+    Some(result)
+}
+
+fn upstream_error() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_GATEWAY,
+        "UPSTREAM_ERROR",
+        "No se pudo consultar el catálogo de Roblox",
+    )
+}
+
+/// `GET /user/:id/clothing`
+pub async fn get_clothing(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<u64>,
+) -> Result<Json<ClothingResponse>, ApiError> {
+    info!("/user/{user_id}/clothing");
+    let items = fetch_clothing(&state, user_id)
+        .await
+        .ok_or_else(upstream_error)?;
+    Ok(Json(ClothingResponse {
+        ok: true,
+        user_id,
+        count: items.len(),
+        items,
+    }))
+}
+
+/// `GET /user/:id/donatables`: passes (de la caché si es posible), developer
+/// products y ropa.
+pub async fn get_donatables(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<u64>,
+    MaybeTenant(tenant): MaybeTenant,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    info!("/user/{user_id}/donatables");
+    let max_age = cache::max_age(&headers, tenant.as_ref(), &state.config);
+    let list = client::cached_full_list(&state, user_id, max_age, tenant.as_ref()).await;
+    let (passes, status) = match list {
+        Ok(list) => list,
+        Err(exhausted) => return Ok(exhausted),
+    };
+    let (clothing, products) = tokio::join!(
+        fetch_clothing(&state, user_id),
+        products::for_user(&state, user_id)
+    );
+    let clothing = clothing.ok_or_else(upstream_error)?;
+
+    let mut items: Vec<DonationItem> = passes
+        .into_iter()
+        .map(|p| DonationItem {
+            item_type: ItemType::GamePass,
+            id: p.id,
+            name: p.name,
+            price: p.price,
+        })
+        .chain(products.into_iter().map(|p| DonationItem {
+            item_type: ItemType::DeveloperProduct,
+            id: p.id,
+            name: p.name,
+            price: p.price,
+        }))
+        .chain(clothing)
+        .collect();
+    items.sort_by_key(|item| (item.price, item.id));
+    let mut response = Json(DonatablesResponse {
+        ok: true,
+        user_id,
+        count: items.len(),
+        items,
+    })
+    .into_response();
+    status.apply(response.headers_mut());
+    Ok(response)
+}
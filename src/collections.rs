@@ -0,0 +1,367 @@
+//! Colecciones de creadores con nombre (`/collection/:name/passes`): un
+//! evento de donaciones con un plantel rotativo de creadores pide los passes
+//! de todos a la vez. Los miembros se gestionan con `/admin/collections`.
+//!
+//! Con `COLLECTION_DIR` se persisten en disco, un JSON por colección.
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    admin::AdminAuth,
+    cache::{self, CacheStatus},
+    config::Config,
+    error::ApiError,
+    extract,
+    roblox::client,
+    tenant::MaybeTenant,
+    AppState,
+};
+
+const MAX_NAME_LEN: usize = 64;
+/// Miembros por colección: cada uno puede costar un escaneo completo.
+const MAX_MEMBERS: usize = 25;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Collection {
+    user_ids: Vec<u64>,
+    updated_at: DateTime<Utc>,
+}
+
+pub struct CollectionStore {
+    collections: Mutex<BTreeMap<String, Collection>>,
+    dir: Option<PathBuf>,
+}
+
+impl CollectionStore {
+    /// Crea el almacén y, si hay `COLLECTION_DIR`, recarga lo guardado.
+    pub fn new(config: &Config) -> Self {
+        let store = CollectionStore {
+            collections: Mutex::default(),
+            dir: config.collection_dir.clone(),
+        };
+        store.load();
+        store
+    }
+
+    /// Colecciones guardadas.
+    pub fn collections(&self) -> usize {
+        self.collections.lock().unwrap().len()
+    }
+
+    fn load(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut collections = self.collections.lock().unwrap();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .filter(|_| path.extension().is_some_and(|ext| ext == "json"))
+                .filter(|name| valid_name(name))
+            else {
+                continue;
+            };
+            let loaded = std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| {
+                    serde_json::from_slice::<Collection>(&bytes).map_err(|e| e.to_string())
+                });
+            match loaded {
+                Ok(collection) => {
+                    collections.insert(name.to_string(), collection);
+                }
+                Err(e) => warn!("Ignorando {}: {e}", path.display()),
+            }
+        }
+    }
+
+    fn persist(&self, name: &str, collection: Option<&Collection>) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let path = dir.join(format!("{name}.json"));
+        let written = match collection {
+            Some(collection) => std::fs::create_dir_all(dir).and_then(|()| {
+                let json = serde_json::to_vec(collection).map_err(std::io::Error::other)?;
+                let tmp = path.with_extension("json.tmp");
+                std::fs::write(&tmp, json)?;
+                std::fs::rename(&tmp, &path)
+            }),
+            None => std::fs::remove_file(&path).or_else(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => Ok(()),
+                _ => Err(e),
+            }),
+        };
+        if let Err(e) = written {
+            warn!("No se pudo guardar {}: {e}", path.display());
+        }
+    }
+
+    /// Guarda los miembros de una colección; devuelve si es nueva.
+    fn put(&self, name: &str, user_ids: Vec<u64>) -> (Collection, bool) {
+        let mut collections = self.collections.lock().unwrap();
+        let collection = Collection {
+            user_ids,
+            updated_at: Utc::now(),
+        };
+        let is_new = collections
+            .insert(name.to_string(), collection.clone())
+            .is_none();
+        self.persist(name, Some(&collection));
+        (collection, is_new)
+    }
+
+    fn get(&self, name: &str) -> Option<Collection> {
+        self.collections.lock().unwrap().get(name).cloned()
+    }
+
+    fn remove(&self, name: &str) -> bool {
+        let mut collections = self.collections.lock().unwrap();
+        let removed = collections.remove(name).is_some();
+        if removed {
+            self.persist(name, None);
+        }
+        removed
+    }
+
+    fn list(&self) -> Vec<(String, Collection)> {
+        let collections = self.collections.lock().unwrap();
+        collections
+            .iter()
+            .map(|(name, c)| (name.clone(), c.clone()))
+            .collect()
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+fn validate_name(name: &str) -> Result<(), ApiError> {
+    if valid_name(name) {
+        Ok(())
+    } else {
+        Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_COLLECTION",
+            format!(
+                "El nombre de colección debe tener entre 1 y {MAX_NAME_LEN} caracteres [a-z0-9-]"
+            ),
+        ))
+    }
+}
+
+fn collection_not_found(name: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::NOT_FOUND,
+        "COLLECTION_NOT_FOUND",
+        format!("No existe la colección '{name}'"),
+    )
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CollectionRequest {
+    user_ids: Vec<u64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionView {
+    name: String,
+    user_ids: Vec<u64>,
+    /// RFC 3339.
+    updated_at: String,
+}
+
+impl CollectionView {
+    fn new(name: String, collection: Collection) -> Self {
+        CollectionView {
+            name,
+            user_ids: collection.user_ids,
+            updated_at: collection.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CollectionResponse {
+    ok: bool,
+    collection: CollectionView,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CollectionListResponse {
+    ok: bool,
+    count: usize,
+    collections: Vec<CollectionView>,
+}
+
+/// Pass de un miembro de la colección.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionPass {
+    /// Creador del pass.
+    pub user_id: u64,
+    pub id: u64,
+    pub name: String,
+    pub price: i64,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionPassesResponse {
+    ok: bool,
+    collection: String,
+    user_ids: Vec<u64>,
+    count: usize,
+    /// Por miembro, en el orden de la colección.
+    passes: Vec<CollectionPass>,
+}
+
+/// `PUT /admin/collections/:name`: crea o reemplaza los miembros.
+pub async fn put_collection(
+    _: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    extract::Json(request): extract::Json<CollectionRequest>,
+) -> Result<(StatusCode, Json<CollectionResponse>), ApiError> {
+    validate_name(&name)?;
+    let mut user_ids: Vec<u64> = Vec::with_capacity(request.user_ids.len());
+    for id in request.user_ids {
+        if !user_ids.contains(&id) {
+            user_ids.push(id);
+        }
+    }
+    if user_ids.is_empty() || user_ids.len() > MAX_MEMBERS || user_ids.contains(&0) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_COLLECTION",
+            format!("userIds debe tener entre 1 y {MAX_MEMBERS} ids de usuario"),
+        ));
+    }
+
+    let (collection, created) = state.collections.put(&name, user_ids);
+    info!(
+        "Colección '{name}' guardada con {} miembros",
+        collection.user_ids.len()
+    );
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((
+        status,
+        Json(CollectionResponse {
+            ok: true,
+            collection: CollectionView::new(name, collection),
+        }),
+    ))
+}
+
+/// `DELETE /admin/collections/:name`
+pub async fn delete_collection(
+    _: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if !state.collections.remove(&name) {
+        return Err(collection_not_found(&name));
+    }
+    info!("Colección '{name}' borrada");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `GET /admin/collections`
+pub async fn list_collections(
+    _: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Json<CollectionListResponse> {
+    let collections: Vec<CollectionView> = state
+        .collections
+        .list()
+        .into_iter()
+        .map(|(name, c)| CollectionView::new(name, c))
+        .collect();
+    Json(CollectionListResponse {
+        ok: true,
+        count: collections.len(),
+        collections,
+    })
+}
+
+/// `GET /collection/:name/passes`: passes de todos los miembros.
+pub async fn collection_passes(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    MaybeTenant(tenant): MaybeTenant,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let collection = state
+        .collections
+        .get(&name)
+        .ok_or_else(|| collection_not_found(&name))?;
+    info!(
+        "/collection/{name}/passes ({} miembros)",
+        collection.user_ids.len()
+    );
+
+    let max_age = cache::max_age(&headers, tenant.as_ref(), &state.config);
+    let mut passes = Vec::new();
+    // Como en `/compare`.
+    let mut status = CacheStatus::Hit {
+        age: Duration::ZERO,
+    };
+    for &user_id in &collection.user_ids {
+        let list = client::cached_full_list(&state, user_id, max_age, tenant.as_ref()).await;
+        let (member_passes, member_status) = match list {
+            Ok(list) => list,
+            Err(exhausted) => return Ok(exhausted),
+        };
+        status = status.combine(member_status);
+        passes.extend(member_passes.into_iter().map(|p| CollectionPass {
+            user_id,
+            id: p.id,
+            name: p.name,
+            price: p.price,
+        }));
+    }
+
+    let mut response = Json(CollectionPassesResponse {
+        ok: true,
+        collection: name,
+        user_ids: collection.user_ids,
+        count: passes.len(),
+        passes,
+    })
+    .into_response();
+    status.apply(response.headers_mut());
+    Ok(response)
+}
@@ -0,0 +1,121 @@
+//! Comparación de varios usuarios (`/compare?userIds=1,2,3`): cuántos passes
+//! tiene cada uno, su rango de precios y el más barato, para que los juegos
+//! "hub" ordenen las cabinas por lo fácil que es donar a cada creador.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    cache::{self, CacheStatus},
+    error::ApiError,
+    extract::Query,
+    roblox::client,
+    snapshots::SnapshotPass,
+    tenant::MaybeTenant,
+    AppState,
+};
+
+/// Usuarios por consulta: cada uno puede costar un escaneo completo.
+const MAX_USERS: usize = 10;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompareQuery {
+    /// Lista separada por comas, p. ej. `1,2,3`.
+    user_ids: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserComparison {
+    pub user_id: u64,
+    pub count: usize,
+    /// `null` si el usuario no tiene passes.
+    pub min_price: Option<i64>,
+    pub max_price: Option<i64>,
+    pub cheapest_pass: Option<SnapshotPass>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompareResponse {
+    ok: bool,
+    /// En el mismo orden que `userIds`, sin repetidos.
+    users: Vec<UserComparison>,
+}
+
+fn parse_user_ids(raw: &str) -> Result<Vec<u64>, ApiError> {
+    let invalid = || {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_QUERY",
+            format!("userIds debe ser una lista de hasta {MAX_USERS} ids separados por comas"),
+        )
+    };
+    let parsed: Vec<u64> = raw
+        .split(',')
+        .map(|id| id.trim().parse::<u64>().ok().filter(|id| *id > 0))
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    let mut ids = Vec::with_capacity(parsed.len());
+    for id in parsed {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if ids.len() > MAX_USERS {
+        return Err(invalid());
+    }
+    Ok(ids)
+}
+
+/// `GET /compare?userIds=1,2,3`
+pub async fn compare(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CompareQuery>,
+    MaybeTenant(tenant): MaybeTenant,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let user_ids = parse_user_ids(&query.user_ids)?;
+    info!("/compare userIds={user_ids:?}");
+
+    let max_age = cache::max_age(&headers, tenant.as_ref(), &state.config);
+    let mut users = Vec::with_capacity(user_ids.len());
+    // Ver `CacheStatus::combine`.
+    let mut status = CacheStatus::Hit {
+        age: Duration::ZERO,
+    };
+    for user_id in user_ids {
+        let list = client::cached_full_list(&state, user_id, max_age, tenant.as_ref()).await;
+        let (passes, user_status) = match list {
+            Ok(list) => list,
+            Err(exhausted) => return Ok(exhausted),
+        };
+        status = status.combine(user_status);
+        let cheapest = passes.iter().min_by_key(|p| (p.price, p.id));
+        users.push(UserComparison {
+            user_id,
+            count: passes.len(),
+            min_price: cheapest.map(|p| p.price),
+            max_price: passes.iter().map(|p| p.price).max(),
+            cheapest_pass: cheapest.map(|p| SnapshotPass {
+                id: p.id,
+                name: p.name.clone(),
+                price: p.price,
+            }),
+        });
+    }
+
+    let mut response = Json(CompareResponse { ok: true, users }).into_response();
+    status.apply(response.headers_mut());
+    Ok(response)
+}
@@ -0,0 +1,1032 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    net::{IpAddr, Ipv4Addr},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::{
+    access_log::AccessLogFormat,
+    backoff::{Backoff, Jitter},
+    limiter::LimiterSettings,
+    models::FetchMode,
+    outbound_tags::{parse_tags, OutboundTag},
+    pricing::{self, PriceField},
+    queue::ShedPolicy,
+    roblox::catalog,
+    tenant::{load_api_keys, ApiKey},
+    upstream::Upstream,
+    views::Fields,
+};
+
+/// Configuración leída al arrancar: del entorno y, debajo, del fichero de
+/// `CONFIG_FILE` (ver `Config::load`).
+pub struct Config {
+    /// Fichero TOML con los ajustes (`CONFIG_FILE`): las mismas variables
+    /// que el entorno (`PORT = 8080`); una variable del entorno lo pisa.
+    pub config_file: Option<PathBuf>,
+    /// Dirección y puerto de escucha (`BIND_ADDR`, todas las interfaces por
+    /// defecto, y `PORT`).
+    pub bind_addr: IpAddr,
+    pub port: u16,
+    /// Opciones del socket de escucha: `TCP_REUSEADDR` (sí por defecto),
+    /// `TCP_REUSEPORT` (para varias instancias en el mismo puerto),
+    /// `TCP_NODELAY` (sí por defecto) y `TCP_BACKLOG`.
+    pub tcp_reuseaddr: bool,
+    pub tcp_reuseport: bool,
+    pub tcp_nodelay: bool,
+    pub tcp_backlog: i32,
+    /// Tiempo máximo de una petición según la ruta (ver `timeout`): escaneos
+    /// completos, rutas con alguna llamada a Roblox y el resto.
+    pub route_timeout_scan: Duration,
+    pub route_timeout_upstream: Duration,
+    pub route_timeout_default: Duration,
+    /// Token para `/admin/*`; sin él, la administración queda deshabilitada.
+    pub admin_token: Option<String>,
+    /// `MINIFY_JSON=true`: ignora `?pretty=1` y siempre responde compacto.
+    pub minify_json: bool,
+    /// Tope del cuerpo de `/user/:id/passes`; por encima se recorta la lista.
+    /// `0` desactiva el límite.
+    pub max_response_bytes: usize,
+    /// Máximo de juegos públicos a escanear por usuario (los más visitados
+    /// primero). `0` (por defecto) = sin límite.
+    pub max_universes: usize,
+    /// Valor por defecto de `?activeGamesOnly`.
+    pub active_games_only: bool,
+    /// Valores por defecto de `?thumbnails`, `?includeRemoved` y `?fields=`
+    /// (`DEFAULT_THUMBNAILS`, `DEFAULT_INCLUDE_REMOVED`, `DEFAULT_FIELDS`),
+    /// para que los clientes de este despliegue no tengan que pedirlos. Un
+    /// parámetro explícito, aunque sea `false` o vacío, manda.
+    pub default_thumbnails: bool,
+    pub default_include_removed: bool,
+    pub default_fields: Fields,
+    /// `assetType` que acepta el fallback del catálogo
+    /// (`CATALOG_FALLBACK_ASSET_TYPES`, separados por comas; por defecto
+    /// solo game passes, 46).
+    pub catalog_fallback_asset_types: Vec<u64>,
+    /// Campos de precio de Roblox por orden de preferencia
+    /// (`PRICE_SOURCE_ORDER`, p. ej. `PriceInRobux,Price`; ver `pricing`).
+    pub price_sources: Vec<PriceField>,
+    /// Días sin actualizar tras los que un juego cuenta como abandonado.
+    pub active_game_days: i64,
+    /// Valor por defecto de `?mode=` (`sequential` o `race`).
+    pub fetch_mode: FetchMode,
+    /// Si las demás fuentes no dan nada, buscar en el inventario público del
+    /// usuario (`INVENTORY_FALLBACK`).
+    pub inventory_fallback: bool,
+    /// Consultas de detalles a economy por escaneo del inventario, sin
+    /// contar las que ya están en la caché de precios
+    /// (`INVENTORY_MAX_LOOKUPS`).
+    pub inventory_max_lookups: usize,
+    /// Páginas que se siguen como máximo en los listados de Roblox
+    /// (`UPSTREAM_MAX_PAGES`).
+    pub upstream_max_pages: usize,
+    /// Máximo de páginas (de 100) de la lista de passes de un juego
+    /// (`GAME_PASSES_MAX_PAGES`, por defecto `UPSTREAM_MAX_PAGES`). Si quedan
+    /// más, la respuesta lo avisa en `warnings`.
+    pub game_passes_max_pages: usize,
+    /// Anotar en la cuarentena lo que Roblox devuelve con una forma
+    /// desconocida en lugar de omitirlo sin más (`STRICT_UPSTREAM`).
+    pub strict_upstream: bool,
+    /// Llamadas simultáneas de un mismo escaneo (listas de passes de cada
+    /// juego y sus precios), `SCAN_CONCURRENCY`. El limitador global sigue
+    /// mandando por encima.
+    pub scan_concurrency: usize,
+    /// Plazo de un escaneo de `/user/:id/passes` (`SCAN_DEADLINE_SECS`; 0 lo
+    /// desactiva): al vencer se responde con lo recogido y `partial: true`.
+    pub scan_deadline: Duration,
+    /// Usuarios por llamada a `POST /users/passes` (`BATCH_MAX_USERS`).
+    pub batch_max_users: usize,
+    /// Límites del control adaptativo (AIMD) de peticiones simultáneas a
+    /// Roblox: arranca en `OUTBOUND_INITIAL_INFLIGHT` y se mueve entre
+    /// `OUTBOUND_MIN_INFLIGHT` y `OUTBOUND_MAX_INFLIGHT`.
+    pub outbound_min_inflight: usize,
+    pub outbound_max_inflight: usize,
+    pub outbound_initial_inflight: usize,
+    /// Latencia por encima de la cual una respuesta cuenta como sobrecarga.
+    pub outbound_latency_target: Duration,
+    /// Factor de bajada del tope ante sobrecarga (`0.5` = a la mitad).
+    pub outbound_decrease_factor: f64,
+    /// `HEDGE_REQUESTS=true` activa las peticiones duplicadas para la cola
+    /// de latencia.
+    pub hedge_requests: bool,
+    /// El hedge se lanza tras `p95 * HEDGE_P95_FACTOR`...
+    pub hedge_p95_factor: f64,
+    /// ...y nunca antes de `HEDGE_MIN_DELAY_MS`.
+    pub hedge_min_delay: Duration,
+    /// Espera entre sondeos de un upstream en mantenimiento:
+    /// `COOLDOWN_JITTER` (`none`, `full`, `equal`, `decorrelated`),
+    /// `COOLDOWN_BASE_SECS` y `COOLDOWN_MAX_SECS`.
+    pub cooldown_backoff: Backoff,
+    /// Reintentos de una llamada a Roblox tras un error de red o un 429/503
+    /// (`UPSTREAM_RETRIES`) y la espera entre ellos: `UPSTREAM_RETRY_JITTER`
+    /// (`full` por defecto), `UPSTREAM_RETRY_BASE_MS` y
+    /// `UPSTREAM_RETRY_MAX_MS`.
+    pub upstream_retries: u32,
+    pub upstream_retry_backoff: Backoff,
+    /// Tiempo máximo de una llamada a Roblox (`UPSTREAM_TIMEOUT_SECS`) y de
+    /// su conexión (`UPSTREAM_CONNECT_TIMEOUT_SECS`).
+    pub upstream_timeout: Duration,
+    pub upstream_connect_timeout: Duration,
+    /// `User-Agent` de las llamadas a Roblox (`UPSTREAM_USER_AGENT`).
+    pub upstream_user_agent: String,
+    /// Conexiones que se mantienen abiertas con cada host de Roblox
+    /// (`UPSTREAM_WARM_CONNECTIONS`, 0 = sin precalentar).
+    pub upstream_warm_connections: usize,
+    /// URL base de cada API de Roblox, sin `/` final: `UPSTREAM_BASE_URL`,
+    /// una plantilla con `{service}` (`https://{service}.roblox.com` por
+    /// defecto; sin `{service}`, todas al mismo servidor, como un mock), y
+    /// `UPSTREAM_GAMES_URL`, `UPSTREAM_ECONOMY_URL`, ... para una sola (ver
+    /// `Upstream::url_var`). Ver `upstream_url`.
+    pub upstream_base_url: String,
+    pub upstream_base_urls: HashMap<Upstream, String>,
+    /// Dominio espejo de `roblox.com` (`UPSTREAM_MIRROR_DOMAIN`, p. ej.
+    /// `roproxy.com`) al que pasar un upstream cuando Roblox pide challenge.
+    pub upstream_mirror_domain: Option<String>,
+    /// Cuánto se sigue usando el espejo antes de volver a probar Roblox
+    /// (`UPSTREAM_MIRROR_SECS`).
+    pub upstream_mirror_duration: Duration,
+    /// Cabeceras o parámetros que se añaden a las llamadas hacia ciertos
+    /// hosts, como la clave de un espejo (`UPSTREAM_TAGS`, ver
+    /// `outbound_tags`).
+    pub upstream_tags: Vec<OutboundTag>,
+    /// Tasa de error de Roblox a partir de la que se entra en cada nivel de
+    /// degradación (`DEGRADATION_THRESHOLDS=0.1,0.25,0.5`; ver `degradation`).
+    pub degradation_thresholds: [f64; 3],
+    /// Capacidad de cada cola de trabajo en segundo plano.
+    pub background_queue_max: usize,
+    /// `BACKGROUND_SHED_POLICY`: `drop-oldest` (por defecto) o `reject`.
+    pub background_shed_policy: ShedPolicy,
+    /// Carpeta de los informes de pánico (`CRASH_REPORT_DIR`).
+    pub crash_report_dir: PathBuf,
+    /// Si está, cada pánico se envía también a Sentry.
+    pub sentry_dsn: Option<String>,
+    /// Claves de API de los tenants (`API_KEYS` y `API_KEYS_FILE`, ver
+    /// `tenant`).
+    pub api_keys: Vec<ApiKey>,
+    pub api_keys_file: Option<PathBuf>,
+    /// Exigir `X-Api-Key` en las rutas públicas (`REQUIRE_API_KEY`), salvo
+    /// desde loopback con `API_KEY_BYPASS_LOOPBACK` (desarrollo local).
+    pub require_api_key: bool,
+    pub api_key_bypass_loopback: bool,
+    /// Usuarios vigilados por clave, salvo que la clave indique otro máximo.
+    pub watch_limit_per_key: usize,
+    /// Intervalo de refresco de una vigilancia si no se indica...
+    pub watch_default_interval: Duration,
+    /// ...y el mínimo y el máximo aceptados.
+    pub watch_min_interval: Duration,
+    pub watch_max_interval: Duration,
+    /// Carpeta donde persistir las fotos de passes (`SNAPSHOT_DIR`; vacío =
+    /// solo en memoria).
+    pub snapshot_dir: Option<PathBuf>,
+    /// Fotos que se conservan por usuario.
+    pub snapshot_max_per_user: usize,
+    /// Antigüedad máxima de una foto (`SNAPSHOT_MAX_AGE_DAYS`, 0 = sin límite).
+    pub snapshot_max_age: Option<Duration>,
+    /// Antigüedad aceptable por defecto de una lista en caché
+    /// (`CACHE_TTL_SECS`); cada petición puede pedir otra con `max-age`.
+    pub cache_ttl: Duration,
+    /// Cuánto se conserva una entrada en caché (`CACHE_RETENTION_SECS`): el
+    /// `max-age` más alto que se puede llegar a servir.
+    pub cache_retention: Duration,
+    /// Redis donde compartir la caché entre instancias (`CACHE_REDIS_URL`,
+    /// con la feature `redis`; vacío = cada instancia en memoria) y prefijo
+    /// de sus claves (`CACHE_REDIS_PREFIX`).
+    pub cache_redis_url: Option<String>,
+    pub cache_redis_prefix: String,
+    /// Cuánto se reutilizan los detalles de economy de un pass, y con ellos
+    /// su precio (`PRICE_CACHE_TTL_SECS`, 0 = siempre se piden).
+    pub price_cache_ttl: Duration,
+    /// Escaneos frescos por minuto y clave de API antes de pasar a servir
+    /// solo caché (`FRESH_FETCHES_PER_MINUTE`, 0 = sin límite).
+    pub fresh_fetches_per_minute: u32,
+    /// Peticiones por segundo y por IP de cliente a las rutas que llaman a
+    /// Roblox (`RATE_LIMIT_RPS`, 0 = sin límite), con ráfagas de hasta
+    /// `RATE_LIMIT_BURST`. `RATE_LIMIT_TRUST_FORWARDED` toma la IP de
+    /// `X-Forwarded-For` (ver `ratelimit`).
+    pub rate_limit_rps: f64,
+    pub rate_limit_burst: u32,
+    pub rate_limit_trust_forwarded: bool,
+    /// Clave de la firma de los cursores de paginación (`CURSOR_SECRET`;
+    /// sin ella, una aleatoria por proceso) y cuánto se guarda una lista
+    /// paginada (`CURSOR_TTL_SECS`).
+    pub cursor_secret: Option<String>,
+    pub cursor_ttl: Duration,
+    /// Tras `POST /admin/drain`, cuánto esperar con `/readyz` en 503 antes
+    /// de dejar de aceptar conexiones (`DRAIN_GRACE_SECS`).
+    pub drain_grace: Duration,
+    /// Ventana en la que un GET repetido por el mismo cliente recibe la
+    /// respuesta del primero en vez de repetirse (`DEDUPE_WINDOW_SECS`; 0 =
+    /// desactivado).
+    pub dedupe_window: Duration,
+    /// Carpeta donde persistir las cabinas (`BOOTH_DIR`; vacío = solo en
+    /// memoria).
+    pub booth_dir: Option<PathBuf>,
+    /// Cabinas por clave de API.
+    pub booth_limit_per_key: usize,
+    /// Tamaño máximo del JSON de una cabina.
+    pub booth_max_bytes: usize,
+    /// Carpeta donde persistir las colecciones (`COLLECTION_DIR`; vacío =
+    /// solo en memoria).
+    pub collection_dir: Option<PathBuf>,
+    /// Carpeta donde persistir las campañas y las compras informadas
+    /// (`CAMPAIGN_DIR`; vacío = solo en memoria).
+    pub campaign_dir: Option<PathBuf>,
+    /// Compras registradas por clave de API (`DONATION_LIMIT_PER_KEY`).
+    pub donation_limit_per_key: usize,
+    /// Formato del log de acceso (`ACCESS_LOG=json|combined`; sin definir,
+    /// desactivado).
+    pub access_log: Option<AccessLogFormat>,
+    /// Archivo del log de acceso (`ACCESS_LOG_FILE`); sin definir, stdout.
+    pub access_log_file: Option<PathBuf>,
+    /// Usuario con el que hacer una llamada canario a Roblox en el self-check
+    /// del arranque (`SELFCHECK_CANARY_USER_ID`; sin definir, no se llama).
+    pub selfcheck_canary_user_id: Option<u64>,
+    /// Usuario cuyos juegos (y catálogo) consulta `/readyz` para comprobar
+    /// cada fuente (`READINESS_PROBE_USER_ID`) y cuánto espera a cada una
+    /// (`READINESS_PROBE_TIMEOUT_SECS`).
+    pub readiness_probe_user_id: u64,
+    pub readiness_probe_timeout: Duration,
+}
+
+impl Config {
+    /// Carga `CONFIG_FILE` (si está definida) y lee la configuración con
+    /// `from_env`. Error si el fichero no se puede leer o no es TOML plano.
+    pub fn load() -> Result<Config, String> {
+        if let Some(path) = env::var_os("CONFIG_FILE").filter(|f| !f.is_empty()) {
+            let values = read_file(Path::new(&path))?;
+            // Solo se carga una vez por proceso; una segunda llamada reusa
+            // los valores ya leídos.
+            let _ = FILE_VALUES.set(values);
+        }
+        let config = Config::from_env();
+        let known: HashSet<&str> = config.effective().iter().map(|s| s.name).collect();
+        for name in FILE_VALUES.get().into_iter().flat_map(HashMap::keys) {
+            if ENV_ONLY.contains(&name.as_str()) {
+                warn!("CONFIG_FILE: {name} solo se lee del entorno; se ignora");
+            } else if !known.contains(name.as_str()) && !OTHER_VARS.contains(&name.as_str()) {
+                warn!("CONFIG_FILE: {name} no es un ajuste conocido; se ignora");
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn from_env() -> Config {
+        let api_keys_file = var("API_KEYS_FILE")
+            .ok()
+            .filter(|f| !f.is_empty())
+            .map(PathBuf::from);
+        let mut config = Config {
+            config_file: env::var_os("CONFIG_FILE")
+                .filter(|f| !f.is_empty())
+                .map(PathBuf::from),
+            bind_addr: env_parse("BIND_ADDR", IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            port: env_parse("PORT", 8080),
+            tcp_reuseaddr: env_bool("TCP_REUSEADDR", true),
+            tcp_reuseport: env_flag("TCP_REUSEPORT"),
+            tcp_nodelay: env_bool("TCP_NODELAY", true),
+            tcp_backlog: env_parse("TCP_BACKLOG", 1024),
+            route_timeout_scan: Duration::from_secs(
+                env_parse("ROUTE_TIMEOUT_SCAN_SECS", 60).max(1),
+            ),
+            route_timeout_upstream: Duration::from_secs(
+                env_parse("ROUTE_TIMEOUT_UPSTREAM_SECS", 20).max(1),
+            ),
+            route_timeout_default: Duration::from_secs(env_parse("ROUTE_TIMEOUT_SECS", 10).max(1)),
+            admin_token: var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty()),
+            minify_json: env_flag("MINIFY_JSON"),
+            max_response_bytes: env_parse("MAX_RESPONSE_BYTES", 256 * 1024),
+            max_universes: env_parse("MAX_UNIVERSES", 0),
+            active_games_only: env_flag("ACTIVE_GAMES_ONLY"),
+            default_thumbnails: env_flag("DEFAULT_THUMBNAILS"),
+            default_include_removed: env_flag("DEFAULT_INCLUDE_REMOVED"),
+            default_fields: match Fields::parse(var("DEFAULT_FIELDS").ok().as_deref()) {
+                Ok(fields) => fields,
+                Err(_) => {
+                    note_invalid("DEFAULT_FIELDS");
+                    Fields::default()
+                }
+            },
+            catalog_fallback_asset_types: match var("CATALOG_FALLBACK_ASSET_TYPES") {
+                Ok(raw) if !raw.is_empty() => parse_asset_types(&raw).unwrap_or_else(|| {
+                    note_invalid("CATALOG_FALLBACK_ASSET_TYPES");
+                    vec![catalog::GAME_PASS]
+                }),
+                _ => vec![catalog::GAME_PASS],
+            },
+            price_sources: match var("PRICE_SOURCE_ORDER") {
+                Ok(raw) if !raw.is_empty() => {
+                    pricing::parse_price_sources(&raw).unwrap_or_else(|| {
+                        warn!("PRICE_SOURCE_ORDER: '{raw}' no es una lista de campos de precio sin repetir; se usa el orden por defecto");
+                        note_invalid("PRICE_SOURCE_ORDER");
+                        pricing::PRICE_FIELDS.to_vec()
+                    })
+                }
+                _ => pricing::PRICE_FIELDS.to_vec(),
+            },
+            active_game_days: env_parse("ACTIVE_GAME_DAYS", 180),
+            inventory_fallback: env_bool("INVENTORY_FALLBACK", true),
+            inventory_max_lookups: env_parse("INVENTORY_MAX_LOOKUPS", 20),
+            fetch_mode: match var("FETCH_MODE").as_deref() {
+                Ok("race") => FetchMode::Race,
+                Ok("sequential") | Err(_) => FetchMode::Sequential,
+                Ok(_) => {
+                    note_invalid("FETCH_MODE");
+                    FetchMode::Sequential
+                }
+            },
+            upstream_max_pages: env_parse("UPSTREAM_MAX_PAGES", 10).max(1),
+            game_passes_max_pages: 0,
+            strict_upstream: env_flag("STRICT_UPSTREAM"),
+            scan_concurrency: env_parse("SCAN_CONCURRENCY", 8).max(1),
+            scan_deadline: Duration::from_secs(env_parse("SCAN_DEADLINE_SECS", 10)),
+            batch_max_users: env_parse("BATCH_MAX_USERS", 50).max(1),
+            outbound_min_inflight: env_parse("OUTBOUND_MIN_INFLIGHT", 2).max(1),
+            outbound_max_inflight: env_parse("OUTBOUND_MAX_INFLIGHT", 64).max(1),
+            outbound_initial_inflight: env_parse("OUTBOUND_INITIAL_INFLIGHT", 16),
+            outbound_latency_target: Duration::from_millis(env_parse(
+                "OUTBOUND_LATENCY_TARGET_MS",
+                2000,
+            )),
+            outbound_decrease_factor: env_parse("OUTBOUND_DECREASE_FACTOR", 0.5_f64)
+                .clamp(0.1, 0.95),
+            hedge_requests: env_flag("HEDGE_REQUESTS"),
+            hedge_p95_factor: env_parse("HEDGE_P95_FACTOR", 1.0),
+            hedge_min_delay: Duration::from_millis(env_parse("HEDGE_MIN_DELAY_MS", 50)),
+            cooldown_backoff: env_backoff(
+                "COOLDOWN",
+                TimeUnit::Secs,
+                Backoff {
+                    jitter: Jitter::None,
+                    base: Duration::from_secs(10),
+                    max: Duration::from_secs(300),
+                },
+            ),
+            upstream_retries: env_parse("UPSTREAM_RETRIES", 1),
+            upstream_retry_backoff: env_backoff(
+                "UPSTREAM_RETRY",
+                TimeUnit::Millis,
+                Backoff {
+                    jitter: Jitter::Full,
+                    base: Duration::from_millis(200),
+                    max: Duration::from_secs(2),
+                },
+            ),
+            upstream_timeout: Duration::from_secs(env_parse("UPSTREAM_TIMEOUT_SECS", 15).max(1)),
+            upstream_connect_timeout: Duration::from_secs(
+                env_parse("UPSTREAM_CONNECT_TIMEOUT_SECS", 5).max(1),
+            ),
+            upstream_user_agent: var("UPSTREAM_USER_AGENT")
+                .ok()
+                .filter(|ua| !ua.is_empty())
+                .unwrap_or_else(|| {
+                    concat!("donations_api/", env!("CARGO_PKG_VERSION")).to_string()
+                }),
+            upstream_warm_connections: env_parse("UPSTREAM_WARM_CONNECTIONS", 2),
+            upstream_base_url: url_var("UPSTREAM_BASE_URL")
+                .unwrap_or_else(|| DEFAULT_UPSTREAM_BASE_URL.to_string()),
+            upstream_base_urls: HashMap::new(),
+            upstream_mirror_domain: var("UPSTREAM_MIRROR_DOMAIN").ok().filter(|d| !d.is_empty()),
+            upstream_mirror_duration: Duration::from_secs(env_parse("UPSTREAM_MIRROR_SECS", 600)),
+            upstream_tags: match var("UPSTREAM_TAGS") {
+                Ok(raw) => {
+                    let (tags, invalid) = parse_tags(&raw);
+                    if invalid {
+                        note_invalid("UPSTREAM_TAGS");
+                    }
+                    tags
+                }
+                Err(_) => Vec::new(),
+            },
+            degradation_thresholds: match var("DEGRADATION_THRESHOLDS") {
+                Ok(raw) if !raw.is_empty() => parse_thresholds(&raw).unwrap_or_else(|| {
+                    note_invalid("DEGRADATION_THRESHOLDS");
+                    DEFAULT_DEGRADATION_THRESHOLDS
+                }),
+                _ => DEFAULT_DEGRADATION_THRESHOLDS,
+            },
+            background_queue_max: env_parse("BACKGROUND_QUEUE_MAX", 32),
+            background_shed_policy: match var("BACKGROUND_SHED_POLICY") {
+                Ok(v) => v.parse().unwrap_or_else(|e| {
+                    warn!("BACKGROUND_SHED_POLICY: {e}, usando drop-oldest");
+                    note_invalid("BACKGROUND_SHED_POLICY");
+                    ShedPolicy::DropOldest
+                }),
+                Err(_) => ShedPolicy::DropOldest,
+            },
+            crash_report_dir: var("CRASH_REPORT_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("crash-reports")),
+            sentry_dsn: var("SENTRY_DSN").ok().filter(|d| !d.is_empty()),
+            api_keys: load_api_keys(var("API_KEYS").ok().as_deref(), api_keys_file.as_deref()),
+            api_keys_file,
+            require_api_key: env_flag("REQUIRE_API_KEY"),
+            api_key_bypass_loopback: env_flag("API_KEY_BYPASS_LOOPBACK"),
+            watch_limit_per_key: env_parse("WATCH_LIMIT_PER_KEY", 50),
+            watch_default_interval: Duration::from_secs(env_parse(
+                "WATCH_DEFAULT_INTERVAL_SECS",
+                300,
+            )),
+            watch_min_interval: Duration::from_secs(env_parse("WATCH_MIN_INTERVAL_SECS", 60)),
+            watch_max_interval: Duration::from_secs(env_parse("WATCH_MAX_INTERVAL_SECS", 604_800)),
+            snapshot_dir: match var("SNAPSHOT_DIR") {
+                Ok(dir) if dir.is_empty() => None,
+                Ok(dir) => Some(PathBuf::from(dir)),
+                Err(_) => Some(PathBuf::from("snapshots")),
+            },
+            cache_ttl: Duration::from_secs(env_parse("CACHE_TTL_SECS", 300)),
+            cache_retention: Duration::from_secs(env_parse("CACHE_RETENTION_SECS", 3600)),
+            cache_redis_url: var("CACHE_REDIS_URL").ok().filter(|u| !u.is_empty()),
+            cache_redis_prefix: var("CACHE_REDIS_PREFIX")
+                .unwrap_or_else(|_| "donations_api:".to_string()),
+            price_cache_ttl: Duration::from_secs(env_parse("PRICE_CACHE_TTL_SECS", 60)),
+            fresh_fetches_per_minute: env_parse("FRESH_FETCHES_PER_MINUTE", 60),
+            rate_limit_rps: env_parse("RATE_LIMIT_RPS", 0.0_f64).max(0.0),
+            rate_limit_burst: env_parse("RATE_LIMIT_BURST", 20).max(1),
+            rate_limit_trust_forwarded: env_flag("RATE_LIMIT_TRUST_FORWARDED"),
+            cursor_secret: var("CURSOR_SECRET").ok().filter(|s| !s.is_empty()),
+            cursor_ttl: Duration::from_secs(env_parse("CURSOR_TTL_SECS", 900).max(1)),
+            drain_grace: Duration::from_secs(env_parse("DRAIN_GRACE_SECS", 10)),
+            dedupe_window: Duration::from_secs(env_parse("DEDUPE_WINDOW_SECS", 0)),
+            booth_dir: match var("BOOTH_DIR") {
+                Ok(dir) if dir.is_empty() => None,
+                Ok(dir) => Some(PathBuf::from(dir)),
+                Err(_) => Some(PathBuf::from("booths")),
+            },
+            booth_limit_per_key: env_parse("BOOTH_LIMIT_PER_KEY", 100),
+            booth_max_bytes: env_parse("BOOTH_MAX_BYTES", 4096),
+            collection_dir: match var("COLLECTION_DIR") {
+                Ok(dir) if dir.is_empty() => None,
+                Ok(dir) => Some(PathBuf::from(dir)),
+                Err(_) => Some(PathBuf::from("collections")),
+            },
+            campaign_dir: match var("CAMPAIGN_DIR") {
+                Ok(dir) if dir.is_empty() => None,
+                Ok(dir) => Some(PathBuf::from(dir)),
+                Err(_) => Some(PathBuf::from("campaigns")),
+            },
+            donation_limit_per_key: env_parse("DONATION_LIMIT_PER_KEY", 100_000),
+            access_log: match var("ACCESS_LOG").as_deref() {
+                Ok("json") => Some(AccessLogFormat::Json),
+                Ok("combined") => Some(AccessLogFormat::Combined),
+                Ok("" | "off") | Err(_) => None,
+                Ok(_) => {
+                    note_invalid("ACCESS_LOG");
+                    None
+                }
+            },
+            selfcheck_canary_user_id: var("SELFCHECK_CANARY_USER_ID")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .and_then(|v| {
+                    v.parse().ok().or_else(|| {
+                        note_invalid("SELFCHECK_CANARY_USER_ID");
+                        None
+                    })
+                }),
+            readiness_probe_user_id: env_parse("READINESS_PROBE_USER_ID", 1).max(1),
+            readiness_probe_timeout: Duration::from_secs(
+                env_parse("READINESS_PROBE_TIMEOUT_SECS", 2).max(1),
+            ),
+            access_log_file: var("ACCESS_LOG_FILE")
+                .ok()
+                .filter(|f| !f.is_empty())
+                .map(PathBuf::from),
+            snapshot_max_per_user: env_parse("SNAPSHOT_MAX_PER_USER", 20),
+            snapshot_max_age: match env_parse::<u64>("SNAPSHOT_MAX_AGE_DAYS", 30) {
+                0 => None,
+                days => Some(Duration::from_secs(days * 24 * 3600)),
+            },
+        };
+        config.outbound_max_inflight = config
+            .outbound_max_inflight
+            .max(config.outbound_min_inflight);
+        config.upstream_base_urls = upstream_base_urls(&config.upstream_base_url);
+        config.game_passes_max_pages =
+            env_parse("GAME_PASSES_MAX_PAGES", config.upstream_max_pages).max(1);
+        config
+    }
+
+    pub fn limiter_settings(&self) -> LimiterSettings {
+        LimiterSettings {
+            min: self.outbound_min_inflight,
+            max: self.outbound_max_inflight,
+            initial: self.outbound_initial_inflight,
+            latency_target: self.outbound_latency_target,
+            decrease_factor: self.outbound_decrease_factor,
+        }
+    }
+}
+
+/// De dónde sale el valor de un ajuste.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// Definido en el entorno.
+    Env,
+    /// Definido en `CONFIG_FILE` (y no en el entorno).
+    File,
+    /// Sin definir: valor por defecto.
+    Default,
+    /// Definido pero no válido: se usa el valor por defecto (o, en
+    /// `UPSTREAM_TAGS`, solo las entradas válidas).
+    Invalid,
+}
+
+/// Un ajuste tal como quedó tras leer el entorno, con el nombre de su
+/// variable.
+#[derive(Serialize, JsonSchema)]
+pub struct Setting {
+    name: &'static str,
+    value: Value,
+    source: Source,
+}
+
+/// Para los secretos: solo si están definidos.
+fn redacted<T>(secret: &Option<T>) -> Value {
+    match secret {
+        Some(_) => json!("…"),
+        None => Value::Null,
+    }
+}
+
+fn path_value(path: &Option<PathBuf>) -> Value {
+    json!(path.as_ref().map(|p| p.display().to_string()))
+}
+
+impl Config {
+    /// Configuración efectiva, en el orden de `Config`, para
+    /// `/admin/config`. Los secretos salen como `…`.
+    pub fn effective(&self) -> Vec<Setting> {
+        let invalid = invalid_vars();
+        let setting = |name: &'static str, value: Value| {
+            let source = if invalid.iter().any(|v| v == name) {
+                Source::Invalid
+            } else if env::var(name).is_ok() {
+                Source::Env
+            } else if file_value(name).is_some() {
+                Source::File
+            } else {
+                Source::Default
+            };
+            Setting {
+                name,
+                value,
+                source,
+            }
+        };
+        let api_keys: Vec<Value> = self
+            .api_keys
+            .iter()
+            .map(|k| {
+                json!({
+                    "id": k.id,
+                    "key": "…",
+                    "maxWatches": k.max_watches,
+                    "maxAgeSecs": k.max_age.map(|d| d.as_secs()),
+                    "freshPerMinute": k.fresh_per_minute,
+                    "rps": k.rps,
+                })
+            })
+            .collect();
+        let upstream_tags: Vec<String> = self.upstream_tags.iter().map(|t| t.redacted()).collect();
+
+        let mut settings = vec![
+            setting("CONFIG_FILE", path_value(&self.config_file)),
+            setting("BIND_ADDR", json!(self.bind_addr)),
+            setting("PORT", json!(self.port)),
+            setting("TCP_REUSEADDR", json!(self.tcp_reuseaddr)),
+            setting("TCP_REUSEPORT", json!(self.tcp_reuseport)),
+            setting("TCP_NODELAY", json!(self.tcp_nodelay)),
+            setting("TCP_BACKLOG", json!(self.tcp_backlog)),
+            setting(
+                "ROUTE_TIMEOUT_SCAN_SECS",
+                json!(self.route_timeout_scan.as_secs()),
+            ),
+            setting(
+                "ROUTE_TIMEOUT_UPSTREAM_SECS",
+                json!(self.route_timeout_upstream.as_secs()),
+            ),
+            setting(
+                "ROUTE_TIMEOUT_SECS",
+                json!(self.route_timeout_default.as_secs()),
+            ),
+            setting("ADMIN_TOKEN", redacted(&self.admin_token)),
+            setting("MINIFY_JSON", json!(self.minify_json)),
+            setting("MAX_RESPONSE_BYTES", json!(self.max_response_bytes)),
+            setting("MAX_UNIVERSES", json!(self.max_universes)),
+            setting("ACTIVE_GAMES_ONLY", json!(self.active_games_only)),
+            setting("DEFAULT_THUMBNAILS", json!(self.default_thumbnails)),
+            setting(
+                "DEFAULT_INCLUDE_REMOVED",
+                json!(self.default_include_removed),
+            ),
+            setting("DEFAULT_FIELDS", json!(self.default_fields.names())),
+            setting(
+                "CATALOG_FALLBACK_ASSET_TYPES",
+                json!(self.catalog_fallback_asset_types),
+            ),
+            setting("PRICE_SOURCE_ORDER", json!(self.price_sources)),
+            setting("ACTIVE_GAME_DAYS", json!(self.active_game_days)),
+            setting(
+                "FETCH_MODE",
+                json!(match self.fetch_mode {
+                    FetchMode::Sequential => "sequential",
+                    FetchMode::Race => "race",
+                }),
+            ),
+            setting("INVENTORY_FALLBACK", json!(self.inventory_fallback)),
+            setting("INVENTORY_MAX_LOOKUPS", json!(self.inventory_max_lookups)),
+            setting("UPSTREAM_MAX_PAGES", json!(self.upstream_max_pages)),
+            setting("GAME_PASSES_MAX_PAGES", json!(self.game_passes_max_pages)),
+            setting("STRICT_UPSTREAM", json!(self.strict_upstream)),
+            setting("SCAN_CONCURRENCY", json!(self.scan_concurrency)),
+            setting("SCAN_DEADLINE_SECS", json!(self.scan_deadline.as_secs())),
+            setting("BATCH_MAX_USERS", json!(self.batch_max_users)),
+            setting("OUTBOUND_MIN_INFLIGHT", json!(self.outbound_min_inflight)),
+            setting("OUTBOUND_MAX_INFLIGHT", json!(self.outbound_max_inflight)),
+            setting(
+                "OUTBOUND_INITIAL_INFLIGHT",
+                json!(self.outbound_initial_inflight),
+            ),
+            setting(
+                "OUTBOUND_LATENCY_TARGET_MS",
+                json!(self.outbound_latency_target.as_millis() as u64),
+            ),
+            setting(
+                "OUTBOUND_DECREASE_FACTOR",
+                json!(self.outbound_decrease_factor),
+            ),
+            setting("HEDGE_REQUESTS", json!(self.hedge_requests)),
+            setting("HEDGE_P95_FACTOR", json!(self.hedge_p95_factor)),
+            setting(
+                "HEDGE_MIN_DELAY_MS",
+                json!(self.hedge_min_delay.as_millis() as u64),
+            ),
+            setting(
+                "COOLDOWN_JITTER",
+                json!(self.cooldown_backoff.jitter.as_str()),
+            ),
+            setting(
+                "COOLDOWN_BASE_SECS",
+                json!(self.cooldown_backoff.base.as_secs()),
+            ),
+            setting(
+                "COOLDOWN_MAX_SECS",
+                json!(self.cooldown_backoff.max.as_secs()),
+            ),
+            setting("UPSTREAM_RETRIES", json!(self.upstream_retries)),
+            setting(
+                "UPSTREAM_RETRY_JITTER",
+                json!(self.upstream_retry_backoff.jitter.as_str()),
+            ),
+            setting(
+                "UPSTREAM_RETRY_BASE_MS",
+                json!(self.upstream_retry_backoff.base.as_millis() as u64),
+            ),
+            setting(
+                "UPSTREAM_RETRY_MAX_MS",
+                json!(self.upstream_retry_backoff.max.as_millis() as u64),
+            ),
+            setting(
+                "UPSTREAM_TIMEOUT_SECS",
+                json!(self.upstream_timeout.as_secs()),
+            ),
+            setting(
+                "UPSTREAM_CONNECT_TIMEOUT_SECS",
+                json!(self.upstream_connect_timeout.as_secs()),
+            ),
+            setting("UPSTREAM_USER_AGENT", json!(self.upstream_user_agent)),
+            setting(
+                "UPSTREAM_WARM_CONNECTIONS",
+                json!(self.upstream_warm_connections),
+            ),
+            setting("UPSTREAM_BASE_URL", json!(self.upstream_base_url)),
+        ];
+        settings.extend(Upstream::ALL.map(|u| setting(u.url_var(), json!(self.upstream_url(u)))));
+        settings.extend([
+            setting("UPSTREAM_MIRROR_DOMAIN", json!(self.upstream_mirror_domain)),
+            setting(
+                "UPSTREAM_MIRROR_SECS",
+                json!(self.upstream_mirror_duration.as_secs()),
+            ),
+            setting("UPSTREAM_TAGS", json!(upstream_tags)),
+            setting("DEGRADATION_THRESHOLDS", json!(self.degradation_thresholds)),
+            setting("BACKGROUND_QUEUE_MAX", json!(self.background_queue_max)),
+            setting("BACKGROUND_SHED_POLICY", json!(self.background_shed_policy)),
+            setting(
+                "CRASH_REPORT_DIR",
+                json!(self.crash_report_dir.display().to_string()),
+            ),
+            setting("SENTRY_DSN", redacted(&self.sentry_dsn)),
+            setting("API_KEYS", json!(api_keys)),
+            setting("API_KEYS_FILE", path_value(&self.api_keys_file)),
+            setting("REQUIRE_API_KEY", json!(self.require_api_key)),
+            setting(
+                "API_KEY_BYPASS_LOOPBACK",
+                json!(self.api_key_bypass_loopback),
+            ),
+            setting("WATCH_LIMIT_PER_KEY", json!(self.watch_limit_per_key)),
+            setting(
+                "WATCH_DEFAULT_INTERVAL_SECS",
+                json!(self.watch_default_interval.as_secs()),
+            ),
+            setting(
+                "WATCH_MIN_INTERVAL_SECS",
+                json!(self.watch_min_interval.as_secs()),
+            ),
+            setting(
+                "WATCH_MAX_INTERVAL_SECS",
+                json!(self.watch_max_interval.as_secs()),
+            ),
+            setting("SNAPSHOT_DIR", path_value(&self.snapshot_dir)),
+            setting("SNAPSHOT_MAX_PER_USER", json!(self.snapshot_max_per_user)),
+            setting(
+                "SNAPSHOT_MAX_AGE_DAYS",
+                json!(self
+                    .snapshot_max_age
+                    .map_or(0, |d| d.as_secs() / (24 * 3600))),
+            ),
+            setting("CACHE_TTL_SECS", json!(self.cache_ttl.as_secs())),
+            setting(
+                "CACHE_RETENTION_SECS",
+                json!(self.cache_retention.as_secs()),
+            ),
+            // Puede llevar la contraseña.
+            setting(
+                "CACHE_REDIS_URL",
+                json!(self.cache_redis_url.as_ref().map(|_| "…")),
+            ),
+            setting("CACHE_REDIS_PREFIX", json!(self.cache_redis_prefix)),
+            setting(
+                "PRICE_CACHE_TTL_SECS",
+                json!(self.price_cache_ttl.as_secs()),
+            ),
+            setting(
+                "FRESH_FETCHES_PER_MINUTE",
+                json!(self.fresh_fetches_per_minute),
+            ),
+            setting("RATE_LIMIT_RPS", json!(self.rate_limit_rps)),
+            setting("RATE_LIMIT_BURST", json!(self.rate_limit_burst)),
+            setting(
+                "RATE_LIMIT_TRUST_FORWARDED",
+                json!(self.rate_limit_trust_forwarded),
+            ),
+            setting(
+                "CURSOR_SECRET",
+                json!(self.cursor_secret.as_ref().map(|_| "…")),
+            ),
+            setting("CURSOR_TTL_SECS", json!(self.cursor_ttl.as_secs())),
+            setting("DRAIN_GRACE_SECS", json!(self.drain_grace.as_secs())),
+            setting("DEDUPE_WINDOW_SECS", json!(self.dedupe_window.as_secs())),
+            setting("BOOTH_DIR", path_value(&self.booth_dir)),
+            setting("BOOTH_LIMIT_PER_KEY", json!(self.booth_limit_per_key)),
+            setting("BOOTH_MAX_BYTES", json!(self.booth_max_bytes)),
+            setting("COLLECTION_DIR", path_value(&self.collection_dir)),
+            setting("CAMPAIGN_DIR", path_value(&self.campaign_dir)),
+            setting("DONATION_LIMIT_PER_KEY", json!(self.donation_limit_per_key)),
+            setting(
+                "ACCESS_LOG",
+                json!(self.access_log.map(|f| match f {
+                    AccessLogFormat::Json => "json",
+                    AccessLogFormat::Combined => "combined",
+                })),
+            ),
+            setting("ACCESS_LOG_FILE", path_value(&self.access_log_file)),
+            setting(
+                "SELFCHECK_CANARY_USER_ID",
+                json!(self.selfcheck_canary_user_id),
+            ),
+            setting(
+                "READINESS_PROBE_USER_ID",
+                json!(self.readiness_probe_user_id),
+            ),
+            setting(
+                "READINESS_PROBE_TIMEOUT_SECS",
+                json!(self.readiness_probe_timeout.as_secs()),
+            ),
+        ]);
+        settings
+    }
+
+    /// URL base (sin `/` final) a la que llamar para `upstream`.
+    pub fn upstream_url(&self, upstream: Upstream) -> &str {
+        &self.upstream_base_urls[&upstream]
+    }
+}
+
+const DEFAULT_UPSTREAM_BASE_URL: &str = "https://{service}.roblox.com";
+
+/// URL base de cada upstream según `UPSTREAM_<SERVICIO>_URL` o, si no,
+/// la plantilla `template`.
+fn upstream_base_urls(template: &str) -> HashMap<Upstream, String> {
+    Upstream::ALL
+        .into_iter()
+        .map(|upstream| {
+            let url = url_var(upstream.url_var())
+                .unwrap_or_else(|| template.replace("{service}", upstream.service()));
+            (upstream, url)
+        })
+        .collect()
+}
+
+/// URL http(s) de la variable `name`, sin `/` final. `None` si no está o no
+/// es válida (se anota).
+fn url_var(name: &str) -> Option<String> {
+    let raw = var(name).ok().filter(|v| !v.is_empty())?;
+    let url = raw.trim_end_matches('/');
+    let valid = reqwest::Url::parse(&url.replace("{service}", "games"))
+        .is_ok_and(|u| matches!(u.scheme(), "http" | "https") && u.query().is_none());
+    if !valid {
+        warn!("{name}: '{raw}' no es una URL http(s) sin query; se ignora");
+        note_invalid(name);
+        return None;
+    }
+    Some(url.to_string())
+}
+
+const DEFAULT_DEGRADATION_THRESHOLDS: [f64; 3] = [0.1, 0.25, 0.5];
+
+/// Tres tasas de error positivas y en orden creciente (una por encima de 1
+/// desactiva ese nivel).
+fn parse_thresholds(raw: &str) -> Option<[f64; 3]> {
+    let values: Vec<f64> = raw
+        .split(',')
+        .map(|v| v.trim().parse().ok().filter(|v: &f64| *v > 0.0))
+        .collect::<Option<_>>()?;
+    let thresholds: [f64; 3] = values.try_into().ok()?;
+    thresholds
+        .windows(2)
+        .all(|w| w[0] <= w[1])
+        .then_some(thresholds)
+}
+
+/// Ids de `assetType` separados por comas (al menos uno; un repetido
+/// cuenta una vez).
+fn parse_asset_types(raw: &str) -> Option<Vec<u64>> {
+    let mut types = Vec::new();
+    for id in raw.split(',').map(str::trim) {
+        let id: u64 = id.parse().ok().filter(|id| *id > 0)?;
+        if !types.contains(&id) {
+            types.push(id);
+        }
+    }
+    Some(types)
+}
+
+/// Variables que se leen antes de la configuración (registro) o que
+/// señalan el propio fichero: en `CONFIG_FILE` no tienen efecto.
+const ENV_ONLY: [&str; 3] = ["CONFIG_FILE", "RUST_LOG", "LOG_FORMAT"];
+/// Variables válidas en `CONFIG_FILE` que no salen en `effective` (ver
+/// `recording`).
+const OTHER_VARS: [&str; 2] = ["UPSTREAM_MODE", "UPSTREAM_CASSETTE_DIR"];
+
+/// Valores de `CONFIG_FILE` por nombre de variable.
+static FILE_VALUES: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Variable `name` del entorno o, si no está, de `CONFIG_FILE`.
+pub fn var(name: &str) -> Result<String, env::VarError> {
+    env::var(name).or_else(|e| file_value(name).ok_or(e))
+}
+
+fn file_value(name: &str) -> Option<String> {
+    FILE_VALUES.get()?.get(name).cloned()
+}
+
+/// Lee un fichero TOML plano: cada clave es una variable (sin distinguir
+/// mayúsculas) y cada valor, un escalar o una lista de escalares, que se
+/// une con comas como en el entorno (`API_KEYS = ["a:k1", "b:k2"]`).
+fn read_file(path: &Path) -> Result<HashMap<String, String>, String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|e| format!("No se pudo leer CONFIG_FILE={}: {e}", path.display()))?;
+    let table: toml::Table = raw
+        .parse()
+        .map_err(|e| format!("CONFIG_FILE={} no es TOML válido: {e}", path.display()))?;
+    table
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match &value {
+                toml::Value::Array(items) => items.iter().map(scalar).collect::<Option<Vec<_>>>(),
+                other => scalar(other).map(|v| vec![v]),
+            };
+            let value = value.ok_or_else(|| {
+                format!(
+                    "CONFIG_FILE={}: {key} debe ser un valor o una lista de valores (sin secciones)",
+                    path.display()
+                )
+            })?;
+            Ok((key.to_ascii_uppercase(), value.join(",")))
+        })
+        .collect()
+}
+
+/// Un valor de TOML tal como se escribiría en la variable.
+fn scalar(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Integer(i) => Some(i.to_string()),
+        toml::Value::Float(f) => Some(f.to_string()),
+        toml::Value::Boolean(b) => Some(b.to_string()),
+        toml::Value::Datetime(d) => Some(d.to_string()),
+        toml::Value::Array(_) | toml::Value::Table(_) => None,
+    }
+}
+
+/// Variables definidas con un valor que no se pudo interpretar (se usó el
+/// valor por defecto). Las revisa el self-check del arranque.
+static INVALID_VARS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn note_invalid(name: &str) {
+    INVALID_VARS.lock().unwrap().push(name.to_string());
+}
+
+/// Variables con valores inválidos vistas al leer la configuración.
+pub fn invalid_vars() -> Vec<String> {
+    INVALID_VARS.lock().unwrap().clone()
+}
+
+/// Valor numérico de `name`, o `default` si falta o no se puede parsear.
+fn env_parse<T: std::str::FromStr>(name: &str, default: T) -> T {
+    let raw = match var(name) {
+        Ok(raw) if !raw.is_empty() => raw,
+        _ => return default,
+    };
+    raw.parse().unwrap_or_else(|_| {
+        note_invalid(name);
+        default
+    })
+}
+
+/// Unidad de las esperas de `env_backoff`.
+#[derive(Clone, Copy)]
+enum TimeUnit {
+    Secs,
+    Millis,
+}
+
+impl TimeUnit {
+    fn suffix(self) -> &'static str {
+        match self {
+            TimeUnit::Secs => "SECS",
+            TimeUnit::Millis => "MS",
+        }
+    }
+
+    fn parse(self, name: &str, default: Duration) -> Duration {
+        match self {
+            TimeUnit::Secs => Duration::from_secs(env_parse(name, default.as_secs())),
+            TimeUnit::Millis => Duration::from_millis(env_parse(name, default.as_millis() as u64)),
+        }
+    }
+
+    fn one(self) -> Duration {
+        match self {
+            TimeUnit::Secs => Duration::from_secs(1),
+            TimeUnit::Millis => Duration::from_millis(1),
+        }
+    }
+}
+
+/// Curva de espera `{prefix}_JITTER` / `{prefix}_BASE_{unidad}` /
+/// `{prefix}_MAX_{unidad}` (`SECS` o `MS`), con `default` para lo que no
+/// esté definido.
+fn env_backoff(prefix: &str, unit: TimeUnit, default: Backoff) -> Backoff {
+    let jitter_var = format!("{prefix}_JITTER");
+    let jitter = match var(&jitter_var) {
+        Ok(v) => v.parse().unwrap_or_else(|e| {
+            warn!("{jitter_var}: {e}, usando {}", default.jitter.as_str());
+            note_invalid(&jitter_var);
+            default.jitter
+        }),
+        Err(_) => default.jitter,
+    };
+    let suffix = unit.suffix();
+    let base = unit
+        .parse(&format!("{prefix}_BASE_{suffix}"), default.base)
+        .max(unit.one());
+    let max = unit.parse(&format!("{prefix}_MAX_{suffix}"), default.max);
+    Backoff {
+        jitter,
+        base,
+        max: max.max(base),
+    }
+}
+
+/// `1`/`true`/`yes` (sin distinguir mayúsculas) cuentan como activado.
+fn env_flag(name: &str) -> bool {
+    env_bool(name, false)
+}
+
+/// Como `env_flag`, pero con `default` si la variable no está definida.
+fn env_bool(name: &str, default: bool) -> bool {
+    var(name)
+        .map(|v| matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(default)
+}
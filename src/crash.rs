@@ -0,0 +1,209 @@
+//! Pánicos en los handlers.
+//!
+//! `CatchPanicLayer` responde con un 500 con el envelope de error en lugar de
+//! cortar la conexión, y además se deja un informe en `CRASH_REPORT_DIR` con
+//! el backtrace, la ruta, el userId y las últimas llamadas a Roblox (y, si
+//! hay `SENTRY_DSN`, se envía también como evento a Sentry).
+
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cell::RefCell,
+    path::Path,
+    sync::{atomic::Ordering, Arc},
+    time::Instant,
+};
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::json;
+use tower_http::catch_panic::ResponseForPanic;
+use tracing::error;
+
+use crate::{
+    error::ApiError,
+    request_id::{self, RequestContext},
+    upstream::Outcome,
+    AppState,
+};
+
+thread_local! {
+    /// Ubicación y backtrace del último pánico de este hilo, que el hook deja
+    /// aquí para que `response_for_panic` (mismo hilo) los recoja.
+    static LAST_PANIC: RefCell<Option<(String, String)>> = const { RefCell::new(None) };
+}
+
+/// Instala el hook de pánicos que captura el backtrace. Se encadena con el
+/// hook anterior, así el mensaje sigue saliendo por stderr.
+pub fn install_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_default();
+        let backtrace = Backtrace::force_capture().to_string();
+        LAST_PANIC.with(|last| *last.borrow_mut() = Some((location, backtrace)));
+        previous(info);
+    }));
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CrashReport {
+    request_id: Option<String>,
+    at: String,
+    method: Option<String>,
+    path: Option<String>,
+    user_id: Option<u64>,
+    message: String,
+    location: Option<String>,
+    backtrace: Option<String>,
+    recent_calls: Vec<RecentCallReport>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecentCallReport {
+    url: String,
+    endpoint: &'static str,
+    /// Código HTTP, o `null` si fue un error de red.
+    status: Option<u16>,
+    latency_ms: u64,
+    ago_ms: u64,
+}
+
+fn build_report(state: &AppState, message: String) -> CrashReport {
+    let context = request_id::context();
+    let (location, backtrace) = LAST_PANIC
+        .with(|last| last.borrow_mut().take())
+        .map_or((None, None), |(l, b)| (Some(l), Some(b)));
+    let now = Instant::now();
+
+    CrashReport {
+        request_id: context.as_ref().map(|c| c.id.clone()),
+        at: Utc::now().to_rfc3339(),
+        method: context.as_ref().map(|c| c.method.clone()),
+        user_id: context.as_ref().and_then(RequestContext::user_id),
+        path: context.map(|c| c.path),
+        message,
+        location,
+        backtrace,
+        recent_calls: state
+            .upstreams
+            .recent_calls()
+            .into_iter()
+            .map(|call| RecentCallReport {
+                endpoint: call.endpoint.path(),
+                url: call.url,
+                status: match call.outcome {
+                    Outcome::Status(code) => Some(code),
+                    Outcome::Transport => None,
+                },
+                latency_ms: call.latency.as_millis() as u64,
+                ago_ms: now.duration_since(call.at).as_millis() as u64,
+            })
+            .collect(),
+    }
+}
+
+fn write_report(dir: &Path, report: &CrashReport) {
+    let name = format!(
+        "crash-{}-{}.json",
+        Utc::now().format("%Y%m%dT%H%M%S"),
+        report.request_id.as_deref().unwrap_or("sin-id")
+    );
+    let path = dir.join(name);
+    let written = std::fs::create_dir_all(dir).and_then(|()| {
+        let json = serde_json::to_vec_pretty(report).map_err(std::io::Error::other)?;
+        std::fs::write(&path, json)
+    });
+    match written {
+        Ok(()) => error!("Informe guardado en {}", path.display()),
+        Err(e) => error!("No se pudo guardar el informe en {}: {e}", path.display()),
+    }
+}
+
+/// Envía el informe como evento a Sentry (API `store`), sin esperar.
+fn send_to_sentry(dsn: &str, report: &CrashReport) {
+    let Ok(url) = reqwest::Url::parse(dsn) else {
+        error!("SENTRY_DSN inválido");
+        return;
+    };
+    let key = url.username().to_string();
+    let project = url.path().trim_matches('/').to_string();
+    let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let endpoint = format!("{}://{host}/api/{project}/store/", url.scheme());
+    let auth = format!(
+        "Sentry sentry_version=7, sentry_key={key}, sentry_client=donations_api/{}",
+        env!("CARGO_PKG_VERSION")
+    );
+    let event = json!({
+        "event_id": format!("{:016x}{:016x}", fastrand::u64(..), fastrand::u64(..)),
+        "timestamp": report.at,
+        "level": "fatal",
+        "platform": "rust",
+        "message": { "formatted": report.message },
+        "tags": {
+            "requestId": report.request_id,
+            "path": report.path,
+        },
+        "extra": report,
+    });
+
+    tokio::spawn(async move {
+        let sent = reqwest::Client::new()
+            .post(&endpoint)
+            .header("X-Sentry-Auth", auth)
+            .json(&event)
+            .send()
+            .await;
+        match sent {
+            Ok(resp) if resp.status().is_success() => {}
+            Ok(resp) => error!("Sentry respondió HTTP {}", resp.status()),
+            Err(e) => error!("No se pudo enviar a Sentry: {e}"),
+        }
+    });
+}
+
+#[derive(Clone)]
+pub struct PanicHandler {
+    pub state: Arc<AppState>,
+}
+
+impl ResponseForPanic for PanicHandler {
+    type ResponseBody = axum::body::BoxBody;
+
+    fn response_for_panic(&mut self, err: Box<dyn Any + Send + 'static>) -> Response {
+        let detail = err
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| err.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "pánico sin mensaje".to_string());
+        self.state.metrics.panics.fetch_add(1, Ordering::Relaxed);
+        error!(
+            "requestId={} {detail}",
+            request_id::current().unwrap_or_default()
+        );
+
+        let report = build_report(&self.state, detail);
+        write_report(&self.state.config.crash_report_dir, &report);
+        if let Some(dsn) = &self.state.config.sentry_dsn {
+            send_to_sentry(dsn, &report);
+        }
+
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INTERNAL_ERROR",
+            "Error interno inesperado",
+        )
+        .into_response()
+    }
+}
@@ -0,0 +1,304 @@
+//! Deduplicación de reintentos (`DEDUPE_WINDOW_SECS`; 0, el valor por
+//! defecto, la desactiva).
+//!
+//! `HttpService` de Roblox reintenta al agotar su tiempo aunque nuestra
+//! respuesta ya estuviera en camino, y cada reintento volvía a escanear. Con
+//! la ventana activa, un GET igual a otro del mismo cliente (su clave de API
+//! o su IP, como en `ratelimit`) se une al que sigue en curso o, si terminó
+//! hace menos de la ventana, recibe la misma respuesta con
+//! `X-Deduplicated: true`. El primero llega hasta el final aunque su cliente
+//! se desconecte, para que el reintento tenga algo que recoger.
+//!
+//! "Igual" es mismo método, ruta, query y cabeceras que cambian la respuesta
+//! (`Accept`, `Accept-Language`, `Cache-Control`, `If-None-Match`). Las
+//! respuestas 5xx y 429 no se guardan: ahí sí conviene volver a intentarlo.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{self, Bytes, Full},
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio::sync::watch;
+use tracing::{debug, warn, Instrument, Span};
+
+use crate::{config::Config, error::ApiError, ratelimit::Client, usage, AppState};
+
+/// Cabeceras de la petición que cambian la respuesta.
+const VARY: [header::HeaderName; 4] = [
+    header::ACCEPT,
+    header::ACCEPT_LANGUAGE,
+    header::CACHE_CONTROL,
+    header::IF_NONE_MATCH,
+];
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct DedupeKey {
+    client: Client,
+    /// Método, URI y las cabeceras de `VARY`.
+    request: String,
+}
+
+/// Respuesta ya leída entera, para servirla varias veces.
+struct Stored {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Stored {
+    fn response(&self, deduplicated: bool) -> Response {
+        let mut response = Response::new(body::boxed(Full::from(self.body.clone())));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        if deduplicated {
+            response
+                .headers_mut()
+                .insert("x-deduplicated", HeaderValue::from_static("true"));
+        }
+        response
+    }
+
+    fn reusable(&self) -> bool {
+        !self.status.is_server_error() && self.status != StatusCode::TOO_MANY_REQUESTS
+    }
+}
+
+enum Slot {
+    /// En curso: el resultado llega por el canal (`None` hasta entonces).
+    Pending(watch::Receiver<Option<Arc<Stored>>>),
+    Done {
+        response: Arc<Stored>,
+        at: Instant,
+    },
+}
+
+pub struct Deduper {
+    window: Duration,
+    slots: Mutex<HashMap<DedupeKey, Slot>>,
+    /// Peticiones servidas con la respuesta de otra, para `/metrics`.
+    hits: AtomicU64,
+}
+
+/// Qué hacer con una petición según lo que haya en su hueco.
+enum Action {
+    Replay(Arc<Stored>),
+    Join(watch::Receiver<Option<Arc<Stored>>>),
+    Lead(watch::Sender<Option<Arc<Stored>>>),
+}
+
+impl Deduper {
+    pub fn new(config: &Config) -> Self {
+        Deduper {
+            window: config.dedupe_window,
+            slots: Mutex::default(),
+            hits: AtomicU64::new(0),
+        }
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    fn action(&self, key: &DedupeKey) -> Action {
+        let mut slots = self.slots.lock().unwrap();
+        match slots.get(key) {
+            Some(Slot::Done { response, at }) if at.elapsed() < self.window => {
+                return Action::Replay(response.clone())
+            }
+            // Si el primero cayó (pánico), su canal está cerrado: se repite.
+            Some(Slot::Pending(rx)) if rx.has_changed().is_ok() => return Action::Join(rx.clone()),
+            _ => {}
+        }
+        let (tx, rx) = watch::channel(None);
+        slots.insert(key.clone(), Slot::Pending(rx));
+        Action::Lead(tx)
+    }
+
+    /// Guarda el resultado del primero (o libera el hueco si no se reusa).
+    fn finish(&self, key: DedupeKey, response: Option<&Arc<Stored>>) {
+        let mut slots = self.slots.lock().unwrap();
+        match response.filter(|r| r.reusable()) {
+            Some(response) => {
+                slots.insert(
+                    key,
+                    Slot::Done {
+                        response: response.clone(),
+                        at: Instant::now(),
+                    },
+                );
+            }
+            None => {
+                slots.remove(&key);
+            }
+        }
+    }
+
+    /// Descarta las respuestas fuera de la ventana.
+    pub fn prune(&self) {
+        let window = self.window;
+        self.slots.lock().unwrap().retain(|_, slot| match slot {
+            Slot::Pending(rx) => rx.has_changed().is_ok(),
+            Slot::Done { at, .. } => at.elapsed() < window,
+        });
+    }
+}
+
+fn request_key<B>(req: &Request<B>) -> String {
+    let mut key = format!("{} {}", req.method(), req.uri());
+    for name in &VARY {
+        for value in req.headers().get_all(name) {
+            key.push('\n');
+            key.push_str(name.as_str());
+            key.push_str(": ");
+            key.push_str(&String::from_utf8_lossy(value.as_bytes()));
+        }
+    }
+    key
+}
+
+/// Middleware: une los reintentos a la petición original (ver el módulo).
+pub async fn dedupe<B: Send + 'static>(
+    State(state): State<Arc<AppState>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let deduper = &state.deduper;
+    if deduper.window.is_zero() || !matches!(*req.method(), Method::GET | Method::HEAD) {
+        return next.run(req).await;
+    }
+    let Some(client) = state.client_limiter.caller(&req, &state.config.api_keys) else {
+        return next.run(req).await;
+    };
+    let key = DedupeKey {
+        client,
+        request: request_key(&req),
+    };
+
+    let tx = match deduper.action(&key) {
+        Action::Replay(stored) => {
+            debug!("Reintento servido con la respuesta anterior");
+            deduper.hits.fetch_add(1, Ordering::Relaxed);
+            return stored.response(true);
+        }
+        Action::Join(mut rx) => {
+            let joined = rx
+                .wait_for(Option::is_some)
+                .await
+                .ok()
+                .and_then(|v| v.clone());
+            let Some(stored) = joined else {
+                return next.run(req).await;
+            };
+            debug!("Reintento unido a la petición en curso");
+            deduper.hits.fetch_add(1, Ordering::Relaxed);
+            return stored.response(true);
+        }
+        Action::Lead(tx) => tx,
+    };
+
+    // En su propia tarea, para que termine aunque el cliente se vaya.
+    let task = tokio::spawn({
+        let state = state.clone();
+        usage::propagate(
+            async move {
+                let response = next.run(req).await;
+                let (parts, body) = response.into_parts();
+                let stored = match hyper::body::to_bytes(body).await {
+                    Ok(body) => Arc::new(Stored {
+                        status: parts.status,
+                        headers: parts.headers,
+                        body,
+                    }),
+                    Err(e) => {
+                        warn!("No se pudo leer la respuesta para deduplicarla: {e}");
+                        state.deduper.finish(key, None);
+                        return None;
+                    }
+                };
+                state.deduper.finish(key, Some(&stored));
+                let _ = tx.send(Some(stored.clone()));
+                Some(stored)
+            }
+            .instrument(Span::current()),
+        )
+    });
+    match task.await {
+        Ok(Some(stored)) => stored.response(false),
+        Ok(None) => ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INTERNAL_ERROR",
+            "No se pudo generar la respuesta",
+        )
+        .into_response(),
+        // Al `CatchPanicLayer`, como si no hubiera tarea de por medio.
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => panic!("tarea de deduplicación cancelada: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deduper() -> Deduper {
+        let mut config = Config::from_env();
+        config.dedupe_window = Duration::from_secs(5);
+        Deduper::new(&config)
+    }
+
+    fn key() -> DedupeKey {
+        DedupeKey {
+            client: Client::Ip([203, 0, 113, 7].into()),
+            request: "GET /user/1/passes".to_string(),
+        }
+    }
+
+    fn stored(status: StatusCode) -> Arc<Stored> {
+        Arc::new(Stored {
+            status,
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn dropped_leader_releases_its_followers() {
+        let deduper = deduper();
+        let Action::Lead(tx) = deduper.action(&key()) else {
+            panic!("la primera petición debería ir a Roblox");
+        };
+        let Action::Join(mut rx) = deduper.action(&key()) else {
+            panic!("la segunda debería esperar a la primera");
+        };
+
+        // La primera cae sin llegar a `finish` (un pánico en el handler).
+        drop(tx);
+
+        // Quien esperaba se entera y hace su propia petición...
+        assert!(rx.wait_for(Option::is_some).await.is_err());
+        // ...y la siguiente no se une a un canal cerrado.
+        assert!(matches!(deduper.action(&key()), Action::Lead(_)));
+    }
+
+    #[test]
+    fn only_reusable_responses_are_replayed() {
+        let deduper = deduper();
+        assert!(matches!(deduper.action(&key()), Action::Lead(_)));
+        deduper.finish(key(), Some(&stored(StatusCode::SERVICE_UNAVAILABLE)));
+        assert!(matches!(deduper.action(&key()), Action::Lead(_)));
+
+        deduper.finish(key(), Some(&stored(StatusCode::OK)));
+        assert!(matches!(deduper.action(&key()), Action::Replay(_)));
+    }
+}
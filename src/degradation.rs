@@ -0,0 +1,135 @@
+//! Degradación automática según la salud de Roblox. Con la tasa de error de
+//! las llamadas salientes (ventana de `STATS_WINDOW`) por encima de cada
+//! umbral de `DEGRADATION_THRESHOLDS` se deja de hacer algo más:
+//!
+//! 1. `no-thumbnails`: se ignora `?thumbnails=true`.
+//! 2. `no-game-details`: sin metadatos de juegos (`groupBy=game`, JSON:API).
+//! 3. `cache-only`: los fallos de caché no escanean; se sirve lo guardado,
+//!    aunque sea viejo, o un 503.
+//!
+//! Se sube de nivel en cuanto se pasa un umbral y se baja de uno en uno, tras
+//! `MIN_HOLD` en el nivel, para no oscilar. El nivel activo sale en las
+//! respuestas de `/user/:id/passes` (`degradation`) y en `/metrics`.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use tracing::info;
+
+use crate::{config::Config, upstream::UpstreamHealth};
+
+/// Llamadas mínimas en la ventana para fiarse de la tasa de error.
+const MIN_CALLS: usize = 20;
+/// Tiempo mínimo en un nivel antes de bajar al anterior.
+const MIN_HOLD: Duration = Duration::from_secs(60);
+/// Cada cuánto se recalcula el nivel como mucho.
+const REFRESH: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Level {
+    Full,
+    NoThumbnails,
+    NoGameDetails,
+    CacheOnly,
+}
+
+impl Level {
+    const ALL: [Level; 4] = [
+        Level::Full,
+        Level::NoThumbnails,
+        Level::NoGameDetails,
+        Level::CacheOnly,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Full => "full",
+            Level::NoThumbnails => "no-thumbnails",
+            Level::NoGameDetails => "no-game-details",
+            Level::CacheOnly => "cache-only",
+        }
+    }
+
+    /// 0 (`full`) a 3 (`cache-only`), para `/metrics`.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+struct Current {
+    level: Level,
+    since: Instant,
+    checked_at: Instant,
+}
+
+pub struct Degradation {
+    /// Tasa de error a partir de la que se entra en cada nivel (del 1 al 3).
+    thresholds: [f64; 3],
+    current: Mutex<Current>,
+}
+
+impl Degradation {
+    pub fn new(config: &Config) -> Self {
+        let now = Instant::now();
+        Degradation {
+            thresholds: config.degradation_thresholds,
+            current: Mutex::new(Current {
+                level: Level::Full,
+                since: now,
+                checked_at: now,
+            }),
+        }
+    }
+
+    /// Nivel que corresponde a una tasa de error.
+    fn target(&self, error_rate: f64) -> Level {
+        let passed = self.thresholds.iter().filter(|t| error_rate >= **t).count();
+        Level::ALL[passed]
+    }
+
+    /// Nivel activo, recalculado como mucho una vez por `REFRESH`.
+    pub fn level(&self, health: &UpstreamHealth) -> Level {
+        let now = Instant::now();
+        let mut current = self.current.lock().unwrap();
+        if now.duration_since(current.checked_at) < REFRESH {
+            return current.level;
+        }
+        current.checked_at = now;
+
+        let (calls, errors) = health
+            .endpoint_reports()
+            .iter()
+            .fold((0, 0), |(calls, errors), r| {
+                (calls + r.calls, errors + r.errors)
+            });
+        let error_rate = if calls < MIN_CALLS {
+            0.0
+        } else {
+            errors as f64 / calls as f64
+        };
+        let target = self.target(error_rate);
+        let next = if target > current.level {
+            target
+        } else if target < current.level && now.duration_since(current.since) >= MIN_HOLD {
+            Level::ALL[current.level.index() - 1]
+        } else {
+            current.level
+        };
+        if next != current.level {
+            info!(
+                "Degradación: {} → {} (tasa de error {:.0}% en {calls} llamadas)",
+                current.level.as_str(),
+                next.as_str(),
+                error_rate * 100.0
+            );
+            current.level = next;
+            current.since = now;
+        }
+        current.level
+    }
+}
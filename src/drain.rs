@@ -0,0 +1,107 @@
+//! Drenaje para despliegues blue/green (`POST /admin/drain`).
+//!
+//! Al drenar, `/readyz` pasa a 503 para que el balanceador deje de mandar
+//! tráfico, el watcher no empieza más refrescos y `POST /watch` se rechaza.
+//! Pasados `DRAIN_GRACE_SECS` (lo que tarde el balanceador en notarlo) el
+//! servidor deja de aceptar conexiones, termina las peticiones en curso y
+//! las tareas de fondo, y el proceso sale con código 0.
+
+use std::{
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use axum::{extract::State, http::StatusCode, Json};
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::{admin::AdminAuth, config::Config, error::ApiError, AppState};
+
+pub struct Drain {
+    started: OnceLock<Instant>,
+    token: CancellationToken,
+    grace: Duration,
+}
+
+impl Drain {
+    pub fn new(config: &Config) -> Self {
+        Drain {
+            started: OnceLock::new(),
+            token: CancellationToken::new(),
+            grace: config.drain_grace,
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.started.get().is_some()
+    }
+
+    /// Empieza a drenar; `false` si ya se estaba drenando.
+    fn start(&self) -> bool {
+        let first = self.started.set(Instant::now()).is_ok();
+        self.token.cancel();
+        first
+    }
+
+    /// Se completa `DRAIN_GRACE_SECS` después de empezar a drenar: entonces
+    /// hay que apagar el servidor.
+    pub async fn finished(&self) {
+        self.token.cancelled().await;
+        tokio::time::sleep(self.grace).await;
+    }
+
+    /// 503 para las rutas que empiezan trabajo de fondo nuevo.
+    pub fn reject_new_work(&self) -> Result<(), ApiError> {
+        if !self.is_draining() {
+            return Ok(());
+        }
+        Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "DRAINING",
+            "La instancia se está apagando; reintenta contra otra",
+        ))
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DrainResponse {
+    ok: bool,
+    /// `true` si ya se estaba drenando antes de esta petición.
+    already_draining: bool,
+    /// Segundos desde que empezó el drenaje.
+    draining_secs: u64,
+    /// Segundos hasta dejar de aceptar conexiones (desde el principio del
+    /// drenaje).
+    grace_secs: u64,
+}
+
+/// `POST /admin/drain`: empieza a drenar la instancia. Repetirlo no cambia
+/// el plazo.
+pub async fn drain(
+    _: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> (StatusCode, Json<DrainResponse>) {
+    let drain = &state.drain;
+    let first = drain.start();
+    if first {
+        warn!(
+            "Drenando: /readyz en 503, apagado en {}s",
+            drain.grace.as_secs()
+        );
+    } else {
+        info!("Drenaje ya en curso");
+    }
+    let started = drain.started.get().copied().unwrap_or_else(Instant::now);
+    (
+        StatusCode::ACCEPTED,
+        Json(DrainResponse {
+            ok: true,
+            already_draining: !first,
+            draining_secs: started.elapsed().as_secs(),
+            grace_secs: drain.grace.as_secs(),
+        }),
+    )
+}
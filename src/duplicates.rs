@@ -0,0 +1,117 @@
+//! Nombres repetidos: un creador suele tener passes que se llaman igual en
+//! varios juegos ("Donate 10" en cada uno). `displayName` añade el juego
+//! entre paréntesis (o el id, si no se sabe el juego o no basta para
+//! distinguirlos) y `duplicateOf` apunta al primer pass de la respuesta con
+//! ese nombre, para agruparlos en un desplegable.
+
+use std::collections::HashMap;
+
+use crate::models::Gamepass;
+
+/// Rellena `display_name` y `duplicate_of`. Los nombres se comparan sin
+/// distinguir mayúsculas ni espacios en los extremos.
+pub fn disambiguate(passes: &mut [Gamepass]) {
+    let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, pass) in passes.iter().enumerate() {
+        groups
+            .entry(pass.name.trim().to_lowercase())
+            .or_default()
+            .push(i);
+    }
+
+    for indices in groups.values() {
+        let first = passes[indices[0]].id;
+        if indices.len() == 1 {
+            let pass = &mut passes[indices[0]];
+            pass.display_name = pass.name.clone();
+            pass.duplicate_of = None;
+            continue;
+        }
+        let games: Vec<Option<String>> = indices.iter().map(|&i| passes[i].game_name()).collect();
+        for (n, &i) in indices.iter().enumerate() {
+            // Dos passes iguales en el mismo juego: el juego no los distingue.
+            let game = games[n]
+                .as_ref()
+                .filter(|g| games.iter().filter(|o| o.as_ref() == Some(g)).count() == 1);
+            let pass = &mut passes[i];
+            pass.display_name = match game {
+                Some(game) => format!("{} ({game})", pass.name),
+                None => format!("{} (#{})", pass.name, pass.id),
+            };
+            pass.duplicate_of = (n > 0).then_some(first);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::views::PassSource;
+
+    fn pass(id: u64, name: &str, game: Option<&str>) -> Gamepass {
+        Gamepass {
+            id,
+            name: name.to_string(),
+            price: 10,
+            original_price: 10,
+            price_changed: false,
+            price_details: None,
+            price_source: None,
+            display_name: String::new(),
+            duplicate_of: None,
+            icon_url: None,
+            links: None,
+            source: PassSource::Games,
+            universe_id: None,
+            game: None,
+            game_name: game.map(str::to_string),
+        }
+    }
+
+    fn labels(passes: &[Gamepass]) -> Vec<(&str, Option<u64>)> {
+        passes
+            .iter()
+            .map(|p| (p.display_name.as_str(), p.duplicate_of))
+            .collect()
+    }
+
+    #[test]
+    fn unique_names_are_left_alone() {
+        let mut passes = vec![pass(1, "VIP", Some("Stand")), pass(2, "Donate", None)];
+        disambiguate(&mut passes);
+        assert_eq!(labels(&passes), [("VIP", None), ("Donate", None)]);
+    }
+
+    #[test]
+    fn repeated_names_take_the_game_and_point_to_the_first() {
+        let mut passes = vec![
+            pass(1, "Donate 10", Some("Stand")),
+            pass(2, " donate 10", Some("Obby")),
+        ];
+        disambiguate(&mut passes);
+        assert_eq!(
+            labels(&passes),
+            [("Donate 10 (Stand)", None), (" donate 10 (Obby)", Some(1))]
+        );
+    }
+
+    #[test]
+    fn same_name_within_one_game_falls_back_to_the_id() {
+        let mut passes = vec![
+            pass(1, "Donate", Some("Stand")),
+            pass(2, "Donate", Some("Stand")),
+            pass(3, "Donate", Some("Obby")),
+            pass(4, "Donate", None),
+        ];
+        disambiguate(&mut passes);
+        assert_eq!(
+            labels(&passes),
+            [
+                ("Donate (#1)", None),
+                ("Donate (#2)", Some(1)),
+                ("Donate (Obby)", Some(1)),
+                ("Donate (#4)", Some(1)),
+            ]
+        );
+    }
+}
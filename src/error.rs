@@ -0,0 +1,88 @@
+use axum::{
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::request_id;
+
+/// Error de la API con el envelope común:
+/// `{ "ok": false, "error": { "code": "...", "message": "...", "requestId": "..." } }`.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ErrorEnvelope<'a> {
+    ok: bool,
+    error: ErrorBody<'a>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct ErrorBody<'a> {
+    code: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorEnvelope {
+            ok: false,
+            error: ErrorBody {
+                code: self.code,
+                message: &self.message,
+                request_id: request_id::current(),
+            },
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// Fallback del router para rutas desconocidas.
+pub async fn route_not_found(uri: Uri) -> ApiError {
+    ApiError::new(
+        StatusCode::NOT_FOUND,
+        "ROUTE_NOT_FOUND",
+        format!("No existe la ruta {}", uri.path()),
+    )
+}
+
+/// Reemplaza el 405 vacío que genera axum por el envelope JSON,
+/// conservando la cabecera `Allow`.
+pub async fn method_not_allowed(response: Response) -> Response {
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+        return response;
+    }
+
+    let allow = response.headers().get(axum::http::header::ALLOW).cloned();
+    let mut new_response = ApiError::new(
+        StatusCode::METHOD_NOT_ALLOWED,
+        "METHOD_NOT_ALLOWED",
+        "Método HTTP no permitido para esta ruta",
+    )
+    .into_response();
+    if let Some(allow) = allow {
+        new_response
+            .headers_mut()
+            .insert(axum::http::header::ALLOW, allow);
+    }
+    new_response
+}
@@ -0,0 +1,95 @@
+use axum::{
+    async_trait,
+    body::HttpBody,
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        FromRequest, FromRequestParts,
+    },
+    http::{request::Parts, Request, StatusCode},
+    BoxError,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::error::ApiError;
+
+/// `axum::extract::Query` con los errores de parseo en el envelope JSON
+/// (`INVALID_QUERY`) en lugar del texto plano de axum.
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        axum::extract::Query::<T>::from_request_parts(parts, state)
+            .await
+            .map(|axum::extract::Query(value)| Query(value))
+            .map_err(|rejection: QueryRejection| {
+                ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "INVALID_QUERY",
+                    rejection.body_text(),
+                )
+            })
+    }
+}
+
+/// `axum::Json` con los errores del cuerpo en el envelope JSON
+/// (`INVALID_BODY`).
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        axum::Json::<T>::from_request(req, state)
+            .await
+            .map(|axum::Json(value)| Json(value))
+            .map_err(|rejection: JsonRejection| {
+                ApiError::new(rejection.status(), "INVALID_BODY", rejection.body_text())
+            })
+    }
+}
+
+/// `?dryRun=1` (o `true`) en los endpoints que escriben: valida y responde lo
+/// que pasaría, sin guardar nada.
+pub struct DryRun(pub bool);
+
+#[derive(Deserialize)]
+struct DryRunQuery {
+    #[serde(rename = "dryRun")]
+    dry_run: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for DryRun
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<DryRunQuery>::from_request_parts(parts, state).await?;
+        match query.dry_run.as_deref() {
+            None | Some("0" | "false") => Ok(DryRun(false)),
+            Some("" | "1" | "true") => Ok(DryRun(true)),
+            Some(_) => Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_QUERY",
+                "dryRun debe ser 1, true, 0 o false",
+            )),
+        }
+    }
+}
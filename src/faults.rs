@@ -0,0 +1,155 @@
+//! Inyección de fallos en las respuestas de Roblox para probar de punta a
+//! punta el cooldown, los reintentos y el servido de datos viejos.
+//! Solo se compila con `--features fault-injection`.
+
+use std::{sync::Arc, sync::RwLock, time::Duration};
+
+use axum::{extract::State, http::StatusCode, Json};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{admin::AdminAuth, error::ApiError, upstream::Endpoint, AppState};
+
+/// Configuración de fallos. Las tasas son probabilidades en `0.0..=1.0`
+/// evaluadas por llamada saliente.
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FaultConfig {
+    /// Latencia extra añadida antes de llamar al upstream.
+    pub latency_ms: u64,
+    pub latency_rate: f64,
+    /// Respuestas 429 sintéticas en lugar de llamar al upstream.
+    pub rate_limit_rate: f64,
+    /// Respuestas 200 con JSON truncado en lugar de llamar al upstream.
+    pub malformed_json_rate: f64,
+    /// Challenges 403 (`rblx-challenge-*`) en lugar de llamar al upstream.
+    pub challenge_rate: f64,
+    /// Hosts afectados (`games.roblox.com`, ...). Vacío = todos. Se compara
+    /// con el host real de la llamada, así que un espejo no queda afectado
+    /// salvo que se liste.
+    pub hosts: Vec<String>,
+}
+
+impl FaultConfig {
+    fn validate(&self) -> Result<(), String> {
+        for (name, rate) in [
+            ("latencyRate", self.latency_rate),
+            ("rateLimitRate", self.rate_limit_rate),
+            ("malformedJsonRate", self.malformed_json_rate),
+            ("challengeRate", self.challenge_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!("{name} debe estar entre 0 y 1"));
+            }
+        }
+        Ok(())
+    }
+
+    fn applies_to(&self, url: &str) -> bool {
+        let host = url
+            .strip_prefix("https://")
+            .and_then(|rest| rest.split('/').next())
+            .unwrap_or_default();
+        self.hosts.is_empty() || self.hosts.iter().any(|h| h == host)
+    }
+}
+
+/// Qué hacer con una llamada concreta.
+pub enum Fault {
+    RateLimited,
+    MalformedJson,
+    Challenge,
+}
+
+#[derive(Default)]
+pub struct FaultInjector {
+    config: RwLock<FaultConfig>,
+}
+
+impl FaultInjector {
+    /// Aplica la latencia configurada y decide si sustituir la respuesta.
+    pub async fn before_call(&self, url: &str) -> Option<Fault> {
+        let config = self.config.read().unwrap().clone();
+        if !config.applies_to(url) {
+            return None;
+        }
+
+        if config.latency_ms > 0 && fastrand::f64() < config.latency_rate {
+            tokio::time::sleep(Duration::from_millis(config.latency_ms)).await;
+        }
+
+        if fastrand::f64() < config.rate_limit_rate {
+            Some(Fault::RateLimited)
+        } else if fastrand::f64() < config.malformed_json_rate {
+            Some(Fault::MalformedJson)
+        } else if fastrand::f64() < config.challenge_rate {
+            Some(Fault::Challenge)
+        } else {
+            None
+        }
+    }
+}
+
+impl Fault {
+    pub fn into_response(self, endpoint: Endpoint) -> reqwest::Response {
+        info!(
+            "Inyectando {} en {}{}",
+            match self {
+                Fault::RateLimited => "429",
+                Fault::MalformedJson => "JSON malformado",
+                Fault::Challenge => "challenge",
+            },
+            endpoint.upstream().host(),
+            endpoint.path()
+        );
+        let builder = http1::Response::builder().header("content-type", "application/json");
+        let response = match self {
+            Fault::RateLimited => builder
+                .status(429)
+                .header("retry-after", "1")
+                .body(r#"{"errors":[{"code":0,"message":"TooManyRequests"}]}"#),
+            Fault::MalformedJson => builder.status(200).body(r#"{"data":[{"id":1,"name":"#),
+            Fault::Challenge => builder
+                .status(403)
+                .header("rblx-challenge-id", "00000000-0000-0000-0000-000000000000")
+                .header("rblx-challenge-type", "captcha")
+                .header("rblx-challenge-metadata", "e30=")
+                .body(r#"{"errors":[{"code":0,"message":"Challenge is required to authorize the request"}]}"#),
+        };
+        reqwest::Response::from(response.expect("respuesta sintética válida"))
+    }
+}
+
+/// `GET /admin/faults`
+pub async fn get_faults(_: AdminAuth, State(state): State<Arc<AppState>>) -> Json<FaultConfig> {
+    Json(state.faults.config.read().unwrap().clone())
+}
+
+/// `PUT /admin/faults`: reemplaza la configuración completa.
+pub async fn put_faults(
+    _: AdminAuth,
+    State(state): State<Arc<AppState>>,
+    Json(config): Json<FaultConfig>,
+) -> Result<Json<FaultConfig>, ApiError> {
+    config
+        .validate()
+        .map_err(|msg| ApiError::new(StatusCode::BAD_REQUEST, "INVALID_FAULT_CONFIG", msg))?;
+    info!(
+        "Nueva configuración: latencia {}ms@{}, 429@{}, JSON malformado@{}, challenge@{}",
+        config.latency_ms,
+        config.latency_rate,
+        config.rate_limit_rate,
+        config.malformed_json_rate,
+        config.challenge_rate
+    );
+    *state.faults.config.write().unwrap() = config.clone();
+    Ok(Json(config))
+}
+
+/// `DELETE /admin/faults`: desactiva todos los fallos.
+pub async fn clear_faults(_: AdminAuth, State(state): State<Arc<AppState>>) -> StatusCode {
+    *state.faults.config.write().unwrap() = FaultConfig::default();
+    info!("Fallos desactivados");
+    StatusCode::NO_CONTENT
+}
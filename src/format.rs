@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    body::{boxed, Full},
+    extract::{FromRequestParts, State},
+    http::{header, request::Parts, HeaderMap, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use crate::{error::ApiError, AppState};
+
+/// `?pretty=1` (o `true`) en la query.
+fn wants_pretty(query: Option<&str>) -> bool {
+    query
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .any(|(k, v)| k == "pretty" && (v == "1" || v == "true"))
+}
+
+/// Si la respuesta a una petición con esta query sale indentada.
+pub fn is_pretty(state: &AppState, query: Option<&str>) -> bool {
+    !state.config.minify_json && wants_pretty(query)
+}
+
+/// Bytes del cuerpo de `value` tal como sale en `format` (indentado si
+/// `pretty`; ver `pretty_json` y `Negotiated`).
+pub fn rendered_len<T: Serialize>(format: Format, pretty: bool, value: &T) -> usize {
+    let rendered = match format {
+        Format::Json if pretty => serde_json::to_vec_pretty(value).map(|b| b.len()),
+        Format::Json => serde_json::to_vec(value).map(|b| b.len()),
+        Format::Xml => {
+            return quick_xml::se::to_string_with_root("response", value)
+                .map_or(0, |xml| XML_DECLARATION.len() + xml.len())
+        }
+    };
+    rendered.unwrap_or(0)
+}
+
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|ct| ct.split(';').next())
+        .is_some_and(|ct| ct == "application/json" || ct.ends_with("+json"))
+}
+
+/// Las respuestas JSON salen compactas; con `?pretty=1` se re-serializan
+/// indentadas para depurar a mano, salvo que `MINIFY_JSON` lo prohíba.
+pub async fn pretty_json<B>(
+    State(state): State<Arc<AppState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let pretty = is_pretty(&state, request.uri().query());
+    let response = next.run(request).await;
+    if !pretty || !is_json(&response) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = hyper::body::to_bytes(body).await else {
+        return (parts.status, "error leyendo la respuesta").into_response();
+    };
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes)
+        .and_then(|v| serde_json::to_vec_pretty(&v))
+    {
+        Ok(pretty) => pretty,
+        Err(_) => bytes.to_vec(),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, boxed(Full::from(body)))
+}
+
+/// Formato del cuerpo según la cabecera `Accept`. JSON por defecto; XML solo
+/// si el cliente lo prefiere explícitamente (`application/xml` o `text/xml`
+/// con más `q` que JSON).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Json,
+    Xml,
+}
+
+impl Format {
+    fn from_headers(headers: &HeaderMap) -> Format {
+        let mut json_q: f32 = 0.0;
+        let mut xml_q: f32 = 0.0;
+        let accept = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|v| v.to_str().ok());
+        for range in accept.flat_map(|v| v.split(',')) {
+            let mut parts = range.split(';').map(str::trim);
+            let media = parts.next().unwrap_or_default().to_ascii_lowercase();
+            let q = parts
+                .filter_map(|p| p.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            match media.as_str() {
+                "application/xml" | "text/xml" => xml_q = xml_q.max(q),
+                "application/json" | "application/*" | "*/*" => json_q = json_q.max(q),
+                _ => {}
+            }
+        }
+        if xml_q > json_q {
+            Format::Xml
+        } else {
+            Format::Json
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Format {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Format::from_headers(&parts.headers))
+    }
+}
+
+/// Respuesta serializada en el formato negociado.
+pub struct Negotiated<T>(pub Format, pub T);
+
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        let Negotiated(format, value) = self;
+        match format {
+            Format::Json => Json(value).into_response(),
+            Format::Xml => match quick_xml::se::to_string_with_root("response", &value) {
+                Ok(xml) => (
+                    [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+                    format!("{XML_DECLARATION}{xml}"),
+                )
+                    .into_response(),
+                Err(e) => ApiError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "SERIALIZATION_ERROR",
+                    format!("No se pudo generar el XML: {e}"),
+                )
+                .into_response(),
+            },
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::http::{header, HeaderMap, StatusCode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::ApiError,
+    upstream::{self, Endpoint, Upstream},
+    AppState,
+};
+
+/// Máximo de universeIds por llamada a `/v1/games`.
+const MULTIGET_CHUNK: usize = 50;
+/// Largo máximo de `?locale=` (una etiqueta BCP 47 razonable).
+const MAX_LOCALE_LEN: usize = 35;
+/// `Accept-Language` más largos se ignoran en lugar de reenviarse.
+const MAX_ACCEPT_LANGUAGE_LEN: usize = 256;
+
+/// Metadatos de un juego según `games.roblox.com/v1/games`.
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GameDetails {
+    pub name: String,
+    pub root_place_id: Option<u64>,
+    pub visits: u64,
+    pub favorited_count: u64,
+    pub playing: u64,
+}
+
+/// Juego con sus passes, para `?groupBy=game`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GameGroup {
+    /// `null` para los passes que no vienen de un juego (catálogo).
+    pub universe_id: Option<u64>,
+    pub name: Option<String>,
+    pub root_place_id: Option<u64>,
+    pub visits: Option<u64>,
+    pub favorited_count: Option<u64>,
+    pub playing: Option<u64>,
+    pub pass_ids: Vec<u64>,
+}
+
+/// Pass con el juego al que pertenece, entrada de `group_by_game`.
+pub struct PassGame {
+    pub pass_id: u64,
+    pub universe_id: Option<u64>,
+    /// Metadatos ya obtenidos durante el escaneo, si los hay.
+    pub details: Option<Arc<GameDetails>>,
+}
+
+/// Idioma para los nombres de los juegos: `?locale=` o, si no viene,
+/// `Accept-Language`, que se reenvía tal cual a Roblox. Un `locale` que no
+/// parece una etiqueta de idioma es un 400; una cabecera rara se ignora.
+pub fn requested_locale(
+    query: Option<&str>,
+    headers: &HeaderMap,
+) -> Result<Option<String>, ApiError> {
+    if let Some(locale) = query {
+        let valid = (2..=MAX_LOCALE_LEN).contains(&locale.len())
+            && locale
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_QUERY",
+                format!("locale no es una etiqueta de idioma válida: '{locale}'"),
+            ));
+        }
+        return Ok(Some(locale.to_string()));
+    }
+    Ok(headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty() && v.len() <= MAX_ACCEPT_LANGUAGE_LEN)
+        .map(str::to_string))
+}
+
+/// Pide los metadatos de varios universos en lotes de `MULTIGET_CHUNK`, con
+/// los nombres en `locale` si se indica. Los universos que fallen
+/// simplemente no aparecen en el mapa.
+pub async fn fetch_game_details(
+    state: &AppState,
+    universe_ids: &[u64],
+    locale: Option<&str>,
+) -> HashMap<u64, Arc<GameDetails>> {
+    let mut details = HashMap::new();
+
+    for chunk in universe_ids.chunks(MULTIGET_CHUNK) {
+        let ids: Vec<String> = chunk.iter().map(u64::to_string).collect();
+        let url = format!(
+            "{}/v1/games?universeIds={}",
+            state.config.upstream_url(Upstream::Games),
+            ids.join(",")
+        );
+        debug!("Pidiendo metadatos de {} juegos en {}", chunk.len(), url);
+
+        let resp = match locale {
+            Some(locale) => {
+                upstream::get_localized(state, Endpoint::GamesMultiget, &url, locale).await
+            }
+            None => upstream::get(state, Endpoint::GamesMultiget, &url).await,
+        };
+        let resp = match resp {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Error HTTP al pedir metadatos de juegos: {e}");
+                continue;
+            }
+        };
+        if !resp.status().is_success() {
+            warn!("Metadatos de juegos HTTP {}", resp.status());
+            continue;
+        }
+        let json: serde_json::Value = match resp.json().await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Error parseando JSON de metadatos de juegos: {e}");
+                continue;
+            }
+        };
+
+        let Some(games) = json.get("data").and_then(|v| v.as_array()) else {
+            info!("Metadatos de juegos sin 'data'");
+            state
+                .quarantine
+                .record(Endpoint::GamesMultiget, "listado sin 'data'", &json);
+            continue;
+        };
+        for game in games {
+            let Some(id) = game.get("id").and_then(|v| v.as_u64()) else {
+                state
+                    .quarantine
+                    .record(Endpoint::GamesMultiget, "juego sin id", game);
+                continue;
+            };
+            let count = |field: &str| game.get(field).and_then(|v| v.as_u64()).unwrap_or(0);
+            details.insert(
+                id,
+                Arc::new(GameDetails {
+                    name: game
+                        .get("name")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    root_place_id: game.get("rootPlaceId").and_then(|v| v.as_u64()),
+                    visits: count("visits"),
+                    favorited_count: count("favoritedCount"),
+                    playing: count("playing"),
+                }),
+            );
+        }
+    }
+
+    details
+}
+
+/// Agrupa los passes por universo, en el orden en que aparecen.
+/// Los passes sin universo (p. ej. del catálogo) van en un grupo aparte al final.
+/// Solo se piden a Roblox los metadatos que no vinieron ya del escaneo, y
+/// ninguno sin `fetch_missing` (servicio degradado). Con `locale` no sirven
+/// los del escaneo, que vienen en el idioma por defecto: se piden todos.
+pub async fn group_by_game(
+    state: &AppState,
+    passes: &[PassGame],
+    fetch_missing: bool,
+    locale: Option<&str>,
+) -> Vec<GameGroup> {
+    let mut order: Vec<Option<u64>> = Vec::new();
+    let mut ids_by_game: HashMap<Option<u64>, Vec<u64>> = HashMap::new();
+    let mut details: HashMap<u64, Arc<GameDetails>> = HashMap::new();
+    for pass in passes {
+        let ids = ids_by_game.entry(pass.universe_id).or_insert_with(|| {
+            order.push(pass.universe_id);
+            Vec::new()
+        });
+        ids.push(pass.pass_id);
+        let scanned = pass.details.as_ref().filter(|_| locale.is_none());
+        if let (Some(universe_id), Some(d)) = (pass.universe_id, scanned) {
+            details.insert(universe_id, d.clone());
+        }
+    }
+    order.sort_by_key(Option::is_none);
+
+    let missing: Vec<u64> = order
+        .iter()
+        .flatten()
+        .filter(|id| !details.contains_key(id))
+        .copied()
+        .collect();
+    if fetch_missing && !missing.is_empty() {
+        details.extend(fetch_game_details(state, &missing, locale).await);
+    }
+
+    order
+        .into_iter()
+        .map(|universe_id| {
+            let pass_ids = ids_by_game.remove(&universe_id).unwrap_or_default();
+            let meta = universe_id.and_then(|id| details.get(&id));
+            GameGroup {
+                universe_id,
+                name: meta.map(|d| d.name.clone()),
+                root_place_id: meta.and_then(|d| d.root_place_id),
+                visits: meta.map(|d| d.visits),
+                favorited_count: meta.map(|d| d.favorited_count),
+                playing: meta.map(|d| d.playing),
+                pass_ids,
+            }
+        })
+        .collect()
+}
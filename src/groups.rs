@@ -0,0 +1,299 @@
+//! Juegos de grupos. Muchos creadores publican su juego de donaciones bajo un
+//! grupo, y esos juegos no salen en `/v2/users/{id}/games`.
+//!
+//! `/group/:id/passes` escanea los juegos públicos de un grupo igual que
+//! `/user/:id/passes` los de un usuario, y `?includeGroups=true` en la ruta
+//! del usuario añade los juegos de los grupos que posee (hasta
+//! `MAX_GROUPS`). Los grupos en los que solo es miembro no cuentan: lo que
+//! se vende ahí no es suyo.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{OriginalUri, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::future::join_all;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    duplicates,
+    error::ApiError,
+    extract::Query,
+    guidance::ScanStats,
+    links,
+    models::{FetchOptions, PublicGame},
+    roblox::{api, client},
+    tenant::MaybeTenant,
+    thumbnails,
+    upstream::{self, Endpoint, Upstream},
+    views, AppState,
+};
+
+/// Grupos de un usuario que se escanean como mucho con `includeGroups`.
+const MAX_GROUPS: usize = 10;
+/// Rango del dueño de un grupo en Roblox.
+const OWNER_RANK: u64 = 255;
+
+/// Query de `/group/:id/passes`: las opciones de `/user/:id/passes` que
+/// tienen sentido para un grupo.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupPassesQuery {
+    max_passes_per_game: Option<usize>,
+    /// Por defecto, `ACTIVE_GAMES_ONLY`.
+    active_games_only: Option<bool>,
+    /// Por defecto, `DEFAULT_THUMBNAILS`.
+    thumbnails: Option<bool>,
+    /// Campos opcionales de cada pass (ver `views`). Por defecto,
+    /// `DEFAULT_FIELDS`.
+    fields: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupPassesResponse {
+    ok: bool,
+    group_id: u64,
+    count: usize,
+    passes: Vec<views::PassView>,
+    links: links::ResponseLinks,
+}
+
+/// Ids de los grupos que posee `user_id`
+/// (`groups.roblox.com/v1/users/{id}/groups/roles`). Si Roblox falla se
+/// anota en `stats` y se sigue sin grupos.
+async fn owned_groups(state: &AppState, user_id: u64, stats: &ScanStats) -> Vec<u64> {
+    let url = format!(
+        "{}/v1/users/{user_id}/groups/roles",
+        state.config.upstream_url(Upstream::Groups)
+    );
+    let data: serde_json::Value = match upstream::get(state, Endpoint::UserGroups, &url).await {
+        Ok(resp) if resp.status().is_success() => match resp.json().await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Error parseando JSON de grupos para userId={user_id}: {e}");
+                stats.upstream_error();
+                return Vec::new();
+            }
+        },
+        Ok(resp) => {
+            warn!(
+                "HTTP {} al pedir grupos para userId={user_id}",
+                resp.status()
+            );
+            stats.upstream_status(resp.status());
+            return Vec::new();
+        }
+        Err(e) => {
+            warn!("Error HTTP al pedir grupos para userId={user_id}: {e}");
+            stats.upstream_error();
+            return Vec::new();
+        }
+    };
+
+    let Some(api::Page {
+        data: memberships, ..
+    }) = state
+        .quarantine
+        .deserialize(Endpoint::UserGroups, "listado sin 'data'", &data)
+    else {
+        return Vec::new();
+    };
+    let memberships: Vec<api::GroupMembership> = state.quarantine.deserialize_all(
+        Endpoint::UserGroups,
+        "grupo con forma desconocida",
+        &memberships,
+    );
+    let owned: Vec<u64> = memberships
+        .into_iter()
+        .filter(|m| {
+            m.group.owner.as_ref().map(|o| o.user_id) == Some(user_id) || m.role.rank == OWNER_RANK
+        })
+        .map(|m| m.group.id)
+        .collect();
+    if owned.len() > MAX_GROUPS {
+        info!(
+            "userId={user_id} posee {} grupos; se escanean solo los {MAX_GROUPS} primeros",
+            owned.len()
+        );
+    }
+    owned.into_iter().take(MAX_GROUPS).collect()
+}
+
+/// Juegos públicos de un grupo (`/v2/groups/{groupId}/gamesV2`). `None`
+/// (anotado en `stats`) si la primera página falla o el grupo no existe.
+async fn fetch_group_games(
+    state: &AppState,
+    group_id: u64,
+    stats: &ScanStats,
+) -> Option<Vec<PublicGame>> {
+    let url = format!(
+        "{}/v2/groups/{group_id}/gamesV2?accessFilter=2&limit=50&sortOrder=Asc",
+        state.config.upstream_url(Upstream::Games)
+    );
+    let what = format!("juegos públicos para groupId={group_id}");
+    let max_pages = state.config.upstream_max_pages;
+    let client::Listing {
+        items: games,
+        error,
+        ..
+    } = client::fetch_listing(state, Endpoint::GroupGames, &url, &what, max_pages).await;
+    if let Some(error) = error {
+        let missing = matches!(
+            error.status,
+            Some(reqwest::StatusCode::BAD_REQUEST | reqwest::StatusCode::NOT_FOUND)
+        );
+        if error.page == 0 && missing {
+            info!("groupId={group_id} no existe");
+            // El dueño de la lista es el grupo.
+            stats.user_not_found();
+            return None;
+        }
+        error.record(stats);
+        if error.page == 0 {
+            return None;
+        }
+    }
+
+    let games: Vec<PublicGame> = state
+        .quarantine
+        .deserialize_all::<api::Game>(Endpoint::GroupGames, "juego con forma desconocida", &games)
+        .into_iter()
+        .map(PublicGame::from)
+        .collect();
+    info!(
+        "Juegos públicos encontrados para groupId={group_id}: {}",
+        games.len()
+    );
+    Some(games)
+}
+
+/// Juegos de todos los grupos que posee `user_id`, para `includeGroups`.
+pub(crate) async fn owned_group_games(
+    state: &AppState,
+    user_id: u64,
+    stats: &ScanStats,
+) -> Vec<PublicGame> {
+    let groups = owned_groups(state, user_id, stats).await;
+    // Un grupo que no existe aquí es solo un dato viejo de Roblox; no debe
+    // marcar al usuario como inexistente.
+    let group_stats = ScanStats::default();
+    let games = join_all(
+        groups
+            .iter()
+            .map(|&group_id| fetch_group_games(state, group_id, &group_stats)),
+    )
+    .await;
+    if group_stats.has_upstream_errors() {
+        stats.upstream_error();
+    }
+    games.into_iter().flatten().flatten().collect()
+}
+
+/// Error para una lista vacía de un grupo, como `guidance::failure`.
+fn failure(group_id: u64, stats: &ScanStats) -> Option<ApiError> {
+    if stats.is_user_missing() {
+        return Some(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "GROUP_NOT_FOUND",
+            format!("Roblox no tiene ningún grupo con groupId {group_id}"),
+        ));
+    }
+    if stats.is_rate_limited() {
+        return Some(ApiError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "UPSTREAM_RATE_LIMITED",
+            format!("Roblox está limitando las peticiones para groupId {group_id}; reintenta en unos segundos"),
+        ));
+    }
+    if stats.has_upstream_errors() {
+        return Some(ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            "UPSTREAM_ERROR",
+            format!("No se pudo consultar Roblox para groupId {group_id}"),
+        ));
+    }
+    None
+}
+
+/// `GET /group/:id/passes`. Sin caché ni fotos: esas van por usuario. Cada
+/// consulta es un escaneo fresco, así que sin presupuesto (ver `budget`) es
+/// un 429.
+pub async fn get_passes(
+    State(state): State<Arc<AppState>>,
+    Path(group_id): Path<u64>,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<GroupPassesQuery>,
+    MaybeTenant(tenant): MaybeTenant,
+) -> Result<Response, ApiError> {
+    info!("/group/{group_id}/passes");
+    let fields = views::Fields::requested(query.fields.as_deref(), &state.config)?;
+    if query.max_passes_per_game == Some(0) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_QUERY",
+            "maxPassesPerGame debe ser al menos 1",
+        ));
+    }
+    let budget = state
+        .fresh_budget
+        .for_tenant(tenant.as_ref(), &state.config, true);
+    if let Some(budget) = budget.filter(|b| !b.granted) {
+        let tenant_id = tenant.as_ref().map_or("", |t| t.id.as_str());
+        return Ok(budget.exhausted(tenant_id, &format!("groupId {group_id}")));
+    }
+    let opts = FetchOptions {
+        max_passes_per_game: query.max_passes_per_game,
+        active_games_only: query
+            .active_games_only
+            .unwrap_or(state.config.active_games_only),
+        ..FetchOptions::default()
+    };
+
+    let mut passes = match fetch_group_games(&state, group_id, &opts.stats).await {
+        Some(games) => {
+            client::passes_from_games(&state, &format!("groupId={group_id}"), games, &opts).await
+        }
+        None => Vec::new(),
+    };
+    if passes.is_empty() {
+        if let Some(error) = failure(group_id, &opts.stats) {
+            return Err(error);
+        }
+    }
+
+    let level = state.degradation.level(&state.upstreams);
+    let thumbnails = query.thumbnails.unwrap_or(state.config.default_thumbnails);
+    if thumbnails && level < crate::degradation::Level::NoThumbnails {
+        let ids: Vec<u64> = passes.iter().map(|p| p.id).collect();
+        let mut icons = thumbnails::resolve_icons(&state, &ids).await;
+        for pass in &mut passes {
+            pass.icon_url = icons.remove(&pass.id);
+        }
+    }
+    for pass in &mut passes {
+        pass.links = Some(links::PassLinks::new(pass.id, None));
+    }
+    duplicates::disambiguate(&mut passes);
+
+    let mut response = Json(GroupPassesResponse {
+        ok: true,
+        group_id,
+        count: passes.len(),
+        passes: views::render(&passes, fields),
+        links: links::ResponseLinks::new(
+            uri.path_and_query().map_or(uri.path(), |pq| pq.as_str()),
+            "group-passes",
+        ),
+    })
+    .into_response();
+    if let Some(budget) = budget {
+        budget.apply(response.headers_mut());
+    }
+    Ok(response)
+}
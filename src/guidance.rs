@@ -0,0 +1,364 @@
+//! Explicación estructurada de una lista vacía (`guidance`): qué se revisó y
+//! por qué no quedó ningún pass, para que el juego pueda mostrar pasos
+//! concretos ("pon tus passes a la venta") en lugar de un "sin passes".
+//! Si la lista está vacía porque Roblox falló, no hay guía sino un error
+//! (`failure`).
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Mutex,
+};
+
+use axum::http::StatusCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+
+/// Contadores de un escaneo. Se comparten (vía `FetchOptions`) entre las
+/// fuentes, que pueden correr en paralelo con `?mode=race`.
+#[derive(Default)]
+pub struct ScanStats {
+    public_games: AtomicUsize,
+    passes_found: AtomicUsize,
+    off_sale: AtomicUsize,
+    zero_price: AtomicUsize,
+    /// Llamadas a Roblox fallidas: con alguna, la lista vacía no es fiable.
+    upstream_errors: AtomicUsize,
+    /// De esas, las que Roblox cortó con un 429.
+    rate_limited: AtomicUsize,
+    /// Roblox no conoce el userId.
+    user_missing: AtomicBool,
+    /// Juegos (universeIds) con más passes que `GAME_PASSES_MAX_PAGES`.
+    truncated_games: Mutex<Vec<u64>>,
+    /// Passes con un precio imposible, descartados.
+    invalid_price: AtomicUsize,
+    /// Passes que no se miraron por `?maxPassesPerGame`.
+    over_game_cap: AtomicUsize,
+    /// Juegos que no se escanearon, y por qué.
+    skipped_games: Mutex<Vec<SkippedGame>>,
+    /// Venció el plazo del escaneo: la lista tiene lo recogido hasta ahí.
+    deadline_exceeded: AtomicBool,
+    /// Elementos del inventario sin mirar por `INVENTORY_MAX_LOOKUPS`.
+    inventory_unchecked: AtomicUsize,
+}
+
+/// Por qué no se escaneó un juego.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum SkipReason {
+    /// Fuera del tope `MAX_UNIVERSES` (los menos populares).
+    MaxUniverses,
+    /// Sin actualizar en `ACTIVE_GAME_DAYS`, con `activeGamesOnly`.
+    Inactive,
+    /// Roblox falló al listar sus passes.
+    UpstreamError,
+    /// Venció el plazo del escaneo antes de listarlos.
+    Deadline,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedGame {
+    pub universe_id: u64,
+    pub reason: SkipReason,
+}
+
+impl ScanStats {
+    pub fn add_public_games(&self, n: usize) {
+        self.public_games.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn pass_found(&self) {
+        self.passes_found.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn off_sale(&self) {
+        self.off_sale.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn zero_price(&self) {
+        self.zero_price.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn upstream_error(&self) {
+        self.upstream_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Respuesta HTTP de error de Roblox; cuenta como `upstream_error`.
+    pub fn upstream_status(&self, status: reqwest::StatusCode) {
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            self.rate_limited.fetch_add(1, Ordering::Relaxed);
+        }
+        self.upstream_error();
+    }
+
+    pub fn user_not_found(&self) {
+        self.user_missing.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_user_missing(&self) -> bool {
+        self.user_missing.load(Ordering::Relaxed)
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        self.rate_limited.load(Ordering::Relaxed) > 0
+    }
+
+    pub fn has_upstream_errors(&self) -> bool {
+        self.upstream_errors.load(Ordering::Relaxed) > 0
+    }
+
+    /// La lista de passes de `universe_id` se cortó en el máximo de páginas.
+    pub fn passes_truncated(&self, universe_id: u64) {
+        self.truncated_games.lock().unwrap().push(universe_id);
+    }
+
+    /// Juegos con la lista de passes cortada, en orden.
+    pub fn truncated_games(&self) -> Vec<u64> {
+        let mut games = self.truncated_games.lock().unwrap().clone();
+        games.sort_unstable();
+        games.dedup();
+        games
+    }
+
+    pub fn invalid_price(&self) {
+        self.invalid_price.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn over_game_cap(&self, n: usize) {
+        self.over_game_cap.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn inventory_unchecked(&self, n: usize) {
+        self.inventory_unchecked.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn inventory_unchecked_count(&self) -> usize {
+        self.inventory_unchecked.load(Ordering::Relaxed)
+    }
+
+    pub fn skip_game(&self, universe_id: u64, reason: SkipReason) {
+        self.skipped_games.lock().unwrap().push(SkippedGame {
+            universe_id,
+            reason,
+        });
+    }
+
+    /// Juegos sin escanear, por universeId.
+    pub fn skipped_games(&self) -> Vec<SkippedGame> {
+        let mut games = self.skipped_games.lock().unwrap().clone();
+        games.sort_by_key(|g| g.universe_id);
+        games
+    }
+
+    pub fn deadline_exceeded(&self) {
+        self.deadline_exceeded.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_partial(&self) -> bool {
+        self.deadline_exceeded.load(Ordering::Relaxed)
+    }
+
+    /// Sin errores de Roblox ni cortes (por páginas o por el plazo): la
+    /// lista es la entera, y solo entonces sirve de foto.
+    pub fn is_complete(&self) -> bool {
+        !self.has_upstream_errors() && !self.is_partial() && self.truncated_games().is_empty()
+    }
+
+    /// Passes encontrados que no salen en la lista, por motivo.
+    pub fn dropped(&self) -> Dropped {
+        Dropped {
+            off_sale: self.off_sale.load(Ordering::Relaxed),
+            zero_price: self.zero_price.load(Ordering::Relaxed),
+            invalid_price: self.invalid_price.load(Ordering::Relaxed),
+            over_game_cap: self.over_game_cap.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Contadores que explican una lista vacía, para exportarlos con la
+    /// caché (que solo guarda escaneos sin errores de Roblox).
+    pub fn counts(&self) -> ScanCounts {
+        ScanCounts {
+            public_games: self.public_games.load(Ordering::Relaxed),
+            passes_found: self.passes_found.load(Ordering::Relaxed),
+            off_sale: self.off_sale.load(Ordering::Relaxed),
+            zero_price: self.zero_price.load(Ordering::Relaxed),
+            user_missing: self.is_user_missing(),
+            truncated_games: self.truncated_games(),
+            invalid_price: self.invalid_price.load(Ordering::Relaxed),
+            over_game_cap: self.over_game_cap.load(Ordering::Relaxed),
+            skipped_games: self.skipped_games(),
+        }
+    }
+}
+
+/// Passes descartados de un escaneo (ver `warnings`).
+pub struct Dropped {
+    pub off_sale: usize,
+    pub zero_price: usize,
+    pub invalid_price: usize,
+    pub over_game_cap: usize,
+}
+
+/// `ScanStats` exportados (ver `cache`).
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanCounts {
+    public_games: usize,
+    passes_found: usize,
+    off_sale: usize,
+    zero_price: usize,
+    user_missing: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    truncated_games: Vec<u64>,
+    #[serde(default)]
+    invalid_price: usize,
+    #[serde(default)]
+    over_game_cap: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skipped_games: Vec<SkippedGame>,
+}
+
+impl From<ScanCounts> for ScanStats {
+    fn from(counts: ScanCounts) -> Self {
+        ScanStats {
+            public_games: counts.public_games.into(),
+            passes_found: counts.passes_found.into(),
+            off_sale: counts.off_sale.into(),
+            zero_price: counts.zero_price.into(),
+            user_missing: counts.user_missing.into(),
+            truncated_games: counts.truncated_games.into(),
+            invalid_price: counts.invalid_price.into(),
+            over_game_cap: counts.over_game_cap.into(),
+            skipped_games: counts.skipped_games.into(),
+            ..ScanStats::default()
+        }
+    }
+}
+
+/// Lo que se revisó durante el escaneo.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Checked {
+    pub public_games: usize,
+    pub passes_found: usize,
+    pub off_sale: usize,
+    pub zero_price: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReasonCode {
+    NoPublicGames,
+    NoPasses,
+    PassesOffSale,
+    PricesZero,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct Reason {
+    pub code: ReasonCode,
+    /// Qué puede hacer el creador, listo para mostrar.
+    pub message: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Guidance {
+    pub checked: Checked,
+    pub reasons: Vec<Reason>,
+    /// Ayuda para crear el primer pass (`?price=` obligatorio).
+    pub create_pass_link: String,
+}
+
+/// Guía para una lista vacía, o `None` si alguna llamada a Roblox falló y no
+/// se puede afirmar que el usuario no tenga passes.
+pub fn explain(user_id: u64, stats: &ScanStats) -> Option<Guidance> {
+    if stats.has_upstream_errors() || stats.is_partial() {
+        return None;
+    }
+    let checked = Checked {
+        public_games: stats.public_games.load(Ordering::Relaxed),
+        passes_found: stats.passes_found.load(Ordering::Relaxed),
+        off_sale: stats.off_sale.load(Ordering::Relaxed),
+        zero_price: stats.zero_price.load(Ordering::Relaxed),
+    };
+
+    let mut reasons = Vec::new();
+    if checked.public_games == 0 {
+        reasons.push(Reason {
+            code: ReasonCode::NoPublicGames,
+            message: "No tienes juegos públicos: publica un juego para poder venderle passes."
+                .to_string(),
+        });
+    }
+    if checked.passes_found == 0 {
+        reasons.push(Reason {
+            code: ReasonCode::NoPasses,
+            message: "No hemos encontrado ningún pass: crea uno en el Creator Hub.".to_string(),
+        });
+    }
+    if checked.off_sale > 0 {
+        reasons.push(Reason {
+            code: ReasonCode::PassesOffSale,
+            message: format!(
+                "{} passes no están a la venta: activa \"Item for Sale\" en cada uno.",
+                checked.off_sale
+            ),
+        });
+    }
+    if checked.zero_price > 0 {
+        reasons.push(Reason {
+            code: ReasonCode::PricesZero,
+            message: format!(
+                "{} passes tienen precio 0: ponles un precio de al menos 1 Robux.",
+                checked.zero_price
+            ),
+        });
+    }
+
+    Some(Guidance {
+        checked,
+        reasons,
+        create_pass_link: format!("/user/{user_id}/create-pass-link"),
+    })
+}
+
+/// Error para una lista vacía que no se puede dar por buena: 404 si Roblox
+/// no conoce al usuario, 429 si nos limitó, 504 si venció el plazo del
+/// escaneo sin nada recogido y 502 si falló de otro modo.
+/// `None` si el escaneo fue completo (la lista vacía es real).
+pub fn failure(user_id: u64, stats: &ScanStats) -> Option<ApiError> {
+    if stats.is_user_missing() {
+        return Some(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "USER_NOT_FOUND",
+            format!("Roblox no tiene ningún usuario con userId {user_id}"),
+        ));
+    }
+    if stats.is_rate_limited() {
+        return Some(ApiError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "UPSTREAM_RATE_LIMITED",
+            format!("Roblox está limitando las peticiones para userId {user_id}; reintenta en unos segundos"),
+        ));
+    }
+    if stats.is_partial() {
+        return Some(ApiError::new(
+            StatusCode::GATEWAY_TIMEOUT,
+            "SCAN_DEADLINE_EXCEEDED",
+            format!(
+                "Roblox no respondió a tiempo para userId {user_id}; reintenta en unos segundos"
+            ),
+        ));
+    }
+    if stats.has_upstream_errors() {
+        return Some(ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            "UPSTREAM_ERROR",
+            format!("No se pudo consultar Roblox para userId {user_id}"),
+        ));
+    }
+    None
+}
@@ -0,0 +1,291 @@
+use std::{sync::Arc, time::Instant};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use futures::future::join_all;
+
+use crate::{
+    roblox::api,
+    upstream::{self, Endpoint, Outcome, Upstream},
+    AppState,
+};
+
+#[derive(Serialize, JsonSchema)]
+pub struct Liveness {
+    ok: bool,
+}
+
+/// Probe mínimo para orquestadores: 204 sin cuerpo y sin tocar Roblox.
+pub async fn ping() -> StatusCode {
+    StatusCode::NO_CONTENT
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerTime {
+    ok: bool,
+    /// Milisegundos desde la época Unix.
+    epoch_millis: i64,
+    /// La misma hora en RFC 3339.
+    time: String,
+}
+
+/// Hora del servidor, para que los clientes (servidores de Roblox con el
+/// reloj desviado) calculen su desfase antes de firmar marcas de tiempo.
+pub async fn time() -> Response {
+    let now = Utc::now();
+    let mut response = Json(ServerTime {
+        ok: true,
+        epoch_millis: now.timestamp_millis(),
+        time: now.to_rfc3339(),
+    })
+    .into_response();
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+/// Liveness: el proceso responde. No mira upstreams.
+pub async fn healthz() -> Json<Liveness> {
+    Json(Liveness { ok: true })
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct Readiness {
+    ok: bool,
+    /// `ready`, `degraded` (falla una fuente opcional), `unready` (falla la
+    /// búsqueda por juegos) o `draining`.
+    status: &'static str,
+    /// Una por fuente configurada; vacío al drenar, que no se comprueba.
+    sources: Vec<SourceReport>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct SourceReport {
+    /// `games` o `catalog`.
+    source: &'static str,
+    host: &'static str,
+    /// Si falla, la instancia no está lista; si no, solo queda `degraded`.
+    required: bool,
+    /// `ok`, `failed` o `timeout`.
+    status: &'static str,
+    latency_ms: u64,
+    error: Option<String>,
+}
+
+/// De dónde saca passes la instancia. Los juegos son imprescindibles; el
+/// catálogo solo es el fallback, y sin él se sigue respondiendo.
+#[derive(Clone, Copy)]
+enum Source {
+    Games,
+    #[cfg(feature = "catalog")]
+    Catalog,
+}
+
+impl Source {
+    fn configured() -> Vec<Source> {
+        vec![
+            Source::Games,
+            #[cfg(feature = "catalog")]
+            Source::Catalog,
+        ]
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Source::Games => "games",
+            #[cfg(feature = "catalog")]
+            Source::Catalog => "catalog",
+        }
+    }
+
+    fn required(self) -> bool {
+        matches!(self, Source::Games)
+    }
+
+    /// La primera página de lo que pide el escaneo, reducida al mínimo.
+    fn request(self, state: &AppState) -> (Endpoint, String) {
+        let user_id = state.config.readiness_probe_user_id;
+        match self {
+            Source::Games => (
+                Endpoint::UserGames,
+                format!(
+                    "{}/v2/users/{user_id}/games?accessFilter=2&limit=10&sortOrder=Asc",
+                    state.config.upstream_url(Upstream::Games)
+                ),
+            ),
+            #[cfg(feature = "catalog")]
+            Source::Catalog => (
+                Endpoint::CatalogSearch,
+                format!(
+                    "{}/v1/search/items/details?creatorTargetId={user_id}&creatorType=User&itemType=Asset&includeNotForSale=true&limit=10&sortType=Updated",
+                    state.config.upstream_url(Upstream::Catalog)
+                ),
+            ),
+        }
+    }
+}
+
+/// Una llamada a la fuente con `READINESS_PROBE_TIMEOUT_SECS` de plazo. Vale
+/// si responde 2xx con un listado (`api::Page`).
+async fn probe(state: &AppState, source: Source) -> SourceReport {
+    let (endpoint, url) = source.request(state);
+    let timeout = state.config.readiness_probe_timeout;
+    let started = Instant::now();
+    let checked = tokio::time::timeout(timeout, async {
+        let resp = upstream::get(state, endpoint, &url)
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(format!("HTTP {status}"));
+        }
+        resp.json::<api::Page>()
+            .await
+            .map(drop)
+            .map_err(|_| "respuesta sin el listado esperado".to_string())
+    })
+    .await;
+    let (status, error) = match checked {
+        Ok(Ok(())) => ("ok", None),
+        Ok(Err(e)) => ("failed", Some(e)),
+        Err(_) => (
+            "timeout",
+            Some(format!("sin respuesta en {}s", timeout.as_secs())),
+        ),
+    };
+    SourceReport {
+        source: source.name(),
+        host: endpoint.upstream().host(),
+        required: source.required(),
+        status,
+        latency_ms: started.elapsed().as_millis() as u64,
+        error,
+    }
+}
+
+/// Readiness para el balanceador: 503 mientras la instancia drena (ver
+/// `drain`) o si no responde la búsqueda por juegos. Las fuentes se
+/// comprueban a la vez, cada una con su plazo; si solo falla el catálogo,
+/// sigue lista y queda `degraded`.
+pub async fn readyz(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Readiness>) {
+    if state.drain.is_draining() {
+        let body = Readiness {
+            ok: false,
+            status: "draining",
+            sources: Vec::new(),
+        };
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(body));
+    }
+    let sources = join_all(
+        Source::configured()
+            .into_iter()
+            .map(|source| probe(&state, source)),
+    )
+    .await;
+    let failing = |required: bool| {
+        sources
+            .iter()
+            .any(|s| s.required == required && s.status != "ok")
+    };
+    let (code, status) = if failing(true) {
+        (StatusCode::SERVICE_UNAVAILABLE, "unready")
+    } else if failing(false) {
+        (StatusCode::OK, "degraded")
+    } else {
+        (StatusCode::OK, "ready")
+    };
+    let body = Readiness {
+        ok: code == StatusCode::OK,
+        status,
+        sources,
+    };
+    (code, Json(body))
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepHealth {
+    ok: bool,
+    /// `ok`, `degraded` (algún upstream en cooldown) o `draining`.
+    status: &'static str,
+    uptime_secs: u64,
+    upstreams: Vec<UpstreamReport>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct UpstreamReport {
+    host: &'static str,
+    /// `available` o `cooldown`.
+    state: &'static str,
+    calls: u64,
+    failures: u64,
+    /// Código HTTP de la última respuesta (`null` si no hubo o fue error de red).
+    last_status: Option<u16>,
+    cooldown: Option<CooldownReport>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct CooldownReport {
+    strikes: u32,
+    since_secs: u64,
+    probe_interval_secs: u64,
+    next_probe_in_secs: u64,
+}
+
+/// Estado detallado, incluyendo los upstreams en mantenimiento.
+pub async fn healthz_deep(State(state): State<Arc<AppState>>) -> Json<DeepHealth> {
+    let now = Instant::now();
+    let upstreams: Vec<UpstreamReport> = state
+        .upstreams
+        .snapshot()
+        .into_iter()
+        .map(|(upstream, stats)| UpstreamReport {
+            host: upstream.host(),
+            state: if stats.cooldown.is_some() {
+                "cooldown"
+            } else {
+                "available"
+            },
+            calls: stats.calls,
+            failures: stats.failures,
+            last_status: match stats.last {
+                Some((Outcome::Status(code), _)) => Some(code),
+                _ => None,
+            },
+            cooldown: stats.cooldown.map(|c| CooldownReport {
+                strikes: c.strikes,
+                since_secs: now.duration_since(c.since).as_secs(),
+                probe_interval_secs: c.interval.as_secs(),
+                next_probe_in_secs: c.until.saturating_duration_since(now).as_secs(),
+            }),
+        })
+        .collect();
+
+    let degraded = upstreams.iter().any(|u| u.cooldown.is_some());
+    let status = if state.drain.is_draining() {
+        "draining"
+    } else if degraded {
+        "degraded"
+    } else {
+        "ok"
+    };
+    Json(DeepHealth {
+        ok: status == "ok",
+        status,
+        uptime_secs: state.started_at.elapsed().as_secs(),
+        upstreams,
+    })
+}
@@ -0,0 +1,141 @@
+//! Salida `?format=jsonapi`: documento conforme a JSON:API 1.1 con los passes
+//! como recursos `gamepass`, relacionados con su `game` y su `user` (el
+//! creador), que van en `included`.
+
+use std::collections::HashSet;
+
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{format, models::Gamepass, routes::ApiResponse};
+
+pub const CONTENT_TYPE: &str = "application/vnd.api+json";
+
+#[derive(Serialize)]
+struct Document {
+    data: Vec<Resource>,
+    included: Vec<Resource>,
+    meta: Map<String, Value>,
+    jsonapi: Value,
+}
+
+#[derive(Serialize)]
+struct Resource {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    id: String,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    attributes: Map<String, Value>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    relationships: Map<String, Value>,
+}
+
+fn linkage(kind: &str, id: u64) -> Value {
+    json!({ "data": { "type": kind, "id": id.to_string() } })
+}
+
+fn attributes(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(map) => map.into_iter().filter(|(_, v)| !v.is_null()).collect(),
+        _ => Map::new(),
+    }
+}
+
+/// `passes` son los internos de `response.passes`, en el mismo orden: de
+/// ellos salen las relaciones; los atributos, de la vista.
+fn document(response: &ApiResponse, passes: &[Gamepass]) -> Document {
+    let user = response.user_id;
+    let mut included = vec![Resource {
+        kind: "user",
+        id: user.to_string(),
+        attributes: Map::new(),
+        relationships: Map::new(),
+    }];
+    let mut seen_games = HashSet::new();
+
+    let data = response
+        .passes
+        .iter()
+        .zip(passes)
+        .map(|(view, pass)| {
+            let mut relationships = Map::new();
+            relationships.insert("creator".into(), linkage("user", user));
+            if let Some(universe_id) = pass.universe_id {
+                relationships.insert("game".into(), linkage("game", universe_id));
+                if seen_games.insert(universe_id) {
+                    included.push(Resource {
+                        kind: "game",
+                        id: universe_id.to_string(),
+                        attributes: pass.game.as_ref().map_or_else(Map::new, |game| {
+                            attributes(json!({
+                                "name": game.name,
+                                "rootPlaceId": game.root_place_id,
+                                "visits": game.visits,
+                                "favoritedCount": game.favorited_count,
+                                "playing": game.playing,
+                            }))
+                        }),
+                        relationships: Map::new(),
+                    });
+                }
+            }
+            Resource {
+                kind: "gamepass",
+                id: pass.id.to_string(),
+                attributes: {
+                    let mut attributes = attributes(json!(view));
+                    // `id` ya es el del recurso; los enlaces no son atributos.
+                    attributes.shift_remove("id");
+                    attributes.shift_remove("links");
+                    attributes
+                },
+                relationships,
+            }
+        })
+        .collect();
+
+    let mut meta = attributes(json!({
+        "count": response.count,
+        "totalCount": response.total_count,
+        "hint": response.hint,
+    }));
+    if response.truncated {
+        meta.insert("truncated".into(), Value::Bool(true));
+    }
+    if response.partial {
+        meta.insert("partial".into(), Value::Bool(true));
+    }
+    if let Some(level) = response.degradation {
+        meta.insert("degradation".into(), level.as_str().into());
+    }
+    if !response.warnings.is_empty() {
+        meta.insert("warnings".into(), json!(response.warnings));
+    }
+
+    Document {
+        data,
+        included,
+        meta,
+        jsonapi: json!({ "version": "1.1" }),
+    }
+}
+
+/// Respuesta JSON:API con su media type.
+pub fn render(response: &ApiResponse, passes: &[Gamepass]) -> Response {
+    (
+        [(header::CONTENT_TYPE, CONTENT_TYPE)],
+        Json(document(response, passes)),
+    )
+        .into_response()
+}
+
+/// Bytes del cuerpo de `render` (indentado si `pretty`), para
+/// `MAX_RESPONSE_BYTES`.
+pub fn rendered_len(response: &ApiResponse, passes: &[Gamepass], pretty: bool) -> usize {
+    format::rendered_len(format::Format::Json, pretty, &document(response, passes))
+}
@@ -0,0 +1,324 @@
+//! API de donaciones: los passes a la venta de un creador de Roblox.
+//!
+//! El binario solo lee la configuración y llama a `serve`. Otro binario o un
+//! test de integración puede montar la misma API con `AppState::new` y
+//! `routes::build_router`, sin escuchar en ningún puerto.
+
+mod access_log;
+mod admin;
+mod backoff;
+mod batch;
+mod boot;
+mod booths;
+mod budget;
+pub mod cache;
+mod campaigns;
+#[cfg(feature = "clothing")]
+mod clothing;
+mod collections;
+mod compare;
+pub mod config;
+pub mod crash;
+mod dedupe;
+mod degradation;
+mod drain;
+mod duplicates;
+mod error;
+mod extract;
+#[cfg(feature = "fault-injection")]
+mod faults;
+mod format;
+mod games;
+mod groups;
+mod guidance;
+mod health;
+mod jsonapi;
+mod limiter;
+mod links;
+mod listener;
+pub mod loadtest;
+pub mod logging;
+mod metrics;
+pub mod models;
+mod onboarding;
+mod outbound_tags;
+mod pagination;
+mod pricing;
+mod products;
+mod quarantine;
+mod queue;
+mod ratelimit;
+mod recording;
+mod request_id;
+mod resolve;
+pub mod roblox;
+pub mod routes;
+mod schema;
+mod selfcheck;
+mod snapshots;
+mod status;
+mod suggest;
+mod supervisor;
+mod systemd;
+pub mod tenant;
+mod thumbnails;
+mod timeout;
+mod upstream;
+mod usage;
+mod views;
+mod warmup;
+mod warnings;
+#[cfg(feature = "watcher")]
+mod watcher;
+
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tracing::{error, info};
+use upstream::UpstreamHealth;
+
+/// Estado compartido entre handlers.
+pub struct AppState {
+    pub started_at: Instant,
+    pub config: config::Config,
+    pub upstreams: UpstreamHealth,
+    /// Cliente HTTP compartido para Roblox (pool de conexiones, timeouts y
+    /// `User-Agent`).
+    pub http: reqwest::Client,
+    #[cfg(feature = "fault-injection")]
+    pub faults: faults::FaultInjector,
+    /// Live, grabación o reproducción de respuestas de Roblox.
+    pub recording: recording::Mode,
+    pub icons: thumbnails::IconCache,
+    /// Nivel de degradación según la tasa de error de Roblox.
+    pub degradation: degradation::Degradation,
+    /// Primera ronda de conexiones precalentadas, para el informe de arranque.
+    pub warmup: warmup::FirstRound,
+    /// Respuestas de Roblox con forma desconocida (`STRICT_UPSTREAM`).
+    pub quarantine: quarantine::Quarantine,
+    pub client_limiter: ratelimit::ClientLimiter,
+    /// Reintentos unidos a la petición original (`DEDUPE_WINDOW_SECS`).
+    pub deduper: dedupe::Deduper,
+    /// `POST /admin/drain`: readiness en 503 y apagado tras la gracia.
+    pub drain: drain::Drain,
+    pub paginator: pagination::Paginator,
+    /// Limitador adaptativo de peticiones salientes (`OUTBOUND_*_INFLIGHT`).
+    pub outbound: limiter::AdaptiveLimiter,
+    /// Peticiones perdedoras de `?mode=race` que siguen en segundo plano.
+    pub race_losers: queue::WorkQueue,
+    /// Tareas de fondo de larga duración (`/admin/tasks`).
+    pub tasks: supervisor::Supervisor,
+    pub metrics: metrics::Metrics,
+    /// Usuarios que el watcher refresca en segundo plano.
+    #[cfg(feature = "watcher")]
+    pub watcher: watcher::Watcher,
+    /// Fotos de las listas de passes, para `/user/:id/passes/diff`.
+    pub snapshots: snapshots::SnapshotStore,
+    /// Configuración de las cabinas de donación (`/booths`).
+    pub booths: booths::BoothStore,
+    /// Colecciones de usuarios con nombre (`/collection/:name/passes`).
+    pub collections: collections::CollectionStore,
+    /// Campañas de donación y las compras que informan los juegos
+    /// (`/campaigns`, `/donations`).
+    pub campaigns: campaigns::CampaignStore,
+    /// Listas de passes ya escaneadas.
+    pub cache: cache::PassCache,
+    /// Backend de caché (memoria o Redis) de las listas, los nombres de
+    /// usuario y los detalles de economy.
+    pub store: Arc<dyn cache::store::Cache>,
+    /// Escaneos frescos gastados por cada clave de API.
+    pub fresh_budget: budget::FreshBudget,
+    /// Peticiones y llamadas a Roblox por clave de API.
+    pub usage: usage::UsageTracker,
+    /// `None` si `ACCESS_LOG` no está activo.
+    pub access_log: Option<access_log::AccessLog>,
+}
+
+impl AppState {
+    /// Estado recién arrancado: cachés vacías y sin tareas de fondo (esas
+    /// las lanza `serve`).
+    pub fn new(config: config::Config) -> Self {
+        let store = cache::store::open(&config);
+        AppState::with_store(config, store)
+    }
+
+    /// Como `new`, con el backend de caché ya abierto (el mismo para varias
+    /// instancias en un proceso, por ejemplo).
+    pub fn with_store(config: config::Config, store: Arc<dyn cache::store::Cache>) -> Self {
+        AppState {
+            started_at: Instant::now(),
+            quarantine: quarantine::Quarantine::new(&config),
+            client_limiter: ratelimit::ClientLimiter::new(&config),
+            deduper: dedupe::Deduper::new(&config),
+            drain: drain::Drain::new(&config),
+            paginator: pagination::Paginator::new(&config),
+            outbound: limiter::AdaptiveLimiter::new(config.limiter_settings()),
+            upstreams: UpstreamHealth::new(config.cooldown_backoff),
+            http: upstream::client(&config),
+            race_losers: queue::WorkQueue::new(
+                "race-losers",
+                config.background_queue_max,
+                config.background_shed_policy,
+            ),
+            tasks: supervisor::Supervisor::default(),
+            metrics: metrics::Metrics::default(),
+            #[cfg(feature = "watcher")]
+            watcher: watcher::Watcher::default(),
+            snapshots: snapshots::SnapshotStore::new(&config),
+            booths: booths::BoothStore::new(&config),
+            collections: collections::CollectionStore::new(&config),
+            campaigns: campaigns::CampaignStore::new(&config),
+            cache: cache::PassCache::new(&config, &store),
+            store,
+            fresh_budget: budget::FreshBudget::default(),
+            usage: usage::UsageTracker::default(),
+            access_log: access_log::AccessLog::new(&config),
+            degradation: degradation::Degradation::new(&config),
+            warmup: warmup::FirstRound::default(),
+            config,
+            #[cfg(feature = "fault-injection")]
+            faults: faults::FaultInjector::default(),
+            recording: recording::Mode::from_env(),
+            icons: thumbnails::IconCache::default(),
+        }
+    }
+
+    /// Alguna clave vigila a `user_id`. Sin la feature `watcher`, nadie.
+    pub fn is_watched(&self, user_id: u64) -> bool {
+        #[cfg(feature = "watcher")]
+        return self.watcher.is_watched(user_id);
+        #[cfg(not(feature = "watcher"))]
+        {
+            let _ = user_id;
+            false
+        }
+    }
+}
+
+/// Lanza las tareas de fondo y sirve la API en `PORT` hasta Ctrl-C o
+/// SIGTERM. Con `selfcheck`, antes comprueba que Roblox responde como se
+/// espera (ver `selfcheck`).
+pub async fn serve(state: Arc<AppState>, selfcheck: bool) {
+    if selfcheck {
+        selfcheck::run(&state).await;
+    } else {
+        info!("Self-check omitido (--skip-selfcheck)");
+    }
+
+    state.tasks.spawn("stats-pruner", {
+        let state = state.clone();
+        move |token| {
+            let state = state.clone();
+            async move {
+                let mut tick = tokio::time::interval(Duration::from_secs(60));
+                loop {
+                    tokio::select! {
+                        _ = token.cancelled() => return,
+                        _ = tick.tick() => {
+                            state.upstreams.prune();
+                            state.snapshots.prune();
+                            state.cache.prune();
+                            state.store.prune();
+                            state.fresh_budget.prune();
+                            state.client_limiter.prune();
+                            state.deduper.prune();
+                            state.paginator.prune();
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    #[cfg(feature = "watcher")]
+    state.tasks.spawn("watcher", {
+        let state = state.clone();
+        move |token| watcher::Watcher::run(state.clone(), token)
+    });
+
+    if warmup::enabled(&state) {
+        state.tasks.spawn("upstream-warmup", {
+            let state = state.clone();
+            move |token| warmup::run(state.clone(), token)
+        });
+    }
+
+    if let Some(interval) = systemd::watchdog_interval() {
+        // Corre en el mismo runtime que atiende las peticiones: si este se
+        // bloquea, dejan de llegar keepalives y systemd reinicia el servicio.
+        state
+            .tasks
+            .spawn("systemd-watchdog", move |token| async move {
+                let mut tick = tokio::time::interval(interval);
+                loop {
+                    tokio::select! {
+                        _ = token.cancelled() => return,
+                        _ = tick.tick() => systemd::watchdog(),
+                    }
+                }
+            });
+    }
+
+    let app = routes::build_router(state.clone());
+
+    let addr = SocketAddr::new(state.config.bind_addr, state.config.port);
+    let listener = listener::bind(addr, &state.config).unwrap_or_else(|e| {
+        error!("No se pudo escuchar en {addr}: {e}");
+        std::process::exit(1);
+    });
+    let server = axum::Server::from_tcp(listener)
+        .unwrap()
+        .tcp_nodelay(state.config.tcp_nodelay)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async {
+            tokio::select! {
+                _ = shutdown_signal() => {}
+                _ = state.drain.finished() => info!("Drenaje terminado"),
+            }
+            info!("Apagando…");
+            systemd::stopping();
+        });
+    state.tasks.spawn("boot-report", {
+        let state = state.clone();
+        move |token| {
+            let state = state.clone();
+            let report = async move {
+                // READY=1 solo tras la primera ronda del precalentado (o su
+                // plazo): hasta entonces la primera petición pagaría el TLS.
+                if warmup::enabled(&state) {
+                    let timeout = warmup::first_round_timeout(&state.config);
+                    state.warmup.wait(timeout).await;
+                }
+                systemd::ready(&format!("escuchando en {addr}"));
+                let ready = state.started_at.elapsed();
+                boot::report(state, addr, selfcheck, ready).await;
+            };
+            async move {
+                tokio::select! {
+                    _ = token.cancelled() => {}
+                    _ = report => {}
+                }
+            }
+        }
+    });
+    server.await.unwrap();
+    state.tasks.shutdown().await;
+}
+
+/// Ctrl-C o SIGTERM (lo que envía systemd al parar la unidad).
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut term = signal(SignalKind::terminate()).expect("manejador de SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = term.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
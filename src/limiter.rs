@@ -0,0 +1,254 @@
+//! Limitador adaptativo (AIMD) de peticiones simultáneas a Roblox.
+//!
+//! El tope sube de forma aditiva (+1 por cada "ventana" de respuestas sanas)
+//! y baja de forma multiplicativa ante un 429, un timeout o una latencia por
+//! encima del objetivo, así el servicio se mantiene cerca del máximo que
+//! Roblox tolera sin ajustarlo a mano.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Notify;
+use tracing::info;
+
+/// Tras una bajada, se ignoran nuevas señales de sobrecarga durante este
+/// tiempo: una ráfaga de 429 de la misma tanda no debe hundir el tope a cero.
+const DECREASE_COOLDOWN: Duration = Duration::from_secs(1);
+
+/// Cómo fue la llamada, para ajustar el tope.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Signal {
+    Healthy,
+    Overloaded,
+    /// Errores que no dicen nada de la carga (p. ej. un 404).
+    Neutral,
+}
+
+pub struct LimiterSettings {
+    pub min: usize,
+    pub max: usize,
+    pub initial: usize,
+    pub latency_target: Duration,
+    pub decrease_factor: f64,
+}
+
+struct Inner {
+    limit: f64,
+    inflight: usize,
+    last_decrease: Option<Instant>,
+}
+
+pub struct AdaptiveLimiter {
+    settings: LimiterSettings,
+    inner: Mutex<Inner>,
+    released: Notify,
+}
+
+/// Estado actual, para `/admin/upstreams`.
+pub struct LimiterSnapshot {
+    pub limit: usize,
+    pub inflight: usize,
+    pub min: usize,
+    pub max: usize,
+}
+
+impl AdaptiveLimiter {
+    pub fn new(settings: LimiterSettings) -> Self {
+        let initial = settings.initial.clamp(settings.min, settings.max) as f64;
+        AdaptiveLimiter {
+            settings,
+            inner: Mutex::new(Inner {
+                limit: initial,
+                inflight: 0,
+                last_decrease: None,
+            }),
+            released: Notify::new(),
+        }
+    }
+
+    pub fn try_acquire(&self) -> Option<Permit<'_>> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.inflight < inner.limit as usize {
+            inner.inflight += 1;
+            Some(Permit {
+                limiter: self,
+                signal: Signal::Neutral,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub async fn acquire(&self) -> Permit<'_> {
+        loop {
+            // Se crea antes de comprobar para no perder un aviso intermedio.
+            let released = self.released.notified();
+            if let Some(permit) = self.try_acquire() {
+                return permit;
+            }
+            released.await;
+        }
+    }
+
+    /// Clasifica una respuesta según su estado HTTP y su latencia.
+    pub fn classify(&self, status: Option<u16>, latency: Duration) -> Signal {
+        match status {
+            Some(429) | None => Signal::Overloaded,
+            Some(_) if latency > self.settings.latency_target => Signal::Overloaded,
+            Some(code) if (200..300).contains(&code) => Signal::Healthy,
+            Some(code) if code >= 500 => Signal::Overloaded,
+            Some(_) => Signal::Neutral,
+        }
+    }
+
+    fn release(&self, signal: Signal) {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.inflight -= 1;
+            match signal {
+                Signal::Healthy => {
+                    inner.limit = (inner.limit + 1.0 / inner.limit).min(self.settings.max as f64);
+                }
+                Signal::Overloaded => {
+                    let now = Instant::now();
+                    let recently = inner
+                        .last_decrease
+                        .is_some_and(|at| now.duration_since(at) < DECREASE_COOLDOWN);
+                    if !recently {
+                        let before = inner.limit;
+                        inner.limit = (inner.limit * self.settings.decrease_factor)
+                            .max(self.settings.min as f64);
+                        inner.last_decrease = Some(now);
+                        info!(
+                            "Sobrecarga: tope de peticiones a Roblox {} → {}",
+                            before as usize, inner.limit as usize
+                        );
+                    }
+                }
+                Signal::Neutral => {}
+            }
+        }
+        self.released.notify_waiters();
+    }
+
+    pub fn snapshot(&self) -> LimiterSnapshot {
+        let inner = self.inner.lock().unwrap();
+        LimiterSnapshot {
+            limit: inner.limit as usize,
+            inflight: inner.inflight,
+            min: self.settings.min,
+            max: self.settings.max,
+        }
+    }
+}
+
+/// Hueco ocupado en el limitador; al soltarse aplica la señal registrada.
+pub struct Permit<'a> {
+    limiter: &'a AdaptiveLimiter,
+    signal: Signal,
+}
+
+impl Permit<'_> {
+    pub fn record(&mut self, signal: Signal) {
+        self.signal = signal;
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.limiter.release(self.signal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(initial: usize) -> AdaptiveLimiter {
+        AdaptiveLimiter::new(LimiterSettings {
+            min: 2,
+            max: 6,
+            initial,
+            latency_target: Duration::from_secs(1),
+            decrease_factor: 0.5,
+        })
+    }
+
+    /// Ocupa un hueco y lo suelta con `signal`.
+    fn call(limiter: &AdaptiveLimiter, signal: Signal) {
+        let mut permit = limiter.try_acquire().expect("hueco libre");
+        permit.record(signal);
+    }
+
+    fn limit(limiter: &AdaptiveLimiter) -> f64 {
+        limiter.inner.lock().unwrap().limit
+    }
+
+    #[test]
+    fn healthy_window_adds_one() {
+        let limiter = limiter(4);
+        for _ in 0..4 {
+            call(&limiter, Signal::Healthy);
+        }
+        // +1/limit por respuesta: una ventana entera (≈ limit respuestas) suma 1.
+        assert!((4.9..5.0).contains(&limit(&limiter)), "{}", limit(&limiter));
+        assert_eq!(limiter.snapshot().inflight, 0);
+    }
+
+    #[test]
+    fn overload_halves_once_per_cooldown() {
+        let limiter = limiter(6);
+        call(&limiter, Signal::Overloaded);
+        assert_eq!(limit(&limiter), 3.0);
+        // La misma ráfaga no vuelve a bajar.
+        call(&limiter, Signal::Overloaded);
+        assert_eq!(limit(&limiter), 3.0);
+
+        limiter.inner.lock().unwrap().last_decrease =
+            Some(Instant::now() - DECREASE_COOLDOWN - Duration::from_millis(1));
+        call(&limiter, Signal::Overloaded);
+        assert_eq!(limit(&limiter), 2.0, "no baja del mínimo");
+    }
+
+    #[test]
+    fn limit_stays_within_bounds() {
+        let full = limiter(6);
+        for _ in 0..50 {
+            call(&full, Signal::Healthy);
+        }
+        assert_eq!(limit(&full), 6.0, "no pasa del máximo");
+        call(&full, Signal::Neutral);
+        assert_eq!(limit(&full), 6.0);
+
+        assert_eq!(limiter(100).snapshot().limit, 6, "el inicial se recorta");
+        assert_eq!(limiter(0).snapshot().limit, 2);
+    }
+
+    #[test]
+    fn permits_stop_at_the_limit() {
+        let limiter = limiter(2);
+        let first = limiter.try_acquire();
+        let second = limiter.try_acquire();
+        assert!(first.is_some() && second.is_some());
+        assert!(limiter.try_acquire().is_none());
+        drop(first);
+        assert!(limiter.try_acquire().is_some());
+    }
+
+    #[test]
+    fn classifies_by_status_and_latency() {
+        let limiter = limiter(4);
+        let fast = Duration::from_millis(10);
+        assert_eq!(limiter.classify(Some(200), fast), Signal::Healthy);
+        assert_eq!(limiter.classify(Some(429), fast), Signal::Overloaded);
+        assert_eq!(limiter.classify(Some(503), fast), Signal::Overloaded);
+        assert_eq!(limiter.classify(None, fast), Signal::Overloaded);
+        assert_eq!(limiter.classify(Some(404), fast), Signal::Neutral);
+        assert_eq!(
+            limiter.classify(Some(200), Duration::from_secs(2)),
+            Signal::Overloaded
+        );
+    }
+}
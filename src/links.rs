@@ -0,0 +1,48 @@
+//! Enlaces hipermedia de las respuestas (`links`), para que un cliente
+//! genérico pueda navegar sin construir URLs a mano.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// Enlaces del envelope.
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseLinks {
+    /// Esta misma petición (ruta y query).
+    #[serde(rename = "self")]
+    pub self_: String,
+    /// JSON Schema de esta respuesta.
+    pub schema: String,
+}
+
+/// Enlaces de un pass.
+#[derive(Serialize, JsonSchema, Clone)]
+pub struct PassLinks {
+    /// Página del pass en Roblox (compra / donación).
+    pub roblox: String,
+    /// Icono del pass vía la API de thumbnails de Roblox.
+    pub thumbnail: String,
+    /// Juego al que pertenece, si se conoce su place raíz.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game: Option<String>,
+}
+
+impl ResponseLinks {
+    pub fn new(path_and_query: &str, schema: &str) -> Self {
+        ResponseLinks {
+            self_: path_and_query.to_string(),
+            schema: format!("/schema/{schema}"),
+        }
+    }
+}
+
+impl PassLinks {
+    pub fn new(pass_id: u64, root_place_id: Option<u64>) -> Self {
+        PassLinks {
+            roblox: format!("https://www.roblox.com/game-pass/{pass_id}"),
+            thumbnail: format!(
+                "https://thumbnails.roblox.com/v1/game-passes?gamePassIds={pass_id}&size=150x150&format=Png&isCircular=false"
+            ),
+            game: root_place_id.map(|place| format!("https://www.roblox.com/games/{place}")),
+        }
+    }
+}
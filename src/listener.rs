@@ -0,0 +1,23 @@
+//! Socket de escucha con las opciones TCP de la configuración.
+//!
+//! Muchos servidores de juego llamando a la vez desde `HttpService` llegan en
+//! ráfagas de conexiones nuevas: con el backlog por defecto del sistema se
+//! pierden, y sin `TCP_NODELAY` las respuestas pequeñas esperan a Nagle.
+
+use std::{io, net::SocketAddr};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::config::Config;
+
+/// Crea el listener (ya en modo no bloqueante, listo para hyper).
+pub fn bind(addr: SocketAddr, config: &Config) -> io::Result<std::net::TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(config.tcp_reuseaddr)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(config.tcp_reuseport)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(config.tcp_backlog)?;
+    Ok(socket.into())
+}
@@ -0,0 +1,244 @@
+//! `donations_api loadtest`: generador de carga contra una instancia en marcha.
+//!
+//! ```text
+//! donations_api loadtest --target http://localhost:8080 --users users.txt --rps 200 \
+//!     [--duration 30] [--max-inflight 1000] [--skew 1.1]
+//! ```
+//!
+//! Los userIds se eligen con una distribución Zipf sobre el orden del archivo
+//! (los primeros son los "calientes"), que se parece al tráfico real de los
+//! juegos: pocos creadores concentran la mayoría de las peticiones.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::{sync::Semaphore, time::MissedTickBehavior};
+
+struct Options {
+    target: String,
+    users_file: String,
+    rps: u32,
+    duration: Duration,
+    max_inflight: usize,
+    skew: f64,
+}
+
+const USAGE: &str = "uso: donations_api loadtest --target URL --users ARCHIVO --rps N \
+[--duration SEGUNDOS] [--max-inflight N] [--skew S]";
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut target = None;
+    let mut users_file = None;
+    let mut rps = None;
+    let mut duration = Duration::from_secs(30);
+    let mut max_inflight = 1000;
+    let mut skew = 1.1;
+
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("falta el valor de {flag}"))
+        };
+        let invalid = |v: &str| format!("valor inválido para {flag}: {v}");
+        match flag.as_str() {
+            "--target" => target = Some(value()?.trim_end_matches('/').to_string()),
+            "--users" => users_file = Some(value()?),
+            "--rps" => {
+                let v = value()?;
+                rps = Some(
+                    v.parse()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| invalid(&v))?,
+                );
+            }
+            "--duration" => {
+                let v = value()?;
+                duration = Duration::from_secs(v.parse().map_err(|_| invalid(&v))?);
+            }
+            "--max-inflight" => {
+                let v = value()?;
+                max_inflight = v
+                    .parse()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| invalid(&v))?;
+            }
+            "--skew" => {
+                let v = value()?;
+                skew = v.parse().map_err(|_| invalid(&v))?;
+            }
+            other => return Err(format!("opción desconocida: {other}")),
+        }
+    }
+
+    Ok(Options {
+        target: target.ok_or("falta --target")?,
+        users_file: users_file.ok_or("falta --users")?,
+        rps: rps.ok_or("falta --rps")?,
+        duration,
+        max_inflight,
+        skew,
+    })
+}
+
+/// Un userId por línea; se ignoran líneas vacías y comentarios `#`.
+fn load_users(path: &str) -> Result<Vec<u64>, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("no se pudo leer {path}: {e}"))?;
+    let mut users = Vec::new();
+    for (n, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let id = line
+            .parse()
+            .map_err(|_| format!("{path}:{}: userId inválido '{line}'", n + 1))?;
+        users.push(id);
+    }
+    if users.is_empty() {
+        return Err(format!("{path} no contiene userIds"));
+    }
+    Ok(users)
+}
+
+/// Pesos acumulados de una Zipf con exponente `skew` sobre `n` elementos.
+fn zipf_cdf(n: usize, skew: f64) -> Vec<f64> {
+    let mut acc = 0.0;
+    let mut cdf: Vec<f64> = (1..=n)
+        .map(|rank| {
+            acc += 1.0 / (rank as f64).powf(skew);
+            acc
+        })
+        .collect();
+    for w in &mut cdf {
+        *w /= acc;
+    }
+    cdf
+}
+
+#[derive(Default)]
+struct Results {
+    latencies: Vec<Duration>,
+    by_status: BTreeMap<String, u64>,
+}
+
+pub async fn run(args: &[String]) -> Result<(), String> {
+    let opts = parse_args(args).map_err(|e| format!("{e}\n{USAGE}"))?;
+    let users = load_users(&opts.users_file)?;
+    let cdf = Arc::new(zipf_cdf(users.len(), opts.skew));
+    let users = Arc::new(users);
+
+    println!(
+        "[LOAD] {} rps durante {}s contra {} ({} usuarios, skew {})",
+        opts.rps,
+        opts.duration.as_secs(),
+        opts.target,
+        users.len(),
+        opts.skew
+    );
+
+    let client = reqwest::Client::builder()
+        .pool_max_idle_per_host(opts.max_inflight)
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("no se pudo crear el cliente HTTP: {e}"))?;
+    let results = Arc::new(Mutex::new(Results::default()));
+    let inflight = Arc::new(Semaphore::new(opts.max_inflight));
+    let mut dropped = 0u64;
+
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / opts.rps as f64));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Burst);
+    let started = Instant::now();
+    let mut tasks = Vec::new();
+
+    while started.elapsed() < opts.duration {
+        ticker.tick().await;
+
+        // Carga de lazo abierto: si se llega al tope de peticiones en vuelo
+        // se descarta el envío en lugar de frenar el ritmo.
+        let Ok(permit) = inflight.clone().try_acquire_owned() else {
+            dropped += 1;
+            continue;
+        };
+
+        let r = fastrand::f64();
+        let idx = cdf.partition_point(|w| *w < r).min(users.len() - 1);
+        let url = format!("{}/user/{}/passes", opts.target, users[idx]);
+        let client = client.clone();
+        let results = results.clone();
+
+        tasks.push(tokio::spawn(async move {
+            let sent = Instant::now();
+            let status = match client.get(&url).send().await {
+                Ok(resp) => {
+                    let status = resp.status().as_u16().to_string();
+                    // Leer el cuerpo completo para medir la respuesta entera.
+                    let _ = resp.bytes().await;
+                    status
+                }
+                Err(e) if e.is_timeout() => "timeout".to_string(),
+                Err(_) => "error".to_string(),
+            };
+            let latency = sent.elapsed();
+            drop(permit);
+
+            let mut results = results.lock().unwrap();
+            results.latencies.push(latency);
+            *results.by_status.entry(status).or_default() += 1;
+        }));
+    }
+
+    for task in tasks {
+        let _ = task.await;
+    }
+    let elapsed = started.elapsed();
+
+    let mut results = results.lock().unwrap();
+    results.latencies.sort_unstable();
+    report(&results, elapsed, dropped);
+    Ok(())
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn report(results: &Results, elapsed: Duration, dropped: u64) {
+    let total = results.latencies.len();
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+
+    println!("\n[LOAD] Resultados");
+    println!("  peticiones:   {total}");
+    println!("  descartadas:  {dropped} (tope de peticiones en vuelo)");
+    println!(
+        "  throughput:   {:.1} req/s",
+        total as f64 / elapsed.as_secs_f64()
+    );
+    println!("  estados:");
+    for (status, count) in &results.by_status {
+        println!("    {status:>8}: {count}");
+    }
+    println!("  latencia (ms):");
+    for p in [50.0, 90.0, 95.0, 99.0] {
+        println!(
+            "    p{:<6} {:>10.1}",
+            p,
+            ms(percentile(&results.latencies, p))
+        );
+    }
+    println!(
+        "    max     {:>10.1}",
+        ms(results.latencies.last().copied().unwrap_or_default())
+    );
+}
@@ -0,0 +1,42 @@
+//! Logs con `tracing`. El nivel se elige con `RUST_LOG` (por defecto `info`,
+//! con la sintaxis de `EnvFilter`: `donations_api=debug,hyper=warn`, ...) y
+//! el formato con `LOG_FORMAT`: `text` (por defecto) o `json`, un objeto por
+//! línea para Loki/CloudWatch. Cada petición abre un span `request` con su
+//! id, método, ruta y, si la ruta lo lleva, el userId.
+
+use std::{env, io::IsTerminal};
+
+use tracing::warn;
+use tracing_subscriber::EnvFilter;
+
+/// Instala el subscriber global. Va antes de leer la configuración para que
+/// sus avisos también salgan.
+pub fn init() {
+    let (filter, bad_filter) = match EnvFilter::try_from_default_env() {
+        Ok(filter) => (filter, None),
+        Err(e) => (
+            EnvFilter::new("info"),
+            env::var("RUST_LOG").ok().map(|raw| (raw, e)),
+        ),
+    };
+    let format = env::var("LOG_FORMAT").unwrap_or_default();
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(std::io::stdout().is_terminal());
+    match format.as_str() {
+        "json" => builder
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .init(),
+        _ => builder.init(),
+    }
+
+    if let Some((raw, e)) = bad_filter {
+        warn!("RUST_LOG={raw:?} no es válido ({e}), usando info");
+    }
+    if !matches!(format.as_str(), "" | "text" | "json") {
+        warn!("LOG_FORMAT={format:?} no es válido (text o json), usando text");
+    }
+}
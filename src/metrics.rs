@@ -0,0 +1,111 @@
+//! Contadores del proceso y `GET /metrics` en formato de texto de Prometheus.
+
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use axum::{extract::State, http::header, response::IntoResponse};
+
+use crate::{admin::AdminAuth, AppState};
+
+#[derive(Default)]
+pub struct Metrics {
+    /// Handlers que entraron en pánico desde el arranque.
+    pub panics: AtomicU64,
+}
+
+/// `GET /metrics` (requiere el token de administración).
+pub async fn metrics(_: AdminAuth, State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let mut out = String::new();
+    let limiter = state.outbound.snapshot();
+    let queue = state.race_losers.snapshot();
+
+    let _ = writeln!(out, "# TYPE donations_api_panics_total counter");
+    let _ = writeln!(
+        out,
+        "donations_api_panics_total {}",
+        state.metrics.panics.load(Ordering::Relaxed)
+    );
+    let _ = writeln!(out, "# TYPE donations_api_outbound_limit gauge");
+    let _ = writeln!(out, "donations_api_outbound_limit {}", limiter.limit);
+    let _ = writeln!(out, "# TYPE donations_api_outbound_inflight gauge");
+    let _ = writeln!(out, "donations_api_outbound_inflight {}", limiter.inflight);
+    let _ = writeln!(out, "# TYPE donations_api_queue_depth gauge");
+    let _ = writeln!(
+        out,
+        "donations_api_queue_depth{{queue=\"{}\"}} {}",
+        queue.name, queue.depth
+    );
+    let _ = writeln!(out, "# TYPE donations_api_queue_shed_total counter");
+    let _ = writeln!(
+        out,
+        "donations_api_queue_shed_total{{queue=\"{}\"}} {}",
+        queue.name, queue.shed
+    );
+    let _ = writeln!(
+        out,
+        "# TYPE donations_api_upstream_challenges_total counter"
+    );
+    for (upstream, kind, count) in state.upstreams.challenge_counts() {
+        let _ = writeln!(
+            out,
+            "donations_api_upstream_challenges_total{{host=\"{}\",type=\"{}\"}} {}",
+            upstream.host(),
+            kind,
+            count
+        );
+    }
+    let _ = writeln!(out, "# TYPE donations_api_upstream_mirror_active gauge");
+    let now = Instant::now();
+    for (upstream, stats) in state.upstreams.snapshot() {
+        let active = stats.mirror_until.is_some_and(|until| until > now);
+        let _ = writeln!(
+            out,
+            "donations_api_upstream_mirror_active{{host=\"{}\"}} {}",
+            upstream.host(),
+            u8::from(active)
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# TYPE donations_api_upstream_quarantined_total counter"
+    );
+    for (endpoint, count) in state.quarantine.counts() {
+        let _ = writeln!(
+            out,
+            "donations_api_upstream_quarantined_total{{host=\"{}\",path=\"{}\"}} {}",
+            endpoint.upstream().host(),
+            endpoint.path(),
+            count
+        );
+    }
+
+    let _ = writeln!(out, "# TYPE donations_api_rate_limited_total counter");
+    let _ = writeln!(
+        out,
+        "donations_api_rate_limited_total {}",
+        state.client_limiter.rejected()
+    );
+
+    let _ = writeln!(out, "# TYPE donations_api_deduplicated_total counter");
+    let _ = writeln!(
+        out,
+        "donations_api_deduplicated_total {}",
+        state.deduper.hits()
+    );
+
+    let _ = writeln!(out, "# TYPE donations_api_degradation_level gauge");
+    let _ = writeln!(
+        out,
+        "donations_api_degradation_level {}",
+        state.degradation.level(&state.upstreams).index()
+    );
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], out)
+}
@@ -0,0 +1,96 @@
+//! Modelo interno de los escaneos: los passes y juegos tal como salen de
+//! Roblox, antes de darles forma de respuesta (ver `views`).
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::{games, guidance, links, pricing, roblox::api, views};
+
+/// Pass escaneado. Es el modelo interno (caché, fotos, vistas derivadas);
+/// lo que sale en las respuestas es `views::PassView`.
+#[derive(Clone)]
+pub struct Gamepass {
+    pub id: u64,
+    pub name: String,
+    pub price: i64,
+    pub original_price: i64,
+    pub price_changed: bool,
+    pub price_details: Option<pricing::PriceDetails>,
+    /// Campo de Roblox del que sale `price` (ver `pricing`); `None` en los
+    /// passes del catálogo, que traen el precio tal cual.
+    pub price_source: Option<pricing::PriceField>,
+    pub display_name: String,
+    pub duplicate_of: Option<u64>,
+    pub icon_url: Option<String>,
+    pub links: Option<links::PassLinks>,
+    /// Fuente de Roblox de la que salió.
+    pub source: views::PassSource,
+    /// Juego al que pertenece, para `groupBy=game`.
+    pub universe_id: Option<u64>,
+    /// Metadatos del juego, si se pidieron durante el escaneo.
+    pub game: Option<Arc<games::GameDetails>>,
+    /// Nombre del juego según el listado de juegos del usuario.
+    pub game_name: Option<String>,
+}
+
+impl Gamepass {
+    pub fn game_name(&self) -> Option<String> {
+        self.game
+            .as_ref()
+            .map(|g| g.name.clone())
+            .or_else(|| self.game_name.clone())
+    }
+}
+
+/// Cómo combinar las fuentes de passes.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum FetchMode {
+    /// Juegos públicos y, si no hay nada, catálogo.
+    Sequential,
+    /// Ambas fuentes a la vez; gana la primera con resultados.
+    Race,
+}
+
+/// Opciones que afectan a cómo se recorren las fuentes de Roblox.
+#[derive(Default, Clone)]
+pub struct FetchOptions {
+    pub max_passes_per_game: Option<usize>,
+    /// Omitir juegos sin actualizar en `ACTIVE_GAME_DAYS`.
+    pub active_games_only: bool,
+    /// Pedir metadatos (nombre, visitas...) de los juegos escaneados.
+    pub game_details: bool,
+    /// Añadir los juegos de los grupos que posee el usuario.
+    pub include_groups: bool,
+    /// Conservar los passes a la venta por 0 Robux.
+    pub include_free: bool,
+    /// Contadores del escaneo, para explicar una lista vacía (`guidance`).
+    pub stats: Arc<guidance::ScanStats>,
+    /// Plazo del escaneo (`SCAN_DEADLINE_SECS`): al vencer se deja de
+    /// esperar a Roblox y se sigue con lo recogido.
+    pub deadline: Option<tokio::time::Instant>,
+}
+
+/// Juego público del usuario (o de uno de sus grupos), con lo necesario
+/// para priorizarlo.
+pub struct PublicGame {
+    pub universe_id: u64,
+    pub name: Option<String>,
+    pub root_place_id: Option<u64>,
+    pub visits: u64,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+impl From<api::Game> for PublicGame {
+    fn from(game: api::Game) -> Self {
+        PublicGame {
+            universe_id: game.id,
+            name: game.name,
+            root_place_id: game.root_place.map(|place| place.id),
+            visits: game.place_visits.unwrap_or(0),
+            updated: game.updated,
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Ayudas para creadores que todavía no tienen passes: a dónde ir en el
+//! Creator Hub para crear uno en su juego más popular
+//! (`/user/:id/create-pass-link`).
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{error::ApiError, extract::Query, guidance, roblox::client, AppState};
+
+/// Precio máximo que Roblox admite para un pass.
+const MAX_PASS_PRICE: u32 = 1_000_000_000;
+
+#[derive(Deserialize)]
+pub struct CreatePassQuery {
+    /// Precio objetivo en Robux.
+    price: u32,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetGame {
+    pub universe_id: u64,
+    pub name: Option<String>,
+    pub root_place_id: Option<u64>,
+    pub visits: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePassLinkResponse {
+    ok: bool,
+    user_id: u64,
+    price: u32,
+    /// Juego donde crear el pass: el más visitado del usuario.
+    game: TargetGame,
+    /// Sección de passes del juego en el Creator Hub.
+    url: String,
+    /// Pasos a mostrar al usuario, en orden.
+    instructions: Vec<String>,
+}
+
+/// `GET /user/:id/create-pass-link?price=100`
+pub async fn create_pass_link(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<u64>,
+    Query(query): Query<CreatePassQuery>,
+) -> Result<Json<CreatePassLinkResponse>, ApiError> {
+    let price = query.price;
+    if !(1..=MAX_PASS_PRICE).contains(&price) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_QUERY",
+            format!("price debe estar entre 1 y {MAX_PASS_PRICE}"),
+        ));
+    }
+
+    let stats = guidance::ScanStats::default();
+    let Some(mut games) = client::fetch_public_games(&state, user_id, &stats).await else {
+        return Err(guidance::failure(user_id, &stats).unwrap_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_GATEWAY,
+                "UPSTREAM_ERROR",
+                "No se pudieron consultar los juegos públicos en Roblox",
+            )
+        }));
+    };
+    client::sort_by_popularity(&mut games);
+    let Some(game) = games.into_iter().next() else {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "NO_PUBLIC_GAMES",
+            format!(
+                "userId {user_id} no tiene juegos públicos; hay que publicar uno en https://create.roblox.com/dashboard/creations antes de crear passes"
+            ),
+        ));
+    };
+
+    let url = format!(
+        "https://create.roblox.com/dashboard/creations/experiences/{}/monetization/passes",
+        game.universe_id
+    );
+    let game_name = game.name.as_deref().unwrap_or("tu juego");
+    let instructions = vec![
+        format!("Abre {url} con la cuenta dueña de \"{game_name}\"."),
+        "Pulsa \"Create a Pass\", sube una imagen y ponle nombre.".to_string(),
+        "Guarda el pass y entra en su página de \"Sales\".".to_string(),
+        format!("Activa \"Item for Sale\" y pon el precio en {price} Robux."),
+        "Guarda los cambios; el pass aparecerá aquí en el siguiente escaneo.".to_string(),
+    ];
+    info!(
+        "create-pass-link para userId={user_id}: universeId={} precio={price}",
+        game.universe_id
+    );
+
+    Ok(Json(CreatePassLinkResponse {
+        ok: true,
+        user_id,
+        price,
+        game: TargetGame {
+            universe_id: game.universe_id,
+            name: game.name,
+            root_place_id: game.root_place_id,
+            visits: game.visits,
+        },
+        url,
+        instructions,
+    }))
+}
@@ -0,0 +1,143 @@
+//! Etiquetas de las llamadas salientes (`UPSTREAM_TAGS`): cabeceras o
+//! parámetros de query que se añaden a las peticiones hacia un host concreto,
+//! p. ej. la clave que pide un espejo. Como van por host de destino, la clave
+//! del espejo nunca llega a `roblox.com` aunque se alternen los dos.
+//!
+//! Formato: `host/header/Nombre=valor,host/query/nombre=valor,...`. El host
+//! puede ser exacto (`games.roproxy.com`) o `*.roproxy.com` para cualquier
+//! subdominio. En el valor, `${VAR}` se sustituye por esa variable de entorno
+//! al arrancar, para no dejar secretos en `UPSTREAM_TAGS`.
+//!
+//! Se añaden al enviar: las grabaciones, `/admin/upstreams` y los logs siguen
+//! viendo la URL sin ellas.
+
+use std::env;
+
+use reqwest::header::{HeaderName, HeaderValue};
+use tracing::warn;
+
+#[derive(Clone, Debug)]
+enum Tag {
+    Header(HeaderName, HeaderValue),
+    Query(String, String),
+}
+
+#[derive(Clone, Debug)]
+pub struct OutboundTag {
+    /// En minúsculas; con `*.` delante vale para los subdominios.
+    host: String,
+    tag: Tag,
+}
+
+impl OutboundTag {
+    /// `host/tipo/nombre=…`, sin el valor, que suele ser una clave.
+    pub fn redacted(&self) -> String {
+        let (kind, name) = match &self.tag {
+            Tag::Header(name, _) => ("header", name.as_str()),
+            Tag::Query(name, _) => ("query", name.as_str()),
+        };
+        format!("{}/{kind}/{name}=…", self.host)
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        match self.host.strip_prefix("*.") {
+            Some(domain) => host
+                .strip_suffix(domain)
+                .is_some_and(|sub| sub.ends_with('.')),
+            None => host == self.host,
+        }
+    }
+}
+
+/// Sustituye cada `${VAR}` por su valor en el entorno.
+fn expand(template: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find('}') else {
+            return Err("'${' sin cerrar".to_string());
+        };
+        let var = &rest[start + 2..start + 2 + len];
+        let value = env::var(var).map_err(|_| format!("la variable {var} no está definida"))?;
+        out.push_str(&value);
+        rest = &rest[start + 3 + len..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn parse_entry(entry: &str) -> Result<OutboundTag, String> {
+    let mut parts = entry.splitn(3, '/');
+    let (Some(host), Some(kind), Some(pair)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err("se esperaba host/header|query/nombre=valor".to_string());
+    };
+    let host = host.trim().to_ascii_lowercase();
+    if host.is_empty() || host.trim_start_matches("*.").contains(['*', ':']) {
+        return Err(format!("host '{host}' no válido"));
+    }
+    let (name, value) = pair
+        .split_once('=')
+        .filter(|(name, _)| !name.is_empty())
+        .ok_or("falta nombre=valor")?;
+    let value = expand(value)?;
+    let tag = match kind {
+        "header" => {
+            let name =
+                HeaderName::try_from(name).map_err(|_| format!("cabecera '{name}' no válida"))?;
+            let mut value = HeaderValue::try_from(value)
+                .map_err(|_| format!("valor no válido para la cabecera {name}"))?;
+            value.set_sensitive(true);
+            Tag::Header(name, value)
+        }
+        "query" => Tag::Query(name.to_string(), value),
+        other => return Err(format!("tipo '{other}' no válido (header o query)")),
+    };
+    Ok(OutboundTag { host, tag })
+}
+
+/// Parsea `UPSTREAM_TAGS`. Las entradas mal formadas se descartan con un
+/// aviso; el `bool` dice si hubo alguna.
+pub fn parse_tags(raw: &str) -> (Vec<OutboundTag>, bool) {
+    let mut invalid = false;
+    let tags = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            parse_entry(entry)
+                .map_err(|e| {
+                    // Solo el host: el resto puede llevar la clave.
+                    let host = entry.split('/').next().unwrap_or_default();
+                    warn!("UPSTREAM_TAGS: entrada para '{host}' ignorada: {e}");
+                    invalid = true;
+                })
+                .ok()
+        })
+        .collect();
+    (tags, invalid)
+}
+
+/// Añade a la petición las etiquetas del host de `url`.
+pub fn apply(
+    tags: &[OutboundTag],
+    url: &str,
+    mut request: reqwest::RequestBuilder,
+) -> reqwest::RequestBuilder {
+    if tags.is_empty() {
+        return request;
+    }
+    let Some(host) = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_ascii_lowercase))
+    else {
+        return request;
+    };
+    for tag in tags.iter().filter(|t| t.matches(&host)) {
+        request = match &tag.tag {
+            Tag::Header(name, value) => request.header(name, value),
+            Tag::Query(name, value) => request.query(&[(name, value)]),
+        };
+    }
+    request
+}
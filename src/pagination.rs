@@ -0,0 +1,388 @@
+//! Paginación de `/user/:id/passes` con `?limit=` y `?cursor=`.
+//!
+//! El cursor es opaco: base64url del usuario, el etag de la lista paginada y
+//! la posición, firmado con HMAC-SHA256 (`CURSOR_SECRET`; sin él, una clave
+//! aleatoria por proceso, así que los cursores no sobreviven a un reinicio).
+//! Al emitir la primera página con más detrás, la lista entera (ya filtrada
+//! y ordenada) se guarda con su etag durante `CURSOR_TTL_SECS`: las páginas
+//! siguientes salen de esa lista aunque entretanto se refresque la caché o
+//! el creador cambie sus passes. Si ya no está guardada pero la lista actual
+//! es la misma (mismo etag), se sigue con la actual; si cambió, 410.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::{hmac, rand::SystemRandom};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    admin::constant_time_eq, config::Config, error::ApiError, models::Gamepass, recording::fnv1a,
+};
+
+/// Listas guardadas como mucho; al llenarse se descarta la más antigua.
+const MAX_PINNED: usize = 1000;
+/// Bytes de la firma que viajan en el cursor.
+const TAG_LEN: usize = 16;
+
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    #[serde(rename = "u")]
+    user_id: u64,
+    #[serde(rename = "e")]
+    etag: String,
+    #[serde(rename = "o")]
+    offset: usize,
+}
+
+/// Una página de la lista: los passes y, si quedan más, el cursor de la
+/// siguiente (ver `next_cursor`).
+pub struct Page {
+    pub(crate) passes: Vec<Gamepass>,
+    /// Posición del primer pass de la página en la lista.
+    pub offset: usize,
+    /// Passes de la lista entera.
+    pub total: usize,
+    etag: String,
+}
+
+struct Pinned {
+    passes: Arc<Vec<Gamepass>>,
+    pinned_at: Instant,
+}
+
+pub struct Paginator {
+    key: hmac::Key,
+    ttl: Duration,
+    pinned: Mutex<HashMap<(u64, String), Pinned>>,
+}
+
+impl Paginator {
+    pub fn new(config: &Config) -> Self {
+        let key = match &config.cursor_secret {
+            Some(secret) => hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
+            None => {
+                info!("Sin CURSOR_SECRET: los cursores de paginación caducan al reiniciar");
+                hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new())
+                    .expect("clave aleatoria para los cursores")
+            }
+        };
+        Paginator {
+            key,
+            ttl: config.cursor_ttl,
+            pinned: Mutex::default(),
+        }
+    }
+
+    fn encode(&self, cursor: &Cursor) -> String {
+        let payload = serde_json::to_vec(cursor).expect("cursor serializable");
+        let tag = hmac::sign(&self.key, &payload);
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(&payload),
+            URL_SAFE_NO_PAD.encode(&tag.as_ref()[..TAG_LEN])
+        )
+    }
+
+    fn decode(&self, raw: &str, user_id: u64) -> Result<Cursor, ApiError> {
+        let invalid = || {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_CURSOR",
+                "cursor inválido: usa el nextCursor de la página anterior sin modificarlo",
+            )
+        };
+        let (payload, tag) = raw.split_once('.').ok_or_else(invalid)?;
+        let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| invalid())?;
+        let tag = URL_SAFE_NO_PAD.decode(tag).map_err(|_| invalid())?;
+        let expected = hmac::sign(&self.key, &payload);
+        // `hmac::verify` exige la firma entera; aquí viaja recortada.
+        let valid = constant_time_eq(&expected.as_ref()[..TAG_LEN], &tag);
+        if !valid {
+            return Err(invalid());
+        }
+        let cursor: Cursor = serde_json::from_slice(&payload).map_err(|_| invalid())?;
+        if cursor.user_id != user_id {
+            return Err(invalid());
+        }
+        Ok(cursor)
+    }
+
+    /// Página de `passes` (la lista actual, filtrada y ordenada) desde
+    /// `cursor`, o desde el principio, con `limit` passes como mucho.
+    pub(crate) fn page(
+        &self,
+        user_id: u64,
+        passes: Vec<Gamepass>,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Page, ApiError> {
+        let current_etag = list_etag(&passes);
+        let (list, etag, offset) =
+            match cursor {
+                None => (Arc::new(passes), current_etag, 0),
+                Some(raw) => {
+                    let cursor = self.decode(raw, user_id)?;
+                    match self.pinned_list(user_id, &cursor.etag) {
+                        Some(list) => (list, cursor.etag, cursor.offset),
+                        None if cursor.etag == current_etag => {
+                            (Arc::new(passes), current_etag, cursor.offset)
+                        }
+                        None => return Err(ApiError::new(
+                            StatusCode::GONE,
+                            "CURSOR_EXPIRED",
+                            "La lista paginada cambió o caducó; vuelve a pedir la primera página",
+                        )),
+                    }
+                }
+            };
+
+        let total = list.len();
+        let end = limit.map_or(total, |l| offset.saturating_add(l).min(total));
+        let page = list
+            .get(offset.min(total)..end)
+            .unwrap_or_default()
+            .to_vec();
+        if end < total {
+            self.pin(user_id, &etag, list);
+        }
+        Ok(Page {
+            passes: page,
+            offset,
+            total,
+            etag,
+        })
+    }
+
+    /// Cursor de la página que sigue a los `served` primeros passes de
+    /// `page` (menos que los de la página si la respuesta se recortó por
+    /// tamaño), o `None` si no queda nada.
+    pub fn next_cursor(&self, user_id: u64, page: &Page, served: usize) -> Option<String> {
+        let next = page.offset + served;
+        (next < page.total).then(|| {
+            self.encode(&Cursor {
+                user_id,
+                etag: page.etag.clone(),
+                offset: next,
+            })
+        })
+    }
+
+    fn pinned_list(&self, user_id: u64, etag: &str) -> Option<Arc<Vec<Gamepass>>> {
+        let pinned = self.pinned.lock().unwrap();
+        let entry = pinned.get(&(user_id, etag.to_string()))?;
+        (entry.pinned_at.elapsed() < self.ttl).then(|| entry.passes.clone())
+    }
+
+    /// Guarda `passes` para las páginas siguientes. Si ya estaba, se
+    /// renueva su plazo.
+    fn pin(&self, user_id: u64, etag: &str, passes: Arc<Vec<Gamepass>>) {
+        let mut pinned = self.pinned.lock().unwrap();
+        if pinned.len() >= MAX_PINNED && !pinned.contains_key(&(user_id, etag.to_string())) {
+            let oldest = pinned
+                .iter()
+                .min_by_key(|(_, p)| p.pinned_at)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                warn!("Demasiadas listas paginadas guardadas; se descarta la más antigua");
+                pinned.remove(&oldest);
+            }
+        }
+        pinned.insert(
+            (user_id, etag.to_string()),
+            Pinned {
+                passes,
+                pinned_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Descarta las listas caducadas.
+    pub fn prune(&self) {
+        let ttl = self.ttl;
+        self.pinned
+            .lock()
+            .unwrap()
+            .retain(|_, p| p.pinned_at.elapsed() < ttl);
+    }
+}
+
+/// Etag de una lista en el orden en que se sirve: cambia si cambia un pass,
+/// su precio o el orden.
+fn list_etag(passes: &[Gamepass]) -> String {
+    let canonical: Vec<(u64, i64, &str)> = passes
+        .iter()
+        .map(|p| (p.id, p.price, p.name.as_str()))
+        .collect();
+    let canonical = serde_json::to_string(&canonical).unwrap_or_default();
+    format!("{:016x}", fnv1a(&canonical))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paginator(ttl: Duration) -> Paginator {
+        let mut config = Config::from_env();
+        config.cursor_secret = Some("secreto-de-prueba".to_string());
+        config.cursor_ttl = ttl;
+        Paginator::new(&config)
+    }
+
+    /// Passes con el id como precio.
+    fn passes(ids: &[u64]) -> Vec<Gamepass> {
+        ids.iter()
+            .map(|&id| Gamepass {
+                id,
+                name: format!("Pass {id}"),
+                price: id as i64,
+                original_price: id as i64,
+                price_changed: false,
+                price_details: None,
+                price_source: None,
+                display_name: String::new(),
+                duplicate_of: None,
+                icon_url: None,
+                links: None,
+                source: Default::default(),
+                universe_id: None,
+                game: None,
+                game_name: None,
+            })
+            .collect()
+    }
+
+    fn ids(page: &Page) -> Vec<u64> {
+        page.passes.iter().map(|p| p.id).collect()
+    }
+
+    fn rejection(result: Result<Page, ApiError>) -> (StatusCode, &'static str) {
+        match result {
+            Ok(page) => panic!("página aceptada: {:?}", ids(&page)),
+            Err(e) => (e.status, e.code),
+        }
+    }
+
+    /// Cursor de la segunda página de `1..=5` de dos en dos.
+    fn second_page_cursor(paginator: &Paginator, user_id: u64) -> String {
+        let first = paginator
+            .page(user_id, passes(&[1, 2, 3, 4, 5]), None, Some(2))
+            .unwrap();
+        paginator.next_cursor(user_id, &first, 2).unwrap()
+    }
+
+    #[test]
+    fn cursors_walk_the_list_to_the_end() {
+        let paginator = paginator(Duration::from_secs(60));
+        let mut cursor = None;
+        let mut seen = Vec::new();
+        loop {
+            let page = paginator
+                .page(7, passes(&[1, 2, 3, 4, 5]), cursor.as_deref(), Some(2))
+                .unwrap();
+            seen.push(ids(&page));
+            let served = page.passes.len();
+            match paginator.next_cursor(7, &page, served) {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen, [vec![1, 2], vec![3, 4], vec![5]]);
+    }
+
+    #[test]
+    fn tampered_payload_is_rejected() {
+        let paginator = paginator(Duration::from_secs(60));
+        let cursor = second_page_cursor(&paginator, 7);
+        let (_, tag) = cursor.split_once('.').unwrap();
+        let forged = Cursor {
+            user_id: 7,
+            etag: list_etag(&passes(&[1, 2, 3, 4, 5])),
+            offset: 4,
+        };
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged).unwrap());
+        let tampered = format!("{payload}.{tag}");
+
+        let result = paginator.page(7, passes(&[1, 2, 3, 4, 5]), Some(&tampered), Some(2));
+        assert_eq!(
+            rejection(result),
+            (StatusCode::BAD_REQUEST, "INVALID_CURSOR")
+        );
+    }
+
+    #[test]
+    fn truncated_tag_is_rejected() {
+        let paginator = paginator(Duration::from_secs(60));
+        let cursor = second_page_cursor(&paginator, 7);
+        let (payload, tag) = cursor.split_once('.').unwrap();
+        // Dos caracteres menos de base64 son un byte menos de firma.
+        for truncated in [&tag[..tag.len() - 2], ""] {
+            let raw = format!("{payload}.{truncated}");
+            let result = paginator.page(7, passes(&[1, 2, 3, 4, 5]), Some(&raw), Some(2));
+            assert_eq!(
+                rejection(result),
+                (StatusCode::BAD_REQUEST, "INVALID_CURSOR"),
+                "{raw}"
+            );
+        }
+    }
+
+    #[test]
+    fn cursor_of_another_user_is_rejected() {
+        let paginator = paginator(Duration::from_secs(60));
+        let cursor = second_page_cursor(&paginator, 7);
+
+        let result = paginator.page(8, passes(&[1, 2, 3, 4, 5]), Some(&cursor), Some(2));
+        assert_eq!(
+            rejection(result),
+            (StatusCode::BAD_REQUEST, "INVALID_CURSOR")
+        );
+    }
+
+    #[test]
+    fn expired_list_continues_only_if_unchanged() {
+        // Sin plazo, la lista guardada caduca en cuanto se guarda.
+        let paginator = paginator(Duration::ZERO);
+        let cursor = second_page_cursor(&paginator, 7);
+
+        let same = paginator
+            .page(7, passes(&[1, 2, 3, 4, 5]), Some(&cursor), Some(2))
+            .unwrap();
+        assert_eq!(ids(&same), [3, 4]);
+
+        let changed = paginator.page(7, passes(&[1, 2, 3, 4, 6]), Some(&cursor), Some(2));
+        assert_eq!(rejection(changed), (StatusCode::GONE, "CURSOR_EXPIRED"));
+    }
+
+    #[test]
+    fn pinned_list_survives_changes() {
+        let paginator = paginator(Duration::from_secs(60));
+        let cursor = second_page_cursor(&paginator, 7);
+
+        let page = paginator
+            .page(7, passes(&[9, 8]), Some(&cursor), Some(2))
+            .unwrap();
+        assert_eq!(ids(&page), [3, 4]);
+        assert_eq!(page.total, 5);
+    }
+
+    #[test]
+    fn offset_past_the_end_is_an_empty_last_page() {
+        let paginator = paginator(Duration::from_secs(60));
+        let list = passes(&[1, 2, 3]);
+        let cursor = paginator.encode(&Cursor {
+            user_id: 7,
+            etag: list_etag(&list),
+            offset: 10,
+        });
+
+        let page = paginator.page(7, list, Some(&cursor), Some(2)).unwrap();
+        assert!(page.passes.is_empty());
+        assert_eq!(paginator.next_cursor(7, &page, 0), None);
+    }
+}
@@ -0,0 +1,313 @@
+//! Precio de un pass según `economy.roblox.com/v2/assets/{id}/details`.
+//!
+//! Lo normal es `PriceInRobux` (o el antiguo `Price`). En los experimentos de
+//! precios regionales Roblox añade `PriceInformation`, con el precio que fijó
+//! el creador (`defaultPriceInRobux`), y `PriceInRobux` pasa a ser el de la
+//! región desde la que se consulta. El precio canónico es el del creador, el
+//! mismo para todos; cuando los campos no coinciden, `priceDetails` los
+//! muestra todos en lugar de quedarse con uno sin avisar.
+//!
+//! El orden en que se miran los campos es `PRICE_SOURCE_ORDER` (por defecto
+//! `PRICE_FIELDS`); un campo que no está en la lista no da precio, aunque
+//! siga saliendo en `priceDetails`.
+
+use schemars::JsonSchema;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::roblox::api::{AssetDetails, Robux};
+
+/// Mayor precio que Roblox deja poner a un pass o a un developer product.
+pub const MAX_PRICE: i64 = 1_000_000_000;
+
+/// Por qué un importe no sirve como precio de venta.
+#[derive(Debug, PartialEq)]
+pub enum InvalidPrice {
+    /// 0 o negativo.
+    NotPositive,
+    /// Por encima de `MAX_PRICE`: un dato corrupto, no un precio.
+    OutOfRange(i64),
+}
+
+/// El importe si es un precio de venta válido (`1..=MAX_PRICE`).
+pub fn checked_price(raw: i64) -> Result<i64, InvalidPrice> {
+    match raw {
+        ..=0 => Err(InvalidPrice::NotPositive),
+        1..=MAX_PRICE => Ok(raw),
+        _ => Err(InvalidPrice::OutOfRange(raw)),
+    }
+}
+
+/// Todos los precios que trae la respuesta, cuando no son uno solo.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceDetails {
+    /// Campo del que sale `price`: `defaultPriceInRobux`, `PriceInRobux` o
+    /// `Price`.
+    #[serde(deserialize_with = "price_field")]
+    pub source: PriceField,
+    pub default_price_in_robux: Option<i64>,
+    pub price_in_robux: Option<i64>,
+    /// `Price`, el campo antiguo.
+    pub legacy_price: Option<i64>,
+    /// `isInActivePriceOptimizationExperiment`.
+    pub in_price_experiment: bool,
+}
+
+/// Uno de `PRICE_FIELDS`. Con alias porque serde toma un `&str` escrito tal
+/// cual como prestado del JSON y exigiría un `'de: 'static` al deserializar.
+pub type PriceField = &'static str;
+
+/// Campos de Roblox de los que puede salir un precio, en el orden por
+/// defecto.
+pub const PRICE_FIELDS: [PriceField; 3] = ["defaultPriceInRobux", "PriceInRobux", "Price"];
+
+/// Orden de `PRICE_SOURCE_ORDER`: campos de `PRICE_FIELDS` separados por
+/// comas, sin repetir. `None` si está vacío o trae otro nombre.
+pub fn parse_price_sources(raw: &str) -> Option<Vec<PriceField>> {
+    let mut order = Vec::new();
+    for name in raw.split(',').map(str::trim) {
+        let field = PRICE_FIELDS.into_iter().find(|field| *field == name)?;
+        if order.contains(&field) {
+            return None;
+        }
+        order.push(field);
+    }
+    Some(order)
+}
+
+/// `source` de unos `PriceDetails` importados (ver `cache`).
+fn price_field<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PriceField, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    PRICE_FIELDS
+        .into_iter()
+        .find(|field| *field == name)
+        .ok_or_else(|| D::Error::custom(format!("campo de precio desconocido: {name}")))
+}
+
+/// `priceSource` de un pass importado (ver `cache`).
+pub fn optional_price_field<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<PriceField>, D::Error> {
+    #[derive(Deserialize)]
+    struct Field(#[serde(deserialize_with = "price_field")] PriceField);
+
+    Ok(Option::<Field>::deserialize(deserializer)?.map(|Field(field)| field))
+}
+
+/// Precio canónico, el campo del que sale y, si hay más de uno, el
+/// desglose.
+#[derive(Debug, PartialEq)]
+pub struct ListedPrice {
+    pub price: Option<i64>,
+    pub source: Option<PriceField>,
+    pub details: Option<PriceDetails>,
+}
+
+/// Precio de `details` según el primer campo de `order` que lo traiga.
+pub fn listed_price(details: &AssetDetails, order: &[PriceField]) -> ListedPrice {
+    let amount = |robux: Option<Robux>| robux.map(|Robux(amount)| amount);
+    let info = details.price_information.as_ref();
+    let default_price = amount(info.and_then(|i| i.default_price_in_robux));
+    let price_in_robux = amount(details.price_in_robux);
+    let legacy_price = amount(details.price);
+    let in_experiment = info
+        .and_then(|i| i.is_in_active_price_optimization_experiment)
+        .unwrap_or(false);
+
+    let (source, price) = order
+        .iter()
+        .find_map(|&source| {
+            let price = match source {
+                "defaultPriceInRobux" => default_price,
+                "PriceInRobux" => price_in_robux,
+                _ => legacy_price,
+            };
+            Some((source, price?))
+        })
+        .map_or((None, None), |(source, price)| (Some(source), Some(price)));
+
+    let mut values: Vec<i64> = [default_price, price_in_robux, legacy_price]
+        .into_iter()
+        .flatten()
+        .collect();
+    values.dedup();
+    let ambiguous = values.len() > 1 || in_experiment;
+    ListedPrice {
+        price,
+        source,
+        details: source.filter(|_| ambiguous).map(|source| PriceDetails {
+            source,
+            default_price_in_robux: default_price,
+            price_in_robux,
+            legacy_price,
+            in_price_experiment: in_experiment,
+        }),
+    }
+}
+
+/// Si `details` trae alguno de los campos de precio, aunque sea a `null`.
+/// Sin ninguno, la respuesta tiene una forma que no conocemos.
+pub fn has_price_fields(details: &Value) -> bool {
+    ["PriceInRobux", "Price", "PriceInformation"]
+        .iter()
+        .any(|field| details.get(field).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn details(value: Value) -> AssetDetails {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn single_price_has_no_details() {
+        let listed = listed_price(
+            &details(json!({ "PriceInRobux": 10, "IsForSale": true })),
+            &PRICE_FIELDS,
+        );
+        assert_eq!(listed.price, Some(10));
+        assert_eq!(listed.details, None);
+
+        let legacy = listed_price(&details(json!({ "Price": 5 })), &PRICE_FIELDS);
+        assert_eq!(legacy.price, Some(5));
+        assert_eq!(legacy.details, None);
+
+        let same = listed_price(
+            &details(json!({ "PriceInRobux": 7, "Price": 7 })),
+            &PRICE_FIELDS,
+        );
+        assert_eq!(same.price, Some(7));
+        assert_eq!(same.details, None);
+    }
+
+    #[test]
+    fn regional_price_uses_creator_default() {
+        let listed = listed_price(
+            &details(json!({
+                "PriceInRobux": 8,
+                "PriceInformation": {
+                    "defaultPriceInRobux": 10,
+                    "isInActivePriceOptimizationExperiment": true
+                }
+            })),
+            &PRICE_FIELDS,
+        );
+        assert_eq!(listed.price, Some(10));
+        assert_eq!(
+            listed.details,
+            Some(PriceDetails {
+                source: "defaultPriceInRobux",
+                default_price_in_robux: Some(10),
+                price_in_robux: Some(8),
+                legacy_price: None,
+                in_price_experiment: true,
+            })
+        );
+    }
+
+    #[test]
+    fn conflicting_fields_are_reported() {
+        let listed = listed_price(
+            &details(json!({ "PriceInRobux": 12, "Price": 15 })),
+            &PRICE_FIELDS,
+        );
+        assert_eq!(listed.price, Some(12));
+        let details = listed.details.expect("priceDetails");
+        assert_eq!(details.source, "PriceInRobux");
+        assert_eq!(details.legacy_price, Some(15));
+        assert!(!details.in_price_experiment);
+    }
+
+    #[test]
+    fn source_order_decides_between_price_in_robux_and_price() {
+        let details = details(json!({ "PriceInRobux": 12, "Price": 15 }));
+
+        let listed = listed_price(&details, &["PriceInRobux", "Price"]);
+        assert_eq!(
+            (listed.price, listed.source),
+            (Some(12), Some("PriceInRobux"))
+        );
+
+        let listed = listed_price(&details, &["Price", "PriceInRobux"]);
+        assert_eq!((listed.price, listed.source), (Some(15), Some("Price")));
+        let details = listed.details.expect("priceDetails");
+        assert_eq!(details.source, "Price");
+        assert_eq!(details.price_in_robux, Some(12));
+    }
+
+    #[test]
+    fn fields_outside_the_order_give_no_price() {
+        let legacy_only = details(json!({ "Price": 5 }));
+        let listed = listed_price(&legacy_only, &["PriceInRobux"]);
+        assert_eq!((listed.price, listed.source), (None, None));
+
+        let listed = listed_price(&legacy_only, &PRICE_FIELDS);
+        assert_eq!((listed.price, listed.source), (Some(5), Some("Price")));
+    }
+
+    #[test]
+    fn price_source_order_is_validated() {
+        assert_eq!(
+            parse_price_sources("Price, PriceInRobux"),
+            Some(vec!["Price", "PriceInRobux"])
+        );
+        assert_eq!(parse_price_sources("PriceInRobux,PriceInRobux"), None);
+        assert_eq!(parse_price_sources("Cost"), None);
+        assert_eq!(parse_price_sources(""), None);
+    }
+
+    #[test]
+    fn no_price_fields() {
+        let listed = listed_price(
+            &details(json!({ "PriceInRobux": null, "IsForSale": false })),
+            &PRICE_FIELDS,
+        );
+        assert_eq!(
+            listed,
+            ListedPrice {
+                price: None,
+                source: None,
+                details: None
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_shape_has_no_price_fields() {
+        assert!(has_price_fields(&json!({ "PriceInRobux": null })));
+        assert!(!has_price_fields(
+            &json!({ "Name": "VIP", "Cost": { "robux": 10 } })
+        ));
+    }
+
+    #[test]
+    fn prices_outside_the_valid_range_are_rejected() {
+        assert_eq!(checked_price(0), Err(InvalidPrice::NotPositive));
+        assert_eq!(checked_price(-5), Err(InvalidPrice::NotPositive));
+        assert_eq!(checked_price(1), Ok(1));
+        assert_eq!(checked_price(MAX_PRICE), Ok(MAX_PRICE));
+        assert_eq!(
+            checked_price(MAX_PRICE + 1),
+            Err(InvalidPrice::OutOfRange(MAX_PRICE + 1))
+        );
+        // Antes se convertía con `as i32` y salía negativo.
+        assert_eq!(
+            checked_price(4_294_967_306),
+            Err(InvalidPrice::OutOfRange(4_294_967_306))
+        );
+    }
+
+    #[test]
+    fn huge_integers_are_out_of_range_not_missing() {
+        let listed = listed_price(&details(json!({ "PriceInRobux": u64::MAX })), &PRICE_FIELDS);
+        assert_eq!(
+            listed.price.map(checked_price),
+            Some(Err(InvalidPrice::OutOfRange(i64::MAX)))
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Developer Products de un juego (`/universe/:id/products`). Cada vez más
+//! juegos de donaciones venden productos en lugar de gamepasses: se pueden
+//! comprar varias veces y no hace falta un pass por importe.
+//!
+//! Salen de `apis.roblox.com/developer-products/v1/developer-products/list`,
+//! paginado por número de página hasta `FinalPage`, con el mismo filtro de
+//! precio que los passes. `/user/:id/donatables` incluye los productos de los
+//! juegos públicos del usuario.
+
+use std::{collections::HashSet, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::ApiError,
+    pricing,
+    roblox::api,
+    upstream::{self, Endpoint, Upstream},
+    AppState,
+};
+#[cfg(feature = "clothing")]
+use {
+    crate::{guidance::ScanStats, roblox::client},
+    futures::stream::{self, StreamExt},
+};
+
+#[derive(Serialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeveloperProduct {
+    /// El id que espera `MarketplaceService:PromptProductPurchase`.
+    pub id: u64,
+    pub name: String,
+    pub price: i64,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductsResponse {
+    ok: bool,
+    universe_id: u64,
+    count: usize,
+    /// En el orden de Roblox.
+    products: Vec<DeveloperProduct>,
+}
+
+/// Productos a la venta de un juego. `None` si falla la primera página; un
+/// fallo en las siguientes devuelve lo ya recogido.
+pub async fn fetch_products(state: &AppState, universe_id: u64) -> Option<Vec<DeveloperProduct>> {
+    let mut result = Vec::new();
+    let mut seen_ids: HashSet<u64> = HashSet::new();
+
+    for page in 1..=state.config.upstream_max_pages {
+        let url = format!(
+            "{}/developer-products/v1/developer-products/list?universeId={universe_id}&page={page}",
+            state.config.upstream_url(Upstream::Apis),
+        );
+        debug!("Pidiendo developer products de universeId={universe_id} (página {page})");
+
+        let data = match upstream::get(state, Endpoint::DeveloperProducts, &url).await {
+            Ok(resp) if resp.status().is_success() => resp.json::<serde_json::Value>().await.ok(),
+            Ok(resp) => {
+                warn!(
+                    "Developer products HTTP {} para universeId={universe_id}",
+                    resp.status()
+                );
+                None
+            }
+            Err(e) => {
+                warn!("Error HTTP al pedir developer products: {e}");
+                None
+            }
+        };
+        let Some(data) = data else {
+            if page == 1 {
+                return None;
+            }
+            break;
+        };
+        let Some(api::DeveloperProductsPage {
+            developer_products,
+            final_page,
+        }) = state.quarantine.deserialize(
+            Endpoint::DeveloperProducts,
+            "listado sin 'DeveloperProducts'",
+            &data,
+        )
+        else {
+            info!("Developer products sin 'DeveloperProducts' para universeId={universe_id}");
+            break;
+        };
+        for raw in &developer_products {
+            let Some(item) = state.quarantine.deserialize::<api::DeveloperProduct>(
+                Endpoint::DeveloperProducts,
+                "producto con forma desconocida",
+                raw,
+            ) else {
+                continue;
+            };
+            let id = item.product_id;
+            if !seen_ids.insert(id) {
+                continue;
+            }
+            let Some(api::Robux(price)) = item.price_in_robux else {
+                continue;
+            };
+            let price = match pricing::checked_price(price) {
+                Ok(price) => price,
+                Err(pricing::InvalidPrice::NotPositive) => continue,
+                Err(pricing::InvalidPrice::OutOfRange(price)) => {
+                    warn!("Precio fuera de rango ({price}), se omite el producto {id}");
+                    state.quarantine.record(
+                        Endpoint::DeveloperProducts,
+                        "precio fuera de rango",
+                        raw,
+                    );
+                    continue;
+                }
+            };
+            let name = item
+                .display_name
+                .filter(|n| !n.is_empty())
+                .or(item.name)
+                .unwrap_or_else(|| "DeveloperProduct".to_string());
+            result.push(DeveloperProduct { id, name, price });
+        }
+
+        if final_page.unwrap_or(true) {
+            break;
+        }
+    }
+
+    info!(
+        "Total developer products con precio > 0 para universeId={universe_id}: {}",
+        result.len()
+    );
+    Some(result)
+}
+
+/// Productos de varios juegos a la vez (hasta `SCAN_CONCURRENCY`), en el
+/// orden de `universe_ids`. Los juegos que fallan se omiten.
+#[cfg(feature = "clothing")]
+pub async fn fetch_many(state: &AppState, universe_ids: &[u64]) -> Vec<DeveloperProduct> {
+    let mut lists: Vec<(usize, Option<Vec<DeveloperProduct>>)> =
+        stream::iter(universe_ids.iter().copied().enumerate())
+            .map(|(i, universe_id)| async move { (i, fetch_products(state, universe_id).await) })
+            .buffer_unordered(state.config.scan_concurrency)
+            .collect()
+            .await;
+    lists.sort_by_key(|(i, _)| *i);
+    lists.into_iter().filter_map(|(_, l)| l).flatten().collect()
+}
+
+/// Productos de los juegos públicos de un usuario, los más populares primero
+/// y con el mismo tope de `MAX_UNIVERSES` que el escaneo de passes.
+#[cfg(feature = "clothing")]
+pub async fn for_user(state: &AppState, user_id: u64) -> Vec<DeveloperProduct> {
+    let stats = ScanStats::default();
+    let Some(mut games) = client::fetch_public_games(state, user_id, &stats).await else {
+        return Vec::new();
+    };
+    client::sort_by_popularity(&mut games);
+    if state.config.max_universes > 0 {
+        games.truncate(state.config.max_universes);
+    }
+    let universe_ids: Vec<u64> = games.iter().map(|g| g.universe_id).collect();
+    fetch_many(state, &universe_ids).await
+}
+
+/// `GET /universe/:id/products`
+pub async fn get_products(
+    State(state): State<Arc<AppState>>,
+    Path(universe_id): Path<u64>,
+) -> Result<Json<ProductsResponse>, ApiError> {
+    info!("/universe/{universe_id}/products");
+    let products = fetch_products(&state, universe_id).await.ok_or_else(|| {
+        ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            "UPSTREAM_ERROR",
+            format!("No se pudieron consultar los developer products de universeId {universe_id}"),
+        )
+    })?;
+    Ok(Json(ProductsResponse {
+        ok: true,
+        universe_id,
+        count: products.len(),
+        products,
+    }))
+}
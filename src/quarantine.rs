@@ -0,0 +1,199 @@
+//! Modo estricto (`STRICT_UPSTREAM`): lo que Roblox devuelve con una forma
+//! desconocida (un listado sin `data`, un pass sin `id`, unos detalles sin
+//! ningún campo de precio) se sigue omitiendo, pero ya no en silencio: se
+//! anota aquí, sube `donations_api_upstream_quarantined_total` y las últimas
+//! `SAMPLES` muestras se pueden ver en `GET /admin/quarantine`. Es la forma
+//! de enterarse pronto de un cambio de esquema de Roblox.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use axum::{extract::State, Json};
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{admin::AdminAuth, config::Config, upstream::Endpoint, AppState};
+
+/// Muestras que se conservan, las más recientes.
+const SAMPLES: usize = 50;
+/// Bytes de JSON guardados por muestra; el resto se corta.
+const MAX_PAYLOAD_BYTES: usize = 2048;
+
+/// Elemento de Roblox que no se pudo interpretar.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Sample {
+    host: &'static str,
+    path: &'static str,
+    /// Qué faltaba o no encajaba.
+    reason: &'static str,
+    /// El error al deserializarlo (ver `roblox::api`), si lo hubo.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    /// RFC 3339.
+    at: String,
+    /// JSON compacto, cortado a `MAX_PAYLOAD_BYTES`.
+    payload: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+}
+
+#[derive(Default)]
+struct Inner {
+    samples: VecDeque<Sample>,
+    counts: HashMap<Endpoint, u64>,
+}
+
+pub struct Quarantine {
+    enabled: bool,
+    inner: Mutex<Inner>,
+}
+
+impl Quarantine {
+    pub fn new(config: &Config) -> Self {
+        Quarantine {
+            enabled: config.strict_upstream,
+            inner: Mutex::default(),
+        }
+    }
+
+    /// Anota `payload`, de `endpoint`, que se omite por `reason`. Sin
+    /// `STRICT_UPSTREAM` no hace nada.
+    pub fn record(&self, endpoint: Endpoint, reason: &'static str, payload: &Value) {
+        self.record_detail(endpoint, reason, payload, None);
+    }
+
+    fn record_detail(
+        &self,
+        endpoint: Endpoint,
+        reason: &'static str,
+        payload: &Value,
+        detail: Option<String>,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let mut payload = payload.to_string();
+        let truncated = payload.len() > MAX_PAYLOAD_BYTES;
+        if truncated {
+            let mut end = MAX_PAYLOAD_BYTES;
+            while !payload.is_char_boundary(end) {
+                end -= 1;
+            }
+            payload.truncate(end);
+        }
+        let upstream = endpoint.upstream();
+        let because = detail
+            .as_deref()
+            .map_or(String::new(), |d| format!(": {d}"));
+        warn!(
+            "Cuarentena: {}{} ({reason}{because}): {payload}",
+            upstream.host(),
+            endpoint.path()
+        );
+
+        let mut inner = self.inner.lock().unwrap();
+        *inner.counts.entry(endpoint).or_default() += 1;
+        if inner.samples.len() >= SAMPLES {
+            inner.samples.pop_front();
+        }
+        inner.samples.push_back(Sample {
+            host: upstream.host(),
+            path: endpoint.path(),
+            reason,
+            detail,
+            at: Utc::now().to_rfc3339(),
+            payload,
+            truncated,
+        });
+    }
+
+    /// Elementos de `items` que `parse` entiende; el resto se anota con
+    /// `reason`.
+    pub fn parse_all<T>(
+        &self,
+        endpoint: Endpoint,
+        reason: &'static str,
+        items: &[Value],
+        parse: impl Fn(&Value) -> Option<T>,
+    ) -> Vec<T> {
+        items
+            .iter()
+            .filter_map(|item| {
+                let parsed = parse(item);
+                if parsed.is_none() {
+                    self.record(endpoint, reason, item);
+                }
+                parsed
+            })
+            .collect()
+    }
+
+    /// `item` como uno de los tipos de `roblox::api`; si no encaja, `None` y
+    /// se anota con `reason` y el error de serde.
+    pub fn deserialize<T: DeserializeOwned>(
+        &self,
+        endpoint: Endpoint,
+        reason: &'static str,
+        item: &Value,
+    ) -> Option<T> {
+        match T::deserialize(item) {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                self.record_detail(endpoint, reason, item, Some(e.to_string()));
+                None
+            }
+        }
+    }
+
+    /// Los elementos de `items` que encajan con `T` (ver `deserialize`).
+    pub fn deserialize_all<T: DeserializeOwned>(
+        &self,
+        endpoint: Endpoint,
+        reason: &'static str,
+        items: &[Value],
+    ) -> Vec<T> {
+        items
+            .iter()
+            .filter_map(|item| self.deserialize(endpoint, reason, item))
+            .collect()
+    }
+
+    /// Elementos anotados por endpoint desde el arranque, para `/metrics`.
+    pub fn counts(&self) -> Vec<(Endpoint, u64)> {
+        let inner = self.inner.lock().unwrap();
+        Endpoint::ALL
+            .into_iter()
+            .map(|e| (e, inner.counts.get(&e).copied().unwrap_or(0)))
+            .collect()
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantineResponse {
+    ok: bool,
+    /// `STRICT_UPSTREAM`; si es `false`, no se anota nada.
+    enabled: bool,
+    /// Elementos anotados desde el arranque.
+    total: u64,
+    /// Las últimas muestras, la más reciente primero.
+    samples: Vec<Sample>,
+}
+
+/// `GET /admin/quarantine`.
+pub async fn list(_: AdminAuth, State(state): State<Arc<AppState>>) -> Json<QuarantineResponse> {
+    let quarantine = &state.quarantine;
+    let inner = quarantine.inner.lock().unwrap();
+    Json(QuarantineResponse {
+        ok: true,
+        enabled: quarantine.enabled,
+        total: inner.counts.values().sum(),
+        samples: inner.samples.iter().rev().cloned().collect(),
+    })
+}
@@ -0,0 +1,138 @@
+//! Colas acotadas para el trabajo en segundo plano.
+//!
+//! Cada cola tiene una capacidad fija y una política para cuando se llena:
+//! descartar la tarea más antigua o rechazar la nueva. Así una caída de
+//! Roblox, con todas las tareas colgadas esperando respuesta, no se traduce
+//! en memoria creciendo sin límite.
+
+use std::{
+    collections::VecDeque,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::task::{AbortHandle, JoinHandle};
+use tracing::warn;
+
+/// Qué hacer cuando llega una tarea y la cola está llena.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShedPolicy {
+    /// Aborta la tarea más antigua para hacer sitio.
+    DropOldest,
+    /// Aborta la tarea nueva.
+    Reject,
+}
+
+impl FromStr for ShedPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "drop-oldest" => Ok(ShedPolicy::DropOldest),
+            "reject" => Ok(ShedPolicy::Reject),
+            other => Err(format!(
+                "política desconocida '{other}' (drop-oldest o reject)"
+            )),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    tasks: VecDeque<(u64, AbortHandle)>,
+    next_id: u64,
+    /// Tareas abortadas por falta de sitio desde el arranque.
+    shed: u64,
+}
+
+pub struct WorkQueue {
+    name: &'static str,
+    capacity: usize,
+    policy: ShedPolicy,
+    inner: Arc<Mutex<Inner>>,
+}
+
+/// Estado de una cola, para `/admin/queues`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSnapshot {
+    pub name: &'static str,
+    pub depth: usize,
+    pub capacity: usize,
+    pub policy: ShedPolicy,
+    pub shed: u64,
+}
+
+impl WorkQueue {
+    pub fn new(name: &'static str, capacity: usize, policy: ShedPolicy) -> Self {
+        WorkQueue {
+            name,
+            capacity: capacity.max(1),
+            policy,
+            inner: Arc::default(),
+        }
+    }
+
+    /// Se hace cargo de una tarea ya lanzada: la cuenta en la profundidad de
+    /// la cola y llama a `on_done` con su resultado si termina sin abortarse.
+    /// Devuelve `false` si la cola estaba llena y la tarea se rechazó.
+    pub fn adopt<T, F>(&self, task: JoinHandle<T>, on_done: F) -> bool
+    where
+        T: Send + 'static,
+        F: FnOnce(T) + Send + 'static,
+    {
+        let id = {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.tasks.len() >= self.capacity {
+                inner.shed += 1;
+                match self.policy {
+                    ShedPolicy::Reject => {
+                        task.abort();
+                        warn!(
+                            "{} llena ({}), tarea nueva rechazada",
+                            self.name, self.capacity
+                        );
+                        return false;
+                    }
+                    ShedPolicy::DropOldest => {
+                        if let Some((_, oldest)) = inner.tasks.pop_front() {
+                            oldest.abort();
+                        }
+                        warn!(
+                            "{} llena ({}), descartada la tarea más antigua",
+                            self.name, self.capacity
+                        );
+                    }
+                }
+            }
+            let id = inner.next_id;
+            inner.next_id += 1;
+            inner.tasks.push_back((id, task.abort_handle()));
+            id
+        };
+
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            let result = task.await;
+            inner.lock().unwrap().tasks.retain(|(t, _)| *t != id);
+            if let Ok(value) = result {
+                on_done(value);
+            }
+        });
+        true
+    }
+
+    pub fn snapshot(&self) -> QueueSnapshot {
+        let inner = self.inner.lock().unwrap();
+        QueueSnapshot {
+            name: self.name,
+            depth: inner.tasks.len(),
+            capacity: self.capacity,
+            policy: self.policy,
+            shed: inner.shed,
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Límite de peticiones por IP de cliente (`RATE_LIMIT_RPS`,
+//! `RATE_LIMIT_BURST`): un cubo de fichas por IP que se rellena a
+//! `RATE_LIMIT_RPS` fichas por segundo hasta `RATE_LIMIT_BURST`. Sin fichas,
+//! 429 con `Retry-After`. Solo cubre las rutas que llaman a Roblox, que son
+//! las que gastan la cuota; el resto lee estado local.
+//!
+//! La IP es la de la conexión. Detrás de un proxy propio,
+//! `RATE_LIMIT_TRUST_FORWARDED=true` usa la última dirección de
+//! `X-Forwarded-For` (la que añadió el proxy; las anteriores las puede poner
+//! el cliente).
+//!
+//! Las peticiones con una clave de API válida tienen su propio cubo, por
+//! clave y no por IP, con el `rps` de la clave si lo tiene (ver `tenant`).
+
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{header, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::{debug, warn};
+
+use crate::{config::Config, error::ApiError, tenant, AppState};
+
+/// De quién es un cubo.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Client {
+    Ip(IpAddr),
+    /// Id de la clave de API.
+    Key(String),
+}
+
+impl fmt::Display for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Client::Ip(ip) => write!(f, "La IP {ip}"),
+            Client::Key(id) => write!(f, "La clave '{id}'"),
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    /// Fichas por segundo del cliente en la última petición.
+    rps: f64,
+    updated: Instant,
+}
+
+pub struct ClientLimiter {
+    /// Fichas por segundo por IP; `0` desactiva el límite.
+    rps: f64,
+    burst: f64,
+    trust_forwarded: bool,
+    buckets: Mutex<HashMap<Client, Bucket>>,
+    /// Peticiones rechazadas desde el arranque, para `/metrics`.
+    rejected: AtomicU64,
+}
+
+impl ClientLimiter {
+    pub fn new(config: &Config) -> Self {
+        ClientLimiter {
+            rps: config.rate_limit_rps,
+            burst: f64::from(config.rate_limit_burst),
+            trust_forwarded: config.rate_limit_trust_forwarded,
+            buckets: Mutex::default(),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Gasta una ficha de `client` (a `rps` > 0), o devuelve cuánto falta
+    /// para la siguiente.
+    fn take(&self, client: &Client, rps: f64, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if !buckets.contains_key(client) {
+            buckets.insert(
+                client.clone(),
+                Bucket {
+                    tokens: self.burst,
+                    rps,
+                    updated: now,
+                },
+            );
+        }
+        let bucket = buckets.get_mut(client).expect("cubo recién creado");
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.rps = rps;
+        bucket.tokens = (bucket.tokens + elapsed * rps).min(self.burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rps))
+    }
+
+    /// Olvida los cubos que ya estarían llenos: para ellos no guardar nada
+    /// es lo mismo.
+    pub fn prune(&self) {
+        let now = Instant::now();
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, b| now.duration_since(b.updated).as_secs_f64() * b.rps < self.burst);
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    fn client_ip<B>(&self, req: &Request<B>) -> Option<IpAddr> {
+        if self.trust_forwarded {
+            let forwarded = req
+                .headers()
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.rsplit(',').next())
+                .and_then(|ip| ip.trim().parse().ok());
+            if forwarded.is_some() {
+                return forwarded;
+            }
+        }
+        req.extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    }
+
+    /// Quién hace la petición, como para el límite: su clave de API si trae
+    /// una válida o, si no, su IP.
+    pub(crate) fn caller<B>(&self, req: &Request<B>, keys: &[tenant::ApiKey]) -> Option<Client> {
+        match tenant::identify(req.headers(), keys) {
+            Some(key) => Some(Client::Key(key.id.clone())),
+            None => self.client_ip(req).map(Client::Ip),
+        }
+    }
+}
+
+/// Middleware: 429 si el cliente (su clave o, si no trae, su IP) se quedó
+/// sin fichas.
+pub async fn enforce<B>(
+    State(state): State<Arc<AppState>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let limiter = &state.client_limiter;
+    let (client, rps) = match tenant::identify(req.headers(), &state.config.api_keys) {
+        Some(key) => (Client::Key(key.id.clone()), key.rps.unwrap_or(limiter.rps)),
+        None => {
+            if limiter.rps <= 0.0 {
+                return next.run(req).await;
+            }
+            let Some(ip) = limiter.client_ip(&req) else {
+                debug!("Petición sin IP de cliente, sin límite");
+                return next.run(req).await;
+            };
+            (Client::Ip(ip), limiter.rps)
+        }
+    };
+    if rps <= 0.0 {
+        return next.run(req).await;
+    }
+    let Err(wait) = limiter.take(&client, rps, Instant::now()) else {
+        return next.run(req).await;
+    };
+
+    limiter.rejected.fetch_add(1, Ordering::Relaxed);
+    let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+    warn!(
+        "{client} superó el límite de {rps} peticiones/s en {}",
+        req.uri().path()
+    );
+    let mut response = ApiError::new(
+        StatusCode::TOO_MANY_REQUESTS,
+        "RATE_LIMITED",
+        format!("{client} superó su límite de peticiones; reintenta en {retry_after}s"),
+    )
+    .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
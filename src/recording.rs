@@ -0,0 +1,175 @@
+//! Grabación y reproducción de respuestas de Roblox.
+//!
+//! `UPSTREAM_MODE=record` guarda cada respuesta real en `UPSTREAM_CASSETTE_DIR`
+//! (por defecto `./cassettes`), un archivo JSON por URL (y cuerpo, en los
+//! POST, e idioma, si se pidió uno). `UPSTREAM_MODE=replay` sirve esas respuestas sin tocar la red; una
+//! URL sin grabación falla.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{config, upstream::Endpoint};
+
+pub enum Mode {
+    Live,
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl Mode {
+    pub fn from_env() -> Mode {
+        let dir = config::var("UPSTREAM_CASSETTE_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("cassettes"));
+        match config::var("UPSTREAM_MODE").as_deref() {
+            Ok("record") => Mode::Record(dir),
+            Ok("replay") => Mode::Replay(dir),
+            Ok("live") | Err(_) => Mode::Live,
+            Ok(other) => {
+                warn!("UPSTREAM_MODE desconocido '{other}', usando live");
+                Mode::Live
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mode::Live => "live",
+            Mode::Record(_) => "record",
+            Mode::Replay(_) => "replay",
+        }
+    }
+}
+
+/// Respuesta grabada en disco.
+#[derive(Serialize, Deserialize)]
+struct Cassette {
+    url: String,
+    /// Cuerpo de la petición, solo en los POST.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_body: Option<String>,
+    /// `Accept-Language` de la petición, si se mandó.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    locale: Option<String>,
+    status: u16,
+    content_type: Option<String>,
+    body: String,
+}
+
+/// FNV-1a de 64 bits: estable entre versiones de Rust, a diferencia de
+/// `DefaultHasher`, así los nombres de archivo no cambian.
+pub fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn cassette_path(
+    dir: &Path,
+    endpoint: Endpoint,
+    url: &str,
+    body: Option<&str>,
+    locale: Option<&str>,
+) -> PathBuf {
+    let slug = match endpoint {
+        Endpoint::UserGames => "user-games",
+        Endpoint::GamePasses => "game-passes",
+        Endpoint::GamesMultiget => "games-multiget",
+        Endpoint::AssetDetails => "asset-details",
+        Endpoint::CatalogSearch => "catalog-search",
+        Endpoint::GamePassIcons => "game-pass-icons",
+        Endpoint::UserInventory => "user-inventory",
+        Endpoint::UsernameLookup => "username-lookup",
+        Endpoint::UserGroups => "user-groups",
+        Endpoint::GroupGames => "group-games",
+        Endpoint::DeveloperProducts => "developer-products",
+    };
+    let mut key = url.to_string();
+    if let Some(body) = body {
+        key = format!("{key}\n{body}");
+    }
+    if let Some(locale) = locale {
+        key = format!("{key}\nAccept-Language: {locale}");
+    }
+    let hash = fnv1a(&key);
+    dir.join(format!("{slug}-{hash:016x}.json"))
+}
+
+fn build_response(cassette: Cassette) -> reqwest::Response {
+    let mut builder = http1::Response::builder().status(cassette.status);
+    if let Some(ct) = &cassette.content_type {
+        builder = builder.header("content-type", ct.as_str());
+    }
+    let response = builder
+        .body(cassette.body)
+        .expect("respuesta grabada válida");
+    reqwest::Response::from(response)
+}
+
+/// Consume la respuesta real, la guarda en disco y devuelve una copia.
+pub async fn record(
+    dir: &Path,
+    endpoint: Endpoint,
+    url: &str,
+    request_body: Option<&str>,
+    locale: Option<&str>,
+    resp: reqwest::Response,
+) -> reqwest::Result<reqwest::Response> {
+    let status = resp.status().as_u16();
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let body = resp.text().await?;
+
+    let cassette = Cassette {
+        url: url.to_string(),
+        request_body: request_body.map(str::to_string),
+        locale: locale.map(str::to_string),
+        status,
+        content_type,
+        body,
+    };
+
+    let path = cassette_path(dir, endpoint, url, request_body, locale);
+    let saved = async {
+        tokio::fs::create_dir_all(dir).await?;
+        let json = serde_json::to_vec_pretty(&cassette)?;
+        tokio::fs::write(&path, json).await
+    }
+    .await;
+    match saved {
+        Ok(()) => info!("Grabado {url} → {}", path.display()),
+        Err(e) => warn!("No se pudo grabar {url} en {}: {e}", path.display()),
+    }
+
+    Ok(build_response(cassette))
+}
+
+/// Devuelve la respuesta grabada para `url` (y `body` y `locale`), si existe.
+pub async fn replay(
+    dir: &Path,
+    endpoint: Endpoint,
+    url: &str,
+    body: Option<&str>,
+    locale: Option<&str>,
+) -> Option<reqwest::Response> {
+    let path = cassette_path(dir, endpoint, url, body, locale);
+    let bytes = match tokio::fs::read(&path).await {
+        Ok(b) => b,
+        Err(_) => {
+            warn!("Sin grabación para {url} ({})", path.display());
+            return None;
+        }
+    };
+    match serde_json::from_slice::<Cassette>(&bytes) {
+        Ok(cassette) => Some(build_response(cassette)),
+        Err(e) => {
+            warn!("Grabación inválida {}: {e}", path.display());
+            None
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Identificador por petición, para cruzar una respuesta de error con los
+//! logs. Se respeta el `X-Request-Id` entrante (p. ej. del balanceador) y si
+//! no viene se genera uno.
+
+use axum::{
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use tracing::{info_span, Instrument};
+
+pub const HEADER: &str = "x-request-id";
+
+/// Datos de la petición en curso, visibles desde cualquier punto del handler.
+#[derive(Clone)]
+pub struct RequestContext {
+    pub id: String,
+    pub method: String,
+    pub path: String,
+}
+
+impl RequestContext {
+    /// userId de las rutas `/user/:id/...`, para el span de la petición y
+    /// los informes de `crash`.
+    pub fn user_id(&self) -> Option<u64> {
+        self.path
+            .strip_prefix("/user/")?
+            .split('/')
+            .next()?
+            .parse()
+            .ok()
+    }
+}
+
+tokio::task_local! {
+    static CONTEXT: RequestContext;
+}
+
+/// Id de la petición en curso, si se está dentro del middleware.
+pub fn current() -> Option<String> {
+    CONTEXT.try_with(|ctx| ctx.id.clone()).ok()
+}
+
+/// Contexto completo de la petición en curso.
+pub fn context() -> Option<RequestContext> {
+    CONTEXT.try_with(RequestContext::clone).ok()
+}
+
+fn generate() -> String {
+    format!("{:016x}", fastrand::u64(..))
+}
+
+/// Fija el id de la petición durante el handler y lo devuelve en la
+/// cabecera `X-Request-Id` de la respuesta.
+pub async fn request_id<B>(req: Request<B>, next: Next<B>) -> Response {
+    let id = req
+        .headers()
+        .get(HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(generate);
+    let context = RequestContext {
+        id: id.clone(),
+        method: req.method().to_string(),
+        path: req.uri().path().to_string(),
+    };
+
+    let span = info_span!(
+        "request",
+        id = %id,
+        method = %context.method,
+        path = %context.path,
+        user_id = context.user_id(),
+    );
+
+    let mut response = CONTEXT.scope(context, next.run(req)).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(HEADER, value);
+    }
+    response
+}
@@ -0,0 +1,159 @@
+//! Nombre de usuario → userId (`/resolve/:name`), y `/username/:name/passes`,
+//! que resuelve el nombre y sigue igual que `/user/:id/passes`. Así el
+//! cliente no necesita conocer el id del creador.
+//!
+//! Roblox solo expone la búsqueda exacta por POST
+//! (`users.roblox.com/v1/usernames/users`); los usuarios baneados no cuentan.
+//! Cada nombre encontrado se guarda `CACHE_TTL_SECS` en la caché (`store`).
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{OriginalUri, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+    Json,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    error::ApiError,
+    extract::Query,
+    format,
+    roblox::api,
+    routes::PassesQuery,
+    tenant::MaybeTenant,
+    upstream::{self, Endpoint, ReadOnlyPost, Upstream},
+    AppState,
+};
+
+/// Usuario de Roblox encontrado por su nombre.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedUser {
+    ok: bool,
+    /// Tal como lo tiene Roblox (la búsqueda no distingue mayúsculas).
+    username: String,
+    user_id: u64,
+    display_name: String,
+}
+
+/// Los nombres de Roblox: 3 a 20 caracteres `[A-Za-z0-9_]`.
+fn valid_username(name: &str) -> bool {
+    (3..=20).contains(&name.len()) && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+/// Busca el usuario en Roblox; 404 si no existe, 502 si Roblox falla.
+async fn lookup(state: &AppState, name: &str) -> Result<ResolvedUser, ApiError> {
+    if !valid_username(name) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_USERNAME",
+            "El nombre de usuario debe tener entre 3 y 20 caracteres [A-Za-z0-9_]",
+        ));
+    }
+    let upstream_error = || {
+        ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            "UPSTREAM_ERROR",
+            "No se pudo resolver el nombre de usuario en Roblox",
+        )
+    };
+
+    // Roblox no distingue mayúsculas.
+    let cache_key = format!("username:{}", name.to_ascii_lowercase());
+    if let Some(user) = state.store.get_json::<ResolvedUser>(&cache_key).await {
+        info!("username={name} → userId={} (caché)", user.user_id);
+        return Ok(user);
+    }
+
+    info!("Resolviendo username={name}");
+    let url = format!(
+        "{}/v1/usernames/users",
+        state.config.upstream_url(Upstream::Users)
+    );
+    let body = serde_json::json!({ "usernames": [name], "excludeBannedUsers": true });
+    let data = match upstream::post_json(state, ReadOnlyPost::USERNAME_LOOKUP, &url, &body).await {
+        Ok(resp) if resp.status().is_success() => resp
+            .json::<serde_json::Value>()
+            .await
+            .map_err(|_| upstream_error())?,
+        Ok(resp) => {
+            warn!("Users HTTP {} resolviendo username={name}", resp.status());
+            return Err(upstream_error());
+        }
+        Err(e) => {
+            warn!("Error HTTP en users: {e}");
+            return Err(upstream_error());
+        }
+    };
+
+    let user = state
+        .quarantine
+        .deserialize::<api::Page>(Endpoint::UsernameLookup, "listado sin 'data'", &data)
+        .and_then(|page| page.data.into_iter().next())
+        .and_then(|user| {
+            state.quarantine.deserialize::<api::UsernameMatch>(
+                Endpoint::UsernameLookup,
+                "usuario con forma desconocida",
+                &user,
+            )
+        });
+    let Some(user) = user else {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "USERNAME_NOT_FOUND",
+            format!("No existe ningún usuario '{name}'"),
+        ));
+    };
+    let user_id = user.id;
+    let username = user.name.unwrap_or_else(|| name.to_string());
+    info!("username={name} → userId={user_id}");
+    let user = ResolvedUser {
+        ok: true,
+        display_name: user.display_name.unwrap_or_else(|| username.clone()),
+        username,
+        user_id,
+    };
+    state
+        .store
+        .set_json(&cache_key, &user, state.config.cache_ttl)
+        .await;
+    Ok(user)
+}
+
+/// `GET /resolve/:name`
+pub async fn resolve(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<ResolvedUser>, ApiError> {
+    info!("/resolve/{name}");
+    lookup(&state, &name).await.map(Json)
+}
+
+/// `GET /username/:name/passes`: acepta lo mismo que `/user/:id/passes`.
+pub async fn get_passes(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    OriginalUri(uri): OriginalUri,
+    query: Query<PassesQuery>,
+    tenant: MaybeTenant,
+    headers: HeaderMap,
+    format: format::Format,
+) -> Result<Response, ApiError> {
+    info!("/username/{name}/passes");
+    let user = lookup(&state, &name).await?;
+    tracing::Span::current().record("user_id", user.user_id);
+    crate::routes::get_passes(
+        State(state),
+        Path(user.user_id),
+        OriginalUri(uri),
+        query,
+        tenant,
+        headers,
+        format,
+    )
+    .await
+}
@@ -0,0 +1,5 @@
+//! Acceso a Roblox.
+
+pub mod api;
+pub mod catalog;
+pub mod client;
@@ -0,0 +1,279 @@
+//! Forma de las respuestas de Roblox que interpreta el escaneo: listados de
+//! juegos y de passes, detalles de economy, catálogo e inventario, y las de
+//! grupos, developer products y nombres de usuario.
+//!
+//! Los elementos de un listado se deserializan uno a uno con
+//! `Quarantine::deserialize`: el que no encaja (falta el `id`, un precio que
+//! no es entero, una fecha con otro formato) se omite y queda anotado con
+//! el error de serde, en lugar de leerse a medias con valores por defecto.
+//! Los campos opcionales aquí son los que Roblox omite o manda a `null` de
+//! verdad; si uno obligatorio empieza a faltar, se nota en la cuarentena.
+
+use chrono::{DateTime, Utc};
+use serde::{de::Error as _, Deserialize, Deserializer};
+use serde_json::{Number, Value};
+
+/// Una página de un listado. Los elementos se quedan como JSON para
+/// deserializarlos (y, si no encajan, anotarlos) por separado. Los listados
+/// que no se paginan (grupos, nombres) no traen cursor.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page {
+    pub data: Vec<Value>,
+    pub next_page_cursor: Option<String>,
+}
+
+/// Importe en Robux. Un entero que no cabe en `i64` sale como `i64::MAX`,
+/// para que `pricing::checked_price` lo rechace en lugar de confundirlo con
+/// un pass sin precio; un número con decimales no es un importe.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Robux(pub i64);
+
+impl<'de> Deserialize<'de> for Robux {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let number = Number::deserialize(deserializer)?;
+        number
+            .as_i64()
+            .or_else(|| number.as_u64().map(|_| i64::MAX))
+            .map(Robux)
+            .ok_or_else(|| D::Error::custom(format!("importe no entero: {number}")))
+    }
+}
+
+/// Juego de `/v2/users/{id}/games` o `/v2/groups/{id}/gamesV2`, que
+/// comparten formato.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Game {
+    pub id: u64,
+    pub name: Option<String>,
+    pub root_place: Option<PlaceRef>,
+    pub place_visits: Option<u64>,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PlaceRef {
+    pub id: u64,
+}
+
+/// Pass de `/v2/games/{universeId}/game-passes`, sin precio.
+#[derive(Deserialize, Debug)]
+pub struct GamePass {
+    pub id: u64,
+    pub name: Option<String>,
+}
+
+/// `economy.roblox.com/v2/assets/{id}/details` (ver `pricing`).
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct AssetDetails {
+    pub name: Option<String>,
+    pub price_in_robux: Option<Robux>,
+    /// El campo antiguo.
+    pub price: Option<Robux>,
+    pub price_information: Option<PriceInformation>,
+    pub is_for_sale: Option<bool>,
+    pub creator: Option<Creator>,
+}
+
+/// Precios regionales (ver `pricing`).
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceInformation {
+    pub default_price_in_robux: Option<Robux>,
+    pub is_in_active_price_optimization_experiment: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct Creator {
+    pub id: Option<u64>,
+}
+
+/// Artículo de `catalog.roblox.com/v1/search/items/details`. Los bundles
+/// no traen `assetType`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogItem {
+    pub id: u64,
+    pub asset_type: Option<AssetType>,
+    pub name: Option<String>,
+    /// `null` cuando no está a la venta.
+    pub price: Option<Robux>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AssetType {
+    pub id: u64,
+}
+
+/// Elemento de `/v2/users/{id}/inventory/{assetType}`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryItem {
+    pub asset_id: u64,
+    pub name: Option<String>,
+}
+
+/// Elemento de `groups.roblox.com/v1/users/{id}/groups/roles`.
+#[derive(Deserialize, Debug)]
+pub struct GroupMembership {
+    pub group: Group,
+    pub role: GroupRole,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Group {
+    pub id: u64,
+    /// `null` en los grupos sin dueño.
+    pub owner: Option<GroupOwner>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupOwner {
+    pub user_id: u64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GroupRole {
+    pub rank: u64,
+}
+
+/// Una página de `apis.roblox.com/developer-products/v1/developer-products/list`.
+/// Sin `FinalPage`, se da por la última.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeveloperProductsPage {
+    pub developer_products: Vec<Value>,
+    pub final_page: Option<bool>,
+}
+
+/// Elemento de `DeveloperProductsPage`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct DeveloperProduct {
+    pub product_id: u64,
+    pub name: Option<String>,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    /// `null` cuando no está a la venta.
+    pub price_in_robux: Option<Robux>,
+}
+
+/// Elemento de `users.roblox.com/v1/usernames/users`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UsernameMatch {
+    pub id: u64,
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn game_with_optional_fields_missing() {
+        let game: Game = serde_json::from_value(json!({ "id": 101, "name": null })).unwrap();
+        assert_eq!(game.id, 101);
+        assert!(game.name.is_none() && game.root_place.is_none() && game.updated.is_none());
+
+        let game: Game = serde_json::from_value(json!({
+            "id": 102,
+            "name": "Stand",
+            "rootPlace": { "id": 1020, "type": "Place" },
+            "placeVisits": 15000,
+            "updated": "2024-11-02T10:00:00.123Z"
+        }))
+        .unwrap();
+        assert_eq!(game.root_place.map(|p| p.id), Some(1020));
+        assert_eq!(game.place_visits, Some(15000));
+        assert!(game.updated.is_some());
+    }
+
+    #[test]
+    fn drifted_shapes_are_errors_not_defaults() {
+        assert!(serde_json::from_value::<Game>(json!({ "name": "sin id" })).is_err());
+        assert!(serde_json::from_value::<Game>(json!({ "id": "101" })).is_err());
+        assert!(serde_json::from_value::<Game>(json!({ "id": 1, "updated": "ayer" })).is_err());
+        assert!(serde_json::from_value::<GamePass>(json!({ "name": "VIP" })).is_err());
+        assert!(serde_json::from_value::<AssetDetails>(json!({ "PriceInRobux": "10" })).is_err());
+        assert!(serde_json::from_value::<AssetDetails>(json!({ "PriceInRobux": 9.5 })).is_err());
+    }
+
+    #[test]
+    fn robux_saturates_instead_of_failing() {
+        let details: AssetDetails =
+            serde_json::from_value(json!({ "PriceInRobux": u64::MAX, "Price": -3 })).unwrap();
+        assert_eq!(details.price_in_robux, Some(Robux(i64::MAX)));
+        assert_eq!(details.price, Some(Robux(-3)));
+    }
+
+    #[test]
+    fn asset_details_with_regional_prices() {
+        let details: AssetDetails = serde_json::from_value(json!({
+            "Name": "Donate",
+            "PriceInRobux": 8,
+            "PriceInformation": {
+                "defaultPriceInRobux": 10,
+                "isInActivePriceOptimizationExperiment": true
+            },
+            "IsForSale": true,
+            "Creator": { "Id": 1, "CreatorType": "User" }
+        }))
+        .unwrap();
+        let info = details.price_information.unwrap();
+        assert_eq!(info.default_price_in_robux, Some(Robux(10)));
+        assert_eq!(info.is_in_active_price_optimization_experiment, Some(true));
+        assert_eq!(details.creator.and_then(|c| c.id), Some(1));
+    }
+
+    #[test]
+    fn catalog_bundles_have_no_asset_type() {
+        let item: CatalogItem = serde_json::from_value(json!({
+            "id": 7, "itemType": "Bundle", "bundleType": 1, "name": "Pack", "price": null
+        }))
+        .unwrap();
+        assert!(item.asset_type.is_none() && item.price.is_none());
+    }
+
+    #[test]
+    fn group_membership_owner_is_optional_but_ids_are_not() {
+        let m: GroupMembership = serde_json::from_value(json!({
+            "group": { "id": 5, "name": "Estudio", "owner": null },
+            "role": { "id": 50, "name": "Owner", "rank": 255 }
+        }))
+        .unwrap();
+        assert!(m.group.owner.is_none());
+        assert_eq!(m.role.rank, 255);
+        assert!(serde_json::from_value::<GroupMembership>(json!({
+            "group": { "owner": { "userId": 1 } },
+            "role": { "rank": 255 }
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn developer_product_without_price() {
+        let p: DeveloperProduct = serde_json::from_value(json!({
+            "ProductId": 9, "Name": "Coins", "displayName": "", "PriceInRobux": null
+        }))
+        .unwrap();
+        assert_eq!(p.product_id, 9);
+        assert!(p.price_in_robux.is_none());
+        assert!(serde_json::from_value::<DeveloperProduct>(json!({ "Name": "x" })).is_err());
+    }
+
+    #[test]
+    fn listing_page_needs_data() {
+        let page: Page =
+            serde_json::from_value(json!({ "data": [], "nextPageCursor": null })).unwrap();
+        assert!(page.data.is_empty() && page.next_page_cursor.is_none());
+        assert!(serde_json::from_value::<Page>(json!({ "errors": [] })).is_err());
+    }
+}
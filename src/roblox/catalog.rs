@@ -0,0 +1,90 @@
+//! Artículos de un creador en el catálogo
+//! (`catalog.roblox.com/v1/search/items/details`), filtrados por
+//! `assetType`. Lo usan el fallback de passes (con
+//! `CATALOG_FALLBACK_ASSET_TYPES`) y la ropa (`clothing`), que solo cambian
+//! en qué tipos aceptan y qué hacen con cada artículo.
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::{
+    roblox::{
+        api,
+        client::{self, PageError},
+    },
+    upstream::{Endpoint, Upstream},
+    AppState,
+};
+
+/// `assetType` de los game passes.
+pub const GAME_PASS: u64 = 46;
+
+/// Un artículo del catálogo de uno de los tipos pedidos.
+pub struct CatalogItem {
+    pub id: u64,
+    pub asset_type: u64,
+    pub name: Option<String>,
+    /// `None` cuando no está a la venta (`price: null`).
+    pub price: Option<i64>,
+    /// El artículo tal cual, para la cuarentena.
+    pub raw: Value,
+}
+
+/// Lo recogido por `search`.
+pub struct CatalogItems {
+    /// Sin repetir, en el orden del catálogo (actualizados primero).
+    pub items: Vec<CatalogItem>,
+    /// Página que falló; `items` trae lo recogido hasta ella.
+    pub error: Option<PageError>,
+}
+
+/// Artículos de `user_id` con un `assetType` de `asset_types`, hasta
+/// `max_pages` páginas de 30.
+pub async fn search(
+    state: &AppState,
+    user_id: u64,
+    asset_types: &[u64],
+    max_pages: usize,
+) -> CatalogItems {
+    let url = format!(
+        "{}/v1/search/items/details?creatorTargetId={user_id}&creatorType=User&itemType=Asset&includeNotForSale=true&limit=30&sortType=Updated",
+        state.config.upstream_url(Upstream::Catalog)
+    );
+    let what = format!("catálogo de userId={user_id} (assetType {asset_types:?})");
+    let listing =
+        client::fetch_listing(state, Endpoint::CatalogSearch, &url, &what, max_pages).await;
+
+    let mut seen_ids: HashSet<u64> = HashSet::new();
+    let mut items = Vec::new();
+    for raw in listing.items {
+        let Some(item) = state.quarantine.deserialize::<api::CatalogItem>(
+            Endpoint::CatalogSearch,
+            "artículo con forma desconocida",
+            &raw,
+        ) else {
+            continue;
+        };
+        let Some(asset_type) = item
+            .asset_type
+            .map(|t| t.id)
+            .filter(|t| asset_types.contains(t))
+        else {
+            continue;
+        };
+        if !seen_ids.insert(item.id) {
+            continue;
+        }
+        items.push(CatalogItem {
+            id: item.id,
+            asset_type,
+            name: item.name,
+            price: item.price.map(|api::Robux(price)| price),
+            raw,
+        });
+    }
+    CatalogItems {
+        items,
+        error: listing.error,
+    }
+}
@@ -0,0 +1,994 @@
+//! Escaneo de los passes de un usuario en las fuentes de Roblox: sus juegos
+//! públicos, el catálogo y el inventario.
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::response::Response;
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::{
+    cache::{self, CacheStatus},
+    games, groups, guidance,
+    models::{FetchOptions, Gamepass, PublicGame},
+    pricing,
+    roblox::api,
+    snapshots,
+    tenant::ApiKey,
+    upstream::{self, Endpoint, Upstream},
+    views, AppState,
+};
+#[cfg(feature = "catalog")]
+use crate::{roblox::catalog, usage};
+
+/// Fallo al pedir una página de un listado de Roblox.
+pub struct PageError {
+    /// 0 para la primera página.
+    pub page: usize,
+    /// Estado HTTP, si Roblox respondió con un error.
+    pub status: Option<reqwest::StatusCode>,
+}
+
+impl PageError {
+    pub fn record(&self, stats: &guidance::ScanStats) {
+        match self.status {
+            Some(status) => stats.upstream_status(status),
+            None => stats.upstream_error(),
+        }
+    }
+}
+
+/// `fut` si termina antes del plazo del escaneo (`opts.deadline`); si no,
+/// `None` y el escaneo queda marcado como parcial.
+async fn before_deadline<F: Future>(opts: &FetchOptions, fut: F) -> Option<F::Output> {
+    let Some(deadline) = opts.deadline else {
+        return Some(fut.await);
+    };
+    match tokio::time::timeout_at(deadline, fut).await {
+        Ok(output) => Some(output),
+        Err(_) => {
+            opts.stats.deadline_exceeded();
+            None
+        }
+    }
+}
+
+/// Se completa en `deadline`; sin plazo, nunca. Corta los `stream` de
+/// `passes_from_games` con `take_until`.
+async fn until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Lo recogido de un listado por `fetch_listing`.
+pub struct Listing {
+    pub items: Vec<serde_json::Value>,
+    /// Página que falló; `items` trae lo recogido hasta ella.
+    pub error: Option<PageError>,
+    /// Se paró en `max_pages` con más páginas por pedir.
+    pub truncated: bool,
+}
+
+/// Elementos `data` de un listado de Roblox, siguiendo `nextPageCursor`
+/// hasta `max_pages` páginas. La primera página es `url` tal cual; las
+/// siguientes añaden `&cursor=`. Si una página falla se devuelve lo
+/// recogido hasta ahí junto con el error.
+pub async fn fetch_listing(
+    state: &AppState,
+    endpoint: Endpoint,
+    url: &str,
+    what: &str,
+    max_pages: usize,
+) -> Listing {
+    let mut items = Vec::new();
+    let mut page_url = url.to_string();
+    let done = |items, error| Listing {
+        items,
+        error,
+        truncated: false,
+    };
+    for page in 0..max_pages {
+        debug!("Pidiendo {what} en {page_url}");
+        let error = |status| Some(PageError { page, status });
+
+        let resp = match upstream::get(state, endpoint, &page_url).await {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Error HTTP al pedir {what}: {e}");
+                return done(items, error(None));
+            }
+        };
+        if !resp.status().is_success() {
+            warn!("HTTP {} al pedir {what}", resp.status());
+            let error = error(Some(resp.status()));
+            return done(items, error);
+        }
+        let json: serde_json::Value = match resp.json().await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Error parseando JSON de {what}: {e}");
+                return done(items, error(None));
+            }
+        };
+        let Some(api::Page {
+            data,
+            next_page_cursor,
+        }) = state
+            .quarantine
+            .deserialize(endpoint, "listado sin 'data'", &json)
+        else {
+            info!("Sin 'data' al pedir {what}");
+            return done(items, error(None));
+        };
+        items.extend(data);
+
+        let Some(cursor) = next_page_cursor.filter(|c| !c.is_empty()) else {
+            return done(items, None);
+        };
+        let Ok(mut next) = reqwest::Url::parse(url) else {
+            return done(items, None);
+        };
+        next.query_pairs_mut().append_pair("cursor", &cursor);
+        page_url = next.into();
+    }
+    info!("{what}: alcanzado el máximo de {max_pages} páginas, se omite el resto");
+    Listing {
+        items,
+        error: None,
+        truncated: true,
+    }
+}
+
+/// Juegos públicos de un usuario (`/v2/users/{userId}/games`), en el orden
+/// de Roblox. `None` (anotado en `stats`) si la primera página falla o el
+/// usuario no existe; si falla una de las siguientes, los ya recogidos.
+pub async fn fetch_public_games(
+    state: &AppState,
+    user_id: u64,
+    stats: &guidance::ScanStats,
+) -> Option<Vec<PublicGame>> {
+    let games_url = format!(
+        "{}/v2/users/{}/games?accessFilter=2&limit=50&sortOrder=Asc",
+        state.config.upstream_url(Upstream::Games),
+        user_id
+    );
+    let what = format!("juegos públicos para userId={user_id}");
+    let max_pages = state.config.upstream_max_pages;
+    let Listing {
+        items: games_arr,
+        error,
+        ..
+    } = fetch_listing(state, Endpoint::UserGames, &games_url, &what, max_pages).await;
+    if let Some(error) = error {
+        // Roblox responde 400 ("The user id is invalid") a un userId que no existe.
+        let missing = matches!(
+            error.status,
+            Some(reqwest::StatusCode::BAD_REQUEST | reqwest::StatusCode::NOT_FOUND)
+        );
+        if error.page == 0 && missing {
+            info!("userId={user_id} no existe");
+            stats.user_not_found();
+            return None;
+        }
+        error.record(stats);
+        if error.page == 0 {
+            return None;
+        }
+    }
+
+    let games: Vec<PublicGame> = state
+        .quarantine
+        .deserialize_all::<api::Game>(
+            Endpoint::UserGames,
+            "juego con forma desconocida",
+            &games_arr,
+        )
+        .into_iter()
+        .map(PublicGame::from)
+        .collect();
+
+    info!(
+        "Juegos públicos encontrados para {}: {} (universeIds)",
+        user_id,
+        games.len()
+    );
+    Some(games)
+}
+
+/// Los más visitados primero; a igualdad, los actualizados más recientemente.
+pub fn sort_by_popularity(games: &mut [PublicGame]) {
+    games.sort_by(|a, b| {
+        b.visits
+            .cmp(&a.visits)
+            .then_with(|| b.updated.cmp(&a.updated))
+            .then_with(|| a.universe_id.cmp(&b.universe_id))
+    });
+}
+
+/// Detalles de economy de un asset, con el JSON tal cual para la cuarentena.
+pub struct Details {
+    pub asset: api::AssetDetails,
+    pub raw: serde_json::Value,
+}
+
+/// `economy.roblox.com/v2/assets/{id}/details`. `None` (anotado en `stats`)
+/// si Roblox falla o responde algo que no encaja con `api::AssetDetails`.
+/// Los detalles válidos se reutilizan `PRICE_CACHE_TTL_SECS` (ver `store`).
+pub async fn fetch_asset_details(
+    state: &AppState,
+    id: u64,
+    stats: &guidance::ScanStats,
+) -> Option<Details> {
+    match cached_asset_details(state, id).await {
+        Some(details) => Some(details),
+        None => lookup_asset_details(state, id, stats).await,
+    }
+}
+
+/// Los detalles de `id` si siguen en la caché de precios, sin ir a Roblox.
+async fn cached_asset_details(state: &AppState, id: u64) -> Option<Details> {
+    if state.config.price_cache_ttl.is_zero() {
+        return None;
+    }
+    let raw = state
+        .store
+        .get_json::<serde_json::Value>(&format!("asset:{id}"))
+        .await?;
+    let asset = serde_json::from_value(raw.clone()).ok()?;
+    Some(Details { asset, raw })
+}
+
+/// Pide los detalles de `id` a Roblox y los guarda en la caché de precios.
+async fn lookup_asset_details(
+    state: &AppState,
+    id: u64,
+    stats: &guidance::ScanStats,
+) -> Option<Details> {
+    let url = format!(
+        "{}/v2/assets/{}/details",
+        state.config.upstream_url(Upstream::Economy),
+        id
+    );
+    let raw = match upstream::get(state, Endpoint::AssetDetails, &url).await {
+        Ok(resp) if resp.status().is_success() => resp.json::<serde_json::Value>().await.ok(),
+        Ok(resp) => {
+            stats.upstream_status(resp.status());
+            return None;
+        }
+        Err(_) => None,
+    };
+    let Some(raw) = raw else {
+        stats.upstream_error();
+        return None;
+    };
+    if !pricing::has_price_fields(&raw) {
+        state.quarantine.record(
+            Endpoint::AssetDetails,
+            "detalles sin campos de precio",
+            &raw,
+        );
+    }
+    let Some(asset) = state.quarantine.deserialize(
+        Endpoint::AssetDetails,
+        "detalles con forma desconocida",
+        &raw,
+    ) else {
+        stats.upstream_error();
+        return None;
+    };
+    state
+        .store
+        .set_json(&format!("asset:{id}"), &raw, state.config.price_cache_ttl)
+        .await;
+    Some(Details { asset, raw })
+}
+
+/// Precio de venta según los detalles de economy (ver `pricing`) junto con
+/// su origen, o `None` (anotado en `stats`) si el pass no está a la venta,
+/// vale 0 (salvo con `includeFree`) o trae un precio imposible.
+pub fn sale_price(
+    state: &AppState,
+    details: &Details,
+    opts: &FetchOptions,
+) -> Option<(i64, pricing::ListedPrice)> {
+    let stats = &opts.stats;
+    // Sin precio o con `IsForSale: false`, el pass no se puede comprar.
+    let for_sale = details.asset.is_for_sale.unwrap_or(true);
+    let listed = pricing::listed_price(&details.asset, &state.config.price_sources);
+    let price = match listed.price {
+        Some(price) if for_sale => price,
+        _ => {
+            stats.off_sale();
+            return None;
+        }
+    };
+    match pricing::checked_price(price) {
+        Ok(price) => Some((price, listed)),
+        Err(pricing::InvalidPrice::NotPositive) if price == 0 && opts.include_free => {
+            Some((0, listed))
+        }
+        Err(pricing::InvalidPrice::NotPositive) => {
+            stats.zero_price();
+            None
+        }
+        Err(pricing::InvalidPrice::OutOfRange(price)) => {
+            warn!("Precio fuera de rango ({price}), se omite el pass");
+            stats.invalid_price();
+            state.quarantine.record(
+                Endpoint::AssetDetails,
+                "precio fuera de rango",
+                &details.raw,
+            );
+            None
+        }
+    }
+}
+
+/// Passes de un juego (`/v2/games/{universeId}/game-passes`), sin precio,
+/// hasta `GAME_PASSES_MAX_PAGES` páginas de 100 (anotado en `stats` si
+/// quedan más).
+/// `None` (anotado en `stats`) si la primera página falla; si falla una de
+/// las siguientes, los ya recogidos.
+pub async fn fetch_game_passes(
+    state: &AppState,
+    universe_id: u64,
+    stats: &guidance::ScanStats,
+) -> Option<Vec<api::GamePass>> {
+    let gp_url = format!(
+        "{}/v2/games/{}/game-passes?limit=100&sortOrder=Asc",
+        state.config.upstream_url(Upstream::Games),
+        universe_id
+    );
+    let what = format!("game-passes del juego (universeId={universe_id})");
+    let max_pages = state.config.game_passes_max_pages;
+    let listing = fetch_listing(state, Endpoint::GamePasses, &gp_url, &what, max_pages).await;
+    if listing.truncated {
+        stats.passes_truncated(universe_id);
+    }
+    let passes = state.quarantine.deserialize_all(
+        Endpoint::GamePasses,
+        "pass con forma desconocida",
+        &listing.items,
+    );
+    match listing.error {
+        Some(error) => {
+            error.record(stats);
+            (error.page > 0).then_some(passes)
+        }
+        None => Some(passes),
+    }
+}
+
+/// Intenta obtener gamepasses a partir de los **juegos públicos** del usuario.
+/// 1) /v2/users/{userId}/games  → juegos públicos
+/// 2) /v2/games/{universeId}/game-passes → passes del juego
+/// 3) /v2/assets/{id}/details → precio
+///
+/// Con `includeGroups`, el paso 1 añade los juegos de los grupos del usuario
+/// (ver `groups`).
+pub async fn fetch_passes_from_public_games(
+    state: &AppState,
+    user_id: u64,
+    opts: &FetchOptions,
+) -> Vec<Gamepass> {
+    // 1) Juegos públicos del usuario
+    let public_games = fetch_public_games(state, user_id, &opts.stats);
+    let Some(Some(mut games)) = before_deadline(opts, public_games).await else {
+        return Vec::new();
+    };
+    if opts.include_groups {
+        let group_games = groups::owned_group_games(state, user_id, &opts.stats);
+        games.extend(before_deadline(opts, group_games).await.unwrap_or_default());
+    }
+    passes_from_games(state, &format!("userId={user_id}"), games, opts).await
+}
+
+/// Pasos 2 y 3 de `fetch_passes_from_public_games` sobre los juegos de
+/// `owner` (solo para los logs). Se lanzan hasta `SCAN_CONCURRENCY` llamadas
+/// a la vez; el resultado sale en el mismo orden que si fueran en serie.
+pub async fn passes_from_games(
+    state: &AppState,
+    owner: &str,
+    mut games: Vec<PublicGame>,
+    opts: &FetchOptions,
+) -> Vec<Gamepass> {
+    let mut result: Vec<Gamepass> = Vec::new();
+    let mut seen_ids: HashSet<u64> = HashSet::new();
+    opts.stats.add_public_games(games.len());
+
+    if opts.active_games_only {
+        let cutoff = Utc::now() - chrono::Duration::days(state.config.active_game_days);
+        let before = games.len();
+        // Sin fecha de actualización no se puede juzgar: se conserva.
+        games.retain(|g| {
+            let active = g.updated.is_none_or(|updated| updated >= cutoff);
+            if !active {
+                opts.stats
+                    .skip_game(g.universe_id, guidance::SkipReason::Inactive);
+            }
+            active
+        });
+        info!(
+            "activeGamesOnly: {} de {} juegos sin actualizar en {} días, omitidos",
+            before - games.len(),
+            before,
+            state.config.active_game_days
+        );
+    }
+
+    // Los más populares primero, para que el tope de universos no deje fuera
+    // el juego de donaciones principal del creador.
+    sort_by_popularity(&mut games);
+    let max_universes = state.config.max_universes;
+    if max_universes > 0 && games.len() > max_universes {
+        info!(
+            "Escaneando solo los {} juegos más populares de {} para {}",
+            max_universes,
+            games.len(),
+            owner
+        );
+        for game in games.drain(max_universes..) {
+            opts.stats
+                .skip_game(game.universe_id, guidance::SkipReason::MaxUniverses);
+        }
+    }
+    let universe_ids: Vec<u64> = games.iter().map(|g| g.universe_id).collect();
+    let game_names: HashMap<u64, String> = games
+        .iter()
+        .filter_map(|g| Some((g.universe_id, g.name.clone()?)))
+        .collect();
+
+    // Metadatos de todos los juegos escaneados en una sola llamada (lotes de 50)
+    let game_details = if opts.game_details {
+        let details = games::fetch_game_details(state, &universe_ids, None);
+        before_deadline(opts, details).await.unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+
+    // 2) Gamepasses de cada juego, varios juegos a la vez. Con plazo, se
+    // esperan solo tres cuartos de lo que queda, para que los passes ya
+    // listados tengan tiempo de recibir su precio.
+    let concurrency = state.config.scan_concurrency;
+    let listing_deadline = opts.deadline.map(|deadline| {
+        let now = Instant::now();
+        now + deadline.saturating_duration_since(now) * 3 / 4
+    });
+    let mut lists: Vec<(usize, u64, Option<Vec<api::GamePass>>)> =
+        stream::iter(universe_ids.iter().copied().enumerate())
+            .map(|(i, universe_id)| async move {
+                let passes = fetch_game_passes(state, universe_id, &opts.stats).await;
+                (i, universe_id, passes)
+            })
+            .buffer_unordered(concurrency)
+            .take_until(until(listing_deadline))
+            .collect()
+            .await;
+    if lists.len() < universe_ids.len() {
+        opts.stats.deadline_exceeded();
+        let listed: HashSet<u64> = lists.iter().map(|(_, id, _)| *id).collect();
+        for universe_id in universe_ids.iter().filter(|id| !listed.contains(id)) {
+            opts.stats
+                .skip_game(*universe_id, guidance::SkipReason::Deadline);
+        }
+    }
+    // Se recorren en el orden de popularidad para que los duplicados y el
+    // tope por juego no dependan de qué respuesta llegó antes.
+    lists.sort_by_key(|(i, _, _)| *i);
+
+    let mut candidates: Vec<(u64, String, u64)> = Vec::new();
+    for (_, universe_id, passes) in lists {
+        let Some(passes) = passes else {
+            opts.stats
+                .skip_game(universe_id, guidance::SkipReason::UpstreamError);
+            continue;
+        };
+
+        let listed = passes.len();
+        let mut considered = 0usize;
+        for (i, pass) in passes.into_iter().enumerate() {
+            let id = pass.id;
+            let name = pass.name.unwrap_or_else(|| "GamePass".to_string());
+
+            // Evitar duplicados
+            if !seen_ids.insert(id) {
+                continue;
+            }
+
+            // Tope por juego antes de pedir precios: ahorra llamadas a economy
+            if opts
+                .max_passes_per_game
+                .is_some_and(|max| considered >= max)
+            {
+                info!(
+                    "Tope de {} passes alcanzado en universeId={}, se omiten el resto",
+                    considered, universe_id
+                );
+                opts.stats.over_game_cap(listed - i);
+                break;
+            }
+            considered += 1;
+            opts.stats.pass_found();
+            candidates.push((id, name, universe_id));
+        }
+    }
+
+    // 3) Precio de cada pass desde economy.roblox.com, también en paralelo
+    let candidates_len = candidates.len();
+    let mut priced: Vec<(usize, Option<Gamepass>)> =
+        stream::iter(candidates.into_iter().enumerate())
+            .map(|(i, (id, name, universe_id))| {
+                let game = game_details.get(&universe_id).cloned();
+                let game_name = game_names.get(&universe_id).cloned();
+                async move {
+                    let Some(details) = fetch_asset_details(state, id, &opts.stats).await else {
+                        return (i, None);
+                    };
+                    let Some((price, listed)) = sale_price(state, &details, opts) else {
+                        return (i, None);
+                    };
+                    debug!(
+                        "GamePass desde juegos públicos → id={}, name='{}', price={}",
+                        id, name, price
+                    );
+                    let pass = Gamepass {
+                        id,
+                        name,
+                        price,
+                        original_price: price,
+                        price_changed: false,
+                        price_details: listed.details,
+                        price_source: listed.source,
+                        display_name: String::new(),
+                        duplicate_of: None,
+                        icon_url: None,
+                        links: None,
+                        source: views::PassSource::Games,
+                        universe_id: Some(universe_id),
+                        game,
+                        game_name,
+                    };
+                    (i, Some(pass))
+                }
+            })
+            .buffer_unordered(concurrency)
+            .take_until(until(opts.deadline))
+            .collect()
+            .await;
+    if priced.len() < candidates_len {
+        info!(
+            "Plazo del escaneo vencido: {} de {} passes sin precio para {}",
+            candidates_len - priced.len(),
+            candidates_len,
+            owner
+        );
+        opts.stats.deadline_exceeded();
+    }
+    priced.sort_by_key(|(i, _)| *i);
+    result.extend(priced.into_iter().filter_map(|(_, pass)| pass));
+
+    info!(
+        "Total gamepasses (por juegos públicos) con precio > 0 para {}: {}",
+        owner,
+        result.len()
+    );
+
+    result
+}
+
+/// Fallback: artículos del usuario en el catálogo de los tipos de
+/// `CATALOG_FALLBACK_ASSET_TYPES` (por defecto solo game passes, assetType
+/// 46), una página.
+#[cfg(feature = "catalog")]
+pub async fn fetch_passes_from_catalog(
+    state: &AppState,
+    user_id: u64,
+    opts: &FetchOptions,
+) -> Vec<Gamepass> {
+    let stats = &opts.stats;
+    let asset_types = &state.config.catalog_fallback_asset_types;
+    let catalog::CatalogItems { items, error } =
+        catalog::search(state, user_id, asset_types, 1).await;
+    if let Some(error) = error {
+        error.record(stats);
+        return Vec::new();
+    }
+
+    info!(
+        "Items de catálogo recibidos para {}: {}",
+        user_id,
+        items.len()
+    );
+
+    let mut result: Vec<Gamepass> = Vec::new();
+    for item in items {
+        let id = item.id;
+        let name = item.name.unwrap_or_else(|| "GamePass".to_string());
+
+        stats.pass_found();
+        let Some(price) = item.price else {
+            stats.off_sale();
+            continue;
+        };
+        let price = match pricing::checked_price(price) {
+            Ok(price) => price,
+            Err(pricing::InvalidPrice::NotPositive) if price == 0 && opts.include_free => 0,
+            Err(pricing::InvalidPrice::NotPositive) => {
+                stats.zero_price();
+                continue;
+            }
+            Err(pricing::InvalidPrice::OutOfRange(price)) => {
+                warn!("Precio fuera de rango ({price}) en el catálogo, se omite el pass {id}");
+                stats.invalid_price();
+                state.quarantine.record(
+                    Endpoint::CatalogSearch,
+                    "precio fuera de rango",
+                    &item.raw,
+                );
+                continue;
+            }
+        };
+
+        debug!(
+            "GamePass desde catálogo → id={}, name='{}', price={}",
+            id, name, price
+        );
+
+        result.push(Gamepass {
+            id,
+            name,
+            price,
+            original_price: price,
+            price_changed: false,
+            price_details: None,
+            price_source: None,
+            display_name: String::new(),
+            duplicate_of: None,
+            icon_url: None,
+            links: None,
+            source: views::PassSource::Catalog,
+            universe_id: None,
+            game: None,
+            game_name: None,
+        });
+    }
+
+    info!(
+        "Total gamepasses (catálogo fallback) con precio > 0 para {}: {}",
+        user_id,
+        result.len()
+    );
+
+    result
+}
+
+/// Último recurso: el inventario público del usuario (assetType 34 =
+/// GamePass). Encuentra passes de juegos privados, que no aparecen ni en sus
+/// juegos públicos ni en el catálogo. Solo cuentan los que creó el propio
+/// usuario (`Creator.Id` en los detalles), no los que compró.
+pub async fn fetch_passes_from_inventory(
+    state: &AppState,
+    user_id: u64,
+    opts: &FetchOptions,
+) -> Vec<Gamepass> {
+    let stats = &opts.stats;
+    let mut result: Vec<Gamepass> = Vec::new();
+
+    let url = format!(
+        "{}/v2/users/{}/inventory/34?limit=100&sortOrder=Asc",
+        state.config.upstream_url(Upstream::Inventory),
+        user_id
+    );
+    debug!(
+        "Pidiendo inventario (fallback) para userId={} en {}",
+        user_id, url
+    );
+
+    let resp = match upstream::get(state, Endpoint::UserInventory, &url).await {
+        Ok(r) => r,
+        Err(e) => {
+            warn!("Error HTTP en inventario: {e}");
+            stats.upstream_error();
+            return result;
+        }
+    };
+
+    // Inventario privado: no es un fallo, simplemente no se puede mirar.
+    if resp.status() == reqwest::StatusCode::FORBIDDEN {
+        info!("Inventario privado para userId={}", user_id);
+        return result;
+    }
+    if !resp.status().is_success() {
+        warn!("Inventario HTTP {} para userId={}", resp.status(), user_id);
+        stats.upstream_status(resp.status());
+        return result;
+    }
+
+    let data: serde_json::Value = match resp.json().await {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("Error parseando JSON de inventario: {e}");
+            stats.upstream_error();
+            return result;
+        }
+    };
+
+    let Some(api::Page { data: items, .. }) =
+        state
+            .quarantine
+            .deserialize(Endpoint::UserInventory, "listado sin 'data'", &data)
+    else {
+        info!("Inventario: sin 'data' para userId={}", user_id);
+        return result;
+    };
+    let items: Vec<api::InventoryItem> = state.quarantine.deserialize_all(
+        Endpoint::UserInventory,
+        "elemento con forma desconocida",
+        &items,
+    );
+
+    // Cada elemento cuesta una consulta de detalles: solo las que ya están
+    // en la caché de precios salen gratis, las demás se cortan en
+    // `INVENTORY_MAX_LOOKUPS`.
+    let mut seen_ids: HashSet<u64> = HashSet::new();
+    let mut lookups = 0;
+    let mut unchecked = 0;
+    for item in items {
+        let id = item.asset_id;
+        if !seen_ids.insert(id) {
+            continue;
+        }
+
+        let details = match cached_asset_details(state, id).await {
+            Some(details) => Some(details),
+            None if lookups < state.config.inventory_max_lookups => {
+                lookups += 1;
+                lookup_asset_details(state, id, stats).await
+            }
+            None => {
+                unchecked += 1;
+                continue;
+            }
+        };
+        let Some(details) = details else {
+            continue;
+        };
+        let creator = details.asset.creator.as_ref().and_then(|c| c.id);
+        if creator != Some(user_id) {
+            continue;
+        }
+        stats.pass_found();
+        let Some((price, listed)) = sale_price(state, &details, opts) else {
+            continue;
+        };
+
+        let name = details
+            .asset
+            .name
+            .or(item.name)
+            .unwrap_or_else(|| "GamePass".to_string());
+        debug!(
+            "GamePass desde inventario → id={}, name='{}', price={}",
+            id, name, price
+        );
+
+        result.push(Gamepass {
+            id,
+            name,
+            price,
+            original_price: price,
+            price_changed: false,
+            price_details: listed.details,
+            price_source: listed.source,
+            display_name: String::new(),
+            duplicate_of: None,
+            icon_url: None,
+            links: None,
+            source: views::PassSource::Inventory,
+            universe_id: None,
+            game: None,
+            game_name: None,
+        });
+    }
+
+    if unchecked > 0 {
+        info!(
+            "Inventario de userId={user_id}: {unchecked} elementos sin mirar por INVENTORY_MAX_LOOKUPS"
+        );
+        stats.inventory_unchecked(unchecked);
+    }
+    info!(
+        "Total gamepasses (inventario) con precio > 0 para {}: {}",
+        user_id,
+        result.len()
+    );
+
+    result
+}
+
+/// Inventario como último recurso, si `INVENTORY_FALLBACK` lo permite.
+pub async fn fetch_passes_from_inventory_fallback(
+    state: &AppState,
+    user_id: u64,
+    opts: &FetchOptions,
+) -> Vec<Gamepass> {
+    if !state.config.inventory_fallback {
+        return Vec::new();
+    }
+    info!("Sin gamepasses en juegos ni catálogo, probando inventario…");
+    fetch_passes_from_inventory(state, user_id, opts).await
+}
+
+/// Modo por defecto: juegos públicos; si no dan nada, catálogo, y si
+/// tampoco, inventario.
+pub async fn fetch_passes_sequential(
+    state: &AppState,
+    user_id: u64,
+    opts: &FetchOptions,
+) -> Vec<Gamepass> {
+    // 1) Primero intentamos por **juegos públicos**
+    let passes = fetch_passes_from_public_games(state, user_id, opts).await;
+    // Un usuario que no existe tampoco tiene catálogo ni inventario, y con
+    // el plazo vencido no queda tiempo para buscar en ellos.
+    if !passes.is_empty() || opts.stats.is_user_missing() || opts.stats.is_partial() {
+        return passes;
+    }
+
+    // 2) Si no encontramos nada, usamos el catálogo como respaldo
+    #[cfg(feature = "catalog")]
+    let passes = {
+        info!("Sin gamepasses por juegos públicos, usando catálogo fallback…");
+        fetch_passes_from_catalog(state, user_id, opts).await
+    };
+    if !passes.is_empty() {
+        return passes;
+    }
+
+    // 3) Passes de juegos privados, solo visibles en el inventario
+    fetch_passes_from_inventory_fallback(state, user_id, opts).await
+}
+
+/// Lista completa (sin filtros) recién escaneada de un usuario, guardando su
+/// foto y refrescando la caché. La usan las vistas derivadas (diff,
+/// sugerencias) y el watcher. Sin foto (`None`) si el escaneo no fue
+/// completo o el usuario no está vigilado.
+pub async fn fetch_full_list(
+    state: &AppState,
+    user_id: u64,
+) -> (Vec<Gamepass>, Option<Arc<snapshots::Snapshot>>) {
+    let opts = FetchOptions::default();
+    let passes = fetch_passes_sequential(state, user_id, &opts).await;
+    let snapshot = snapshots::record_scan(state, user_id, &passes, &opts.stats);
+    state
+        .cache
+        .insert(
+            cache::CacheKey::new(user_id, &opts),
+            passes.clone(),
+            opts.stats,
+        )
+        .await;
+    (passes, snapshot)
+}
+
+/// `fetch_full_list` a cuenta del presupuesto de escaneos frescos de
+/// `tenant` (ver `budget`). Sin presupuesto, la lista guardada aunque sea
+/// vieja (`CacheOnly`), con una foto sin guardar si estaba completa; sin
+/// nada guardado, `Err` con el 429.
+pub async fn budgeted_full_list(
+    state: &AppState,
+    user_id: u64,
+    tenant: Option<&ApiKey>,
+) -> Result<(Vec<Gamepass>, Option<Arc<snapshots::Snapshot>>, CacheStatus), Response> {
+    let budget = state.fresh_budget.for_tenant(tenant, &state.config, true);
+    match budget {
+        Some(budget) if !budget.granted => {
+            let tenant_id = tenant.map_or("", |t| t.id.as_str());
+            let key = cache::CacheKey::new(user_id, &FetchOptions::default());
+            let Some(hit) = state.cache.get_stale(&key).await else {
+                return Err(budget.exhausted(tenant_id, &format!("userId {user_id}")));
+            };
+            info!(
+                "Presupuesto agotado para '{tenant_id}': userId={user_id} servido solo de caché ({}s)",
+                hit.age.as_secs()
+            );
+            let snapshot = hit
+                .stats
+                .is_complete()
+                .then(|| Arc::new(snapshots::Snapshot::new(&hit.passes)));
+            Ok((
+                hit.passes,
+                snapshot,
+                CacheStatus::CacheOnly { age: hit.age },
+            ))
+        }
+        _ => {
+            let (passes, snapshot) = fetch_full_list(state, user_id).await;
+            Ok((passes, snapshot, CacheStatus::Miss))
+        }
+    }
+}
+
+/// Lista completa de un usuario desde la caché si no es más vieja que
+/// `max_age`; si no, se escanea con `budgeted_full_list`.
+pub async fn cached_full_list(
+    state: &AppState,
+    user_id: u64,
+    max_age: Duration,
+    tenant: Option<&ApiKey>,
+) -> Result<(Vec<Gamepass>, CacheStatus), Response> {
+    let key = cache::CacheKey::new(user_id, &FetchOptions::default());
+    if let Some(hit) = state.cache.get(&key, max_age).await {
+        return Ok((hit.passes, CacheStatus::Hit { age: hit.age }));
+    }
+    let (passes, _, status) = budgeted_full_list(state, user_id, tenant).await?;
+    Ok((passes, status))
+}
+
+/// Modo latencia: lanza juegos públicos y catálogo a la vez y devuelve el
+/// primero que traiga passes. La otra búsqueda sigue en segundo plano hasta
+/// terminar (sus llamadas ya están en vuelo).
+#[cfg(feature = "catalog")]
+pub async fn fetch_passes_racing(
+    state: Arc<AppState>,
+    user_id: u64,
+    opts: FetchOptions,
+) -> Vec<Gamepass> {
+    let stats = opts.stats.clone();
+    let others = opts.clone();
+    let key = cache::CacheKey::new(user_id, &opts);
+    let mut games = tokio::spawn({
+        let state = state.clone();
+        usage::propagate(
+            async move { fetch_passes_from_public_games(&state, user_id, &opts).await },
+        )
+    });
+    let mut catalog = tokio::spawn({
+        let state = state.clone();
+        let opts = others.clone();
+        usage::propagate(async move { fetch_passes_from_catalog(&state, user_id, &opts).await })
+    });
+
+    let (winner, first, other, other_name) = tokio::select! {
+        r = &mut games => ("juegos públicos", r.unwrap_or_default(), catalog, "catálogo"),
+        r = &mut catalog => ("catálogo", r.unwrap_or_default(), games, "juegos públicos"),
+    };
+
+    if first.is_empty() {
+        info!("Carrera: {winner} sin resultados, esperando a {other_name}…");
+        let passes = other.await.unwrap_or_default();
+        if passes.is_empty() && !stats.is_user_missing() && !stats.is_partial() {
+            return fetch_passes_from_inventory_fallback(&state, user_id, &others).await;
+        }
+        return passes;
+    }
+
+    info!(
+        "Carrera: gana {winner} con {} passes para userId={user_id}",
+        first.len()
+    );
+    let cache_state = state.clone();
+    state.race_losers.adopt(other, move |passes| {
+        info!(
+            "Carrera: {other_name} terminó en segundo plano con {} passes para userId={user_id}",
+            passes.len()
+        );
+        // Refresca la caché con su resultado, salvo que no traiga nada o que
+        // algo fallara (se quedaría a medias).
+        if passes.is_empty() || !stats.is_complete() {
+            return;
+        }
+        tokio::spawn(async move {
+            cache_state.cache.insert(key, passes, stats).await;
+        });
+    });
+    first
+}
@@ -0,0 +1,675 @@
+//! Rutas de la API (`build_router`) y el handler principal,
+//! `/user/:id/passes`.
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{DefaultBodyLimit, OriginalUri, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Router,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tower_http::catch_panic::CatchPanicLayer;
+use tracing::info;
+
+#[cfg(feature = "watcher")]
+use axum::routing::delete;
+
+#[cfg(feature = "clothing")]
+use crate::clothing;
+#[cfg(feature = "fault-injection")]
+use crate::faults;
+#[cfg(feature = "watcher")]
+use crate::watcher;
+use crate::{
+    access_log, admin, batch, booths,
+    cache::{self, CacheStatus},
+    campaigns, collections, compare, crash, dedupe, degradation, drain, duplicates,
+    error::{self, ApiError},
+    extract::Query,
+    format, games, groups, guidance, health, jsonapi, links, metrics,
+    models::{FetchMode, FetchOptions, Gamepass},
+    onboarding, pricing, products, quarantine, ratelimit, request_id, resolve,
+    roblox::client,
+    schema, snapshots, status, suggest,
+    tenant::{self, MaybeTenant},
+    thumbnails, timeout, usage, views, warnings, AppState,
+};
+
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct ApiResponse {
+    pub(crate) ok: bool,
+    #[serde(rename = "userId")]
+    pub(crate) user_id: u64,
+    pub(crate) count: usize,
+    pub(crate) passes: Vec<views::PassView>,
+    /// Solo presente (y `true`) si la lista se recortó por `MAX_RESPONSE_BYTES`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) truncated: bool,
+    /// Solo presente (y `true`) si venció `SCAN_DEADLINE_SECS`: la lista
+    /// tiene los passes recogidos hasta entonces.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) partial: bool,
+    /// Total de passes antes de recortar.
+    #[serde(rename = "totalCount", skip_serializing_if = "Option::is_none")]
+    pub(crate) total_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) hint: Option<&'static str>,
+    /// Con `?groupBy=game`: juegos con sus métricas y los ids de sus passes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) games: Option<Vec<games::GameGroup>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) links: Option<links::ResponseLinks>,
+    /// Si quedan passes tras esta página: el `?cursor=` de la siguiente.
+    #[serde(rename = "nextCursor", skip_serializing_if = "Option::is_none")]
+    pub(crate) next_cursor: Option<String>,
+    /// Solo con la lista vacía: por qué no hay passes y qué puede hacer el
+    /// creador.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) guidance: Option<guidance::Guidance>,
+    /// Solo con `?includeRemoved=true`: passes que el usuario vendía y ya
+    /// no, con la fecha de retirada.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) removed: Option<Vec<snapshots::RemovedPass>>,
+    /// Solo si el servicio está degradado por fallos de Roblox: qué se dejó
+    /// de hacer en esta respuesta.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) degradation: Option<degradation::Level>,
+    /// Lo que se omitió o se sirvió de otro modo sin que la petición
+    /// fallara (ver `warnings`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) warnings: Vec<warnings::Warning>,
+}
+
+impl ApiResponse {
+    fn new(user_id: u64, passes: Vec<views::PassView>) -> Self {
+        ApiResponse {
+            ok: true,
+            user_id,
+            count: passes.len(),
+            passes,
+            truncated: false,
+            partial: false,
+            total_count: None,
+            hint: None,
+            games: None,
+            links: None,
+            next_cursor: None,
+            guidance: None,
+            removed: None,
+            degradation: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Recorta `passes` (conservando el orden, así el corte es determinista)
+    /// hasta que el cuerpo quepa en `max_bytes`. `measure` da los bytes del
+    /// cuerpo tal como sale, con `games` y en su formato (JSON:API,
+    /// indentado, XML), no solo el JSON compacto. `0` desactiva el límite.
+    fn limit_size(mut self, max_bytes: usize, measure: impl Fn(&Self) -> usize) -> Self {
+        if max_bytes == 0 || measure(&self) <= max_bytes {
+            return self;
+        }
+
+        let total = self.passes.len();
+        let all = std::mem::take(&mut self.passes);
+        self.truncated = true;
+        self.total_count = Some(total);
+        self.hint = Some(
+            "Respuesta recortada por tamaño; usa paginación o filtros para pedir menos passes",
+        );
+        self.warnings
+            .push(warnings::response_truncated(total, total));
+        let fits = |this: &mut Self, kept: usize| {
+            this.passes = all[..kept].to_vec();
+            this.count = kept;
+            if let Some(warning) = this.warnings.last_mut() {
+                *warning = warnings::response_truncated(kept, total);
+            }
+            measure(this) <= max_bytes
+        };
+
+        // El cuerpo crece con cada pass: se busca el prefijo más largo que
+        // cabe. Si ni la lista vacía cabe, sale vacía.
+        let (mut lo, mut hi) = (0, total);
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            if fits(&mut self, mid) {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        fits(&mut self, lo);
+        info!(
+            "Respuesta recortada para userId={}: {} de {} passes (máx {} bytes)",
+            self.user_id, lo, total, max_bytes
+        );
+        self
+    }
+}
+
+/// Query de `/user/:id/passes`.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PassesQuery {
+    /// Máximo de passes a considerar por juego (antes de pedir precios).
+    max_passes_per_game: Option<usize>,
+    group_by: Option<GroupBy>,
+    /// Por defecto, `ACTIVE_GAMES_ONLY`.
+    active_games_only: Option<bool>,
+    /// Incluir `iconUrl` en cada pass. Por defecto, `DEFAULT_THUMBNAILS`.
+    thumbnails: Option<bool>,
+    /// `true` ignora la caché, como `Cache-Control: no-cache`.
+    #[serde(default)]
+    fresh: bool,
+    /// Añadir `removed`: passes que el usuario retiró de la venta. Por
+    /// defecto, `DEFAULT_INCLUDE_REMOVED`.
+    include_removed: Option<bool>,
+    /// Escanear también los juegos de los grupos que posee el usuario.
+    #[serde(default)]
+    include_groups: bool,
+    /// Precio mínimo y máximo (incluidos) de los passes de la respuesta.
+    min_price: Option<i64>,
+    max_price: Option<i64>,
+    /// Incluir los passes gratuitos (a la venta por 0), que se omiten por
+    /// defecto.
+    #[serde(default)]
+    include_free: bool,
+    /// Por defecto, `FETCH_MODE`.
+    mode: Option<FetchMode>,
+    /// `jsonapi` para un documento JSON:API en lugar del envelope propio.
+    format: Option<OutputFormat>,
+    /// Campos opcionales de cada pass (ver `views`). Por defecto,
+    /// `DEFAULT_FIELDS`.
+    fields: Option<String>,
+    /// Idioma de los nombres de juego con `groupBy=game`; si no viene, el
+    /// de `Accept-Language`.
+    locale: Option<String>,
+    /// Orden de los passes; por defecto, el del escaneo.
+    sort: Option<PassSort>,
+    /// Máximo de passes de la respuesta, tras filtrar y ordenar. Si quedan
+    /// más, la respuesta trae `nextCursor`.
+    limit: Option<usize>,
+    /// `nextCursor` de la página anterior (ver `pagination`).
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    JsonApi,
+}
+
+#[derive(Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum GroupBy {
+    Game,
+}
+
+/// `?sort=`. Los empates conservan el orden del escaneo.
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum PassSort {
+    PriceAsc,
+    PriceDesc,
+    /// Sin distinguir mayúsculas.
+    Name,
+    /// Roblox asigna los ids en orden, así que el id más alto es el pass
+    /// más reciente.
+    Newest,
+}
+
+impl PassSort {
+    fn apply(self, passes: &mut [Gamepass]) {
+        match self {
+            PassSort::PriceAsc => passes.sort_by_key(|p| p.price),
+            PassSort::PriceDesc => passes.sort_by_key(|p| std::cmp::Reverse(p.price)),
+            PassSort::Name => passes.sort_by_cached_key(|p| p.name.to_lowercase()),
+            PassSort::Newest => passes.sort_by_key(|p| std::cmp::Reverse(p.id)),
+        }
+    }
+}
+
+/// La API entera, con sus middlewares, sobre `state`.
+pub fn build_router(state: Arc<AppState>) -> Router {
+    let config = &state.config;
+    // Escaneos completos: pueden recorrer decenas de juegos y precios.
+    let scans = Router::new()
+        .route("/user/:id/passes", get(get_passes))
+        .route("/user/:id/passes/diff", get(snapshots::get_diff))
+        .route("/user/:id/passes/suggest", get(suggest::suggest))
+        .route("/username/:name/passes", get(resolve::get_passes))
+        .route("/group/:id/passes", get(groups::get_passes))
+        .route("/users/passes", post(batch::user_passes))
+        .route("/compare", get(compare::compare))
+        .route(
+            "/collection/:name/passes",
+            get(collections::collection_passes),
+        );
+    #[cfg(feature = "clothing")]
+    let scans = scans.route("/user/:id/donatables", get(clothing::get_donatables));
+    let scans = scans
+        .route_layer(middleware::from_fn_with_state(
+            config.route_timeout_scan,
+            timeout::enforce,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            ratelimit::enforce,
+        ))
+        // Fuera del límite: un reintento deduplicado no gasta ficha.
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            dedupe::dedupe,
+        ));
+
+    // Unas pocas llamadas a Roblox.
+    let upstream_calls = Router::new()
+        .route(
+            "/user/:id/create-pass-link",
+            get(onboarding::create_pass_link),
+        )
+        .route("/universe/:id/products", get(products::get_products))
+        .route("/resolve/:name", get(resolve::resolve));
+    #[cfg(feature = "clothing")]
+    let upstream_calls = upstream_calls.route("/user/:id/clothing", get(clothing::get_clothing));
+    let upstream_calls = upstream_calls
+        .route_layer(middleware::from_fn_with_state(
+            config.route_timeout_upstream,
+            timeout::enforce,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            ratelimit::enforce,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            dedupe::dedupe,
+        ));
+
+    let local = Router::new()
+        .route("/", get(status::status_page))
+        .route("/healthz", get(health::healthz))
+        .route("/healthz/deep", get(health::healthz_deep))
+        .route("/readyz", get(health::readyz))
+        .route("/ping", get(health::ping))
+        .route("/time", get(health::time))
+        .route("/user/:id/passes/snapshots", get(snapshots::list_snapshots))
+        .route(
+            "/user/:id/passes/snapshots/:since",
+            get(snapshots::get_snapshot),
+        )
+        .route("/admin/upstreams", get(admin::upstreams))
+        .route("/admin/queues", get(admin::queues))
+        .route("/admin/tasks", get(admin::tasks))
+        .route("/admin/config", get(admin::config))
+        .route("/admin/quarantine", get(quarantine::list))
+        .route("/admin/usage", get(usage::usage_report))
+        .route("/admin/drain", post(drain::drain))
+        .route("/admin/cache/top", get(cache::top))
+        .route("/admin/cache/export", get(cache::export))
+        .route(
+            "/admin/cache/import",
+            post(cache::import).layer(DefaultBodyLimit::max(cache::IMPORT_MAX_BYTES)),
+        )
+        .route("/admin/collections", get(collections::list_collections))
+        .route(
+            "/admin/collections/:name",
+            put(collections::put_collection).delete(collections::delete_collection),
+        )
+        .route("/usage", get(usage::get_usage))
+        .route("/metrics", get(metrics::metrics))
+        .route("/schema", get(schema::index))
+        .route("/schema/:name", get(schema::get_schema))
+        .route("/booths", get(booths::list_booths))
+        .route(
+            "/booths/:booth_id",
+            get(booths::get_booth)
+                .put(booths::put_booth)
+                .delete(booths::delete_booth),
+        )
+        .route("/donations", post(campaigns::post_donation))
+        .route("/campaigns", get(campaigns::list_campaigns))
+        .route(
+            "/campaigns/:campaign_id",
+            get(campaigns::get_campaign)
+                .put(campaigns::put_campaign)
+                .delete(campaigns::delete_campaign),
+        );
+
+    #[cfg(feature = "watcher")]
+    let local = local
+        .route(
+            "/watch",
+            get(watcher::list_watches).post(watcher::add_watch),
+        )
+        .route("/watch/:user_id", delete(watcher::remove_watch));
+
+    #[cfg(feature = "fault-injection")]
+    let local = local.route(
+        "/admin/faults",
+        get(faults::get_faults)
+            .put(faults::put_faults)
+            .delete(faults::clear_faults),
+    );
+
+    let app = local
+        .route_layer(middleware::from_fn_with_state(
+            config.route_timeout_default,
+            timeout::enforce,
+        ))
+        .merge(scans)
+        .merge(upstream_calls);
+
+    app.fallback(error::route_not_found)
+        .layer(middleware::map_response(error::method_not_allowed))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            tenant::require,
+        ))
+        .layer(middleware::from_fn_with_state(state.clone(), usage::track))
+        .layer(CatchPanicLayer::custom(crash::PanicHandler {
+            state: state.clone(),
+        }))
+        .layer(middleware::from_fn(request_id::request_id))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            format::pretty_json,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            access_log::log,
+        ))
+        .with_state(state.clone())
+}
+
+pub(crate) async fn get_passes(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<u64>,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PassesQuery>,
+    MaybeTenant(tenant): MaybeTenant,
+    headers: HeaderMap,
+    format: format::Format,
+) -> Result<Response, ApiError> {
+    info!("/user/{}/passes", user_id);
+
+    if query.max_passes_per_game == Some(0) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_QUERY",
+            "maxPassesPerGame debe ser al menos 1",
+        ));
+    }
+    if query.limit == Some(0) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_QUERY",
+            "limit debe ser al menos 1",
+        ));
+    }
+    let fields = views::Fields::requested(query.fields.as_deref(), &state.config)?;
+    let locale = games::requested_locale(query.locale.as_deref(), &headers)?;
+    let min_price = query.min_price.unwrap_or(0);
+    let max_price = query.max_price.unwrap_or(pricing::MAX_PRICE);
+    if min_price < 0 || min_price > max_price {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_QUERY",
+            "minPrice y maxPrice deben ser positivos y minPrice no puede superar a maxPrice",
+        ));
+    }
+    let level = state.degradation.level(&state.upstreams);
+    let opts = FetchOptions {
+        max_passes_per_game: query.max_passes_per_game,
+        active_games_only: query
+            .active_games_only
+            .unwrap_or(state.config.active_games_only),
+        // JSON:API incluye los juegos como recursos con sus atributos. Con
+        // un idioma, `group_by_game` los pide de nuevo ya traducidos.
+        game_details: (query.group_by == Some(GroupBy::Game) && locale.is_none()
+            || query.format == Some(OutputFormat::JsonApi))
+            && level < degradation::Level::NoGameDetails,
+        include_groups: query.include_groups,
+        include_free: query.include_free,
+        stats: Arc::default(),
+        deadline: (!state.config.scan_deadline.is_zero())
+            .then(|| tokio::time::Instant::now() + state.config.scan_deadline),
+    };
+
+    // Solo las listas completas sirven de punto de partida para un diff; con
+    // los grupos, la lista no es solo del usuario, y con los gratuitos trae
+    // passes que las fotos no esperan.
+    let full_list = opts.max_passes_per_game.is_none()
+        && !opts.active_games_only
+        && !opts.include_groups
+        && !opts.include_free;
+
+    let key = cache::CacheKey::new(user_id, &opts);
+    let max_age = if query.fresh {
+        Duration::ZERO
+    } else {
+        cache::max_age(&headers, tenant.as_ref(), &state.config)
+    };
+    let cached = state.cache.get(&key, max_age).await;
+    let cache_only = level == degradation::Level::CacheOnly;
+    // Solo los escaneos gastan presupuesto; los aciertos lo consultan.
+    let budget = state.fresh_budget.for_tenant(
+        tenant.as_ref(),
+        &state.config,
+        cached.is_none() && !cache_only,
+    );
+    let (mut passes, stats, cache_status) = match (cached, budget) {
+        (Some(hit), _) => {
+            info!(
+                "Caché: userId={} servido con {}s de antigüedad",
+                user_id,
+                hit.age.as_secs()
+            );
+            (hit.passes, hit.stats, CacheStatus::Hit { age: hit.age })
+        }
+        (None, Some(budget)) if !budget.granted => {
+            let tenant_id = tenant.as_ref().map_or("", |t| t.id.as_str());
+            // Cualquier dato guardado es mejor que un 429.
+            let Some(hit) = state.cache.get_stale(&key).await else {
+                return Ok(budget.exhausted(tenant_id, &format!("userId {user_id}")));
+            };
+            info!(
+                "Presupuesto agotado para '{tenant_id}': userId={} servido solo de caché ({}s)",
+                user_id,
+                hit.age.as_secs()
+            );
+            (
+                hit.passes,
+                hit.stats,
+                CacheStatus::CacheOnly { age: hit.age },
+            )
+        }
+        (None, _) if cache_only => {
+            let Some(hit) = state.cache.get_stale(&key).await else {
+                return Err(ApiError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "DEGRADED_CACHE_ONLY",
+                    format!(
+                        "Roblox está fallando y solo se sirven datos en caché; no hay ninguno para userId {user_id}"
+                    ),
+                ));
+            };
+            info!(
+                "Degradado a solo caché: userId={} servido con {}s de antigüedad",
+                user_id,
+                hit.age.as_secs()
+            );
+            (
+                hit.passes,
+                hit.stats,
+                CacheStatus::CacheOnly { age: hit.age },
+            )
+        }
+        (None, _) => {
+            let stats = opts.stats.clone();
+            let passes = match query.mode.unwrap_or(state.config.fetch_mode) {
+                FetchMode::Sequential => {
+                    client::fetch_passes_sequential(&state, user_id, &opts).await
+                }
+                #[cfg(feature = "catalog")]
+                FetchMode::Race => client::fetch_passes_racing(state.clone(), user_id, opts).await,
+                // Sin catálogo no hay con qué competir.
+                #[cfg(not(feature = "catalog"))]
+                FetchMode::Race => client::fetch_passes_sequential(&state, user_id, &opts).await,
+            };
+            state.cache.insert(key, passes.clone(), stats.clone()).await;
+            (passes, stats, CacheStatus::Miss)
+        }
+    };
+
+    // Sin foto (ni ETag) para un escaneo a medias o de un usuario que nadie
+    // vigila (ver `snapshots`).
+    let snapshot = full_list
+        .then(|| snapshots::record_scan(&state, user_id, &passes, &stats))
+        .flatten();
+    let original_prices = state.snapshots.original_prices(user_id);
+    for pass in &mut passes {
+        pass.original_price = original_prices.get(&pass.id).copied().unwrap_or(pass.price);
+        pass.price_changed = pass.original_price != pass.price;
+    }
+
+    // Los filtros de precio se aplican sobre la lista ya escaneada (y
+    // cacheada), así no cambian la clave de caché. `scanned` distingue una
+    // lista vacía de una que se quedó vacía al filtrar.
+    let scanned = passes.len();
+    passes.retain(|p| (min_price..=max_price).contains(&p.price));
+    let price_filtered = scanned - passes.len();
+    if let Some(sort) = query.sort {
+        sort.apply(&mut passes);
+    }
+    let mut page = state
+        .paginator
+        .page(user_id, passes, query.cursor.as_deref(), query.limit)?;
+    let mut passes = std::mem::take(&mut page.passes);
+
+    let thumbnails = query.thumbnails.unwrap_or(state.config.default_thumbnails);
+    if thumbnails && level < degradation::Level::NoThumbnails {
+        let ids: Vec<u64> = passes.iter().map(|p| p.id).collect();
+        let mut icons = thumbnails::resolve_icons(&state, &ids).await;
+        for pass in &mut passes {
+            pass.icon_url = icons.remove(&pass.id);
+        }
+    }
+
+    for pass in &mut passes {
+        let root_place = pass.game.as_ref().and_then(|g| g.root_place_id);
+        pass.links = Some(links::PassLinks::new(pass.id, root_place));
+    }
+    duplicates::disambiguate(&mut passes);
+
+    if scanned == 0 {
+        if let Some(error) = guidance::failure(user_id, &stats) {
+            return Err(error);
+        }
+    }
+
+    let mut response = ApiResponse::new(user_id, views::render(&passes, fields));
+    if scanned == 0 {
+        response.guidance = guidance::explain(user_id, &stats);
+    }
+    if query
+        .include_removed
+        .unwrap_or(state.config.default_include_removed)
+    {
+        response.removed = Some(state.snapshots.removed(user_id));
+    }
+    response.degradation = (level != degradation::Level::Full).then_some(level);
+    response.warnings =
+        warnings::from_scan(&stats, state.config.game_passes_max_pages, price_filtered);
+    if let CacheStatus::CacheOnly { age } = cache_status {
+        response.warnings.push(warnings::stale_cache(age.as_secs()));
+    }
+    response.partial = stats.is_partial();
+    if response.partial {
+        response
+            .warnings
+            .push(warnings::deadline_exceeded(state.config.scan_deadline));
+    }
+    response.links = Some(links::ResponseLinks::new(
+        uri.path_and_query().map_or(uri.path(), |pq| pq.as_str()),
+        "passes",
+    ));
+
+    let json_api = query.format == Some(OutputFormat::JsonApi);
+    if query.group_by == Some(GroupBy::Game) && !json_api {
+        let pass_games: Vec<games::PassGame> = passes
+            .iter()
+            .map(|p| games::PassGame {
+                pass_id: p.id,
+                universe_id: p.universe_id,
+                details: p.game.clone(),
+            })
+            .collect();
+        let fetch_missing = level < degradation::Level::NoGameDetails;
+        let locale = locale.as_deref();
+        response.games =
+            Some(games::group_by_game(&state, &pass_games, fetch_missing, locale).await);
+    }
+
+    // Se mide el cuerpo que sale de verdad. `games` se mide entero: quitarle
+    // después los passes recortados solo lo encoge.
+    let pretty = format::is_pretty(&state, uri.query());
+    let measure = |response: &ApiResponse| {
+        if json_api {
+            jsonapi::rendered_len(response, &passes[..response.passes.len()], pretty)
+        } else {
+            format::rendered_len(format, pretty, response)
+        }
+    };
+    let mut response = response.limit_size(state.config.max_response_bytes, measure);
+    // El recorte conserva el orden: los passes que quedan son los primeros.
+    passes.truncate(response.passes.len());
+    response.next_cursor = state
+        .paginator
+        .next_cursor(user_id, &page, response.passes.len());
+    // Que `passIds` no apunte a passes que ya no están en la respuesta.
+    if let Some(games) = response.games.as_mut().filter(|_| response.truncated) {
+        let kept: HashSet<u64> = passes.iter().map(|p| p.id).collect();
+        for game in games.iter_mut() {
+            game.pass_ids.retain(|id| kept.contains(id));
+        }
+        games.retain(|game| !game.pass_ids.is_empty());
+    }
+
+    let mut response = if json_api {
+        jsonapi::render(&response, &passes)
+    } else {
+        format::Negotiated(format, response).into_response()
+    };
+    cache_status.apply(response.headers_mut());
+    if let Some(budget) = budget {
+        budget.apply(response.headers_mut());
+    }
+    // El ETag es el de la foto (el `since` de `/diff`) e identifica la lista
+    // completa tal como sale sin parámetros: una filtrada por precio, una
+    // página, otro orden, otros campos u otro formato son cuerpos distintos
+    // y no pueden compartirlo.
+    let canonical = query.min_price.is_none()
+        && query.max_price.is_none()
+        && query.limit.is_none()
+        && query.cursor.is_none()
+        && query.sort.is_none()
+        && query.fields.is_none()
+        && query.thumbnails.is_none()
+        && query.group_by.is_none()
+        && query.format.is_none()
+        && query.include_removed.is_none()
+        && format == format::Format::Json;
+    if let Some(snapshot) = snapshot.filter(|_| canonical) {
+        response
+            .headers_mut()
+            .insert(header::ETAG, snapshots::etag_header(&snapshot));
+    }
+    Ok(response)
+}
@@ -0,0 +1,224 @@
+//! JSON Schema de las respuestas de la API (`GET /schema`, `GET /schema/:name`),
+//! generados con `schemars` a partir de los mismos tipos que se serializan,
+//! para que los clientes en otros lenguajes puedan generar sus tipos.
+
+use axum::{extract::Path, http::StatusCode, Json};
+use schemars::{generate::SchemaSettings, JsonSchema, Schema};
+use serde::Serialize;
+
+#[cfg(feature = "clothing")]
+use crate::clothing;
+#[cfg(feature = "watcher")]
+use crate::watcher;
+use crate::{
+    admin, batch, booths, cache, campaigns, collections, compare, drain, error::ApiError,
+    error::ErrorEnvelope, groups, health, onboarding, products, quarantine, resolve,
+    routes::ApiResponse, snapshots, suggest, usage,
+};
+
+type SchemaFn = fn() -> Schema;
+
+/// Schema de `T` tal como se serializa (los campos con
+/// `skip_serializing_if` no son obligatorios).
+fn response_schema<T: JsonSchema>() -> Schema {
+    SchemaSettings::default()
+        .for_serialize()
+        .into_generator()
+        .into_root_schema_for::<T>()
+}
+
+/// Nombre público de cada schema y su generador.
+fn schemas() -> Vec<(&'static str, SchemaFn)> {
+    vec![
+        ("passes", response_schema::<ApiResponse>),
+        (
+            "group-passes",
+            response_schema::<groups::GroupPassesResponse>,
+        ),
+        ("error", response_schema::<ErrorEnvelope<'static>>),
+        ("healthz", response_schema::<health::Liveness>),
+        ("healthz-deep", response_schema::<health::DeepHealth>),
+        ("readyz", response_schema::<health::Readiness>),
+        ("time", response_schema::<health::ServerTime>),
+        (
+            "admin-upstreams",
+            response_schema::<admin::UpstreamsResponse>,
+        ),
+        ("admin-queues", response_schema::<admin::QueuesResponse>),
+        ("admin-tasks", response_schema::<admin::TasksResponse>),
+        ("admin-config", response_schema::<admin::ConfigResponse>),
+        (
+            "admin-quarantine",
+            response_schema::<quarantine::QuarantineResponse>,
+        ),
+        ("passes-diff", response_schema::<snapshots::DiffResponse>),
+        (
+            "passes-suggest",
+            response_schema::<suggest::SuggestResponse>,
+        ),
+        (
+            "create-pass-link",
+            response_schema::<onboarding::CreatePassLinkResponse>,
+        ),
+        (
+            "snapshots",
+            response_schema::<snapshots::SnapshotListResponse>,
+        ),
+        ("snapshot", response_schema::<snapshots::SnapshotResponse>),
+        #[cfg(feature = "watcher")]
+        ("watch", response_schema::<watcher::WatchResponse>),
+        ("booth", response_schema::<booths::BoothResponse>),
+        ("booth-list", response_schema::<booths::BoothListResponse>),
+        ("campaign", response_schema::<campaigns::CampaignResponse>),
+        (
+            "campaign-list",
+            response_schema::<campaigns::CampaignListResponse>,
+        ),
+        ("donation", response_schema::<campaigns::DonationResponse>),
+        #[cfg(feature = "watcher")]
+        ("watch-list", response_schema::<watcher::WatchListResponse>),
+        ("usage", response_schema::<usage::UsageResponse>),
+        ("compare", response_schema::<compare::CompareResponse>),
+        (
+            "users-passes",
+            response_schema::<batch::BatchPassesResponse>,
+        ),
+        ("resolve", response_schema::<resolve::ResolvedUser>),
+        #[cfg(feature = "clothing")]
+        ("clothing", response_schema::<clothing::ClothingResponse>),
+        (
+            "universe-products",
+            response_schema::<products::ProductsResponse>,
+        ),
+        #[cfg(feature = "clothing")]
+        (
+            "donatables",
+            response_schema::<clothing::DonatablesResponse>,
+        ),
+        ("admin-usage", response_schema::<usage::UsageReportResponse>),
+        ("admin-drain", response_schema::<drain::DrainResponse>),
+        (
+            "admin-cache-top",
+            response_schema::<cache::CacheTopResponse>,
+        ),
+        (
+            "admin-cache-export",
+            response_schema::<cache::CacheExportResponse>,
+        ),
+        (
+            "admin-cache-import",
+            response_schema::<cache::CacheImportResponse>,
+        ),
+        (
+            "collection",
+            response_schema::<collections::CollectionResponse>,
+        ),
+        (
+            "admin-collections",
+            response_schema::<collections::CollectionListResponse>,
+        ),
+        (
+            "collection-passes",
+            response_schema::<collections::CollectionPassesResponse>,
+        ),
+        #[cfg(feature = "fault-injection")]
+        (
+            "admin-faults",
+            response_schema::<crate::faults::FaultConfig>,
+        ),
+    ]
+}
+
+#[derive(Serialize)]
+pub struct SchemaIndex {
+    ok: bool,
+    schemas: Vec<&'static str>,
+}
+
+/// `GET /schema`: nombres de los schemas disponibles.
+pub async fn index() -> Json<SchemaIndex> {
+    Json(SchemaIndex {
+        ok: true,
+        schemas: schemas().into_iter().map(|(name, _)| name).collect(),
+    })
+}
+
+/// `GET /schema/:name`
+pub async fn get_schema(Path(name): Path<String>) -> Result<Json<Schema>, ApiError> {
+    schemas()
+        .into_iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, schema)| Json(schema()))
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                "SCHEMA_NOT_FOUND",
+                format!("No existe el schema '{name}'"),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    fn fixtures_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/schema")
+    }
+
+    /// Cada schema tiene al menos un ejemplo en `tests/fixtures/schema/<name>*.json`
+    /// y todos los ejemplos validan contra su schema.
+    #[test]
+    fn fixtures_match_schemas() {
+        let entries: Vec<PathBuf> = fs::read_dir(fixtures_dir())
+            .expect("tests/fixtures/schema")
+            .map(|e| e.unwrap().path())
+            .collect();
+
+        for (name, schema) in schemas() {
+            let schema = serde_json::to_value(schema()).unwrap();
+            let validator = jsonschema::validator_for(&schema)
+                .unwrap_or_else(|e| panic!("schema '{name}' inválido: {e}"));
+
+            let fixtures: Vec<&PathBuf> = entries
+                .iter()
+                .filter(|p| {
+                    let stem = p.file_stem().unwrap().to_str().unwrap();
+                    stem == name || stem.strip_prefix(name).is_some_and(|r| r.starts_with('.'))
+                })
+                .collect();
+            assert!(!fixtures.is_empty(), "sin fixtures para el schema '{name}'");
+
+            for path in fixtures {
+                let instance: serde_json::Value =
+                    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+                let errors: Vec<String> = validator
+                    .iter_errors(&instance)
+                    .map(|e| format!("{} en {}", e, e.instance_path()))
+                    .collect();
+                assert!(
+                    errors.is_empty(),
+                    "{} no valida contra '{name}': {errors:?}",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    /// Un fixture que no cumple el schema tiene que fallar, para que el test
+    /// anterior no pase por un schema demasiado permisivo.
+    #[test]
+    fn rejects_invalid_passes_response() {
+        let schema = serde_json::to_value(response_schema::<ApiResponse>()).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let instance = serde_json::json!({
+            "ok": true,
+            "userId": "no-es-un-numero",
+            "count": 0,
+            "passes": [],
+        });
+        assert!(!validator.is_valid(&instance));
+    }
+}
@@ -0,0 +1,250 @@
+//! Comprobaciones al arrancar: configuración, carpetas donde se persiste y,
+//! con `SELFCHECK_CANARY_USER_ID`, una llamada canario a Roblox. Si algo
+//! falla el proceso sale con un mensaje que dice qué corregir, en lugar de
+//! descubrirlo con la primera petición real. `--skip-selfcheck` lo omite.
+
+use std::{
+    collections::HashSet,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use tracing::{error, info, warn};
+
+use crate::{
+    config::{self, Config},
+    recording::Mode,
+    upstream::{self, Endpoint, Upstream},
+    AppState,
+};
+
+const CANARY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Algo que impide arrancar, con lo que hay que hacer para arreglarlo.
+struct Problem {
+    what: String,
+    hint: String,
+}
+
+impl Problem {
+    fn new(what: impl Into<String>, hint: impl Into<String>) -> Self {
+        Problem {
+            what: what.into(),
+            hint: hint.into(),
+        }
+    }
+}
+
+/// Revisa el entorno; los avisos se imprimen y los problemas se devuelven.
+fn check_config(config: &Config, recording: &Mode) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    for name in config::invalid_vars() {
+        // `UPSTREAM_TAGS` puede llevar claves; al parsearla ya se avisó de
+        // qué entrada falla.
+        let value = match name.as_str() {
+            "UPSTREAM_TAGS" => "…".to_string(),
+            _ => config::var(&name).unwrap_or_default(),
+        };
+        problems.push(Problem::new(
+            format!("{name}={value:?} no es un valor válido"),
+            format!("corrige o elimina {name} para usar el valor por defecto"),
+        ));
+    }
+
+    if config::var("API_KEYS").is_ok_and(|raw| !raw.trim().is_empty()) && config.api_keys.is_empty()
+    {
+        problems.push(Problem::new(
+            "API_KEYS está definida pero ninguna entrada es válida",
+            "usa el formato id:clave[:maxWatches[:maxAgeSecs[:freshPerMinute]]],...",
+        ));
+    }
+    let mut ids = HashSet::new();
+    let mut keys = HashSet::new();
+    for key in &config.api_keys {
+        if !ids.insert(key.id.as_str()) {
+            problems.push(Problem::new(
+                format!("API_KEYS repite el id '{}'", key.id),
+                "cada tenant necesita un id distinto",
+            ));
+        }
+        if !keys.insert(key.key.as_str()) {
+            problems.push(Problem::new(
+                format!("API_KEYS repite la clave del id '{}'", key.id),
+                "genera una clave distinta para cada tenant",
+            ));
+        }
+    }
+
+    if let Some(domain) = &config.upstream_mirror_domain {
+        if domain.contains('/') || domain.contains(':') {
+            problems.push(Problem::new(
+                format!("UPSTREAM_MIRROR_DOMAIN={domain:?} no es un dominio"),
+                "pon solo el dominio, p. ej. roproxy.com",
+            ));
+        }
+    }
+
+    if let Mode::Replay(dir) = recording {
+        if !dir.is_dir() {
+            problems.push(Problem::new(
+                format!("UPSTREAM_MODE=replay pero {} no existe", dir.display()),
+                "graba primero con UPSTREAM_MODE=record o ajusta UPSTREAM_CASSETTE_DIR",
+            ));
+        }
+    }
+
+    if let Some(url) = &config.cache_redis_url {
+        if !cfg!(feature = "redis") {
+            problems.push(Problem::new(
+                "CACHE_REDIS_URL está definida pero el binario no tiene la feature redis",
+                "compila con --features redis o vacía CACHE_REDIS_URL",
+            ));
+        }
+        #[cfg(feature = "redis")]
+        if let Err(e) = redis::Client::open(url.as_str()) {
+            problems.push(Problem::new(
+                format!("CACHE_REDIS_URL no es una URL de Redis válida: {e}"),
+                "usa redis://[:contraseña@]host[:puerto][/db]",
+            ));
+        }
+        #[cfg(not(feature = "redis"))]
+        let _ = url;
+    }
+
+    if config.admin_token.as_ref().is_some_and(|t| t.len() < 16) {
+        warn!("ADMIN_TOKEN tiene menos de 16 caracteres");
+    }
+    if config.cache_ttl > config.cache_retention {
+        warn!(
+            "CACHE_TTL_SECS ({}) supera CACHE_RETENTION_SECS ({}); las entradas se descartan antes",
+            config.cache_ttl.as_secs(),
+            config.cache_retention.as_secs()
+        );
+    }
+    problems
+}
+
+/// Crea la carpeta si falta y prueba a escribir en ella.
+fn check_writable(var: &str, dir: &Path) -> Option<Problem> {
+    let probe = dir.join(".selfcheck");
+    let written = std::fs::create_dir_all(dir)
+        .and_then(|()| std::fs::write(&probe, b"ok"))
+        .and_then(|()| std::fs::remove_file(&probe));
+    written.err().map(|e| {
+        Problem::new(
+            format!("no se puede escribir en {} ({var}): {e}", dir.display()),
+            format!("revisa los permisos o apunta {var} a otra carpeta"),
+        )
+    })
+}
+
+/// Las carpetas donde el servicio guarda estado. No hay base de datos: el
+/// almacenamiento son estos archivos.
+fn check_storage(config: &Config) -> Vec<Problem> {
+    let mut dirs: Vec<(&str, &Path)> = vec![("CRASH_REPORT_DIR", &config.crash_report_dir)];
+    if let Some(dir) = &config.snapshot_dir {
+        dirs.push(("SNAPSHOT_DIR", dir));
+    }
+    if let Some(dir) = &config.booth_dir {
+        dirs.push(("BOOTH_DIR", dir));
+    }
+    if let Some(dir) = &config.collection_dir {
+        dirs.push(("COLLECTION_DIR", dir));
+    }
+    if let Some(parent) = config
+        .access_log_file
+        .as_deref()
+        .and_then(Path::parent)
+        .filter(|p| !p.as_os_str().is_empty())
+    {
+        dirs.push(("ACCESS_LOG_FILE", parent));
+    }
+    dirs.into_iter()
+        .filter_map(|(var, dir)| check_writable(var, dir))
+        .collect()
+}
+
+/// Una llamada real a `/v2/users/{id}/games`, la misma URL que usa el escaneo.
+async fn check_canary(state: &AppState, user_id: u64) -> Option<Problem> {
+    let url = format!(
+        "{}/v2/users/{user_id}/games?accessFilter=2&limit=50&sortOrder=Asc",
+        state.config.upstream_url(Upstream::Games)
+    );
+    let started = Instant::now();
+    let call = tokio::time::timeout(
+        CANARY_TIMEOUT,
+        upstream::get(state, Endpoint::UserGames, &url),
+    )
+    .await;
+    let hint = "revisa la salida a internet, UPSTREAM_MODE o UPSTREAM_MIRROR_DOMAIN; \
+                SELFCHECK_CANARY_USER_ID vacío desactiva el canario";
+    let resp = match call {
+        Err(_) => {
+            return Some(Problem::new(
+                format!("Roblox no respondió en {}s", CANARY_TIMEOUT.as_secs()),
+                hint,
+            ))
+        }
+        Ok(Err(e)) => return Some(Problem::new(format!("llamada canario fallida: {e}"), hint)),
+        Ok(Ok(resp)) => resp,
+    };
+    let status = resp.status();
+    if !status.is_success() {
+        return Some(Problem::new(
+            format!("llamada canario respondió HTTP {status}"),
+            hint,
+        ));
+    }
+    let body: Option<serde_json::Value> = resp.json().await.ok();
+    if body.as_ref().and_then(|b| b.get("data")).is_none() {
+        return Some(Problem::new(
+            "la llamada canario no devolvió el JSON esperado",
+            hint,
+        ));
+    }
+    info!(
+        "Roblox: canario userId={user_id} ok en {}ms",
+        started.elapsed().as_millis()
+    );
+    None
+}
+
+/// Ejecuta todas las comprobaciones y sale del proceso si alguna falla.
+pub async fn run(state: &AppState) {
+    let mut problems = check_config(&state.config, &state.recording);
+    problems.extend(check_storage(&state.config));
+    if problems.is_empty() {
+        info!("Configuración y almacenamiento: ok");
+    }
+    // Sin Redis se sigue sirviendo (cada instancia con su caché): se avisa,
+    // no se aborta.
+    #[cfg(feature = "redis")]
+    if let Some(url) = &state.config.cache_redis_url {
+        if let Ok(cache) =
+            crate::cache::store::RedisCache::new(url, &state.config.cache_redis_prefix)
+        {
+            match cache.ping().await {
+                Ok(()) => info!("Redis: ok"),
+                Err(e) => {
+                    warn!("Redis no responde ({e}); la caché no se comparte hasta que vuelva")
+                }
+            }
+        }
+    }
+    if let Some(user_id) = state.config.selfcheck_canary_user_id {
+        problems.extend(check_canary(state, user_id).await);
+    }
+    if problems.is_empty() {
+        return;
+    }
+
+    for problem in &problems {
+        error!("{} → {}", problem.what, problem.hint);
+    }
+    error!(
+        "Arranque abortado: {} problema(s). Corrígelos o arranca con --skip-selfcheck.",
+        problems.len()
+    );
+    std::process::exit(1);
+}
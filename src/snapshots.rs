@@ -0,0 +1,656 @@
+//! Fotos de la lista de passes de cada usuario, para que los clientes que
+//! sondean puedan pedir solo lo que cambió (`/user/:id/passes/diff`) o volver
+//! a mostrar una lista anterior (`/user/:id/passes/snapshots/:etag`).
+//!
+//! Cada foto se identifica por un etag: un hash del contenido (id, nombre y
+//! precio, ordenado por id), así dos escaneos iguales dan el mismo etag y no
+//! se guarda una foto nueva si nada cambió.
+//!
+//! Con `SNAPSHOT_DIR` las fotos se guardan además en disco, un JSON por
+//! usuario, y se recargan al arrancar. La retención se controla con
+//! `SNAPSHOT_MAX_PER_USER` y `SNAPSHOT_MAX_AGE_DAYS`; la foto más reciente de
+//! cada usuario se conserva siempre, por vieja que sea.
+//!
+//! Solo se guardan fotos de los usuarios vigilados (`/watch`) y de escaneos
+//! completos (ver `ScanStats::is_complete`): en uno a medias faltan passes
+//! que siguen a la venta, y un diff contra él los daría por retirados.
+//!
+//! Cuando un pass desaparece de un escaneo queda una lápida con la
+//! fecha de retirada, que no caduca con la retención de las fotos
+//! (`/user/:id/passes?includeRemoved=true`). Si el pass vuelve, se quita.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    config::Config, error::ApiError, extract::Query, guidance::ScanStats, models::Gamepass,
+    recording::fnv1a, roblox::client, tenant::MaybeTenant, AppState,
+};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct SnapshotPass {
+    pub id: u64,
+    pub name: String,
+    pub price: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub etag: String,
+    pub taken_at: DateTime<Utc>,
+    /// Ordenados por id.
+    pub passes: Vec<SnapshotPass>,
+}
+
+impl Snapshot {
+    pub(crate) fn new(passes: &[Gamepass]) -> Self {
+        let mut passes: Vec<SnapshotPass> = passes
+            .iter()
+            .map(|p| SnapshotPass {
+                id: p.id,
+                name: p.name.clone(),
+                price: p.price,
+            })
+            .collect();
+        passes.sort_by_key(|p| p.id);
+        let canonical = serde_json::to_string(&passes).unwrap_or_default();
+        Snapshot {
+            etag: format!("{:016x}", fnv1a(&canonical)),
+            taken_at: Utc::now(),
+            passes,
+        }
+    }
+}
+
+/// Pass que el usuario ya no vende.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Tombstone {
+    id: u64,
+    name: String,
+    /// Último precio visto.
+    price: i64,
+    removed_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovedPass {
+    pub id: u64,
+    pub name: String,
+    pub price: i64,
+    /// Primer escaneo en el que ya no estaba, RFC 3339.
+    pub removed_at: String,
+}
+
+type History = VecDeque<Arc<Snapshot>>;
+
+pub struct SnapshotStore {
+    users: Mutex<HashMap<u64, History>>,
+    /// Lápidas por usuario, ordenadas por id de pass.
+    removed: Mutex<HashMap<u64, BTreeMap<u64, Tombstone>>>,
+    dir: Option<PathBuf>,
+    /// Escritor de `SNAPSHOT_DIR` (ver `spawn_writer`), si hay carpeta.
+    writer: Option<mpsc::Sender<(String, Vec<u8>)>>,
+    max_per_user: usize,
+    max_age: Option<Duration>,
+}
+
+impl SnapshotStore {
+    /// Crea el almacén y, si hay `SNAPSHOT_DIR`, recarga las fotos guardadas.
+    pub fn new(config: &Config) -> Self {
+        let store = SnapshotStore {
+            users: Mutex::default(),
+            removed: Mutex::default(),
+            dir: config.snapshot_dir.clone(),
+            writer: config.snapshot_dir.clone().map(spawn_writer),
+            max_per_user: config.snapshot_max_per_user.max(1),
+            max_age: config.snapshot_max_age,
+        };
+        store.load();
+        store
+    }
+
+    /// Usuarios con fotos guardadas.
+    pub fn users(&self) -> usize {
+        self.users.lock().unwrap().len()
+    }
+
+    fn load(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut users = self.users.lock().unwrap();
+        let mut removed = self.removed.lock().unwrap();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if let Some(user_id) = stem
+                .strip_suffix(".removed")
+                .and_then(|s| s.parse::<u64>().ok())
+            {
+                let loaded = std::fs::read(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| {
+                        serde_json::from_slice::<Vec<Tombstone>>(&bytes).map_err(|e| e.to_string())
+                    });
+                match loaded {
+                    Ok(tombstones) => {
+                        removed
+                            .insert(user_id, tombstones.into_iter().map(|t| (t.id, t)).collect());
+                    }
+                    Err(e) => warn!("Ignorando {}: {e}", path.display()),
+                }
+                continue;
+            }
+            let Ok(user_id) = stem.parse::<u64>() else {
+                continue;
+            };
+            let loaded = std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| {
+                    serde_json::from_slice::<Vec<Snapshot>>(&bytes).map_err(|e| e.to_string())
+                });
+            match loaded {
+                Ok(snapshots) => {
+                    users.insert(user_id, snapshots.into_iter().map(Arc::new).collect());
+                }
+                Err(e) => warn!("Ignorando {}: {e}", path.display()),
+            }
+        }
+        info!(
+            "{} usuarios con fotos cargados de {}",
+            users.len(),
+            dir.display()
+        );
+    }
+
+    /// Reescribe el archivo de fotos de un usuario.
+    fn persist(&self, user_id: u64, history: &History) {
+        let snapshots: Vec<&Snapshot> = history.iter().map(|s| s.as_ref()).collect();
+        self.write(format!("{user_id}.json"), &snapshots);
+    }
+
+    /// Reescribe el archivo de lápidas de un usuario.
+    fn persist_removed(&self, user_id: u64, tombstones: &BTreeMap<u64, Tombstone>) {
+        let tombstones: Vec<&Tombstone> = tombstones.values().collect();
+        self.write(format!("{user_id}.removed.json"), &tombstones);
+    }
+
+    /// Encarga al escritor el archivo `name` de `SNAPSHOT_DIR`. Se serializa
+    /// aquí, con el candado puesto, para que el escritor reciba las versiones
+    /// en orden.
+    fn write<T: Serialize>(&self, name: String, value: &T) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        match serde_json::to_vec(value) {
+            Ok(json) => {
+                let _ = writer.send((name, json));
+            }
+            Err(e) => warn!("No se pudo serializar {name}: {e}"),
+        }
+    }
+
+    /// Aplica la retención a un historial; devuelve si se borró algo.
+    fn apply_retention(&self, history: &mut History) -> bool {
+        let before = history.len();
+        while history.len() > self.max_per_user {
+            history.pop_front();
+        }
+        if let Some(max_age) = self.max_age {
+            let cutoff = Utc::now() - max_age;
+            while history.len() > 1 && history.front().is_some_and(|s| s.taken_at < cutoff) {
+                history.pop_front();
+            }
+        }
+        history.len() != before
+    }
+
+    /// Guarda la lista de passes de un escaneo completo y devuelve su foto
+    /// (la última guardada si el contenido no cambió).
+    pub(crate) fn record(&self, user_id: u64, passes: &[Gamepass]) -> Arc<Snapshot> {
+        let snapshot = Snapshot::new(passes);
+        let mut users = self.users.lock().unwrap();
+        let history = users.entry(user_id).or_default();
+        if let Some(last) = history.back().filter(|s| s.etag == snapshot.etag) {
+            return last.clone();
+        }
+        self.update_removed(user_id, history.back().map(Arc::as_ref), &snapshot);
+        let snapshot = Arc::new(snapshot);
+        history.push_back(snapshot.clone());
+        self.apply_retention(history);
+        self.persist(user_id, history);
+        snapshot
+    }
+
+    /// Pone lápida a lo que estaba en `last` y ya no está en `current`, y
+    /// se la quita a lo que volvió.
+    fn update_removed(&self, user_id: u64, last: Option<&Snapshot>, current: &Snapshot) {
+        let mut removed = self.removed.lock().unwrap();
+        let tombstones = removed.entry(user_id).or_default();
+        let mut changed = false;
+        for pass in &current.passes {
+            changed |= tombstones.remove(&pass.id).is_some();
+        }
+        let now = Utc::now();
+        let gone = last.into_iter().flat_map(|s| &s.passes).filter(|p| {
+            current
+                .passes
+                .binary_search_by_key(&p.id, |c| c.id)
+                .is_err()
+        });
+        for pass in gone {
+            info!(
+                "userId={user_id}: el pass {} ('{}') ya no está a la venta",
+                pass.id, pass.name
+            );
+            tombstones.insert(
+                pass.id,
+                Tombstone {
+                    id: pass.id,
+                    name: pass.name.clone(),
+                    price: pass.price,
+                    removed_at: now,
+                },
+            );
+            changed = true;
+        }
+        if changed {
+            self.persist_removed(user_id, tombstones);
+        }
+        if tombstones.is_empty() {
+            removed.remove(&user_id);
+        }
+    }
+
+    /// Passes que el usuario vendía y retiró, por id.
+    pub fn removed(&self, user_id: u64) -> Vec<RemovedPass> {
+        let removed = self.removed.lock().unwrap();
+        removed
+            .get(&user_id)
+            .into_iter()
+            .flat_map(|t| t.values())
+            .map(|t| RemovedPass {
+                id: t.id,
+                name: t.name.clone(),
+                price: t.price,
+                removed_at: t.removed_at.to_rfc3339(),
+            })
+            .collect()
+    }
+
+    /// Quita las fotos más viejas que `SNAPSHOT_MAX_AGE_DAYS`.
+    pub fn prune(&self) {
+        let mut users = self.users.lock().unwrap();
+        let mut pruned = 0;
+        for (user_id, history) in users.iter_mut() {
+            if self.apply_retention(history) {
+                self.persist(*user_id, history);
+                pruned += 1;
+            }
+        }
+        if pruned > 0 {
+            info!("Retención aplicada a {pruned} usuarios");
+        }
+    }
+
+    /// Foto por etag o, si `since` es una fecha (RFC 3339 o segundos Unix), la
+    /// última tomada hasta ese momento.
+    fn find(&self, user_id: u64, since: &str) -> Option<Arc<Snapshot>> {
+        let users = self.users.lock().unwrap();
+        let history = users.get(&user_id)?;
+        let etag = since.trim_start_matches("W/").trim_matches('"');
+        if let Some(snapshot) = history.iter().find(|s| s.etag == etag) {
+            return Some(snapshot.clone());
+        }
+        let at = parse_timestamp(since)?;
+        history.iter().rev().find(|s| s.taken_at <= at).cloned()
+    }
+
+    /// Precio más antiguo conservado de cada pass del usuario.
+    pub fn original_prices(&self, user_id: u64) -> HashMap<u64, i64> {
+        let users = self.users.lock().unwrap();
+        let mut prices = HashMap::new();
+        for snapshot in users.get(&user_id).into_iter().flatten() {
+            for pass in &snapshot.passes {
+                prices.entry(pass.id).or_insert(pass.price);
+            }
+        }
+        prices
+    }
+
+    /// Fotos de un usuario, de la más reciente a la más antigua.
+    fn list(&self, user_id: u64) -> Vec<Arc<Snapshot>> {
+        let users = self.users.lock().unwrap();
+        users
+            .get(&user_id)
+            .map(|history| history.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(raw) {
+        return Some(at.with_timezone(&Utc));
+    }
+    raw.parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+}
+
+/// Guarda la foto de un escaneo de la lista entera, si toca: solo de
+/// usuarios vigilados (guardar la de cualquier userId consultado haría
+/// crecer memoria y disco sin límite) y solo de escaneos completos.
+pub(crate) fn record_scan(
+    state: &AppState,
+    user_id: u64,
+    passes: &[Gamepass],
+    stats: &ScanStats,
+) -> Option<Arc<Snapshot>> {
+    (stats.is_complete() && state.is_watched(user_id))
+        .then(|| state.snapshots.record(user_id, passes))
+}
+
+/// Hilo que escribe los archivos de `dir`, uno tras otro y en el orden en
+/// que se encargan, para no bloquear las peticiones (ni los candados del
+/// almacén) con el disco. Lo pendiente al salir del proceso se pierde: el
+/// siguiente escaneo lo vuelve a escribir.
+fn spawn_writer(dir: PathBuf) -> mpsc::Sender<(String, Vec<u8>)> {
+    let (tx, rx) = mpsc::channel::<(String, Vec<u8>)>();
+    let spawned = std::thread::Builder::new()
+        .name("snapshots".to_string())
+        .spawn(move || {
+            for (name, json) in rx {
+                let path = dir.join(&name);
+                // Escritura atómica: un apagado a medias no deja un JSON roto.
+                let written = std::fs::create_dir_all(&dir).and_then(|()| {
+                    let tmp = path.with_extension("json.tmp");
+                    std::fs::write(&tmp, json)?;
+                    std::fs::rename(&tmp, &path)
+                });
+                if let Err(e) = written {
+                    warn!("No se pudo guardar {}: {e}", path.display());
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Sin escritor de fotos ({e}): solo en memoria");
+    }
+    tx
+}
+
+/// Valor de la cabecera `ETag` para una foto.
+pub fn etag_header(snapshot: &Snapshot) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", snapshot.etag)).expect("etag hexadecimal")
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    /// Etag de una respuesta anterior o fecha (RFC 3339 / segundos Unix).
+    since: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceChange {
+    pub id: u64,
+    pub name: String,
+    pub old_price: i64,
+    pub new_price: i64,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffResponse {
+    ok: bool,
+    user_id: u64,
+    /// Etag de la foto de partida.
+    since: String,
+    /// Etag de la lista actual: el `since` de la próxima consulta.
+    etag: String,
+    /// RFC 3339.
+    taken_at: String,
+    added: Vec<SnapshotPass>,
+    removed: Vec<SnapshotPass>,
+    price_changed: Vec<PriceChange>,
+}
+
+fn diff(user_id: u64, base: &Snapshot, current: &Snapshot) -> DiffResponse {
+    let old: HashMap<u64, &SnapshotPass> = base.passes.iter().map(|p| (p.id, p)).collect();
+    let new: HashMap<u64, &SnapshotPass> = current.passes.iter().map(|p| (p.id, p)).collect();
+
+    let mut added = Vec::new();
+    let mut price_changed = Vec::new();
+    for pass in &current.passes {
+        match old.get(&pass.id) {
+            None => added.push(pass.clone()),
+            Some(before) if before.price != pass.price => price_changed.push(PriceChange {
+                id: pass.id,
+                name: pass.name.clone(),
+                old_price: before.price,
+                new_price: pass.price,
+            }),
+            Some(_) => {}
+        }
+    }
+    let removed = base
+        .passes
+        .iter()
+        .filter(|p| !new.contains_key(&p.id))
+        .cloned()
+        .collect();
+
+    DiffResponse {
+        ok: true,
+        user_id,
+        since: base.etag.clone(),
+        etag: current.etag.clone(),
+        taken_at: current.taken_at.to_rfc3339(),
+        added,
+        removed,
+        price_changed,
+    }
+}
+
+fn snapshot_not_found(user_id: u64, since: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::NOT_FOUND,
+        "SNAPSHOT_NOT_FOUND",
+        format!("No hay ninguna foto de userId {user_id} para '{since}'; pide la lista completa"),
+    )
+}
+
+/// `GET /user/:id/passes/diff?since=<etag|fecha>`
+pub async fn get_diff(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<u64>,
+    Query(query): Query<DiffQuery>,
+    MaybeTenant(tenant): MaybeTenant,
+) -> Result<Response, ApiError> {
+    info!("/user/{user_id}/passes/diff since={}", query.since);
+    if !state.is_watched(user_id) {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "USER_NOT_WATCHED",
+            format!(
+                "Solo hay diff de usuarios vigilados (POST /watch); userId {user_id} no lo está"
+            ),
+        ));
+    }
+    // Se busca antes de escanear: la foto nueva no puede servir de partida.
+    let base = state
+        .snapshots
+        .find(user_id, &query.since)
+        .ok_or_else(|| snapshot_not_found(user_id, &query.since))?;
+
+    let (_, current, status) =
+        match client::budgeted_full_list(&state, user_id, tenant.as_ref()).await {
+            Ok(list) => list,
+            Err(exhausted) => return Ok(exhausted),
+        };
+    // Contra una lista a medias, lo que falta saldría como retirado.
+    let current = current.ok_or_else(|| {
+        ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "SCAN_INCOMPLETE",
+            format!("No se pudo escanear entera la lista de userId {user_id}; reintenta en unos segundos"),
+        )
+    })?;
+
+    let etag = etag_header(&current);
+    let mut response = Json(diff(user_id, &base, &current)).into_response();
+    response.headers_mut().insert(header::ETAG, etag);
+    status.apply(response.headers_mut());
+    Ok(response)
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotSummary {
+    etag: String,
+    /// RFC 3339.
+    taken_at: String,
+    count: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotListResponse {
+    ok: bool,
+    user_id: u64,
+    /// De la más reciente a la más antigua.
+    snapshots: Vec<SnapshotSummary>,
+}
+
+/// `GET /user/:id/passes/snapshots`: fotos guardadas de un usuario.
+pub async fn list_snapshots(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<u64>,
+) -> Json<SnapshotListResponse> {
+    let snapshots = state
+        .snapshots
+        .list(user_id)
+        .iter()
+        .map(|s| SnapshotSummary {
+            etag: s.etag.clone(),
+            taken_at: s.taken_at.to_rfc3339(),
+            count: s.passes.len(),
+        })
+        .collect();
+    Json(SnapshotListResponse {
+        ok: true,
+        user_id,
+        snapshots,
+    })
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotResponse {
+    ok: bool,
+    user_id: u64,
+    etag: String,
+    /// RFC 3339.
+    taken_at: String,
+    count: usize,
+    passes: Vec<SnapshotPass>,
+}
+
+/// `GET /user/:id/passes/snapshots/:since`: una foto concreta (por etag o
+/// fecha), para volver a mostrar una lista anterior.
+pub async fn get_snapshot(
+    State(state): State<Arc<AppState>>,
+    Path((user_id, since)): Path<(u64, String)>,
+) -> Result<Response, ApiError> {
+    let snapshot = state
+        .snapshots
+        .find(user_id, &since)
+        .ok_or_else(|| snapshot_not_found(user_id, &since))?;
+    let etag = etag_header(&snapshot);
+    let mut response = Json(SnapshotResponse {
+        ok: true,
+        user_id,
+        etag: snapshot.etag.clone(),
+        taken_at: snapshot.taken_at.to_rfc3339(),
+        count: snapshot.passes.len(),
+        passes: snapshot.passes.clone(),
+    })
+    .into_response();
+    response.headers_mut().insert(header::ETAG, etag);
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(etag: &str, passes: &[(u64, i64)]) -> Snapshot {
+        Snapshot {
+            etag: etag.to_string(),
+            taken_at: Utc::now(),
+            passes: passes
+                .iter()
+                .map(|&(id, price)| SnapshotPass {
+                    id,
+                    name: format!("Pass {id}"),
+                    price,
+                })
+                .collect(),
+        }
+    }
+
+    fn ids(passes: &[SnapshotPass]) -> Vec<u64> {
+        passes.iter().map(|p| p.id).collect()
+    }
+
+    #[test]
+    fn diff_separates_added_removed_and_repriced() {
+        let base = snapshot("a", &[(1, 10), (2, 50), (3, 100)]);
+        let current = snapshot("b", &[(1, 10), (2, 75), (4, 5)]);
+        let delta = diff(7, &base, &current);
+        assert_eq!((delta.since.as_str(), delta.etag.as_str()), ("a", "b"));
+        assert_eq!(ids(&delta.added), [4]);
+        assert_eq!(ids(&delta.removed), [3]);
+        let changes: Vec<_> = delta
+            .price_changed
+            .iter()
+            .map(|c| (c.id, c.old_price, c.new_price))
+            .collect();
+        assert_eq!(changes, [(2, 50, 75)]);
+    }
+
+    #[test]
+    fn a_price_change_is_not_a_removal() {
+        let delta = diff(7, &snapshot("a", &[(1, 10)]), &snapshot("b", &[(1, 20)]));
+        assert!(delta.added.is_empty() && delta.removed.is_empty());
+        assert_eq!(delta.price_changed.len(), 1);
+
+        let same = snapshot("a", &[(1, 10)]);
+        let delta = diff(7, &same, &same);
+        assert!(
+            delta.added.is_empty() && delta.removed.is_empty() && delta.price_changed.is_empty()
+        );
+    }
+}
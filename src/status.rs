@@ -0,0 +1,132 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{extract::State, response::Html};
+
+use crate::{
+    upstream::{HostStats, Outcome},
+    AppState,
+};
+
+/// Página HTML mínima en `/`: versión, uptime y salud de los upstreams.
+/// Todo se renderiza en el servidor, sin CSS/JS externos.
+pub async fn status_page(State(state): State<Arc<AppState>>) -> Html<String> {
+    let upstreams = state.upstreams.snapshot();
+    let all_healthy = upstreams
+        .iter()
+        .all(|(_, stats)| stats.cooldown.is_none() && stats.healthy() != Some(false));
+
+    let mut rows = String::new();
+    for (upstream, stats) in &upstreams {
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape(upstream.host()),
+            health_class(stats),
+            health_label(stats),
+            stats.calls,
+            stats.failures,
+            last_outcome(stats),
+            stats
+                .last_success
+                .map(|t| format!("hace {}", format_duration(t.elapsed())))
+                .unwrap_or_else(|| "nunca".to_string()),
+        ));
+    }
+
+    let (overall_class, overall_label) = if all_healthy {
+        ("ok", "Operativo")
+    } else {
+        ("err", "Degradado")
+    };
+
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="es">
+<head>
+<meta charset="utf-8">
+<title>donations_api · estado</title>
+<style>
+body {{ font-family: system-ui, sans-serif; margin: 2rem; color: #222; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ccc; padding: .3rem .7rem; text-align: left; }}
+.ok {{ color: #1a7f37; font-weight: bold; }}
+.err {{ color: #cf222e; font-weight: bold; }}
+.unk {{ color: #777; }}
+</style>
+</head>
+<body>
+<h1>donations_api <span class="{overall_class}">{overall_label}</span></h1>
+<p>Versión <b>{version}</b> · uptime <b>{uptime}</b></p>
+<h2>Upstreams</h2>
+<table>
+<tr><th>Host</th><th>Estado</th><th>Llamadas</th><th>Errores</th><th>Última respuesta</th><th>Último éxito</th></tr>
+{rows}</table>
+</body>
+</html>
+"#,
+        version = env!("CARGO_PKG_VERSION"),
+        uptime = format_duration(state.started_at.elapsed()),
+    ))
+}
+
+fn health_class(stats: &HostStats) -> &'static str {
+    if stats.cooldown.is_some() {
+        return "err";
+    }
+    match stats.healthy() {
+        Some(true) => "ok",
+        Some(false) => "err",
+        None => "unk",
+    }
+}
+
+fn health_label(stats: &HostStats) -> String {
+    if let Some(cooldown) = stats.cooldown {
+        return format!(
+            "Mantenimiento (sondeo en {})",
+            format_duration(cooldown.until.saturating_duration_since(Instant::now()))
+        );
+    }
+    match stats.healthy() {
+        Some(true) => "OK",
+        Some(false) => "Fallando",
+        None => "Sin datos",
+    }
+    .to_string()
+}
+
+fn last_outcome(stats: &HostStats) -> String {
+    match stats.last {
+        Some((Outcome::Status(code), at)) => {
+            format!("HTTP {code} (hace {})", format_duration(at.elapsed()))
+        }
+        Some((Outcome::Transport, at)) => {
+            format!("error de red (hace {})", format_duration(at.elapsed()))
+        }
+        None => "-".to_string(),
+    }
+}
+
+/// Formatea una duración como `1d 2h 3m 4s`, omitiendo las unidades mayores en cero.
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (days, hours, mins, secs) = (secs / 86_400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    if days > 0 {
+        format!("{days}d {hours}h {mins}m {secs}s")
+    } else if hours > 0 {
+        format!("{hours}h {mins}m {secs}s")
+    } else if mins > 0 {
+        format!("{mins}m {secs}s")
+    } else {
+        format!("{secs}s")
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
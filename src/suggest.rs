@@ -0,0 +1,173 @@
+//! Sugerencia de passes por importe (`/user/:id/passes/suggest`): a cada
+//! cantidad pedida le corresponde el pass de precio más cercano, para que los
+//! juegos con botones de donación por niveles ("10", "50", "100"...) no tengan
+//! que repetir esa lógica en Lua.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    error::ApiError,
+    extract::Query,
+    roblox::client,
+    snapshots::{Snapshot, SnapshotPass},
+    tenant::MaybeTenant,
+    AppState,
+};
+
+/// Importes aceptados por consulta.
+const MAX_AMOUNTS: usize = 50;
+
+#[derive(Deserialize)]
+pub struct SuggestQuery {
+    /// Lista separada por comas, p. ej. `10,50,100,500`.
+    amounts: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    pub amount: i64,
+    /// `null` si el usuario no tiene passes.
+    pub pass: Option<SnapshotPass>,
+    /// El precio coincide exactamente con el importe.
+    pub exact: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestResponse {
+    ok: bool,
+    user_id: u64,
+    /// En el mismo orden que `amounts`.
+    suggestions: Vec<Suggestion>,
+}
+
+fn parse_amounts(raw: &str) -> Result<Vec<i64>, ApiError> {
+    let invalid = || {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_QUERY",
+            format!("amounts debe ser una lista de hasta {MAX_AMOUNTS} enteros positivos separados por comas"),
+        )
+    };
+    let amounts: Vec<i64> = raw
+        .split(',')
+        .map(|a| a.trim().parse::<i64>().ok().filter(|a| *a > 0))
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    if amounts.len() > MAX_AMOUNTS {
+        return Err(invalid());
+    }
+    Ok(amounts)
+}
+
+/// Pass de precio más cercano a `amount`; ante un empate, el más barato
+/// (mejor quedarse corto que cobrar de más).
+fn closest(passes: &[SnapshotPass], amount: i64) -> Option<&SnapshotPass> {
+    passes
+        .iter()
+        .min_by_key(|p| (p.price.abs_diff(amount), p.price))
+}
+
+/// `GET /user/:id/passes/suggest?amounts=10,50,100`
+pub async fn suggest(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<u64>,
+    Query(query): Query<SuggestQuery>,
+    MaybeTenant(tenant): MaybeTenant,
+) -> Result<Response, ApiError> {
+    let amounts = parse_amounts(&query.amounts)?;
+    info!("/user/{user_id}/passes/suggest amounts={amounts:?}");
+
+    let list = client::budgeted_full_list(&state, user_id, tenant.as_ref()).await;
+    let (passes, snapshot, status) = match list {
+        Ok(list) => list,
+        Err(exhausted) => return Ok(exhausted),
+    };
+    // Un escaneo a medias no deja foto, pero lo encontrado sirve igual.
+    let snapshot = snapshot.unwrap_or_else(|| Arc::new(Snapshot::new(&passes)));
+    let suggestions = amounts
+        .into_iter()
+        .map(|amount| {
+            let pass = closest(&snapshot.passes, amount).cloned();
+            Suggestion {
+                amount,
+                exact: pass.as_ref().is_some_and(|p| p.price == amount),
+                pass,
+            }
+        })
+        .collect();
+
+    let mut response = Json(SuggestResponse {
+        ok: true,
+        user_id,
+        suggestions,
+    })
+    .into_response();
+    status.apply(response.headers_mut());
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passes(prices: &[(u64, i64)]) -> Vec<SnapshotPass> {
+        prices
+            .iter()
+            .map(|&(id, price)| SnapshotPass {
+                id,
+                name: format!("Pass {id}"),
+                price,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn closest_prefers_the_exact_price_then_the_nearest() {
+        let passes = passes(&[(1, 10), (2, 50), (3, 100)]);
+        assert_eq!(closest(&passes, 50).map(|p| p.id), Some(2));
+        assert_eq!(closest(&passes, 60).map(|p| p.id), Some(2));
+        assert_eq!(closest(&passes, 1000).map(|p| p.id), Some(3));
+        assert!(closest(&[], 10).is_none());
+    }
+
+    #[test]
+    fn closest_breaks_ties_towards_the_cheaper_pass() {
+        // 30 está a 20 de los dos; el orden de la lista no importa.
+        assert_eq!(
+            closest(&passes(&[(1, 50), (2, 10)]), 30).map(|p| p.id),
+            Some(2)
+        );
+        assert_eq!(
+            closest(&passes(&[(2, 10), (1, 50)]), 30).map(|p| p.id),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn amounts_are_positive_integers_up_to_the_limit() {
+        assert_eq!(parse_amounts("10, 50,100").unwrap(), [10, 50, 100]);
+        for bad in ["", "10,", "0", "-5", "1.5", "diez"] {
+            let e = parse_amounts(bad).unwrap_err();
+            assert_eq!(
+                (e.status, e.code),
+                (StatusCode::BAD_REQUEST, "INVALID_QUERY"),
+                "{bad}"
+            );
+        }
+        let max = vec!["1"; MAX_AMOUNTS].join(",");
+        assert_eq!(parse_amounts(&max).unwrap().len(), MAX_AMOUNTS);
+        assert!(parse_amounts(&format!("{max},1")).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Supervisor de las tareas de fondo de larga duración.
+//!
+//! Cada tarea recibe un `CancellationToken` y debe terminar cuando se cancela.
+//! Si la tarea entra en pánico se relanza tras una espera creciente; si
+//! termina sola se da por acabada. Al apagar el servidor se cancelan todas y
+//! se espera a que terminen (`TaskTracker`).
+
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::{info, warn};
+
+use crate::backoff::{Backoff, Jitter};
+
+/// Espera antes de relanzar una tarea que entró en pánico.
+const RESTART_BACKOFF: Backoff = Backoff {
+    jitter: Jitter::Equal,
+    base: Duration::from_secs(1),
+    max: Duration::from_secs(60),
+};
+
+#[derive(Clone, Copy, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskState {
+    Running,
+    /// Cayó y está esperando para relanzarse.
+    Restarting,
+    /// Terminó por sí sola.
+    Finished,
+    /// Cancelada por el apagado.
+    Stopped,
+}
+
+struct TaskStatus {
+    name: &'static str,
+    state: TaskState,
+    restarts: u32,
+    last_error: Option<String>,
+    since: Instant,
+}
+
+/// Estado de una tarea, para `/admin/tasks`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskReport {
+    pub name: &'static str,
+    pub state: TaskState,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    /// Segundos en el estado actual.
+    pub since_secs: u64,
+}
+
+#[derive(Default)]
+pub struct Supervisor {
+    tracker: TaskTracker,
+    token: CancellationToken,
+    tasks: Mutex<Vec<Arc<Mutex<TaskStatus>>>>,
+}
+
+impl Supervisor {
+    /// Lanza `make(token)` bajo supervisión. `make` se vuelve a llamar cada
+    /// vez que haya que relanzar la tarea.
+    pub fn spawn<F, Fut>(&self, name: &'static str, make: F)
+    where
+        F: Fn(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let status = Arc::new(Mutex::new(TaskStatus {
+            name,
+            state: TaskState::Running,
+            restarts: 0,
+            last_error: None,
+            since: Instant::now(),
+        }));
+        self.tasks.lock().unwrap().push(status.clone());
+
+        let token = self.token.clone();
+        self.tracker.spawn(async move {
+            let set_state = |state| {
+                let mut status = status.lock().unwrap();
+                status.state = state;
+                status.since = Instant::now();
+            };
+            let mut crashes = 0;
+            let mut prev_delay = None;
+            loop {
+                let started = Instant::now();
+                set_state(TaskState::Running);
+                let result = tokio::spawn(make(token.clone())).await;
+                if token.is_cancelled() {
+                    set_state(TaskState::Stopped);
+                    return;
+                }
+                let Err(e) = result else {
+                    info!("{name} terminó");
+                    set_state(TaskState::Finished);
+                    return;
+                };
+
+                // Si llevaba un buen rato en marcha, la caída no es "seguida".
+                if started.elapsed() > RESTART_BACKOFF.max {
+                    crashes = 0;
+                    prev_delay = None;
+                }
+                crashes += 1;
+                let delay = RESTART_BACKOFF.delay(crashes, prev_delay);
+                prev_delay = Some(delay);
+                warn!("{name} cayó ({e}), relanzando en {}ms", delay.as_millis());
+                {
+                    let mut status = status.lock().unwrap();
+                    status.restarts += 1;
+                    status.last_error = Some(e.to_string());
+                }
+                set_state(TaskState::Restarting);
+                tokio::select! {
+                    _ = token.cancelled() => {
+                        set_state(TaskState::Stopped);
+                        return;
+                    }
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+        });
+    }
+
+    /// Cancela todas las tareas y espera a que terminen.
+    pub async fn shutdown(&self) {
+        self.token.cancel();
+        self.tracker.close();
+        self.tracker.wait().await;
+    }
+
+    pub fn reports(&self) -> Vec<TaskReport> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|status| {
+                let status = status.lock().unwrap();
+                TaskReport {
+                    name: status.name,
+                    state: status.state,
+                    restarts: status.restarts,
+                    last_error: status.last_error.clone(),
+                    since_secs: status.since.elapsed().as_secs(),
+                }
+            })
+            .collect()
+    }
+}
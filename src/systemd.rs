@@ -0,0 +1,34 @@
+//! Integración con systemd (`Type=notify`): `READY=1` cuando el servicio ya
+//! puede atender peticiones y `WATCHDOG=1` periódicos si la unidad tiene
+//! `WatchdogSec=`. Fuera de systemd (sin `NOTIFY_SOCKET`) no hace nada.
+
+use std::time::Duration;
+
+use sd_notify::NotifyState;
+use tracing::warn;
+
+fn notify(states: &[NotifyState]) {
+    if let Err(e) = sd_notify::notify(false, states) {
+        warn!("No se pudo notificar a systemd: {e}");
+    }
+}
+
+/// Listener abierto y arranque terminado.
+pub fn ready(status: &str) {
+    notify(&[NotifyState::Ready, NotifyState::Status(status)]);
+}
+
+pub fn stopping() {
+    notify(&[NotifyState::Stopping]);
+}
+
+pub fn watchdog() {
+    notify(&[NotifyState::Watchdog]);
+}
+
+/// Cada cuánto enviar `WATCHDOG=1`: la mitad del plazo configurado en la
+/// unidad, como recomienda systemd. `None` si el watchdog no está activo.
+pub fn watchdog_interval() -> Option<Duration> {
+    let mut usec = 0;
+    sd_notify::watchdog_enabled(false, &mut usec).then(|| Duration::from_micros(usec) / 2)
+}
@@ -0,0 +1,239 @@
+//! Claves de API de los tenants (`API_KEYS`) y el extractor que las exige.
+//!
+//! Formato: `API_KEYS=id:clave[:maxWatches[:maxAgeSecs[:freshPerMinute[:rps]]]],...`,
+//! p. ej. `API_KEYS=booth:k1::600,dashboard:k2:200:30:120:5`. El `id` es lo que aparece
+//! en logs y reportes; la clave solo viaja en la cabecera `X-Api-Key`.
+//! `API_KEYS_FILE` añade las de un archivo, una entrada por línea (con `#`
+//! para comentarios), para no dejar las claves en el entorno.
+//!
+//! Con `REQUIRE_API_KEY=true`, `require` rechaza las peticiones anónimas
+//! salvo a las rutas abiertas (`OPEN_PATHS`) y, con
+//! `API_KEY_BYPASS_LOOPBACK=true`, las que llegan desde la propia máquina
+//! (desarrollo local).
+
+use std::{net::SocketAddr, path::Path, sync::Arc, time::Duration};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, State},
+    http::{request::Parts, HeaderMap, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::{info, warn};
+
+use crate::{admin::constant_time_eq, error::ApiError, AppState};
+
+pub const HEADER: &str = "x-api-key";
+
+/// Rutas que no piden clave con `REQUIRE_API_KEY`: la portada, las sondas
+/// de salud, el esquema y lo que ya va con el token de administración.
+const OPEN_PATHS: &[&str] = &[
+    "/",
+    "/healthz",
+    "/healthz/deep",
+    "/readyz",
+    "/ping",
+    "/time",
+    "/metrics",
+];
+const OPEN_PREFIXES: &[&str] = &["/admin/", "/schema"];
+
+/// Una clave configurada.
+#[derive(Clone, Debug)]
+pub struct ApiKey {
+    pub id: String,
+    pub key: String,
+    /// Máximo de usuarios vigilados; `None` usa `WATCH_LIMIT_PER_KEY`.
+    pub max_watches: Option<usize>,
+    /// Antigüedad aceptable de los datos en caché para esta clave; `None`
+    /// usa `CACHE_TTL_SECS`.
+    pub max_age: Option<Duration>,
+    /// Escaneos frescos por minuto (0 = sin límite); `None` usa
+    /// `FRESH_FETCHES_PER_MINUTE`.
+    pub fresh_per_minute: Option<u32>,
+    /// Peticiones por segundo a las rutas que llaman a Roblox (0 = sin
+    /// límite); `None` usa `RATE_LIMIT_RPS`, por IP (ver `ratelimit`).
+    pub rps: Option<f64>,
+}
+
+/// Campo numérico opcional de una entrada; vacío o ausente es `None`.
+fn optional_field<T: std::str::FromStr>(id: &str, field: &str, raw: Option<&str>) -> Option<T> {
+    let raw = raw.filter(|r| !r.is_empty())?;
+    match raw.parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            warn!("API_KEYS: {field} inválido para '{id}', ignorado");
+            None
+        }
+    }
+}
+
+/// Parsea `API_KEYS`. Las entradas mal formadas se descartan con un aviso.
+pub fn parse_api_keys(raw: &str) -> Vec<ApiKey> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let mut parts = entry.split(':');
+            let id = parts.next().unwrap_or_default();
+            let key = parts.next().unwrap_or_default();
+            if id.is_empty() || key.is_empty() {
+                warn!("API_KEYS: entrada '{id}' sin id o clave, ignorada");
+                return None;
+            }
+            let max_watches = optional_field(id, "maxWatches", parts.next());
+            let max_age = optional_field(id, "maxAgeSecs", parts.next()).map(Duration::from_secs);
+            let fresh_per_minute = optional_field(id, "freshPerMinute", parts.next());
+            let rps = optional_field(id, "rps", parts.next()).filter(|rps: &f64| *rps >= 0.0);
+            Some(ApiKey {
+                id: id.to_string(),
+                key: key.to_string(),
+                max_watches,
+                max_age,
+                fresh_per_minute,
+                rps,
+            })
+        })
+        .collect()
+}
+
+/// Claves de `API_KEYS` y de `API_KEYS_FILE`. Un id repetido se queda con
+/// la primera entrada.
+pub fn load_api_keys(raw: Option<&str>, file: Option<&Path>) -> Vec<ApiKey> {
+    let mut keys = raw.map(parse_api_keys).unwrap_or_default();
+    if let Some(path) = file {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                let entries: Vec<&str> = contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.starts_with('#'))
+                    .collect();
+                let loaded = parse_api_keys(&entries.join(","));
+                info!(
+                    "{} claves de API leídas de {}",
+                    loaded.len(),
+                    path.display()
+                );
+                keys.extend(loaded);
+            }
+            Err(e) => warn!("API_KEYS_FILE: no se pudo leer {}: {e}", path.display()),
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    keys.retain(|k| {
+        let first = seen.insert(k.id.clone());
+        if !first {
+            warn!(
+                "API_KEYS: id '{}' repetido, se usa la primera entrada",
+                k.id
+            );
+        }
+        first
+    });
+    keys
+}
+
+/// Clave de la cabecera `X-Api-Key`: `Ok(None)` si no viene, error si viene
+/// y no es válida.
+fn authenticate(parts: &Parts, keys: &[ApiKey]) -> Result<Option<ApiKey>, ApiError> {
+    if !parts.headers.contains_key(HEADER) {
+        return Ok(None);
+    }
+    identify(&parts.headers, keys)
+        .map(|k| Some(k.clone()))
+        .ok_or_else(invalid_key)
+}
+
+/// Clave configurada que corresponde a la cabecera `X-Api-Key`, si viene y
+/// es válida.
+pub fn identify<'a>(headers: &HeaderMap, keys: &'a [ApiKey]) -> Option<&'a ApiKey> {
+    let provided = headers.get(HEADER)?.to_str().unwrap_or_default();
+    keys.iter()
+        .find(|k| constant_time_eq(provided.as_bytes(), k.key.as_bytes()))
+}
+
+fn invalid_key() -> ApiError {
+    ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        "INVALID_API_KEY",
+        "Clave de API inválida o ausente (cabecera X-Api-Key)",
+    )
+}
+
+/// Extractor que exige una `X-Api-Key` válida y devuelve su tenant.
+pub struct Tenant(pub ApiKey);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for Tenant {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let keys = &state.config.api_keys;
+        if keys.is_empty() {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "API_KEYS_DISABLED",
+                "Endpoint deshabilitado: no hay claves de API configuradas (API_KEYS)",
+            ));
+        }
+        authenticate(parts, keys)?
+            .map(Tenant)
+            .ok_or_else(invalid_key)
+    }
+}
+
+/// Para endpoints públicos: el tenant si la petición trae `X-Api-Key` (que
+/// entonces tiene que ser válida), `None` si es anónima.
+pub struct MaybeTenant(pub Option<ApiKey>);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for MaybeTenant {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let keys = &state.config.api_keys;
+        // Sin claves configuradas la cabecera no significa nada.
+        if keys.is_empty() {
+            return Ok(MaybeTenant(None));
+        }
+        authenticate(parts, keys).map(MaybeTenant)
+    }
+}
+
+fn is_open(path: &str) -> bool {
+    OPEN_PATHS.contains(&path) || OPEN_PREFIXES.iter().any(|p| path.starts_with(p))
+}
+
+/// Middleware (`REQUIRE_API_KEY`): 401 sin una `X-Api-Key` válida, salvo en
+/// las rutas abiertas y, con `API_KEY_BYPASS_LOOPBACK`, desde loopback.
+pub async fn require<B>(
+    State(state): State<Arc<AppState>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let config = &state.config;
+    if !config.require_api_key || is_open(req.uri().path()) {
+        return next.run(req).await;
+    }
+    if config.api_key_bypass_loopback {
+        let loopback = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .is_some_and(|ConnectInfo(addr)| addr.ip().is_loopback());
+        if loopback {
+            return next.run(req).await;
+        }
+    }
+    if identify(req.headers(), &config.api_keys).is_none() {
+        return invalid_key().into_response();
+    }
+    next.run(req).await
+}
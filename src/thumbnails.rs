@@ -0,0 +1,113 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use tracing::{debug, info, warn};
+
+use crate::{
+    upstream::{self, Endpoint, Upstream},
+    AppState,
+};
+
+/// Máximo de ids por llamada a `thumbnails.roblox.com/v1/game-passes`.
+const BATCH_SIZE: usize = 100;
+/// Tope de iconos recordados; al llenarse se vacía entero.
+const MAX_CACHED_ICONS: usize = 50_000;
+
+/// Iconos ya renderizados (`state: Completed`), por id de pass.
+///
+/// Los renders pendientes o fallidos no se guardan: se vuelven a pedir la
+/// próxima vez que una respuesta los necesite.
+#[derive(Default)]
+pub struct IconCache {
+    icons: Mutex<HashMap<u64, String>>,
+}
+
+impl IconCache {
+    fn get_many(&self, ids: &[u64]) -> HashMap<u64, String> {
+        let icons = self.icons.lock().unwrap();
+        ids.iter()
+            .filter_map(|id| icons.get(id).map(|url| (*id, url.clone())))
+            .collect()
+    }
+
+    fn insert_many(&self, resolved: &HashMap<u64, String>) {
+        let mut icons = self.icons.lock().unwrap();
+        if icons.len() + resolved.len() > MAX_CACHED_ICONS {
+            icons.clear();
+        }
+        icons.extend(resolved.iter().map(|(id, url)| (*id, url.clone())));
+    }
+}
+
+/// Resuelve los iconos de `pass_ids` con una llamada por cada 100 ids que no
+/// estén ya en caché. Los que no estén listos simplemente no aparecen.
+pub async fn resolve_icons(state: &AppState, pass_ids: &[u64]) -> HashMap<u64, String> {
+    let mut icons = state.icons.get_many(pass_ids);
+    let missing: Vec<u64> = pass_ids
+        .iter()
+        .filter(|id| !icons.contains_key(id))
+        .copied()
+        .collect();
+
+    for chunk in missing.chunks(BATCH_SIZE) {
+        let ids: Vec<String> = chunk.iter().map(u64::to_string).collect();
+        let url = format!(
+            "{}/v1/game-passes?gamePassIds={}&size=150x150&format=Png&isCircular=false",
+            state.config.upstream_url(Upstream::Thumbnails),
+            ids.join(",")
+        );
+        debug!("Pidiendo {} iconos de gamepasses", chunk.len());
+
+        let resp = match upstream::get(state, Endpoint::GamePassIcons, &url).await {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Error HTTP al pedir iconos: {e}");
+                continue;
+            }
+        };
+        if !resp.status().is_success() {
+            warn!("Iconos HTTP {}", resp.status());
+            continue;
+        }
+        let json: serde_json::Value = match resp.json().await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Error parseando JSON de iconos: {e}");
+                continue;
+            }
+        };
+        let Some(items) = json.get("data").and_then(|v| v.as_array()) else {
+            info!("Iconos sin 'data'");
+            state
+                .quarantine
+                .record(Endpoint::GamePassIcons, "listado sin 'data'", &json);
+            continue;
+        };
+
+        let mut resolved = HashMap::new();
+        let mut not_ready = 0;
+        for item in items {
+            let id = item.get("targetId").and_then(|v| v.as_u64());
+            if id.is_none() {
+                state
+                    .quarantine
+                    .record(Endpoint::GamePassIcons, "icono sin targetId", item);
+            }
+            let completed = item.get("state").and_then(|v| v.as_str()) == Some("Completed");
+            let image = item.get("imageUrl").and_then(|v| v.as_str());
+            match (id, completed, image) {
+                (Some(id), true, Some(image)) if !image.is_empty() => {
+                    resolved.insert(id, image.to_string());
+                }
+                _ => not_ready += 1,
+            }
+        }
+        if not_ready > 0 {
+            info!("{not_ready} iconos aún sin render; se reintentarán en otra petición");
+        }
+
+        state.icons.insert_many(&resolved);
+        icons.extend(resolved);
+    }
+
+    icons
+}
@@ -0,0 +1,40 @@
+//! Tiempo máximo por ruta. Las rutas se agrupan por lo que cuestan al montar
+//! el router: escaneos completos (`ROUTE_TIMEOUT_SCAN_SECS`), rutas con
+//! alguna llamada a Roblox (`ROUTE_TIMEOUT_UPSTREAM_SECS`) y el resto, que
+//! solo leen estado local (`ROUTE_TIMEOUT_SECS`). Pasado el tiempo se
+//! abandona el handler y se responde 504.
+
+use std::time::Duration;
+
+use axum::{
+    extract::State,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::warn;
+
+use crate::error::ApiError;
+
+/// Middleware: corta el handler si tarda más de `limit`.
+pub async fn enforce<B>(State(limit): State<Duration>, req: Request<B>, next: Next<B>) -> Response {
+    let path = req.uri().path().to_string();
+    match tokio::time::timeout(limit, next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!(
+                "{path} superó su límite de {}s, abandonada",
+                limit.as_secs()
+            );
+            ApiError::new(
+                StatusCode::GATEWAY_TIMEOUT,
+                "REQUEST_TIMEOUT",
+                format!(
+                    "La petición superó el límite de {}s de esta ruta",
+                    limit.as_secs()
+                ),
+            )
+            .into_response()
+        }
+    }
+}
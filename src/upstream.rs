@@ -0,0 +1,1040 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use reqwest::{header, StatusCode};
+use tracing::{info, warn};
+
+use crate::{
+    backoff::Backoff,
+    config::Config,
+    limiter::Permit,
+    outbound_tags,
+    recording::{self, Mode},
+    AppState,
+};
+
+/// Ventana móvil para tasas de error y percentiles de latencia.
+pub const STATS_WINDOW: Duration = Duration::from_secs(300);
+/// Máximo de muestras por endpoint dentro de la ventana.
+const MAX_SAMPLES: usize = 10_000;
+/// Muestras mínimas en la ventana para fiarse del p95 al decidir un hedge.
+const HEDGE_MIN_SAMPLES: usize = 20;
+/// Últimas llamadas guardadas para los informes de fallos.
+const RECENT_CALLS: usize = 20;
+/// Cada cuánto se recalcula el p95 usado para los hedges.
+const HEDGE_P95_REFRESH: Duration = Duration::from_secs(1);
+/// Conexiones libres que se conservan tras este tiempo sin uso se cierran.
+pub(crate) const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Keepalive TCP de las conexiones del pool.
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+/// Un `Retry-After` más largo que esto no se espera: se devuelve la
+/// respuesta tal cual en lugar de retener la petición del cliente.
+const RETRY_AFTER_MAX: Duration = Duration::from_secs(5);
+
+/// APIs de Roblox de las que depende el servicio.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Upstream {
+    Games,
+    Economy,
+    Catalog,
+    Thumbnails,
+    Inventory,
+    Users,
+    Groups,
+    Apis,
+}
+
+impl Upstream {
+    pub const ALL: [Upstream; 8] = [
+        Upstream::Games,
+        Upstream::Economy,
+        Upstream::Catalog,
+        Upstream::Thumbnails,
+        Upstream::Inventory,
+        Upstream::Users,
+        Upstream::Groups,
+        Upstream::Apis,
+    ];
+
+    /// Host de Roblox. Es el nombre del upstream en registros y métricas
+    /// aunque `UPSTREAM_*_URL` lo mande a otra parte.
+    pub fn host(self) -> &'static str {
+        match self {
+            Upstream::Games => "games.roblox.com",
+            Upstream::Economy => "economy.roblox.com",
+            Upstream::Catalog => "catalog.roblox.com",
+            Upstream::Thumbnails => "thumbnails.roblox.com",
+            Upstream::Inventory => "inventory.roblox.com",
+            Upstream::Users => "users.roblox.com",
+            Upstream::Groups => "groups.roblox.com",
+            Upstream::Apis => "apis.roblox.com",
+        }
+    }
+
+    /// Subdominio de Roblox, el `{service}` de `UPSTREAM_BASE_URL`.
+    pub fn service(self) -> &'static str {
+        self.host()
+            .strip_suffix(".roblox.com")
+            .expect("host de roblox.com")
+    }
+
+    /// Variable con la URL base de este upstream, que pisa a
+    /// `UPSTREAM_BASE_URL`.
+    pub fn url_var(self) -> &'static str {
+        match self {
+            Upstream::Games => "UPSTREAM_GAMES_URL",
+            Upstream::Economy => "UPSTREAM_ECONOMY_URL",
+            Upstream::Catalog => "UPSTREAM_CATALOG_URL",
+            Upstream::Thumbnails => "UPSTREAM_THUMBNAILS_URL",
+            Upstream::Inventory => "UPSTREAM_INVENTORY_URL",
+            Upstream::Users => "UPSTREAM_USERS_URL",
+            Upstream::Groups => "UPSTREAM_GROUPS_URL",
+            Upstream::Apis => "UPSTREAM_APIS_URL",
+        }
+    }
+}
+
+/// Endpoints concretos de Roblox que consume el servicio.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Endpoint {
+    /// `games.roblox.com/v2/users/{id}/games`
+    UserGames,
+    /// `games.roblox.com/v2/games/{universeId}/game-passes`
+    GamePasses,
+    /// `games.roblox.com/v1/games?universeIds=...`
+    GamesMultiget,
+    /// `economy.roblox.com/v2/assets/{id}/details`
+    AssetDetails,
+    /// `catalog.roblox.com/v1/search/items/details`
+    CatalogSearch,
+    /// `thumbnails.roblox.com/v1/game-passes?gamePassIds=...`
+    GamePassIcons,
+    /// `inventory.roblox.com/v2/users/{id}/inventory/34`
+    UserInventory,
+    /// `users.roblox.com/v1/usernames/users` (POST)
+    UsernameLookup,
+    /// `groups.roblox.com/v1/users/{id}/groups/roles`
+    UserGroups,
+    /// `games.roblox.com/v2/groups/{groupId}/gamesV2`
+    GroupGames,
+    /// `apis.roblox.com/developer-products/v1/developer-products/list`
+    DeveloperProducts,
+}
+
+impl Endpoint {
+    pub const ALL: [Endpoint; 11] = [
+        Endpoint::UserGames,
+        Endpoint::GamePasses,
+        Endpoint::GamesMultiget,
+        Endpoint::AssetDetails,
+        Endpoint::CatalogSearch,
+        Endpoint::GamePassIcons,
+        Endpoint::UserInventory,
+        Endpoint::UsernameLookup,
+        Endpoint::UserGroups,
+        Endpoint::GroupGames,
+        Endpoint::DeveloperProducts,
+    ];
+
+    pub fn upstream(self) -> Upstream {
+        match self {
+            Endpoint::UserGames
+            | Endpoint::GamePasses
+            | Endpoint::GamesMultiget
+            | Endpoint::GroupGames => Upstream::Games,
+            Endpoint::AssetDetails => Upstream::Economy,
+            Endpoint::CatalogSearch => Upstream::Catalog,
+            Endpoint::GamePassIcons => Upstream::Thumbnails,
+            Endpoint::UserInventory => Upstream::Inventory,
+            Endpoint::UsernameLookup => Upstream::Users,
+            Endpoint::UserGroups => Upstream::Groups,
+            Endpoint::DeveloperProducts => Upstream::Apis,
+        }
+    }
+
+    pub fn path(self) -> &'static str {
+        match self {
+            Endpoint::UserGames => "/v2/users/{userId}/games",
+            Endpoint::GamePasses => "/v2/games/{universeId}/game-passes",
+            Endpoint::GamesMultiget => "/v1/games",
+            Endpoint::AssetDetails => "/v2/assets/{assetId}/details",
+            Endpoint::CatalogSearch => "/v1/search/items/details",
+            Endpoint::GamePassIcons => "/v1/game-passes",
+            Endpoint::UserInventory => "/v2/users/{userId}/inventory/34",
+            Endpoint::UsernameLookup => "/v1/usernames/users",
+            Endpoint::UserGroups => "/v1/users/{userId}/groups/roles",
+            Endpoint::GroupGames => "/v2/groups/{groupId}/gamesV2",
+            Endpoint::DeveloperProducts => "/developer-products/v1/developer-products/list",
+        }
+    }
+}
+
+/// Resultado de la última llamada a un upstream.
+#[derive(Clone, Copy)]
+pub enum Outcome {
+    /// Respuesta HTTP recibida (puede ser 2xx o no).
+    Status(u16),
+    /// Error de red / timeout: no hubo respuesta.
+    Transport,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct HostStats {
+    pub calls: u64,
+    pub failures: u64,
+    pub last: Option<(Outcome, Instant)>,
+    pub last_success: Option<Instant>,
+    /// Presente mientras el upstream está en mantenimiento / challenge.
+    pub cooldown: Option<Cooldown>,
+    /// Hasta cuándo se llama al espejo (`UPSTREAM_MIRROR_DOMAIN`) en lugar
+    /// de a Roblox, tras un challenge.
+    pub mirror_until: Option<Instant>,
+}
+
+/// Estado de enfriamiento: no se llama al upstream hasta `until`, salvo
+/// una petición de sondeo cuando vence.
+#[derive(Clone, Copy)]
+pub struct Cooldown {
+    pub since: Instant,
+    pub until: Instant,
+    pub interval: Duration,
+    pub strikes: u32,
+}
+
+impl HostStats {
+    /// `None` si todavía no hubo llamadas; si no, si la última fue exitosa.
+    pub fn healthy(&self) -> Option<bool> {
+        self.last.map(|(outcome, _)| is_success(outcome))
+    }
+}
+
+fn is_success(outcome: Outcome) -> bool {
+    matches!(outcome, Outcome::Status(code) if (200..300).contains(&code))
+}
+
+struct Sample {
+    at: Instant,
+    latency: Duration,
+    ok: bool,
+}
+
+/// Muestras recientes de un endpoint (ventana `STATS_WINDOW`).
+#[derive(Default)]
+struct EndpointWindow {
+    samples: VecDeque<Sample>,
+    /// Llamadas evitadas por cooldown dentro de la ventana.
+    short_circuited: VecDeque<Instant>,
+    /// p95 cacheado para los hedges: (calculado en, p95, muestras).
+    hedge_p95: Option<(Instant, Duration, usize)>,
+    /// Totales desde el arranque: hedges lanzados y cuántos respondieron antes.
+    hedges_fired: u64,
+    hedges_won: u64,
+}
+
+impl EndpointWindow {
+    fn prune(&mut self, now: Instant) {
+        while self
+            .samples
+            .front()
+            .is_some_and(|s| now.duration_since(s.at) > STATS_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+        while self
+            .short_circuited
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > STATS_WINDOW)
+        {
+            self.short_circuited.pop_front();
+        }
+    }
+}
+
+/// Tasa de error y latencias de un endpoint dentro de la ventana.
+pub struct EndpointReport {
+    pub endpoint: Endpoint,
+    pub calls: usize,
+    pub errors: usize,
+    pub short_circuited: usize,
+    pub p50: Option<Duration>,
+    pub p95: Option<Duration>,
+    pub hedges_fired: u64,
+    pub hedges_won: u64,
+}
+
+impl EndpointReport {
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.calls as f64
+        }
+    }
+}
+
+/// Percentil `p` (0..=100) por el método nearest-rank sobre datos ordenados.
+fn percentile(sorted: &[Duration], p: usize) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    Some(sorted[rank - 1])
+}
+
+/// Contadores por upstream, alimentados por cada llamada saliente.
+pub struct UpstreamHealth {
+    hosts: Mutex<HashMap<Upstream, HostStats>>,
+    endpoints: Mutex<HashMap<Endpoint, EndpointWindow>>,
+    /// Curva de espera entre sondeos de un upstream en mantenimiento.
+    cooldown_backoff: Backoff,
+    /// Últimas `RECENT_CALLS` llamadas, de cualquier endpoint.
+    recent: Mutex<VecDeque<RecentCall>>,
+    /// Challenges recibidos desde el arranque, por upstream y tipo.
+    challenges: Mutex<HashMap<(Upstream, String), u64>>,
+}
+
+/// Llamada reciente a Roblox, para dar contexto a un informe de fallo.
+#[derive(Clone)]
+pub struct RecentCall {
+    pub endpoint: Endpoint,
+    pub url: String,
+    pub outcome: Outcome,
+    pub latency: Duration,
+    pub at: Instant,
+}
+
+impl UpstreamHealth {
+    pub fn new(cooldown_backoff: Backoff) -> Self {
+        UpstreamHealth {
+            hosts: Mutex::default(),
+            endpoints: Mutex::default(),
+            cooldown_backoff,
+            recent: Mutex::default(),
+            challenges: Mutex::default(),
+        }
+    }
+
+    /// Decide si se puede llamar al upstream. En cooldown devuelve el tiempo
+    /// restante; al vencer deja pasar una sola llamada como sondeo y reprograma
+    /// el siguiente vencimiento para que el resto siga esperando.
+    pub fn admit(&self, upstream: Upstream) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut hosts = self.hosts.lock().unwrap();
+        let Some(cooldown) = hosts.get_mut(&upstream).and_then(|s| s.cooldown.as_mut()) else {
+            return Ok(());
+        };
+        if now < cooldown.until {
+            return Err(cooldown.until - now);
+        }
+        cooldown.until = now + cooldown.interval;
+        info!(
+            "Sondeando {} tras mantenimiento (intento {})",
+            upstream.host(),
+            cooldown.strikes + 1
+        );
+        Ok(())
+    }
+
+    pub fn record(
+        &self,
+        endpoint: Endpoint,
+        outcome: Outcome,
+        latency: Duration,
+        maintenance: bool,
+    ) {
+        let now = Instant::now();
+        {
+            let mut endpoints = self.endpoints.lock().unwrap();
+            let window = endpoints.entry(endpoint).or_default();
+            window.prune(now);
+            if window.samples.len() >= MAX_SAMPLES {
+                window.samples.pop_front();
+            }
+            window.samples.push_back(Sample {
+                at: now,
+                latency,
+                ok: is_success(outcome),
+            });
+        }
+
+        let upstream = endpoint.upstream();
+        let mut hosts = self.hosts.lock().unwrap();
+        let stats = hosts.entry(upstream).or_default();
+        stats.calls += 1;
+        if is_success(outcome) {
+            stats.last_success = Some(now);
+        } else {
+            stats.failures += 1;
+        }
+        stats.last = Some((outcome, now));
+
+        if maintenance {
+            let cooldown = match stats.cooldown {
+                Some(prev) => {
+                    let interval = self
+                        .cooldown_backoff
+                        .delay(prev.strikes + 1, Some(prev.interval));
+                    Cooldown {
+                        until: now + interval,
+                        interval,
+                        strikes: prev.strikes + 1,
+                        ..prev
+                    }
+                }
+                None => {
+                    let interval = self.cooldown_backoff.delay(1, None);
+                    Cooldown {
+                        since: now,
+                        until: now + interval,
+                        interval,
+                        strikes: 1,
+                    }
+                }
+            };
+            warn!(
+                "{} en mantenimiento/challenge, próximo sondeo en {}s",
+                upstream.host(),
+                cooldown.interval.as_secs()
+            );
+            stats.cooldown = Some(cooldown);
+        } else if matches!(outcome, Outcome::Status(_)) && stats.cooldown.take().is_some() {
+            info!("{} vuelve a responder, fin del cooldown", upstream.host());
+        }
+    }
+
+    /// Anota un challenge y, con `mirror_for`, pasa el upstream al espejo.
+    fn record_challenge(&self, upstream: Upstream, kind: &str, mirror_for: Option<Duration>) {
+        *self
+            .challenges
+            .lock()
+            .unwrap()
+            .entry((upstream, kind.to_string()))
+            .or_default() += 1;
+        let Some(mirror_for) = mirror_for else {
+            return;
+        };
+        warn!(
+            "{} pide challenge ({kind}), usando el espejo durante {}s",
+            upstream.host(),
+            mirror_for.as_secs()
+        );
+        let mut hosts = self.hosts.lock().unwrap();
+        hosts.entry(upstream).or_default().mirror_until = Some(Instant::now() + mirror_for);
+    }
+
+    /// Si las llamadas a `upstream` deben ir al espejo.
+    fn mirror_active(&self, upstream: Upstream) -> bool {
+        let mut hosts = self.hosts.lock().unwrap();
+        let Some(stats) = hosts.get_mut(&upstream) else {
+            return false;
+        };
+        match stats.mirror_until {
+            Some(until) if Instant::now() < until => true,
+            Some(_) => {
+                stats.mirror_until = None;
+                info!("{}: fin del espejo, volviendo a Roblox", upstream.host());
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Challenges por upstream y tipo, para `/metrics`.
+    pub fn challenge_counts(&self) -> Vec<(Upstream, String, u64)> {
+        let mut counts: Vec<_> = self
+            .challenges
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((upstream, kind), n)| (*upstream, kind.clone(), *n))
+            .collect();
+        counts.sort_by(|a, b| (a.0.host(), &a.1).cmp(&(b.0.host(), &b.1)));
+        counts
+    }
+
+    fn record_short_circuit(&self, endpoint: Endpoint) {
+        let now = Instant::now();
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let window = endpoints.entry(endpoint).or_default();
+        window.prune(now);
+        window.short_circuited.push_back(now);
+    }
+
+    /// p95 reciente del endpoint y número de muestras, recalculado como mucho
+    /// una vez por `HEDGE_P95_REFRESH`.
+    fn recent_p95(&self, endpoint: Endpoint) -> Option<(Duration, usize)> {
+        let now = Instant::now();
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let window = endpoints.entry(endpoint).or_default();
+        if let Some((at, p95, n)) = window.hedge_p95 {
+            if now.duration_since(at) < HEDGE_P95_REFRESH {
+                return Some((p95, n));
+            }
+        }
+        window.prune(now);
+        let mut latencies: Vec<Duration> = window.samples.iter().map(|s| s.latency).collect();
+        latencies.sort_unstable();
+        let p95 = percentile(&latencies, 95)?;
+        window.hedge_p95 = Some((now, p95, latencies.len()));
+        Some((p95, latencies.len()))
+    }
+
+    fn record_hedge(&self, endpoint: Endpoint, won: bool) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let window = endpoints.entry(endpoint).or_default();
+        window.hedges_fired += 1;
+        if won {
+            window.hedges_won += 1;
+        }
+    }
+
+    fn remember(&self, endpoint: Endpoint, url: &str, outcome: Outcome, latency: Duration) {
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() >= RECENT_CALLS {
+            recent.pop_front();
+        }
+        recent.push_back(RecentCall {
+            endpoint,
+            url: url.to_string(),
+            outcome,
+            latency,
+            at: Instant::now(),
+        });
+    }
+
+    /// Últimas llamadas a Roblox, de la más antigua a la más reciente.
+    pub fn recent_calls(&self) -> Vec<RecentCall> {
+        self.recent.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Descarta las muestras fuera de la ventana de todos los endpoints,
+    /// también de los que llevan rato sin llamadas.
+    pub fn prune(&self) {
+        let now = Instant::now();
+        let mut endpoints = self.endpoints.lock().unwrap();
+        for window in endpoints.values_mut() {
+            window.prune(now);
+        }
+    }
+
+    /// Tasa de error y p50/p95 por endpoint en la ventana móvil.
+    pub fn endpoint_reports(&self) -> Vec<EndpointReport> {
+        let now = Instant::now();
+        let mut endpoints = self.endpoints.lock().unwrap();
+        Endpoint::ALL
+            .iter()
+            .map(|endpoint| {
+                let window = endpoints.entry(*endpoint).or_default();
+                window.prune(now);
+                let mut latencies: Vec<Duration> =
+                    window.samples.iter().map(|s| s.latency).collect();
+                latencies.sort_unstable();
+                EndpointReport {
+                    endpoint: *endpoint,
+                    calls: window.samples.len(),
+                    errors: window.samples.iter().filter(|s| !s.ok).count(),
+                    short_circuited: window.short_circuited.len(),
+                    p50: percentile(&latencies, 50),
+                    p95: percentile(&latencies, 95),
+                    hedges_fired: window.hedges_fired,
+                    hedges_won: window.hedges_won,
+                }
+            })
+            .collect()
+    }
+
+    pub fn snapshot(&self) -> Vec<(Upstream, HostStats)> {
+        let hosts = self.hosts.lock().unwrap();
+        Upstream::ALL
+            .iter()
+            .map(|u| (*u, hosts.get(u).copied().unwrap_or_default()))
+            .collect()
+    }
+}
+
+/// Error de una llamada saliente.
+#[derive(Debug)]
+pub enum UpstreamError {
+    /// El upstream está en cooldown por mantenimiento; no se llamó.
+    Cooldown {
+        retry_in: Duration,
+    },
+    Http(reqwest::Error),
+    /// Modo replay sin grabación para la URL.
+    NotRecorded(String),
+    /// Roblox respondió con un challenge (captcha / verificación) en lugar
+    /// de datos, y no había espejo al que pasar.
+    Challenged {
+        kind: String,
+    },
+}
+
+impl fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpstreamError::Cooldown { retry_in } => write!(
+                f,
+                "upstream en mantenimiento, reintento en {}s",
+                retry_in.as_secs()
+            ),
+            UpstreamError::Http(e) => e.fmt(f),
+            UpstreamError::NotRecorded(url) => write!(f, "sin grabación para {url}"),
+            UpstreamError::Challenged { kind } => {
+                write!(f, "Roblox pidió un challenge ({kind}) en lugar de datos")
+            }
+        }
+    }
+}
+
+/// Challenge que Roblox sirve a veces a IPs de datacenter en lugar de la
+/// respuesta: cabeceras `rblx-challenge-*` (el tipo viene en
+/// `rblx-challenge-type`) o una página HTML intersticial con 200/403/429.
+fn challenge_kind(resp: &reqwest::Response) -> Option<String> {
+    let headers = resp.headers();
+    if headers.contains_key("rblx-challenge-id") {
+        let kind = headers
+            .get("rblx-challenge-type")
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty())
+            .unwrap_or("unknown");
+        return Some(kind.to_string());
+    }
+    let is_html = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/html"));
+    let status = resp.status();
+    let interstitial = status.is_success()
+        || status == StatusCode::FORBIDDEN
+        || status == StatusCode::TOO_MANY_REQUESTS;
+    (is_html && interstitial).then(|| "interstitial".to_string())
+}
+
+/// URL equivalente en el espejo: `games.roblox.com` → `games.<domain>`.
+/// `None` si la URL no es de `roblox.com` (una URL base propia no se desvía).
+fn mirror_url(url: &str, domain: &str) -> Option<String> {
+    let rest = url.strip_prefix("https://")?;
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let subdomain = host.strip_suffix("roblox.com")?;
+    Some(format!("https://{subdomain}{domain}/{path}"))
+}
+
+/// Respuestas típicas de Roblox durante incidentes: 503 o páginas HTML de
+/// error en lugar de JSON. Los challenges se tratan aparte.
+fn is_maintenance(resp: &reqwest::Response) -> bool {
+    let status = resp.status();
+    if status == StatusCode::SERVICE_UNAVAILABLE {
+        return true;
+    }
+    let is_html = resp
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/html"));
+    is_html && (status.is_server_error() || status == StatusCode::FORBIDDEN)
+}
+
+/// Cliente compartido por todas las llamadas a Roblox: reutiliza conexiones
+/// (y su TLS) en lugar de abrir una por petición.
+pub fn client(config: &Config) -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(config.upstream_user_agent.as_str())
+        .timeout(config.upstream_timeout)
+        .connect_timeout(config.upstream_connect_timeout)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(TCP_KEEPALIVE)
+        .build()
+        .expect("cliente HTTP")
+}
+
+/// GET a Roblox que respeta el cooldown del upstream, registra el
+/// resultado y la latencia en `UpstreamHealth` y reintenta los errores de red
+/// y los 429/503.
+pub async fn get(
+    state: &AppState,
+    endpoint: Endpoint,
+    url: &str,
+) -> Result<reqwest::Response, UpstreamError> {
+    with_retries(state, endpoint, |retry| {
+        attempt(state, endpoint, url, Payload::default(), retry)
+    })
+    .await
+}
+
+/// Como `get`, pero con `Accept-Language: locale`, para los endpoints que
+/// devuelven textos traducidos (nombres de juegos).
+pub async fn get_localized(
+    state: &AppState,
+    endpoint: Endpoint,
+    url: &str,
+    locale: &str,
+) -> Result<reqwest::Response, UpstreamError> {
+    let payload = Payload {
+        locale: Some(locale),
+        ..Payload::default()
+    };
+    with_retries(state, endpoint, |retry| {
+        attempt(state, endpoint, url, payload, retry)
+    })
+    .await
+}
+
+/// Como `get`, pero un POST con cuerpo JSON. Solo acepta un `ReadOnlyPost`:
+/// consultas que Roblox expone por POST sin cambiar nada, que por eso se
+/// reintentan igual que un GET.
+pub async fn post_json(
+    state: &AppState,
+    post: ReadOnlyPost,
+    url: &str,
+    body: &serde_json::Value,
+) -> Result<reqwest::Response, UpstreamError> {
+    let body = body.to_string();
+    let payload = Payload {
+        body: Some(&body),
+        ..Payload::default()
+    };
+    with_retries(state, post, |retry| {
+        attempt(state, post.0, url, payload, retry)
+    })
+    .await
+}
+
+/// Un POST de solo lectura. No se construye desde fuera: cada uno es una
+/// constante de esta lista, revisada a mano.
+#[derive(Clone, Copy)]
+pub struct ReadOnlyPost(Endpoint);
+
+impl ReadOnlyPost {
+    /// `/v1/usernames/users`: resuelve nombres a ids, no escribe nada.
+    pub const USERNAME_LOOKUP: Self = Self(Endpoint::UsernameLookup);
+}
+
+/// Lo que acompaña a la URL en cada intento (y en su grabación).
+#[derive(Clone, Copy, Default)]
+struct Payload<'a> {
+    /// Cuerpo JSON; con él, la petición es un POST.
+    body: Option<&'a str>,
+    /// `Accept-Language` que se reenvía a Roblox.
+    locale: Option<&'a str>,
+}
+
+/// Un intento: admisión, envío (con hedge si toca) y registro. Ante un
+/// challenge, si hay espejo configurado, se repite una vez contra el espejo.
+/// Un reintento (`retry`) no pasa por la admisión: el cooldown que lo frenaría
+/// lo ha abierto el 503 del intento anterior, que es justo lo que se reintenta.
+async fn attempt(
+    state: &AppState,
+    endpoint: Endpoint,
+    url: &str,
+    payload: Payload<'_>,
+    retry: bool,
+) -> Result<reqwest::Response, UpstreamError> {
+    let health = &state.upstreams;
+    let upstream = endpoint.upstream();
+    if !retry {
+        if let Err(retry_in) = health.admit(upstream) {
+            health.record_short_circuit(endpoint);
+            return Err(UpstreamError::Cooldown { retry_in });
+        }
+    }
+
+    let mirror = state.config.upstream_mirror_domain.as_deref();
+    let mut switched = false;
+    loop {
+        let mirrored = mirror
+            .filter(|_| health.mirror_active(upstream))
+            .and_then(|domain| mirror_url(url, domain));
+        let target = mirrored.as_deref().unwrap_or(url);
+
+        let started = Instant::now();
+        // Solo los GETs llevan hedge: el POST de nombres va por lotes y
+        // duplicarlo por latencia gastaría el doble de cuota. Si falla, lo
+        // repite `with_retries`, como a cualquier `ReadOnlyPost`.
+        let hedge = payload.body.is_none().then(|| hedge_delay(state, endpoint));
+        let resp = match hedge.flatten() {
+            Some(delay) => send_hedged(state, endpoint, target, payload, delay).await,
+            None => send_limited(state, endpoint, target, payload).await,
+        };
+        let latency = started.elapsed();
+        return match resp {
+            Ok(resp) => {
+                let outcome = Outcome::Status(resp.status().as_u16());
+                health.remember(endpoint, target, outcome, latency);
+                let Some(kind) = challenge_kind(&resp) else {
+                    health.record(endpoint, outcome, latency, is_maintenance(&resp));
+                    return Ok(resp);
+                };
+                // Con un espejo al que pasar no hace falta enfriar el
+                // upstream; sin él, se le deja en paz como en un
+                // mantenimiento.
+                let switch = mirror.is_some() && mirrored.is_none() && !switched;
+                health.record(endpoint, outcome, latency, !switch);
+                health.record_challenge(
+                    upstream,
+                    &kind,
+                    switch.then_some(state.config.upstream_mirror_duration),
+                );
+                if switch {
+                    switched = true;
+                    continue;
+                }
+                Err(UpstreamError::Challenged { kind })
+            }
+            Err(UpstreamError::Http(e)) => {
+                health.record(endpoint, Outcome::Transport, latency, false);
+                health.remember(endpoint, target, Outcome::Transport, latency);
+                Err(UpstreamError::Http(e))
+            }
+            Err(e) => Err(e),
+        };
+    }
+}
+
+/// Operación que se puede repetir sin efectos duplicados. Es un trait
+/// sellado: lo cumplen `Endpoint`, que `get` solo usa para GETs, y
+/// `ReadOnlyPost`, los POST que solo consultan. Una escritura (donación,
+/// webhook) no puede pasar por `with_retries` salvo que se añada aquí un
+/// tipo que la acompañe de su clave de idempotencia.
+pub trait Idempotent: sealed::Sealed + Copy {
+    /// Nombre para los logs.
+    fn label(self) -> String;
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for super::Endpoint {}
+    impl Sealed for super::ReadOnlyPost {}
+}
+
+impl Idempotent for Endpoint {
+    fn label(self) -> String {
+        format!("{}{}", self.upstream().host(), self.path())
+    }
+}
+
+impl Idempotent for ReadOnlyPost {
+    fn label(self) -> String {
+        format!("POST {}", self.0.label())
+    }
+}
+
+/// Repite `attempt` tras un error de red (la petición pudo llegar o no a
+/// Roblox, por eso solo vale para operaciones `Idempotent`) o un 429/503,
+/// hasta `UPSTREAM_RETRIES` veces. Se espera según `UPSTREAM_RETRY_*`, o lo que
+/// pida `Retry-After` si Roblox lo manda (sin pasar de `RETRY_AFTER_MAX`).
+/// `attempt` recibe si es un reintento.
+async fn with_retries<Op, F, Fut>(
+    state: &AppState,
+    op: Op,
+    mut attempt: F,
+) -> Result<reqwest::Response, UpstreamError>
+where
+    Op: Idempotent,
+    F: FnMut(bool) -> Fut,
+    Fut: Future<Output = Result<reqwest::Response, UpstreamError>>,
+{
+    let mut retries = 0;
+    let mut prev_delay = None;
+    loop {
+        let result = attempt(retries > 0).await;
+        let (reason, retry_after) = match &result {
+            Err(UpstreamError::Http(e)) => (e.to_string(), None),
+            Ok(resp)
+                if resp.status() == StatusCode::TOO_MANY_REQUESTS
+                    || resp.status() == StatusCode::SERVICE_UNAVAILABLE =>
+            {
+                (resp.status().to_string(), retry_after(resp))
+            }
+            _ => return result,
+        };
+        if retries >= state.config.upstream_retries {
+            return result;
+        }
+        retries += 1;
+        let delay = match retry_after {
+            Some(wait) if wait > RETRY_AFTER_MAX => {
+                warn!(
+                    "{} falló ({reason}) y pide esperar {}s, sin reintento",
+                    op.label(),
+                    wait.as_secs()
+                );
+                return result;
+            }
+            Some(wait) => wait,
+            None => state
+                .config
+                .upstream_retry_backoff
+                .delay(retries, prev_delay),
+        };
+        warn!(
+            "{} falló ({reason}), reintento {retries} en {}ms",
+            op.label(),
+            delay.as_millis()
+        );
+        prev_delay = Some(delay);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Espera pedida en `Retry-After`, en segundos o como fecha HTTP.
+fn retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    let value = resp
+        .headers()
+        .get(header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Espera antes de lanzar un hedge: p95 reciente del endpoint por
+/// `HEDGE_P95_FACTOR`, con un mínimo de `HEDGE_MIN_DELAY_MS`. `None` si los
+/// hedges están desactivados o aún no hay muestras suficientes.
+fn hedge_delay(state: &AppState, endpoint: Endpoint) -> Option<Duration> {
+    let config = &state.config;
+    if !config.hedge_requests {
+        return None;
+    }
+    let (p95, samples) = state.upstreams.recent_p95(endpoint)?;
+    if samples < HEDGE_MIN_SAMPLES {
+        return None;
+    }
+    Some(
+        p95.mul_f64(config.hedge_p95_factor)
+            .max(config.hedge_min_delay),
+    )
+}
+
+/// Envía ocupando un hueco del limitador adaptativo de peticiones salientes.
+async fn send_limited(
+    state: &AppState,
+    endpoint: Endpoint,
+    url: &str,
+    payload: Payload<'_>,
+) -> Result<reqwest::Response, UpstreamError> {
+    let permit = state.outbound.acquire().await;
+    send_with_permit(state, endpoint, url, payload, permit).await
+}
+
+/// Envía y deja en el permiso la señal (sana / sobrecarga) para el AIMD.
+async fn send_with_permit(
+    state: &AppState,
+    endpoint: Endpoint,
+    url: &str,
+    payload: Payload<'_>,
+    mut permit: Permit<'_>,
+) -> Result<reqwest::Response, UpstreamError> {
+    let started = Instant::now();
+    let resp = send(state, endpoint, url, payload).await;
+    let status = match &resp {
+        Ok(r) => Some(r.status().as_u16()),
+        Err(UpstreamError::Http(_)) => None,
+        // Sin llamada real (replay sin grabación): no dice nada de la carga.
+        Err(_) => return resp,
+    };
+    crate::usage::count_call();
+    permit.record(state.outbound.classify(status, started.elapsed()));
+    resp
+}
+
+/// Petición con hedge: si la primera no respondió tras `delay`, lanza una
+/// segunda idéntica (solo si el limitador tiene hueco libre en ese momento)
+/// y se queda con la primera respuesta exitosa de las dos. Solo para GETs:
+/// `attempt` no lo usa con los `ReadOnlyPost` (`post_json`).
+async fn send_hedged(
+    state: &AppState,
+    endpoint: Endpoint,
+    url: &str,
+    payload: Payload<'_>,
+    delay: Duration,
+) -> Result<reqwest::Response, UpstreamError> {
+    let primary = send_limited(state, endpoint, url, payload);
+    tokio::pin!(primary);
+
+    tokio::select! {
+        resp = &mut primary => return resp,
+        _ = tokio::time::sleep(delay) => {}
+    }
+
+    // Un hedge nunca hace cola: sin hueco libre, se espera a la primera.
+    let Some(permit) = state.outbound.try_acquire() else {
+        return primary.await;
+    };
+    info!(
+        "Hedge: {}{} sin respuesta tras {}ms, lanzando segunda petición",
+        endpoint.upstream().host(),
+        endpoint.path(),
+        delay.as_millis()
+    );
+    let secondary = send_with_permit(state, endpoint, url, payload, permit);
+    tokio::pin!(secondary);
+
+    let (first, hedge_won) = tokio::select! {
+        resp = &mut primary => (resp, false),
+        resp = &mut secondary => (resp, true),
+    };
+    let resp = match first {
+        Ok(resp) => Ok(resp),
+        // Si la que llegó antes falló, se da una oportunidad a la otra.
+        Err(_) if hedge_won => primary.await,
+        Err(_) => secondary.await,
+    };
+    state
+        .upstreams
+        .record_hedge(endpoint, hedge_won && resp.is_ok());
+    resp
+}
+
+/// Envía la petición (GET, o POST si lleva `body`) según el modo (fallos
+/// inyectados, live, grabación o reproducción).
+async fn send(
+    state: &AppState,
+    endpoint: Endpoint,
+    url: &str,
+    payload: Payload<'_>,
+) -> Result<reqwest::Response, UpstreamError> {
+    #[cfg(feature = "fault-injection")]
+    if let Some(fault) = state.faults.before_call(url).await {
+        return Ok(fault.into_response(endpoint));
+    }
+
+    let request = || {
+        let mut request = match payload.body {
+            Some(body) => state
+                .http
+                .post(url)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body.to_string()),
+            None => state.http.get(url),
+        };
+        if let Some(locale) = payload.locale {
+            request = request.header(header::ACCEPT_LANGUAGE, locale);
+        }
+        outbound_tags::apply(&state.config.upstream_tags, url, request)
+    };
+    match &state.recording {
+        Mode::Live => request().send().await.map_err(UpstreamError::Http),
+        Mode::Record(dir) => {
+            let resp = request().send().await.map_err(UpstreamError::Http)?;
+            recording::record(dir, endpoint, url, payload.body, payload.locale, resp)
+                .await
+                .map_err(UpstreamError::Http)
+        }
+        Mode::Replay(dir) => recording::replay(dir, endpoint, url, payload.body, payload.locale)
+            .await
+            .ok_or_else(|| UpstreamError::NotRecorded(url.to_string())),
+    }
+}
@@ -0,0 +1,193 @@
+//! Consumo por clave de API: peticiones recibidas y, sobre todo, llamadas
+//! reales a Roblox que provocaron (fallos de caché, refrescos del watcher).
+//! Es el coste que importa para poner precios o límites justos: mil aciertos
+//! de caché cuestan menos que un escaneo de un usuario con veinte juegos.
+//!
+//! Las llamadas se cuentan con un contador por tarea (`counting`) que
+//! `upstream` incrementa en cada envío; `propagate` lo lleva a las tareas
+//! lanzadas con `tokio::spawn` durante la petición.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use axum::{extract::State, http::Request, middleware::Next, response::Response, Json};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{admin::AdminAuth, tenant, tenant::Tenant, AppState};
+
+tokio::task_local! {
+    static CALLS: Arc<AtomicU64>;
+}
+
+/// Ejecuta `fut` contando las llamadas a Roblox que hace.
+pub async fn counting<F: Future>(fut: F) -> (F::Output, u64) {
+    let calls = Arc::new(AtomicU64::new(0));
+    let output = CALLS.scope(calls.clone(), fut).await;
+    (output, calls.load(Ordering::Relaxed))
+}
+
+/// Lleva el contador de la tarea actual (si lo hay) a `fut`, para usarlo con
+/// `tokio::spawn`. Se captura al llamar, no al ejecutarse en la otra tarea.
+pub fn propagate<F: Future>(fut: F) -> impl Future<Output = F::Output> {
+    let calls = CALLS.try_with(Arc::clone).ok();
+    async move {
+        match calls {
+            Some(calls) => CALLS.scope(calls, fut).await,
+            None => fut.await,
+        }
+    }
+}
+
+/// Anota una llamada real a Roblox en el contador de la tarea actual.
+pub fn count_call() {
+    let _ = CALLS.try_with(|calls| calls.fetch_add(1, Ordering::Relaxed));
+}
+
+#[derive(Default, Clone, Copy, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantUsage {
+    pub requests: u64,
+    /// Llamadas a Roblox provocadas por sus peticiones.
+    pub upstream_calls: u64,
+    /// Llamadas de los refrescos de sus watches. Un usuario vigilado por
+    /// varias claves se cobra entero a cada una.
+    pub watch_upstream_calls: u64,
+}
+
+#[derive(Default)]
+pub struct UsageTracker {
+    /// `None` agrupa las peticiones sin clave.
+    tenants: Mutex<HashMap<Option<String>, TenantUsage>>,
+}
+
+impl UsageTracker {
+    fn record_request(&self, tenant: Option<String>, upstream_calls: u64) {
+        let mut tenants = self.tenants.lock().unwrap();
+        let usage = tenants.entry(tenant).or_default();
+        usage.requests += 1;
+        usage.upstream_calls += upstream_calls;
+    }
+
+    /// Carga un refresco del watcher a cada clave que vigila al usuario.
+    pub fn record_watch(&self, owners: &[String], upstream_calls: u64) {
+        let mut tenants = self.tenants.lock().unwrap();
+        for owner in owners {
+            tenants
+                .entry(Some(owner.clone()))
+                .or_default()
+                .watch_upstream_calls += upstream_calls;
+        }
+    }
+
+    /// Consumo de una clave, o de las peticiones anónimas con `None`.
+    pub fn get(&self, tenant: Option<&str>) -> TenantUsage {
+        let tenants = self.tenants.lock().unwrap();
+        tenants
+            .get(&tenant.map(str::to_string))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Lo contado para una petición; viaja en las extensiones de la respuesta
+/// para el log de acceso.
+#[derive(Clone)]
+pub struct RequestUsage {
+    pub tenant: Option<String>,
+    pub upstream_calls: u64,
+}
+
+/// Middleware: cuenta la petición y sus llamadas a Roblox para la clave de
+/// `X-Api-Key` (sin validar el acceso, de eso se encarga cada handler).
+pub async fn track<B>(
+    State(state): State<Arc<AppState>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let tenant = tenant::identify(req.headers(), &state.config.api_keys).map(|k| k.id.clone());
+    let (mut response, calls) = counting(next.run(req)).await;
+    state.usage.record_request(tenant.clone(), calls);
+    response.extensions_mut().insert(RequestUsage {
+        tenant,
+        upstream_calls: calls,
+    });
+    response
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageResponse {
+    ok: bool,
+    tenant: String,
+    /// Inicio del periodo contado (arranque del proceso), RFC 3339.
+    since: String,
+    usage: TenantUsage,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantUsageEntry {
+    tenant: String,
+    #[serde(flatten)]
+    usage: TenantUsage,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReportResponse {
+    ok: bool,
+    /// RFC 3339.
+    since: String,
+    tenants: Vec<TenantUsageEntry>,
+    /// Peticiones sin clave de API.
+    anonymous: TenantUsage,
+}
+
+fn since(state: &AppState) -> String {
+    let uptime = chrono::Duration::from_std(state.started_at.elapsed()).unwrap_or_default();
+    let started: DateTime<Utc> = Utc::now() - uptime;
+    started.to_rfc3339()
+}
+
+/// `GET /usage`: consumo de la clave que llama.
+pub async fn get_usage(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+) -> Json<UsageResponse> {
+    Json(UsageResponse {
+        ok: true,
+        usage: state.usage.get(Some(&tenant.id)),
+        tenant: tenant.id,
+        since: since(&state),
+    })
+}
+
+/// `GET /admin/usage`: consumo de todas las claves configuradas.
+pub async fn usage_report(
+    _: AdminAuth,
+    State(state): State<Arc<AppState>>,
+) -> Json<UsageReportResponse> {
+    let tenants = state
+        .config
+        .api_keys
+        .iter()
+        .map(|key| TenantUsageEntry {
+            tenant: key.id.clone(),
+            usage: state.usage.get(Some(&key.id)),
+        })
+        .collect();
+    Json(UsageReportResponse {
+        ok: true,
+        since: since(&state),
+        tenants,
+        anonymous: state.usage.get(None),
+    })
+}
@@ -0,0 +1,138 @@
+//! Forma de un pass en las respuestas (`PassView`), separada del `Gamepass`
+//! interno. Los scripts Luau de los juegos leen los passes por forma (y
+//! algunos por posición), así que los campos de siempre no cambian de orden
+//! ni de tipo, y lo nuevo solo sale si se pide con `?fields=`.
+//!
+//! Para añadir un campo: va al final de `PassView`, opcional, y con su nombre
+//! en `Fields`.
+
+use axum::http::StatusCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, error::ApiError, links, models::Gamepass, pricing};
+
+/// De qué fuente de Roblox salió un pass.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PassSource {
+    /// Juegos públicos del usuario (o de sus grupos).
+    #[default]
+    Games,
+    #[cfg(feature = "catalog")]
+    Catalog,
+    Inventory,
+}
+
+/// Campos opcionales pedidos con `?fields=` (lista separada por comas).
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Fields {
+    pub source: bool,
+    pub price_source: bool,
+}
+
+impl Fields {
+    /// `None` o vacío: ningún campo extra. 400 con un nombre desconocido.
+    pub fn parse(raw: Option<&str>) -> Result<Self, ApiError> {
+        let mut fields = Fields::default();
+        for name in raw.unwrap_or_default().split(',').map(str::trim) {
+            match name {
+                "" => {}
+                "source" => fields.source = true,
+                "priceSource" => fields.price_source = true,
+                other => {
+                    return Err(ApiError::new(
+                        StatusCode::BAD_REQUEST,
+                        "INVALID_QUERY",
+                        format!(
+                            "Campo desconocido en fields: '{other}' (válidos: source, priceSource)"
+                        ),
+                    ))
+                }
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Nombres de los campos activos, como en `?fields=`.
+    pub fn names(self) -> Vec<&'static str> {
+        [
+            self.source.then_some("source"),
+            self.price_source.then_some("priceSource"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// `?fields=` si viene (vacío = ninguno) o, si no, `DEFAULT_FIELDS`.
+    pub fn requested(raw: Option<&str>, config: &Config) -> Result<Self, ApiError> {
+        match raw {
+            Some(raw) => Fields::parse(Some(raw)),
+            None => Ok(config.default_fields),
+        }
+    }
+}
+
+/// Un pass tal como sale en las respuestas.
+#[derive(Serialize, Clone, JsonSchema)]
+pub struct PassView {
+    id: u64,
+    name: String,
+    price: i64,
+    /// Precio más antiguo visto en las fotos guardadas (`SNAPSHOT_*`).
+    #[serde(rename = "originalPrice")]
+    original_price: i64,
+    /// `price` distinto de `originalPrice`: el creador cambió el precio.
+    #[serde(rename = "priceChanged")]
+    price_changed: bool,
+    /// Solo si Roblox dio más de un precio (precios regionales): todos ellos
+    /// y de cuál sale `price`.
+    #[serde(rename = "priceDetails", skip_serializing_if = "Option::is_none")]
+    price_details: Option<pricing::PriceDetails>,
+    /// `name`, con el juego (o el id) entre paréntesis si otro pass de la
+    /// respuesta se llama igual (ver `duplicates`).
+    #[serde(rename = "displayName")]
+    display_name: String,
+    /// Id del primer pass de la respuesta con el mismo nombre; no sale en
+    /// ese primero ni en los nombres únicos.
+    #[serde(rename = "duplicateOf", skip_serializing_if = "Option::is_none")]
+    duplicate_of: Option<u64>,
+    /// URL del icono, solo con `?thumbnails=true` y si ya está renderizado.
+    #[serde(rename = "iconUrl", skip_serializing_if = "Option::is_none")]
+    icon_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    links: Option<links::PassLinks>,
+    /// Solo con `?fields=source`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<PassSource>,
+    /// Solo con `?fields=priceSource`: campo de Roblox del que sale `price`
+    /// (según `PRICE_SOURCE_ORDER`), para depurar precios que no cuadran.
+    /// No sale en los passes del catálogo.
+    #[serde(rename = "priceSource", skip_serializing_if = "Option::is_none")]
+    price_source: Option<pricing::PriceField>,
+}
+
+impl PassView {
+    pub fn new(pass: &Gamepass, fields: Fields) -> Self {
+        PassView {
+            id: pass.id,
+            name: pass.name.clone(),
+            price: pass.price,
+            original_price: pass.original_price,
+            price_changed: pass.price_changed,
+            price_details: pass.price_details.clone(),
+            display_name: pass.display_name.clone(),
+            duplicate_of: pass.duplicate_of,
+            icon_url: pass.icon_url.clone(),
+            links: pass.links.clone(),
+            source: fields.source.then_some(pass.source),
+            price_source: pass.price_source.filter(|_| fields.price_source),
+        }
+    }
+}
+
+/// Vistas de `passes` con los mismos `fields`.
+pub fn render(passes: &[Gamepass], fields: Fields) -> Vec<PassView> {
+    passes.iter().map(|p| PassView::new(p, fields)).collect()
+}
@@ -0,0 +1,161 @@
+//! Conexiones precalentadas con Roblox (`UPSTREAM_WARM_CONNECTIONS`): al
+//! arrancar, y cada vez que un host lleva un rato sin llamadas, se abren unas
+//! cuantas conexiones con un `HEAD /`. Así la primera petición real tras un
+//! rato tranquilo no paga DNS + TCP + TLS.
+//!
+//! Las conexiones quedan en el pool del cliente compartido, que cierra las
+//! que llevan `POOL_IDLE_TIMEOUT` sin uso; por eso se recalientan antes. Estos
+//! `HEAD` no pasan por el limitador ni cuentan en `/admin/upstreams`, y no se
+//! tocan los hosts en cooldown o desviados al espejo.
+//!
+//! El resultado de la primera ronda queda en `FirstRound` para el informe de
+//! arranque (`boot`).
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::future::join_all;
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::{
+    config::Config,
+    outbound_tags,
+    recording::Mode,
+    upstream::{Upstream, POOL_IDLE_TIMEOUT},
+    AppState,
+};
+
+/// Cada cuánto se revisa qué hosts están ociosos.
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// Sin llamadas durante este tiempo, un host se recalienta.
+const IDLE_AFTER: Duration = Duration::from_secs(POOL_IDLE_TIMEOUT.as_secs() * 2 / 3);
+
+/// Conexiones que respondieron en un host.
+#[derive(Clone, Serialize)]
+pub struct WarmedHost {
+    host: &'static str,
+    ok: usize,
+}
+
+/// Primera ronda de precalentado, en cuanto termina.
+pub struct FirstRound(watch::Sender<Option<Vec<WarmedHost>>>);
+
+impl Default for FirstRound {
+    fn default() -> Self {
+        FirstRound(watch::channel(None).0)
+    }
+}
+
+impl FirstRound {
+    /// Solo cuenta la primera ronda; si la tarea se relanza, las siguientes
+    /// no la pisan.
+    fn record(&self, hosts: Vec<WarmedHost>) {
+        self.0.send_if_modified(|round| {
+            if round.is_some() {
+                return false;
+            }
+            *round = Some(hosts);
+            true
+        });
+    }
+
+    /// Espera a la primera ronda como mucho `timeout`.
+    pub async fn wait(&self, timeout: Duration) -> Option<Vec<WarmedHost>> {
+        let mut rx = self.0.subscribe();
+        let done = tokio::time::timeout(timeout, rx.wait_for(Option::is_some))
+            .await
+            .is_ok_and(|r| r.is_ok());
+        done.then(|| rx.borrow().clone()).flatten()
+    }
+}
+
+/// Lo más que se espera a la primera ronda antes de avisar a systemd y
+/// escribir el informe de arranque.
+pub fn first_round_timeout(config: &Config) -> Duration {
+    config.upstream_timeout + Duration::from_secs(1)
+}
+
+/// Si hay que precalentar: con `UPSTREAM_WARM_CONNECTIONS` > 0 y red (en
+/// replay no hay a qué conectarse).
+pub fn enabled(state: &AppState) -> bool {
+    state.config.upstream_warm_connections > 0
+        && matches!(state.recording, Mode::Live | Mode::Record(_))
+}
+
+/// Abre `connections` conexiones con `upstream` a la vez; devuelve cuántas
+/// respondieron.
+async fn warm(state: &AppState, upstream: Upstream, connections: usize) -> usize {
+    let url = format!("{}/", state.config.upstream_url(upstream));
+    let requests = (0..connections).map(|_| {
+        let request =
+            outbound_tags::apply(&state.config.upstream_tags, &url, state.http.head(&url));
+        request.send()
+    });
+    join_all(requests)
+        .await
+        .into_iter()
+        .filter(Result::is_ok)
+        .count()
+}
+
+/// Tarea de fondo: calienta todos los hosts al empezar y luego los ociosos.
+pub async fn run(state: Arc<AppState>, token: CancellationToken) {
+    let connections = state.config.upstream_warm_connections;
+    let mut warmed_at: HashMap<Upstream, Instant> = HashMap::new();
+    let mut tick = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = token.cancelled() => return,
+            _ = tick.tick() => {}
+        }
+
+        let now = Instant::now();
+        let idle: Vec<Upstream> = state
+            .upstreams
+            .snapshot()
+            .into_iter()
+            .filter(|(upstream, stats)| {
+                let busy = stats.cooldown.is_some() || stats.mirror_until.is_some_and(|u| u > now);
+                let last_used = stats
+                    .last
+                    .map(|(_, at)| at)
+                    .into_iter()
+                    .chain(warmed_at.get(upstream).copied())
+                    .max();
+                !busy && last_used.is_none_or(|at| now.duration_since(at) >= IDLE_AFTER)
+            })
+            .map(|(upstream, _)| upstream)
+            .collect();
+        if idle.is_empty() {
+            state.warmup.record(Vec::new());
+            continue;
+        }
+
+        let started = Instant::now();
+        let results = join_all(idle.iter().map(|&u| warm(&state, u, connections))).await;
+        let warmed: Vec<WarmedHost> = idle
+            .iter()
+            .zip(results)
+            .map(|(u, ok)| WarmedHost { host: u.host(), ok })
+            .collect();
+        let summary: Vec<String> = warmed
+            .iter()
+            .map(|w| format!("{} {}/{connections}", w.host, w.ok))
+            .collect();
+        info!(
+            "Conexiones precalentadas en {}ms: {}",
+            started.elapsed().as_millis(),
+            summary.join(", ")
+        );
+        state.warmup.record(warmed);
+        for upstream in idle {
+            warmed_at.insert(upstream, Instant::now());
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Avisos de una respuesta (`warnings`): lo que el servicio hizo por su
+//! cuenta y deja la lista incompleta o distinta de lo que hay en Roblox,
+//! aunque la petición saliera bien. Cada aviso trae un `code` estable para
+//! los scripts, un `message` para personas y, en `context`, los datos
+//! concretos (qué juegos, cuántos passes).
+
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::guidance::{ScanStats, SkipReason};
+
+#[derive(Serialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WarningCode {
+    /// Juegos con más passes de los que se leen (`GAME_PASSES_MAX_PAGES`).
+    PassesTruncated,
+    /// La lista se recortó por `MAX_RESPONSE_BYTES`.
+    ResponseTruncated,
+    /// Juegos que no se escanearon (tope, inactivos o fallo de Roblox).
+    UniversesSkipped,
+    /// Lista servida de caché más vieja de lo pedido, sin consultar Roblox.
+    StaleCache,
+    /// Passes encontrados que no salen en la lista.
+    ItemsFiltered,
+    /// Venció `SCAN_DEADLINE_SECS`: la lista tiene lo recogido hasta ahí.
+    DeadlineExceeded,
+    /// Elementos del inventario sin mirar por `INVENTORY_MAX_LOOKUPS`.
+    InventoryTruncated,
+}
+
+#[derive(Serialize, JsonSchema, Clone, Debug)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+    /// Objeto con los datos del aviso; sus campos dependen de `code`.
+    pub context: Value,
+}
+
+impl Warning {
+    fn new(code: WarningCode, message: String, context: Value) -> Self {
+        Warning {
+            code,
+            message,
+            context,
+        }
+    }
+}
+
+/// Avisos que salen del propio escaneo. `max_pages` es
+/// `GAME_PASSES_MAX_PAGES`; `price_filtered`, los passes que quitaron
+/// `minPrice`/`maxPrice`.
+pub fn from_scan(stats: &ScanStats, max_pages: usize, price_filtered: usize) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    let truncated = stats.truncated_games();
+    if !truncated.is_empty() {
+        warnings.push(Warning::new(
+            WarningCode::PassesTruncated,
+            format!(
+                "Solo se leyeron los primeros {} passes de {} juego(s) que tienen más; puede faltar alguno",
+                max_pages * 100,
+                truncated.len()
+            ),
+            json!({ "universeIds": truncated, "maxPassesPerGame": max_pages * 100 }),
+        ));
+    }
+
+    let skipped = stats.skipped_games();
+    if !skipped.is_empty() {
+        let failed = skipped
+            .iter()
+            .filter(|g| g.reason == SkipReason::UpstreamError)
+            .count();
+        let message = if failed > 0 {
+            format!(
+                "No se escanearon {} juego(s), {failed} por un fallo de Roblox; puede faltar algún pass",
+                skipped.len()
+            )
+        } else {
+            format!(
+                "No se escanearon {} juego(s) por los límites del escaneo",
+                skipped.len()
+            )
+        };
+        warnings.push(Warning::new(
+            WarningCode::UniversesSkipped,
+            message,
+            json!({ "universes": skipped }),
+        ));
+    }
+
+    let unchecked = stats.inventory_unchecked_count();
+    if unchecked > 0 {
+        warnings.push(Warning::new(
+            WarningCode::InventoryTruncated,
+            format!(
+                "No se miraron {unchecked} elemento(s) del inventario por el tope de consultas; puede faltar algún pass"
+            ),
+            json!({ "unchecked": unchecked }),
+        ));
+    }
+
+    let dropped = stats.dropped();
+    let counts = [
+        ("offSale", dropped.off_sale),
+        ("zeroPrice", dropped.zero_price),
+        ("invalidPrice", dropped.invalid_price),
+        ("overGameCap", dropped.over_game_cap),
+        ("priceFilter", price_filtered),
+    ];
+    let total: usize = counts.iter().map(|(_, n)| n).sum();
+    if total > 0 {
+        let context: Map<String, Value> = counts
+            .into_iter()
+            .filter(|(_, n)| *n > 0)
+            .map(|(name, n)| (name.to_string(), n.into()))
+            .collect();
+        warnings.push(Warning::new(
+            WarningCode::ItemsFiltered,
+            format!("Se omitieron {total} passes (ver context para los motivos)"),
+            Value::Object(context),
+        ));
+    }
+
+    warnings
+}
+
+/// Lista servida de caché con `age_secs` sin volver a consultar Roblox.
+pub fn stale_cache(age_secs: u64) -> Warning {
+    Warning::new(
+        WarningCode::StaleCache,
+        format!(
+            "Lista de caché con {age_secs}s de antigüedad, servida sin consultar Roblox; puede no estar al día"
+        ),
+        json!({ "ageSecs": age_secs }),
+    )
+}
+
+/// La respuesta lleva `count` de `total` passes por `MAX_RESPONSE_BYTES`.
+pub fn response_truncated(count: usize, total: usize) -> Warning {
+    Warning::new(
+        WarningCode::ResponseTruncated,
+        format!("Respuesta recortada por tamaño: {count} de {total} passes"),
+        json!({ "count": count, "totalCount": total }),
+    )
+}
+
+/// Venció el plazo del escaneo (`deadline`) sin que Roblox terminara.
+pub fn deadline_exceeded(deadline: Duration) -> Warning {
+    Warning::new(
+        WarningCode::DeadlineExceeded,
+        format!(
+            "Roblox no terminó en {}s; la lista tiene solo los passes recogidos hasta entonces",
+            deadline.as_secs()
+        ),
+        json!({ "deadlineSecs": deadline.as_secs() }),
+    )
+}
@@ -0,0 +1,353 @@
+//! Usuarios vigilados: el watcher vuelve a escanear sus passes en segundo
+//! plano cada cierto intervalo. Cada tenant (clave de API) gestiona su propia
+//! lista con `POST /watch`, `GET /watch` y `DELETE /watch/:userId`; si varios
+//! tenants vigilan al mismo usuario se refresca una sola vez, con el
+//! intervalo más corto de los pedidos.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::{
+    error::ApiError,
+    extract::{self, DryRun},
+    roblox::client,
+    tenant::Tenant,
+    usage, AppState,
+};
+
+/// Cada cuánto se buscan vigilancias vencidas.
+const TICK: Duration = Duration::from_secs(1);
+/// Vencimiento de un intervalo que no cabe en un `Instant`: en la práctica,
+/// nunca.
+const NEVER: Duration = Duration::from_secs(100 * 365 * 24 * 3600);
+
+struct Watch {
+    /// Intervalo pedido por cada tenant que vigila a este usuario.
+    owners: HashMap<String, Duration>,
+    added_at: HashMap<String, DateTime<Utc>>,
+    next_due: Instant,
+    last_refresh: Option<DateTime<Utc>>,
+    last_count: Option<usize>,
+}
+
+impl Watch {
+    fn interval(&self) -> Duration {
+        self.owners.values().min().copied().unwrap_or(Duration::MAX)
+    }
+}
+
+/// Vigilancia tal como la ve un tenant.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchView {
+    pub user_id: u64,
+    /// Intervalo pedido por este tenant.
+    pub interval_secs: u64,
+    /// RFC 3339.
+    pub added_at: String,
+    /// Último refresco (de cualquier tenant), RFC 3339.
+    pub last_refresh_at: Option<String>,
+    /// Passes encontrados en el último refresco.
+    pub last_pass_count: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct Watcher {
+    watches: Mutex<HashMap<u64, Watch>>,
+}
+
+impl Watcher {
+    fn view(user_id: u64, watch: &Watch, tenant: &str) -> Option<WatchView> {
+        Some(WatchView {
+            user_id,
+            interval_secs: watch.owners.get(tenant)?.as_secs(),
+            added_at: watch.added_at.get(tenant)?.to_rfc3339(),
+            last_refresh_at: watch.last_refresh.map(|at| at.to_rfc3339()),
+            last_pass_count: watch.last_count,
+        })
+    }
+
+    /// Alta o cambio de intervalo (salvo con `dry_run`). Devuelve la
+    /// vigilancia y si es nueva, o `Err(limit)` si el tenant ya llegó a su
+    /// máximo.
+    fn upsert(
+        &self,
+        tenant: &str,
+        user_id: u64,
+        interval: Duration,
+        limit: usize,
+        dry_run: bool,
+    ) -> Result<(WatchView, bool), usize> {
+        let mut watches = self.watches.lock().unwrap();
+        let owned = watches
+            .values()
+            .filter(|w| w.owners.contains_key(tenant))
+            .count();
+        let is_new = !watches
+            .get(&user_id)
+            .is_some_and(|w| w.owners.contains_key(tenant));
+        if is_new && owned >= limit {
+            return Err(limit);
+        }
+        if dry_run {
+            let existing = watches.get(&user_id);
+            let added_at = existing
+                .and_then(|w| w.added_at.get(tenant).copied())
+                .unwrap_or_else(Utc::now);
+            let view = WatchView {
+                user_id,
+                interval_secs: interval.as_secs(),
+                added_at: added_at.to_rfc3339(),
+                last_refresh_at: existing
+                    .and_then(|w| w.last_refresh)
+                    .map(|at| at.to_rfc3339()),
+                last_pass_count: existing.and_then(|w| w.last_count),
+            };
+            return Ok((view, is_new));
+        }
+
+        let watch = watches.entry(user_id).or_insert_with(|| Watch {
+            owners: HashMap::new(),
+            added_at: HashMap::new(),
+            next_due: Instant::now(),
+            last_refresh: None,
+            last_count: None,
+        });
+        watch.owners.insert(tenant.to_string(), interval);
+        watch
+            .added_at
+            .entry(tenant.to_string())
+            .or_insert_with(Utc::now);
+        // Si el nuevo intervalo es más corto, que no espere al vencimiento viejo.
+        if let Some(due) = Instant::now().checked_add(interval) {
+            watch.next_due = watch.next_due.min(due);
+        }
+        let view = Self::view(user_id, watch, tenant).expect("recién insertada");
+        Ok((view, is_new))
+    }
+
+    fn remove(&self, tenant: &str, user_id: u64) -> bool {
+        let mut watches = self.watches.lock().unwrap();
+        let Some(watch) = watches.get_mut(&user_id) else {
+            return false;
+        };
+        let removed = watch.owners.remove(tenant).is_some();
+        watch.added_at.remove(tenant);
+        if watch.owners.is_empty() {
+            watches.remove(&user_id);
+        }
+        removed
+    }
+
+    fn list(&self, tenant: &str) -> Vec<WatchView> {
+        let watches = self.watches.lock().unwrap();
+        let mut views: Vec<WatchView> = watches
+            .iter()
+            .filter_map(|(id, watch)| Self::view(*id, watch, tenant))
+            .collect();
+        views.sort_by_key(|v| v.user_id);
+        views
+    }
+
+    /// Usuarios a refrescar ya; su siguiente vencimiento queda reprogramado.
+    fn take_due(&self) -> Vec<u64> {
+        let now = Instant::now();
+        let mut watches = self.watches.lock().unwrap();
+        watches
+            .iter_mut()
+            .filter(|(_, w)| w.next_due <= now)
+            .map(|(id, w)| {
+                w.next_due = now.checked_add(w.interval()).unwrap_or(now + NEVER);
+                *id
+            })
+            .collect()
+    }
+
+    /// Claves que vigilan a `user_id`.
+    fn owners(&self, user_id: u64) -> Vec<String> {
+        let watches = self.watches.lock().unwrap();
+        watches
+            .get(&user_id)
+            .map(|w| w.owners.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Alguna clave vigila a `user_id`.
+    pub fn is_watched(&self, user_id: u64) -> bool {
+        self.watches.lock().unwrap().contains_key(&user_id)
+    }
+
+    fn record(&self, user_id: u64, count: usize) {
+        if let Some(watch) = self.watches.lock().unwrap().get_mut(&user_id) {
+            watch.last_refresh = Some(Utc::now());
+            watch.last_count = Some(count);
+        }
+    }
+
+    /// Bucle del watcher (tarea supervisada): refresca, de uno en uno, los
+    /// usuarios cuyo intervalo venció.
+    pub async fn run(state: Arc<AppState>, token: CancellationToken) {
+        let mut tick = tokio::time::interval(TICK);
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => return,
+                _ = tick.tick() => {}
+            }
+            for user_id in state.watcher.take_due() {
+                if token.is_cancelled() {
+                    return;
+                }
+                // Drenando: el refresco en curso termina, pero no se empieza
+                // ninguno más.
+                if state.drain.is_draining() {
+                    info!("Watcher parado por el drenaje");
+                    return;
+                }
+                info!("Refrescando userId={user_id}");
+                let ((passes, _), calls) =
+                    usage::counting(client::fetch_full_list(&state, user_id)).await;
+                state.watcher.record(user_id, passes.len());
+                state
+                    .usage
+                    .record_watch(&state.watcher.owners(user_id), calls);
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchRequest {
+    user_id: u64,
+    /// Por defecto, `WATCH_DEFAULT_INTERVAL_SECS`.
+    interval_secs: Option<u64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct WatchResponse {
+    ok: bool,
+    watch: WatchView,
+    /// Solo presente (y `true`) con `?dryRun=1`: no se guardó nada.
+    #[serde(rename = "dryRun", skip_serializing_if = "std::ops::Not::not")]
+    dry_run: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct WatchListResponse {
+    ok: bool,
+    count: usize,
+    /// Máximo de usuarios vigilados para esta clave.
+    limit: usize,
+    watches: Vec<WatchView>,
+}
+
+/// `POST /watch`: empieza a vigilar a un usuario (o cambia su intervalo).
+/// Con `?dryRun=1` solo valida y responde lo que pasaría.
+pub async fn add_watch(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+    DryRun(dry_run): DryRun,
+    extract::Json(request): extract::Json<WatchRequest>,
+) -> Result<(StatusCode, Json<WatchResponse>), ApiError> {
+    state.drain.reject_new_work()?;
+    let config = &state.config;
+    let interval_secs = request
+        .interval_secs
+        .unwrap_or(config.watch_default_interval.as_secs());
+    let (min, max) = (
+        config.watch_min_interval.as_secs(),
+        config.watch_max_interval.as_secs(),
+    );
+    if !(min..=max).contains(&interval_secs) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_INTERVAL",
+            format!("intervalSecs debe estar entre {min} y {max}"),
+        ));
+    }
+
+    let limit = tenant.max_watches.unwrap_or(config.watch_limit_per_key);
+    let (watch, created) = state
+        .watcher
+        .upsert(
+            &tenant.id,
+            request.user_id,
+            Duration::from_secs(interval_secs),
+            limit,
+            dry_run,
+        )
+        .map_err(|limit| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "WATCH_LIMIT_REACHED",
+                format!("La clave ya vigila el máximo de {limit} usuarios"),
+            )
+        })?;
+    info!(
+        "{} vigila userId={} cada {interval_secs}s{}",
+        tenant.id,
+        request.user_id,
+        if dry_run { " (dryRun)" } else { "" }
+    );
+
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((
+        status,
+        Json(WatchResponse {
+            ok: true,
+            watch,
+            dry_run,
+        }),
+    ))
+}
+
+/// `GET /watch`: usuarios vigilados por esta clave.
+pub async fn list_watches(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+) -> Json<WatchListResponse> {
+    let watches = state.watcher.list(&tenant.id);
+    Json(WatchListResponse {
+        ok: true,
+        count: watches.len(),
+        limit: tenant
+            .max_watches
+            .unwrap_or(state.config.watch_limit_per_key),
+        watches,
+    })
+}
+
+/// `DELETE /watch/:userId`
+pub async fn remove_watch(
+    Tenant(tenant): Tenant,
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    if !state.watcher.remove(&tenant.id, user_id) {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "WATCH_NOT_FOUND",
+            format!("Esta clave no vigila al userId {user_id}"),
+        ));
+    }
+    info!("{} deja de vigilar userId={user_id}", tenant.id);
+    Ok(StatusCode::NO_CONTENT)
+}
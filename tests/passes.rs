@@ -0,0 +1,1184 @@
+//! `/user/:id/passes` de punta a punta: el router entero (con sus capas)
+//! contra un Roblox simulado con wiremock que sirve las respuestas de
+//! `tests/fixtures/roblox`.
+
+use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use donations_api::{
+    cache::store::{Cache, MemoryCache},
+    config::Config,
+    routes, tenant, AppState,
+};
+use futures::future::BoxFuture;
+use serde_json::Value;
+use tower::ServiceExt;
+#[cfg(feature = "catalog")]
+use wiremock::matchers::query_param;
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+fn fixture(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/roblox")
+        .join(name);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+}
+
+fn json(status: u16, fixture_name: &str) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_raw(fixture(fixture_name), "application/json")
+}
+
+/// Responde `GET path` con el fixture `fixture_name`.
+async fn serve(server: &MockServer, route: &str, status: u16, fixture_name: &str) {
+    Mock::given(method("GET"))
+        .and(path(route))
+        .respond_with(json(status, fixture_name))
+        .mount(server)
+        .await;
+}
+
+/// Estado con todas las APIs de Roblox apuntando a `server`, sin reintentos
+/// ni nada en disco.
+fn state(server: &MockServer) -> Arc<AppState> {
+    state_with(server, |_| {})
+}
+
+/// Como `state`, con `configure` aplicado antes de crear el estado (para lo
+/// que se lee al arrancar).
+fn state_with(server: &MockServer, configure: impl FnOnce(&mut Config)) -> Arc<AppState> {
+    let mut config = config(server);
+    configure(&mut config);
+    Arc::new(AppState::new(config))
+}
+
+fn config(server: &MockServer) -> Config {
+    let mut config = Config::from_env();
+    for url in config.upstream_base_urls.values_mut() {
+        *url = server.uri();
+    }
+    config.upstream_retries = 0;
+    config.default_thumbnails = false;
+    config.snapshot_dir = None;
+    config.booth_dir = None;
+    config.collection_dir = None;
+    config.campaign_dir = None;
+    config
+}
+
+async fn get(state: &Arc<AppState>, uri: &str) -> (StatusCode, Value) {
+    let app = routes::build_router(state.clone());
+    let request = Request::get(uri).body(Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    // `Null` si no es JSON (los rechazos de `Path` de axum son texto).
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+/// Como `state`, con el usuario 1 vigilado: solo de los vigilados se
+/// guardan fotos (y hay `ETag` y diff).
+#[cfg(feature = "watcher")]
+async fn watching_user_1(server: &MockServer) -> Arc<AppState> {
+    let state = state_with(server, |config| {
+        config.api_keys = tenant::parse_api_keys("acme:secreto");
+    });
+    let request = Request::post("/watch")
+        .header("x-api-key", "secreto")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"userId": 1}"#))
+        .unwrap();
+    let app = routes::build_router(state.clone());
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    state
+}
+
+fn ids_and_prices(body: &Value) -> Vec<(u64, i64)> {
+    body["passes"]
+        .as_array()
+        .expect("passes")
+        .iter()
+        .map(|p| (p["id"].as_u64().unwrap(), p["price"].as_i64().unwrap()))
+        .collect()
+}
+
+/// Usuario 1: dos juegos públicos; el pass 12 sale en los dos y el 13 no
+/// está a la venta.
+async fn mount_public_games(server: &MockServer) {
+    serve(server, "/v2/users/1/games", 200, "user-games.json").await;
+    serve(
+        server,
+        "/v2/games/101/game-passes",
+        200,
+        "game-passes-101.json",
+    )
+    .await;
+    serve(
+        server,
+        "/v2/games/102/game-passes",
+        200,
+        "game-passes-102.json",
+    )
+    .await;
+    for id in [11, 13, 14] {
+        let route = format!("/v2/assets/{id}/details");
+        serve(server, &route, 200, &format!("asset-details-{id}.json")).await;
+    }
+    // Un pass repetido se pide una sola vez.
+    Mock::given(method("GET"))
+        .and(path("/v2/assets/12/details"))
+        .respond_with(json(200, "asset-details-12.json"))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn lists_passes_from_public_games() {
+    let server = MockServer::start().await;
+    mount_public_games(&server).await;
+    let state = state(&server);
+
+    let (status, body) = get(&state, "/user/1/passes").await;
+
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["userId"], 1);
+    // El juego más visitado primero; el 13 no está a la venta.
+    assert_eq!(ids_and_prices(&body), [(11, 10), (12, 100), (14, 1000)]);
+    assert_eq!(body["count"], 3);
+    assert!(body.get("guidance").is_none());
+}
+
+#[tokio::test]
+async fn deduplicates_passes_listed_in_several_games() {
+    let server = MockServer::start().await;
+    mount_public_games(&server).await;
+    let state = state(&server);
+
+    let (_, body) = get(&state, "/user/1/passes?fields=source").await;
+
+    let ids: Vec<u64> = ids_and_prices(&body).iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids.iter().filter(|id| **id == 12).count(), 1, "{body}");
+    assert!(body["passes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|p| p["source"] == "games"));
+    // `expect(1)` del pass 12 se comprueba al soltar el servidor.
+}
+
+#[tokio::test]
+async fn filters_by_price_after_the_scan() {
+    let server = MockServer::start().await;
+    mount_public_games(&server).await;
+    let state = state(&server);
+
+    let (status, body) = get(&state, "/user/1/passes?minPrice=50&maxPrice=500").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(ids_and_prices(&body), [(12, 100)]);
+
+    // El filtro no cambia la clave de caché: la segunda petición no escanea
+    // (si no, el pass 12 se pediría dos veces).
+    let (status, body) = get(&state, "/user/1/passes?maxPrice=10").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(ids_and_prices(&body), [(11, 10)]);
+
+    let (status, body) = get(&state, "/user/1/passes?minPrice=500&maxPrice=50").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], "INVALID_QUERY");
+}
+
+#[cfg(feature = "catalog")]
+#[tokio::test]
+async fn falls_back_to_the_catalog_without_public_passes() {
+    let server = MockServer::start().await;
+    serve(&server, "/v2/users/2/games", 200, "user-games-empty.json").await;
+    Mock::given(method("GET"))
+        .and(path("/v1/search/items/details"))
+        .and(query_param("creatorTargetId", "2"))
+        .respond_with(json(200, "catalog-search.json"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = state(&server);
+
+    let (status, body) = get(&state, "/user/2/passes?fields=source").await;
+
+    assert_eq!(status, StatusCode::OK, "{body}");
+    // Solo los game passes (assetType 46), sin repetir el 21.
+    assert_eq!(ids_and_prices(&body), [(21, 5), (23, 50)]);
+    assert!(body["passes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|p| p["source"] == "catalog"));
+}
+
+#[tokio::test]
+async fn drifted_items_are_skipped_and_quarantined() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v2/games/101/game-passes"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "nextPageCursor": null,
+            "data": [{ "passId": 11, "name": "Donate 10" }, { "id": 14, "name": "Donate 1000" }]
+        })))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_public_games(&server).await;
+    let token = "t".repeat(20);
+    let state = state_with(&server, |config| {
+        config.strict_upstream = true;
+        config.admin_token = Some(token.clone());
+    });
+
+    let (status, body) = get(&state, "/user/1/passes").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    // El 11 ya no tiene `id`; el 12 sigue llegando por el juego 102.
+    assert_eq!(ids_and_prices(&body), [(14, 1000), (12, 100)]);
+
+    let app = routes::build_router(state.clone());
+    let request = Request::get("/admin/quarantine")
+        .header("authorization", format!("Bearer {token}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let sample = &body["samples"][0];
+    assert_eq!(sample["reason"], "pass con forma desconocida", "{body}");
+    assert!(
+        sample["detail"]
+            .as_str()
+            .unwrap()
+            .contains("missing field `id`"),
+        "{body}"
+    );
+}
+
+#[cfg(feature = "catalog")]
+#[tokio::test]
+async fn catalog_fallback_accepts_the_configured_asset_types() {
+    let server = MockServer::start().await;
+    serve(&server, "/v2/users/2/games", 200, "user-games-empty.json").await;
+    serve(
+        &server,
+        "/v1/search/items/details",
+        200,
+        "catalog-search.json",
+    )
+    .await;
+    let mut state = state(&server);
+    Arc::get_mut(&mut state)
+        .unwrap()
+        .config
+        .catalog_fallback_asset_types = vec![46, 2];
+
+    let (status, body) = get(&state, "/user/2/passes").await;
+
+    assert_eq!(status, StatusCode::OK, "{body}");
+    // La camiseta (assetType 2) entra junto a los passes.
+    assert_eq!(ids_and_prices(&body), [(21, 5), (22, 5), (23, 50)]);
+}
+
+#[cfg(feature = "clothing")]
+#[tokio::test]
+async fn clothing_keeps_only_clothing_asset_types() {
+    let server = MockServer::start().await;
+    serve(
+        &server,
+        "/v1/search/items/details",
+        200,
+        "catalog-search.json",
+    )
+    .await;
+    let state = state(&server);
+
+    let (status, body) = get(&state, "/user/2/clothing").await;
+
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        body["items"],
+        serde_json::json!([{ "type": "tshirt", "id": 22, "name": "T-Shirt", "price": 5 }])
+    );
+}
+
+#[tokio::test]
+async fn unknown_user_is_404() {
+    let server = MockServer::start().await;
+    serve(&server, "/v2/users/5/games", 400, "invalid-user.json").await;
+    let state = state(&server);
+
+    let (status, body) = get(&state, "/user/5/passes").await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+    assert_eq!(body["ok"], false);
+    assert_eq!(body["error"]["code"], "USER_NOT_FOUND");
+}
+
+#[tokio::test]
+async fn roblox_rate_limit_is_429() {
+    let server = MockServer::start().await;
+    // También el catálogo y el inventario, que se prueban al no haber passes.
+    Mock::given(method("GET"))
+        .respond_with(json(429, "too-many-requests.json"))
+        .mount(&server)
+        .await;
+    let state = state(&server);
+
+    let (status, body) = get(&state, "/user/6/passes").await;
+
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS, "{body}");
+    assert_eq!(body["error"]["code"], "UPSTREAM_RATE_LIMITED");
+}
+
+#[tokio::test]
+async fn roblox_failure_is_502_not_an_empty_list() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&server)
+        .await;
+    let state = state(&server);
+
+    let (status, body) = get(&state, "/user/7/passes").await;
+
+    assert_eq!(status, StatusCode::BAD_GATEWAY, "{body}");
+    assert_eq!(body["error"]["code"], "UPSTREAM_ERROR");
+}
+
+#[tokio::test]
+async fn invalid_user_id_is_400_without_calling_roblox() {
+    let server = MockServer::start().await;
+    let state = state(&server);
+
+    let (status, _) = get(&state, "/user/abc/passes").await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn reports_what_was_left_out_as_warnings() {
+    let server = MockServer::start().await;
+    mount_public_games(&server).await;
+    let state = state(&server);
+
+    let (status, body) = get(&state, "/user/1/passes?maxPrice=500").await;
+
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(ids_and_prices(&body), [(11, 10), (12, 100)]);
+    let warnings = body["warnings"].as_array().expect("warnings");
+    assert_eq!(warnings.len(), 1, "{body}");
+    assert_eq!(warnings[0]["code"], "ITEMS_FILTERED");
+    // El 13 no está a la venta; el 14 lo quita `maxPrice`.
+    assert_eq!(
+        warnings[0]["context"],
+        serde_json::json!({ "offSale": 1, "priceFilter": 1 })
+    );
+
+    // Sin filtro de precio solo queda el pass fuera de venta.
+    let (_, body) = get(&state, "/user/1/passes").await;
+    assert_eq!(
+        body["warnings"][0]["context"],
+        serde_json::json!({ "offSale": 1 })
+    );
+}
+
+#[tokio::test]
+async fn deadline_returns_the_passes_gathered_so_far() {
+    let server = MockServer::start().await;
+    // El juego 102 tarda más que el plazo; el resto responde al momento.
+    Mock::given(method("GET"))
+        .and(path("/v2/games/102/game-passes"))
+        .respond_with(json(200, "game-passes-102.json").set_delay(Duration::from_secs(30)))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_public_games(&server).await;
+    let mut state = state(&server);
+    Arc::get_mut(&mut state).unwrap().config.scan_deadline = Duration::from_secs(1);
+
+    let (status, body) = get(&state, "/user/1/passes").await;
+
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["partial"], true);
+    assert_eq!(ids_and_prices(&body), [(11, 10), (12, 100)]);
+    let codes: Vec<&str> = body["warnings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|w| w["code"].as_str().unwrap())
+        .collect();
+    assert!(codes.contains(&"DEADLINE_EXCEEDED"), "{body}");
+    let skipped = body["warnings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|w| w["code"] == "UNIVERSES_SKIPPED")
+        .expect("UNIVERSES_SKIPPED");
+    assert_eq!(
+        skipped["context"]["universes"],
+        serde_json::json!([{ "universeId": 102, "reason": "deadline" }])
+    );
+}
+
+#[tokio::test]
+async fn deadline_with_nothing_gathered_is_504() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v2/users/1/games"))
+        .respond_with(json(200, "user-games.json").set_delay(Duration::from_secs(30)))
+        .mount(&server)
+        .await;
+    let mut state = state(&server);
+    Arc::get_mut(&mut state).unwrap().config.scan_deadline = Duration::from_secs(1);
+
+    let (status, body) = get(&state, "/user/1/passes").await;
+
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT, "{body}");
+    assert_eq!(body["error"]["code"], "SCAN_DEADLINE_EXCEEDED");
+}
+
+/// Usuario 1 con el pass 11 respondiendo `details` en lugar de su fixture.
+async fn mount_asset_11(server: &MockServer, details: Value) {
+    Mock::given(method("GET"))
+        .and(path("/v2/assets/11/details"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(details))
+        .with_priority(1)
+        .mount(server)
+        .await;
+    mount_public_games(server).await;
+}
+
+fn price_source_of_11(body: &Value) -> (i64, Value) {
+    let pass = body["passes"]
+        .as_array()
+        .expect("passes")
+        .iter()
+        .find(|p| p["id"] == 11)
+        .expect("pass 11");
+    (pass["price"].as_i64().unwrap(), pass["priceSource"].clone())
+}
+
+#[tokio::test]
+async fn price_comes_from_price_in_robux_or_legacy_price() {
+    // Respuesta actual: solo `PriceInRobux`.
+    let server = MockServer::start().await;
+    mount_public_games(&server).await;
+    let (_, body) = get(&state(&server), "/user/1/passes?fields=priceSource").await;
+    assert_eq!(price_source_of_11(&body), (10, "PriceInRobux".into()));
+
+    // Respuesta antigua: solo `Price`.
+    let server = MockServer::start().await;
+    mount_asset_11(
+        &server,
+        serde_json::json!({ "Price": 10, "IsForSale": true }),
+    )
+    .await;
+    let (_, body) = get(&state(&server), "/user/1/passes?fields=priceSource").await;
+    assert_eq!(price_source_of_11(&body), (10, "Price".into()));
+    assert!(body["passes"][0].get("priceDetails").is_none(), "{body}");
+}
+
+#[tokio::test]
+async fn price_source_order_settles_conflicting_fields() {
+    let conflicting = serde_json::json!({ "PriceInRobux": 10, "Price": 12, "IsForSale": true });
+
+    let server = MockServer::start().await;
+    mount_asset_11(&server, conflicting.clone()).await;
+    let (_, body) = get(&state(&server), "/user/1/passes?fields=priceSource").await;
+    assert_eq!(price_source_of_11(&body), (10, "PriceInRobux".into()));
+    assert_eq!(body["passes"][0]["priceDetails"]["legacyPrice"], 12);
+
+    let server = MockServer::start().await;
+    mount_asset_11(&server, conflicting).await;
+    let mut state = state(&server);
+    Arc::get_mut(&mut state).unwrap().config.price_sources = vec!["Price", "PriceInRobux"];
+    let (_, body) = get(&state, "/user/1/passes?fields=priceSource").await;
+    assert_eq!(price_source_of_11(&body), (12, "Price".into()));
+
+    // Sin `?fields=priceSource` no sale.
+    let (_, body) = get(&state, "/user/1/passes").await;
+    assert!(body["passes"][0].get("priceSource").is_none(), "{body}");
+}
+
+#[cfg(feature = "catalog")]
+#[tokio::test]
+async fn readyz_stays_ready_when_only_the_catalog_is_down() {
+    let server = MockServer::start().await;
+    serve(&server, "/v2/users/1/games", 200, "user-games.json").await;
+    Mock::given(method("GET"))
+        .and(path("/v1/search/items/details"))
+        .respond_with(json(200, "catalog-search.json").set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+    let state = state_with(&server, |config| {
+        config.readiness_probe_timeout = Duration::from_secs(1);
+    });
+
+    let started = std::time::Instant::now();
+    let (status, body) = get(&state, "/readyz").await;
+
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "degraded");
+    let sources: Vec<(&str, &str)> = body["sources"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| (s["source"].as_str().unwrap(), s["status"].as_str().unwrap()))
+        .collect();
+    assert_eq!(sources, [("games", "ok"), ("catalog", "timeout")]);
+    // Se espera al catálogo solo su plazo, no lo que tarda en responder.
+    assert!(started.elapsed() < Duration::from_secs(3));
+}
+
+#[tokio::test]
+async fn readyz_is_503_when_games_are_down() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v2/users/1/games"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&server)
+        .await;
+    serve(
+        &server,
+        "/v1/search/items/details",
+        200,
+        "catalog-search.json",
+    )
+    .await;
+    let state = state(&server);
+
+    let (status, body) = get(&state, "/readyz").await;
+
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{body}");
+    assert_eq!(body["status"], "unready");
+    let games = &body["sources"][0];
+    assert_eq!(games["source"], "games");
+    assert_eq!(games["status"], "failed");
+    assert_eq!(games["error"], "HTTP 500 Internal Server Error");
+}
+
+/// Memoria que se presenta como compartida: dos `AppState` con la misma
+/// hacen de dos réplicas con un Redis.
+#[derive(Default)]
+struct SharedMemory(MemoryCache);
+
+impl Cache for SharedMemory {
+    fn backend(&self) -> &'static str {
+        "shared-memory"
+    }
+
+    fn is_shared(&self) -> bool {
+        true
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Vec<u8>>> {
+        self.0.get(key)
+    }
+
+    fn set<'a>(&'a self, key: &'a str, value: Vec<u8>, ttl: Duration) -> BoxFuture<'a, ()> {
+        self.0.set(key, value, ttl)
+    }
+}
+
+#[tokio::test]
+async fn replicas_share_scans_through_the_store() {
+    let server = MockServer::start().await;
+    // Solo la primera réplica escanea.
+    Mock::given(method("GET"))
+        .and(path("/v2/users/1/games"))
+        .respond_with(json(200, "user-games.json"))
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    mount_public_games(&server).await;
+    let store: Arc<dyn Cache> = Arc::new(SharedMemory::default());
+    let first = Arc::new(AppState::with_store(config(&server), store.clone()));
+    let second = Arc::new(AppState::with_store(config(&server), store));
+
+    let (status, scanned) = get(&first, "/user/1/passes").await;
+    assert_eq!(status, StatusCode::OK, "{scanned}");
+    let app = routes::build_router(second.clone());
+    let request = Request::get("/user/1/passes").body(Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-cache"], "HIT");
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let cached: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(ids_and_prices(&cached), ids_and_prices(&scanned));
+}
+
+#[tokio::test]
+async fn fresh_scans_reuse_cached_prices() {
+    let server = MockServer::start().await;
+    // `mount_public_games` espera una sola llamada a los detalles del 12.
+    mount_public_games(&server).await;
+    let state = state(&server);
+
+    let (_, first) = get(&state, "/user/1/passes").await;
+    let (status, second) = get(&state, "/user/1/passes?fresh=true").await;
+
+    assert_eq!(status, StatusCode::OK, "{second}");
+    assert_eq!(ids_and_prices(&second), ids_and_prices(&first));
+}
+
+#[tokio::test]
+async fn resolved_usernames_are_cached() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/usernames/users"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "data": [{ "id": 1, "name": "Builder", "displayName": "Builder" }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = state(&server);
+
+    let (_, first) = get(&state, "/resolve/builder").await;
+    let (status, second) = get(&state, "/resolve/BUILDER").await;
+
+    assert_eq!(status, StatusCode::OK, "{second}");
+    assert_eq!(second["userId"], 1);
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn slow_posts_are_never_hedged() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/usernames/users"))
+        .and(body_string_contains("slowpoke"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "data": [{ "id": 9, "name": "slowpoke" }] }))
+                .set_delay(Duration::from_millis(500)),
+        )
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/usernames/users"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "data": [] })))
+        .mount(&server)
+        .await;
+    let state = state_with(&server, |config| {
+        config.hedge_requests = true;
+        config.hedge_min_delay = Duration::from_millis(20);
+    });
+    // Muestras rápidas suficientes para que un GET lento llevara hedge.
+    for i in 0..25 {
+        get(&state, &format!("/resolve/user{i}")).await;
+    }
+    // El p95 de los hedges se recalcula como mucho una vez por segundo.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    let (status, body) = get(&state, "/resolve/slowpoke").await;
+
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["userId"], 9);
+}
+
+#[tokio::test]
+async fn wrong_method_on_ping_and_time_is_a_json_405() {
+    let server = MockServer::start().await;
+    let state = state(&server);
+    for uri in ["/ping", "/time"] {
+        let app = routes::build_router(state.clone());
+        let request = Request::post(uri).body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED, "{uri}");
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "METHOD_NOT_ALLOWED", "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn a_503_is_retried_and_the_retry_is_served() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v2/users/1/games"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_public_games(&server).await;
+    let state = state_with(&server, |config| {
+        config.upstream_retries = 1;
+        config.upstream_retry_backoff.base = Duration::from_millis(1);
+        config.upstream_retry_backoff.max = Duration::from_millis(5);
+    });
+
+    let (status, body) = get(&state, "/user/1/passes").await;
+
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(ids_and_prices(&body), [(11, 10), (12, 100), (14, 1000)]);
+}
+
+/// `ETag` de la respuesta, si lo trae.
+async fn etag(state: &Arc<AppState>, uri: &str) -> Option<String> {
+    let app = routes::build_router(state.clone());
+    let request = Request::get(uri).body(Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK, "{uri}");
+    let etag = response.headers().get("etag")?;
+    Some(etag.to_str().unwrap().to_string())
+}
+
+#[cfg(feature = "watcher")]
+#[tokio::test]
+async fn etag_only_on_the_unparameterized_list() {
+    let server = MockServer::start().await;
+    mount_public_games(&server).await;
+    let state = watching_user_1(&server).await;
+
+    assert!(etag(&state, "/user/1/passes").await.is_some());
+    for query in [
+        "sort=price_desc",
+        "fields=source",
+        "thumbnails=true",
+        "groupBy=game",
+        "format=jsonapi",
+        "includeRemoved=true",
+        "minPrice=50",
+        "limit=1",
+    ] {
+        let uri = format!("/user/1/passes?{query}");
+        assert_eq!(etag(&state, &uri).await, None, "{query}");
+    }
+}
+
+#[tokio::test]
+async fn exhausted_bucket_is_a_429_with_retry_after() {
+    let server = MockServer::start().await;
+    mount_public_games(&server).await;
+    let state = state_with(&server, |config| {
+        config.rate_limit_rps = 0.5;
+        config.rate_limit_burst = 2;
+        config.rate_limit_trust_forwarded = true;
+    });
+    let app = routes::build_router(state);
+    let request = || {
+        Request::get("/user/1/passes")
+            .header("x-forwarded-for", "203.0.113.7")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    for _ in 0..2 {
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = app.oneshot(request()).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "2");
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["ok"], false);
+    assert_eq!(body["error"]["code"], "RATE_LIMITED");
+    assert!(body["error"]["message"].as_str().unwrap().contains("2s"));
+    assert!(body["error"]["requestId"].is_string(), "{body}");
+}
+
+#[tokio::test]
+async fn concurrent_retries_share_one_scan() {
+    let server = MockServer::start().await;
+    // Lento, para que todas lleguen mientras la primera sigue escaneando.
+    Mock::given(method("GET"))
+        .and(path("/v2/users/1/games"))
+        .respond_with(json(200, "user-games.json").set_delay(Duration::from_millis(300)))
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    mount_public_games(&server).await;
+    let state = state_with(&server, |config| {
+        config.dedupe_window = Duration::from_secs(5);
+        config.rate_limit_trust_forwarded = true;
+    });
+    let app = routes::build_router(state);
+
+    let requests = (0..5).map(|_| {
+        let request = Request::get("/user/1/passes")
+            .header("x-forwarded-for", "203.0.113.7")
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request)
+    });
+    let responses = futures::future::join_all(requests).await;
+
+    let mut deduplicated = 0;
+    for response in responses {
+        let response = response.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        if response.headers().contains_key("x-deduplicated") {
+            deduplicated += 1;
+        }
+    }
+    assert_eq!(deduplicated, 4);
+}
+
+#[cfg(feature = "watcher")]
+#[tokio::test]
+async fn watch_interval_out_of_range_is_400_and_leaves_the_list_intact() {
+    let server = MockServer::start().await;
+    let state = state_with(&server, |config| {
+        config.api_keys = tenant::parse_api_keys("acme:secreto");
+    });
+    let app = routes::build_router(state);
+    let watch = |body: &str| {
+        Request::post("/watch")
+            .header("x-api-key", "secreto")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(watch(
+            r#"{"userId": 1, "intervalSecs": 18446744073709551615}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["code"], "INVALID_INTERVAL");
+
+    // Nada quedó a medias: la lista sigue respondiendo y admite altas.
+    let list = Request::get("/watch")
+        .header("x-api-key", "secreto")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(list).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["count"], 0);
+
+    let response = app
+        .oneshot(watch(r#"{"userId": 1, "intervalSecs": 3600}"#))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[cfg(feature = "watcher")]
+#[tokio::test]
+async fn incomplete_scans_leave_no_snapshot_and_no_diff() {
+    let server = MockServer::start().await;
+    // El juego 102 responde la primera vez y luego falla.
+    Mock::given(method("GET"))
+        .and(path("/v2/games/102/game-passes"))
+        .respond_with(json(200, "game-passes-102.json"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/games/102/game-passes"))
+        .respond_with(ResponseTemplate::new(500))
+        .with_priority(2)
+        .mount(&server)
+        .await;
+    serve(&server, "/v2/users/1/games", 200, "user-games.json").await;
+    serve(
+        &server,
+        "/v2/games/101/game-passes",
+        200,
+        "game-passes-101.json",
+    )
+    .await;
+    for id in [11, 12, 13, 14] {
+        let route = format!("/v2/assets/{id}/details");
+        serve(&server, &route, 200, &format!("asset-details-{id}.json")).await;
+    }
+    let state = watching_user_1(&server).await;
+
+    let base = etag(&state, "/user/1/passes").await.expect("ETag");
+    assert_eq!(etag(&state, "/user/1/passes?fresh=true").await, None);
+    let (_, snapshots) = get(&state, "/user/1/passes/snapshots").await;
+    assert_eq!(snapshots["snapshots"].as_array().unwrap().len(), 1);
+
+    let since = base.trim_matches('"');
+    let (status, body) = get(&state, &format!("/user/1/passes/diff?since={since}")).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{body}");
+    assert_eq!(body["error"]["code"], "SCAN_INCOMPLETE");
+}
+
+#[tokio::test]
+async fn only_watched_users_are_snapshotted() {
+    let server = MockServer::start().await;
+    mount_public_games(&server).await;
+    let state = state(&server);
+
+    assert_eq!(etag(&state, "/user/1/passes").await, None);
+    let (_, body) = get(&state, "/user/1/passes/snapshots").await;
+    assert_eq!(body["snapshots"], serde_json::json!([]));
+    let (status, body) = get(&state, "/user/1/passes/diff?since=0").await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+    assert_eq!(body["error"]["code"], "USER_NOT_WATCHED");
+}
+
+#[tokio::test]
+async fn fresh_budget_holds_on_every_scan_path() {
+    let server = MockServer::start().await;
+    // Un solo escaneo: el que paga el presupuesto.
+    Mock::given(method("GET"))
+        .and(path("/v2/users/1/games"))
+        .respond_with(json(200, "user-games.json"))
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    mount_public_games(&server).await;
+    let state = state_with(&server, |config| {
+        config.api_keys = tenant::parse_api_keys("acme:secreto:::1");
+    });
+    let app = routes::build_router(state);
+    let request = |uri: &str| {
+        Request::get(uri)
+            .header("x-api-key", "secreto")
+            .header("cache-control", "no-cache")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(request("/user/1/passes"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Sin presupuesto, lo guardado aunque se pida fresco...
+    for uri in ["/compare?userIds=1", "/user/1/passes/suggest?amounts=10"] {
+        let response = app.clone().oneshot(request(uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        assert_eq!(response.headers()["x-degraded"], "cache-only", "{uri}");
+    }
+    // ...y sin nada guardado, 429.
+    let response = app
+        .oneshot(request("/user/2/passes/suggest?amounts=10"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["code"], "FRESH_BUDGET_EXHAUSTED");
+}
+
+#[cfg(feature = "catalog")]
+#[tokio::test]
+async fn race_loser_refreshes_the_cache() {
+    let server = MockServer::start().await;
+    // Los juegos públicos tardan: gana el catálogo.
+    Mock::given(method("GET"))
+        .and(path("/v2/users/1/games"))
+        .respond_with(json(200, "user-games.json").set_delay(Duration::from_millis(300)))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    mount_public_games(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/v1/search/items/details"))
+        .and(query_param("creatorTargetId", "1"))
+        .respond_with(json(200, "catalog-search.json"))
+        .mount(&server)
+        .await;
+    let state = state_with(&server, |config| {
+        config.fetch_mode = donations_api::models::FetchMode::Race;
+    });
+
+    let (status, body) = get(&state, "/user/1/passes").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(ids_and_prices(&body), [(21, 5), (23, 50)]);
+
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    let (_, body) = get(&state, "/user/1/passes").await;
+    assert_eq!(ids_and_prices(&body), [(11, 10), (12, 100), (14, 1000)]);
+}
+
+#[tokio::test]
+async fn inventory_details_stop_at_the_lookup_cap() {
+    let server = MockServer::start().await;
+    serve(&server, "/v2/users/3/games", 200, "user-games-empty.json").await;
+    let empty = r#"{"data": [], "nextPageCursor": null}"#;
+    Mock::given(method("GET"))
+        .and(path("/v1/search/items/details"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(empty, "application/json"))
+        .mount(&server)
+        .await;
+    let inventory = r#"{"data": [{"assetId": 11}, {"assetId": 13}, {"assetId": 14}]}"#;
+    Mock::given(method("GET"))
+        .and(path("/v2/users/3/inventory/34"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(inventory, "application/json"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/assets/11/details"))
+        .respond_with(json(200, "asset-details-11.json"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let state = state_with(&server, |config| config.inventory_max_lookups = 1);
+
+    let (status, body) = get(&state, "/user/3/passes").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let warning = body["warnings"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|w| w["code"] == "INVENTORY_TRUNCATED")
+        .unwrap_or_else(|| panic!("{body}"));
+    assert_eq!(warning["context"]["unchecked"], 2);
+}
+
+/// `method uri` con la clave `secreto` y `body` como JSON.
+async fn send(state: &Arc<AppState>, method: &str, uri: &str, body: Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("x-api-key", "secreto")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let app = routes::build_router(state.clone());
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn campaigns_count_reported_purchases_inside_their_window() {
+    let server = MockServer::start().await;
+    let state = state_with(&server, |config| {
+        config.api_keys = tenant::parse_api_keys("acme:secreto");
+    });
+    let campaign = serde_json::json!({
+        "name": "Fin de semana",
+        "userIds": [1, 2],
+        "startsAt": "2026-10-09T00:00:00Z",
+        "endsAt": "2026-10-12T00:00:00Z"
+    });
+    let (status, body) = send(&state, "PUT", "/campaigns/finde", campaign).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+
+    let purchases = [
+        ("a", 10, 1, 100, "2026-10-09T10:00:00Z"),
+        ("b", 11, 2, 50, "2026-10-10T10:00:00Z"),
+        ("c", 11, 1, 25, "2026-10-11T10:00:00Z"),
+        // El mismo `purchaseId` otra vez: el juego reintentó.
+        ("a", 10, 1, 100, "2026-10-09T10:00:00Z"),
+        // Antes de la campaña, y a alguien que no participa.
+        ("d", 10, 1, 500, "2026-10-08T23:59:59Z"),
+        ("e", 10, 3, 500, "2026-10-09T10:00:00Z"),
+    ];
+    let mut statuses = Vec::new();
+    for (purchase_id, donor_id, recipient_id, amount, at) in purchases {
+        let event = serde_json::json!({
+            "purchaseId": purchase_id,
+            "donorId": donor_id,
+            "recipientId": recipient_id,
+            "amount": amount,
+            "at": at,
+        });
+        let (status, body) = send(&state, "POST", "/donations", event).await;
+        statuses.push((status.as_u16(), body["duplicate"].as_bool()));
+    }
+    assert_eq!(
+        statuses,
+        [
+            (201, Some(false)),
+            (201, Some(false)),
+            (201, Some(false)),
+            (200, Some(true)),
+            (201, Some(false)),
+            (201, Some(false)),
+        ]
+    );
+
+    let (status, body) = send(&state, "GET", "/campaigns/finde", Value::Null).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        body["total"],
+        serde_json::json!({ "robux": 175, "donations": 3 })
+    );
+    let recipients: Vec<(u64, i64)> = body["recipients"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| (r["userId"].as_u64().unwrap(), r["robux"].as_i64().unwrap()))
+        .collect();
+    assert_eq!(recipients, [(1, 125), (2, 50)]);
+    let leaderboard: Vec<(u64, i64)> = body["leaderboard"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| (d["donorId"].as_u64().unwrap(), d["robux"].as_i64().unwrap()))
+        .collect();
+    assert_eq!(leaderboard, [(10, 100), (11, 75)]);
+
+    let backwards = serde_json::json!({
+        "userIds": [1],
+        "startsAt": "2026-10-12T00:00:00Z",
+        "endsAt": "2026-10-09T00:00:00Z"
+    });
+    let (status, body) = send(&state, "PUT", "/campaigns/otra", backwards).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], "INVALID_CAMPAIGN");
+}
+
+/// Cuerpo crudo de `GET uri`.
+async fn get_raw(state: &Arc<AppState>, uri: &str) -> (StatusCode, Vec<u8>) {
+    let app = routes::build_router(state.clone());
+    let request = Request::get(uri).body(Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, body.to_vec())
+}
+
+#[tokio::test]
+async fn max_response_bytes_counts_games_and_pretty_output() {
+    let uri = "/user/1/passes?groupBy=game&pretty=1";
+    let server = MockServer::start().await;
+    mount_public_games(&server).await;
+    let (status, full) = get_raw(&state(&server), uri).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let server = MockServer::start().await;
+    mount_public_games(&server).await;
+    let max = full.len() - 1;
+    let state = state_with(&server, |config| config.max_response_bytes = max);
+    let (status, body) = get_raw(&state, uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.len() <= max, "{} bytes > {max}", body.len());
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["truncated"], true, "{body}");
+    let kept: Vec<u64> = ids_and_prices(&body).iter().map(|(id, _)| *id).collect();
+    assert!(!kept.is_empty() && kept.len() < body["totalCount"].as_u64().unwrap() as usize);
+    for game in body["games"].as_array().unwrap() {
+        for id in game["passIds"].as_array().unwrap() {
+            assert!(kept.contains(&id.as_u64().unwrap()), "{body}");
+        }
+    }
+}